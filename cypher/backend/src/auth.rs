@@ -1,9 +1,15 @@
+use crate::models::{AppState, SessionInfo};
 use crate::vendored::atrium_oauth_client::{
     AtprotoLocalhostClientMetadata, DefaultHttpClient, KnownScope, OAuthClient, OAuthClientConfig,
     OAuthResolverConfig, Scope,
 };
 use atrium_identity::did::{CommonDidResolver, CommonDidResolverConfig, DEFAULT_PLC_DIRECTORY_URL};
 use atrium_identity::handle::{AtprotoHandleResolver, AtprotoHandleResolverConfig, DnsTxtResolver};
+use axum::extract::FromRequestParts;
+use axum::http::StatusCode;
+use axum::http::request::Parts;
+use axum_extra::TypedHeader;
+use headers::Cookie;
 use hickory_resolver::TokioAsyncResolver;
 use std::sync::Arc;
 
@@ -71,3 +77,30 @@ pub fn init_oauth() -> OAuthClient<
     };
     OAuthClient::new(client_config).expect("Failed to create OAuthClient")
 }
+
+/// The signed in user, taken from the `session` cookie set by the OAuth callback
+#[derive(Clone, Debug)]
+pub struct AuthSession(pub SessionInfo);
+
+impl AuthSession {
+    pub fn from_cookies(app_state: &AppState, cookies: &Cookie) -> Option<Self> {
+        let session_id = cookies.get("session")?;
+        let sessions = app_state.sessions.lock().unwrap();
+        sessions.get(session_id).cloned().map(AuthSession)
+    }
+}
+
+impl FromRequestParts<AppState> for AuthSession {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let unauthorized = || (StatusCode::UNAUTHORIZED, "Not signed in".to_string());
+        let TypedHeader(cookies) = TypedHeader::<Cookie>::from_request_parts(parts, app_state)
+            .await
+            .map_err(|_| unauthorized())?;
+        AuthSession::from_cookies(app_state, &cookies).ok_or_else(unauthorized)
+    }
+}
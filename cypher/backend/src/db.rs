@@ -1,11 +1,112 @@
 use crate::models::Post;
+use chrono::{DateTime, Utc};
+use std::fmt::{self, Display};
 use surrealdb::engine::local::Db;
 use surrealdb::{Error as SurrealError, Surreal};
 
+/// `indexed_at` is serialized as a string, it's cast on write so the feed can be ordered by the
+/// index. Posts stored before the field was defined are converted once.
+pub async fn define_schema(db: &Surreal<Db>) -> Result<(), SurrealError> {
+    db.query(
+        "DEFINE FIELD IF NOT EXISTS indexed_at ON post VALUE <datetime> $value; \
+         DEFINE INDEX IF NOT EXISTS post_indexed_at ON post FIELDS indexed_at; \
+         UPDATE post SET indexed_at = indexed_at WHERE type::is::string(indexed_at);",
+    )
+    .await?
+    .check()?;
+    Ok(())
+}
+
 pub async fn save_post(db: &Surreal<Db>, post: Post) -> Result<(), SurrealError> {
-    // Use the post.uri as the record ID in SurrealDB (post:uri)
-    db.update::<Option<Post>>(("post", &post.uri))
+    // Use the post.uri as the record ID in SurrealDB (post:uri). `update` skips records that
+    // don't exist yet, so this has to be an upsert.
+    db.upsert::<Option<Post>>(("post", &post.uri))
         .content(post)
         .await?;
     Ok(())
 }
+
+/// Fails if a post with the same uri is already stored
+pub async fn create_post(db: &Surreal<Db>, post: Post) -> Result<Option<Post>, SurrealError> {
+    db.create(("post", &post.uri)).content(post).await
+}
+
+/// Returns `None` without writing anything when the post doesn't exist
+pub async fn update_post(db: &Surreal<Db>, post: Post) -> Result<Option<Post>, SurrealError> {
+    db.update(("post", &post.uri)).content(post).await
+}
+
+pub async fn get_post(db: &Surreal<Db>, uri: &String) -> Result<Option<Post>, SurrealError> {
+    db.select(("post", uri)).await
+}
+
+pub async fn delete_post(db: &Surreal<Db>, uri: &String) -> Result<Option<Post>, SurrealError> {
+    db.delete(("post", uri)).await
+}
+
+/// Position of the last post of a feed page. Posts indexed at the same time are ordered by uri,
+/// so none are skipped or repeated across pages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedCursor {
+    pub indexed_at: DateTime<Utc>,
+    pub uri: String,
+}
+
+impl FeedCursor {
+    pub fn new(post: &Post) -> Self {
+        Self {
+            indexed_at: post.indexed_at,
+            uri: post.uri.clone(),
+        }
+    }
+
+    pub fn parse(cursor: &str) -> Option<Self> {
+        let (indexed_at, uri) = cursor.split_once("::")?;
+        Some(Self {
+            indexed_at: DateTime::parse_from_rfc3339(indexed_at).ok()?.to_utc(),
+            uri: uri.to_string(),
+        })
+    }
+}
+
+impl Display for FeedCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}", self.indexed_at.to_rfc3339(), self.uri)
+    }
+}
+
+/// Firehose and local-only posts together, newest first, after `cursor`
+pub async fn get_feed(
+    db: &Surreal<Db>,
+    include_local: bool,
+    limit: u32,
+    cursor: Option<FeedCursor>,
+) -> Result<Vec<Post>, SurrealError> {
+    let mut conditions = Vec::new();
+    if !include_local {
+        conditions.push("local_only = false");
+    }
+    if cursor.is_some() {
+        conditions.push(
+            "(indexed_at < <datetime> $cursor_at \
+             OR (indexed_at = <datetime> $cursor_at AND id < type::thing('post', $cursor_uri)))",
+        );
+    }
+    let filter = match conditions.is_empty() {
+        true => String::new(),
+        false => format!("WHERE {}", conditions.join(" AND ")),
+    };
+    let (cursor_at, cursor_uri) = match cursor {
+        Some(cursor) => (Some(cursor.indexed_at.to_rfc3339()), Some(cursor.uri)),
+        None => (None, None),
+    };
+    let mut response = db
+        .query(format!(
+            "SELECT * FROM post {filter} ORDER BY indexed_at DESC, id DESC LIMIT $limit"
+        ))
+        .bind(("cursor_at", cursor_at))
+        .bind(("cursor_uri", cursor_uri))
+        .bind(("limit", limit))
+        .await?;
+    response.take(0)
+}
@@ -1,5 +1,5 @@
 use crate::db::save_post;
-use crate::models::Post;
+use crate::models::{Post, StreamEvent};
 use chrono::Utc;
 use futures::StreamExt as _;
 use reqwest::Url;
@@ -16,7 +16,7 @@ use tokio::sync::{Semaphore, broadcast};
 use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::protocol::Message;
 
-async fn process(message: Vec<u8>, surreal: &Surreal<Db>, tx: &broadcast::Sender<Post>) {
+async fn process(message: Vec<u8>, surreal: &Surreal<Db>, tx: &broadcast::Sender<StreamEvent>) {
     let mut posts_to_create = Vec::new();
 
     match rsky_firehose::firehose::read(&message) {
@@ -86,6 +86,7 @@ async fn process(message: Vec<u8>, surreal: &Surreal<Db>, tx: &broadcast::Sender
                                                 created_at: post_record.created_at,
                                                 labels: None,
                                                 local_only: false,
+                                                edited_at: None,
                                             };
                                             if let Some(PostLabels::SelfLabels(self_labels)) =
                                                 post_record.labels
@@ -166,11 +167,14 @@ async fn process(message: Vec<u8>, surreal: &Surreal<Db>, tx: &broadcast::Sender
     }
     for post in posts_to_create {
         save_post(surreal, post.clone()).await.ok();
-        let _ = tx.send(post).ok();
+        let _ = tx.send(StreamEvent::Post(post)).ok();
     }
 }
 
-pub async fn run_firehose(surreal: Surreal<Db>, tx: broadcast::Sender<Post>) -> anyhow::Result<()> {
+pub async fn run_firehose(
+    surreal: Surreal<Db>,
+    tx: broadcast::Sender<StreamEvent>,
+) -> anyhow::Result<()> {
     let subscriber_base_path =
         env::var("FIREHOSE_SUBSCRIPTION_PATH").unwrap_or_else(|_| "wss://bsky.network".to_string());
 
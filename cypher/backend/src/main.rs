@@ -1,8 +1,12 @@
 use axum::response::IntoResponse;
-use axum::{Router, response::Html, routing::get};
+use axum::routing::{get, post, put};
+use axum::{Router, response::Html};
 use backend::firehose;
-use backend::models::{AppState, Post};
-use backend::routes::{callback_handler, feed_handler, login_handler, sse_handler};
+use backend::models::{AppState, StreamEvent};
+use backend::routes::{
+    callback_handler, create_post_handler, delete_post_handler, edit_post_handler, feed_handler,
+    login_handler, posts_handler, sse_handler,
+};
 use std::convert::Infallible;
 use std::env;
 use std::sync::Arc;
@@ -17,9 +21,10 @@ async fn main() -> anyhow::Result<()> {
     let db_path = env::var("DB_PATH").unwrap_or_else(|_| String::from("data/surrealdb"));
     let db = Surreal::new::<RocksDb>(&db_path).await?;
     db.use_ns("cs").use_db("cs").await?;
+    backend::db::define_schema(&db).await?;
 
-    // --- Create a broadcast channel for posts ---
-    let (tx, _rx) = broadcast::channel::<Post>(100);
+    // --- Create a broadcast channel for firehose and local-only post events ---
+    let (tx, _rx) = broadcast::channel::<StreamEvent>(100);
 
     let sessions = Arc::new(std::sync::Mutex::new(std::collections::HashMap::new()));
     let oauth_client = backend::auth::init_oauth();
@@ -55,6 +60,13 @@ async fn main() -> anyhow::Result<()> {
         .route("/stream", get(sse_handler))
         .route("/login", get(login_handler))
         .route("/callback", get(callback_handler))
+        .route("/posts", get(posts_handler))
+        // Local-only posts, stored here and never written to the author's PDS
+        .route("/post", post(create_post_handler))
+        .route(
+            "/post/{id}",
+            put(edit_post_handler).delete(delete_post_handler),
+        )
         // Serve static assets from ./dist
         .fallback_service(serve_dir)
         .layer(
@@ -22,7 +22,7 @@ pub struct SessionInfo {
 pub struct AppState {
     pub db: Surreal<Db>,
     pub sessions: Arc<Mutex<HashMap<String, SessionInfo>>>, // <-- use SessionInfo here
-    pub tx: broadcast::Sender<Post>,
+    pub tx: broadcast::Sender<StreamEvent>,
     pub oauth_client: Arc<
         OAuthClient<
             MemoryStateStore,
@@ -54,4 +54,23 @@ pub struct Post {
     pub created_at: DateTime<Utc>,
     pub labels: Option<Vec<String>>,
     pub local_only: bool,
+    // Only local-only posts can be edited, firehose posts are replaced by the author's PDS
+    #[serde(default)]
+    pub edited_at: Option<DateTime<Utc>>,
+}
+
+/// What the SSE stream sends to clients
+#[derive(Clone, Debug)]
+pub enum StreamEvent {
+    Post(Post),
+    Delete { uri: String, local_only: bool },
+}
+
+impl StreamEvent {
+    pub fn local_only(&self) -> bool {
+        match self {
+            StreamEvent::Post(post) => post.local_only,
+            StreamEvent::Delete { local_only, .. } => *local_only,
+        }
+    }
 }
@@ -1,17 +1,26 @@
-use crate::models::{AppState, SessionInfo};
+use crate::auth::AuthSession;
+use crate::db::{FeedCursor, create_post, delete_post, get_feed, get_post, update_post};
+use crate::models::{AppState, Post, SessionInfo, StreamEvent};
 use crate::vendored::atrium_oauth_client::{AuthorizeOptions, CallbackParams, KnownScope, Scope};
-use axum::extract::{Query, State};
+use axum::Json;
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::sse::{Event, KeepAlive};
 use axum::response::{IntoResponse, Redirect, Response, Sse};
 use axum_extra::TypedHeader;
+use chrono::Utc;
 use futures::Stream;
 use futures::StreamExt;
 use headers::Cookie;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use tokio_stream::wrappers::BroadcastStream;
 
+/// Local-only posts live in this app-view alone, so they get their own scheme instead of an
+/// `at://` uri pointing into a repo that doesn't hold them
+pub const LOCAL_POST_SCHEME: &str = "local";
+pub const MAX_POST_CHARS: usize = 3000;
+
 pub async fn login_handler(State(app_state): State<AppState>) -> Redirect {
     let oauth = &app_state.oauth_client;
 
@@ -99,16 +108,202 @@ pub async fn feed_handler(
 
 pub async fn sse_handler(
     State(app_state): State<AppState>,
+    cookies: Option<TypedHeader<Cookie>>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // local-only posts never leave the instance, so only signed in users see them
+    let include_local = cookies
+        .and_then(|TypedHeader(cookies)| AuthSession::from_cookies(&app_state, &cookies))
+        .is_some();
     let rx = app_state.tx.subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(|result| async move {
+    let stream = BroadcastStream::new(rx).filter_map(move |result| async move {
         match result {
-            Ok(post) => {
-                let json = serde_json::to_string(&post).unwrap();
-                Some(Ok(Event::default().data(json)))
-            }
-            Err(_) => None,
+            Ok(event) if include_local || !event.local_only() => match event {
+                StreamEvent::Post(post) => {
+                    let json = serde_json::to_string(&post).unwrap();
+                    Some(Ok(Event::default().data(json)))
+                }
+                StreamEvent::Delete { uri, .. } => {
+                    Some(Ok(Event::default().event("delete").data(uri)))
+                }
+            },
+            _ => None,
         }
     });
     Sse::new(stream).keep_alive(KeepAlive::default())
 }
+
+#[derive(Deserialize)]
+pub struct FeedQuery {
+    limit: Option<u32>,
+    cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct FeedOutput {
+    cursor: Option<String>,
+    posts: Vec<Post>,
+}
+
+pub async fn posts_handler(
+    State(app_state): State<AppState>,
+    cookies: Option<TypedHeader<Cookie>>,
+    Query(query): Query<FeedQuery>,
+) -> Result<Json<FeedOutput>, (StatusCode, String)> {
+    let include_local = cookies
+        .and_then(|TypedHeader(cookies)| AuthSession::from_cookies(&app_state, &cookies))
+        .is_some();
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let cursor = match query.cursor {
+        None => None,
+        Some(cursor) => Some(
+            FeedCursor::parse(&cursor)
+                .ok_or_else(|| (StatusCode::BAD_REQUEST, "Malformed cursor".to_string()))?,
+        ),
+    };
+    let posts = get_feed(&app_state.db, include_local, limit, cursor)
+        .await
+        .map_err(internal_error)?;
+    let cursor = match posts.len() as u32 == limit {
+        true => posts.last().map(|post| FeedCursor::new(post).to_string()),
+        false => None,
+    };
+    Ok(Json(FeedOutput { cursor, posts }))
+}
+
+#[derive(Deserialize)]
+pub struct CreatePostInput {
+    text: Option<String>,
+    langs: Option<Vec<String>>,
+    // uri of a firehose or local-only post
+    reply_parent: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct EditPostInput {
+    text: Option<String>,
+    langs: Option<Vec<String>>,
+}
+
+pub fn local_post_uri(did: &str, id: &str) -> String {
+    format!("{LOCAL_POST_SCHEME}://{did}/{id}")
+}
+
+fn validate_text(text: Option<String>) -> Result<String, (StatusCode, String)> {
+    let text = text.unwrap_or_default().trim().to_string();
+    if text.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Post text is empty".to_string()));
+    }
+    if text.chars().count() > MAX_POST_CHARS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Post text is longer than {MAX_POST_CHARS} characters"),
+        ));
+    }
+    Ok(text)
+}
+
+fn internal_error(error: impl std::fmt::Display) -> (StatusCode, String) {
+    tracing::error!("Database error: {error}");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Database error".to_string(),
+    )
+}
+
+fn not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Post not found".to_string())
+}
+
+/// Stores a local-only post by the signed in user. It's only ever written here, never to the
+/// user's PDS repo.
+pub async fn create_post_handler(
+    State(app_state): State<AppState>,
+    AuthSession(session): AuthSession,
+    Json(input): Json<CreatePostInput>,
+) -> Result<Json<Post>, (StatusCode, String)> {
+    let text = validate_text(input.text)?;
+    let (reply_parent, reply_root) = match input.reply_parent {
+        None => (None, None),
+        Some(parent_uri) => {
+            let parent = get_post(&app_state.db, &parent_uri)
+                .await
+                .map_err(internal_error)?
+                .ok_or_else(not_found)?;
+            let root = parent.reply_root.unwrap_or(parent.uri.clone());
+            (Some(parent.uri), Some(root))
+        }
+    };
+
+    let now = Utc::now();
+    let post = Post {
+        uri: local_post_uri(&session.did, &uuid::Uuid::new_v4().to_string()),
+        cid: String::new(),
+        reply_parent,
+        reply_root,
+        indexed_at: now,
+        prev: None,
+        sequence: 0,
+        text,
+        langs: input.langs,
+        author: session.did,
+        external_uri: None,
+        external_title: None,
+        external_description: None,
+        external_thumb: None,
+        quote_uri: None,
+        quote_cid: None,
+        created_at: now,
+        labels: None,
+        local_only: true,
+        edited_at: None,
+    };
+    let post = create_post(&app_state.db, post)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| internal_error("Post wasn't stored"))?;
+    let _ = app_state.tx.send(StreamEvent::Post(post.clone()));
+    Ok(Json(post))
+}
+
+pub async fn edit_post_handler(
+    State(app_state): State<AppState>,
+    AuthSession(session): AuthSession,
+    Path(id): Path<String>,
+    Json(input): Json<EditPostInput>,
+) -> Result<Json<Post>, (StatusCode, String)> {
+    // the uri is built from the session, so users can only reach their own posts
+    let uri = local_post_uri(&session.did, &id);
+    let mut post = get_post(&app_state.db, &uri)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+    post.text = validate_text(input.text)?;
+    if input.langs.is_some() {
+        post.langs = input.langs;
+    }
+    post.edited_at = Some(Utc::now());
+    // deleted since it was read
+    let post = update_post(&app_state.db, post)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+    let _ = app_state.tx.send(StreamEvent::Post(post.clone()));
+    Ok(Json(post))
+}
+
+pub async fn delete_post_handler(
+    State(app_state): State<AppState>,
+    AuthSession(session): AuthSession,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let uri = local_post_uri(&session.did, &id);
+    delete_post(&app_state.db, &uri)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+    let _ = app_state.tx.send(StreamEvent::Delete {
+        uri,
+        local_only: true,
+    });
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1 +1,227 @@
+#![cfg(test)]
 
+use crate::auth::{AuthSession, init_oauth};
+use crate::db::{define_schema, get_post, save_post};
+use crate::models::{AppState, Post, SessionInfo, StreamEvent};
+use crate::routes::{create_post_handler, delete_post_handler, edit_post_handler, posts_handler};
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderValue, StatusCode};
+use axum_extra::TypedHeader;
+use headers::{Cookie, Header};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use surrealdb::Surreal;
+use surrealdb::engine::local::Mem;
+use tokio::sync::broadcast;
+
+async fn app_state() -> AppState {
+    let db = Surreal::new::<Mem>(()).await.unwrap();
+    db.use_ns("cs").use_db("cs").await.unwrap();
+    define_schema(&db).await.unwrap();
+    let (tx, _rx) = broadcast::channel::<StreamEvent>(100);
+    let sessions = HashMap::from([("alice-session".to_string(), session("did:plc:alice"))]);
+    AppState {
+        db,
+        sessions: Arc::new(Mutex::new(sessions)),
+        tx,
+        oauth_client: Arc::new(init_oauth()),
+    }
+}
+
+fn session(did: &str) -> SessionInfo {
+    SessionInfo {
+        did: did.to_string(),
+        token: String::new(),
+    }
+}
+
+fn cookie(value: &'static str) -> Option<TypedHeader<Cookie>> {
+    let value = HeaderValue::from_static(value);
+    Some(TypedHeader(
+        Cookie::decode(&mut std::iter::once(&value)).unwrap(),
+    ))
+}
+
+async fn feed(app_state: &AppState, cookies: Option<TypedHeader<Cookie>>) -> Vec<Post> {
+    let query = serde_json::from_value(json!({})).unwrap();
+    let Json(output) = posts_handler(State(app_state.clone()), cookies, Query(query))
+        .await
+        .unwrap();
+    serde_json::from_value(serde_json::to_value(output).unwrap()["posts"].clone()).unwrap()
+}
+
+fn post_id(post: &Post) -> String {
+    post.uri.rsplit('/').next().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn local_posts_are_stored_edited_and_deleted() {
+    let app_state = app_state().await;
+    let mut rx = app_state.tx.subscribe();
+    let alice = AuthSession(session("did:plc:alice"));
+
+    let Json(post) = create_post_handler(
+        State(app_state.clone()),
+        alice.clone(),
+        Json(serde_json::from_value(json!({ "text": "hello" })).unwrap()),
+    )
+    .await
+    .unwrap();
+    assert!(post.local_only);
+    assert!(post.uri.starts_with("local://did:plc:alice/"));
+    assert!(matches!(rx.recv().await.unwrap(), StreamEvent::Post(p) if p.uri == post.uri));
+    // so the feed order doesn't depend on both posts landing in the same microsecond
+    std::thread::sleep(Duration::from_millis(2));
+
+    let Json(reply) = create_post_handler(
+        State(app_state.clone()),
+        alice.clone(),
+        Json(serde_json::from_value(json!({ "text": "reply", "reply_parent": post.uri })).unwrap()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(reply.reply_parent.as_ref(), Some(&post.uri));
+    assert_eq!(reply.reply_root.as_ref(), Some(&post.uri));
+
+    // newest first, and only for signed in users
+    let posts = feed(&app_state, cookie("session=alice-session")).await;
+    let uris: Vec<_> = posts.iter().map(|p| p.uri.clone()).collect();
+    assert_eq!(uris, vec![reply.uri.clone(), post.uri.clone()]);
+    assert!(feed(&app_state, None).await.is_empty());
+    assert!(feed(&app_state, cookie("session=unknown")).await.is_empty());
+
+    let Json(edited) = edit_post_handler(
+        State(app_state.clone()),
+        alice.clone(),
+        Path(post_id(&post)),
+        Json(serde_json::from_value(json!({ "text": "edited" })).unwrap()),
+    )
+    .await
+    .unwrap();
+    assert!(edited.edited_at.is_some());
+    let stored = get_post(&app_state.db, &post.uri).await.unwrap().unwrap();
+    assert_eq!(stored.text, "edited");
+
+    // the uri is built from the caller's DID, so someone else's post isn't found
+    let error = edit_post_handler(
+        State(app_state.clone()),
+        AuthSession(session("did:plc:mallory")),
+        Path(post_id(&post)),
+        Json(serde_json::from_value(json!({ "text": "mine now" })).unwrap()),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error.0, StatusCode::NOT_FOUND);
+
+    let status = delete_post_handler(
+        State(app_state.clone()),
+        alice.clone(),
+        Path(post_id(&post)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(get_post(&app_state.db, &post.uri).await.unwrap().is_none());
+
+    let error = delete_post_handler(
+        State(app_state.clone()),
+        alice.clone(),
+        Path(post_id(&post)),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error.0, StatusCode::NOT_FOUND);
+    let error = edit_post_handler(
+        State(app_state.clone()),
+        alice,
+        Path(post_id(&post)),
+        Json(serde_json::from_value(json!({ "text": "too late" })).unwrap()),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn empty_and_orphan_posts_are_rejected() {
+    let app_state = app_state().await;
+    let alice = AuthSession(session("did:plc:alice"));
+
+    let error = create_post_handler(
+        State(app_state.clone()),
+        alice.clone(),
+        Json(serde_json::from_value(json!({ "text": "  " })).unwrap()),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error.0, StatusCode::BAD_REQUEST);
+
+    let error = create_post_handler(
+        State(app_state.clone()),
+        alice,
+        Json(
+            serde_json::from_value(json!({ "text": "hi", "reply_parent": "at://missing" }))
+                .unwrap(),
+        ),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn feed_pages_through_posts_indexed_at_the_same_time() {
+    let app_state = app_state().await;
+    let indexed_at = chrono::Utc::now();
+    for rkey in ["a", "b", "c"] {
+        let post: Post = serde_json::from_value(json!({
+            "uri": format!("at://did:plc:bob/app.bsky.feed.post/{rkey}"),
+            "cid": "",
+            "reply_parent": null,
+            "reply_root": null,
+            "indexed_at": indexed_at,
+            "prev": null,
+            "sequence": 0,
+            "text": rkey,
+            "langs": null,
+            "author": "did:plc:bob",
+            "external_uri": null,
+            "external_title": null,
+            "external_description": null,
+            "external_thumb": null,
+            "quote_uri": null,
+            "quote_cid": null,
+            "created_at": indexed_at,
+            "labels": null,
+            "local_only": false,
+        }))
+        .unwrap();
+        save_post(&app_state.db, post).await.unwrap();
+    }
+
+    let mut texts = vec![];
+    let mut cursor = None;
+    loop {
+        let query = serde_json::from_value(json!({ "limit": 2, "cursor": cursor })).unwrap();
+        let Json(output) = posts_handler(State(app_state.clone()), None, Query(query))
+            .await
+            .unwrap();
+        let output = serde_json::to_value(output).unwrap();
+        let posts: Vec<Post> = serde_json::from_value(output["posts"].clone()).unwrap();
+        texts.extend(posts.into_iter().map(|post| post.text));
+        match output["cursor"].as_str() {
+            Some(next) => cursor = Some(next.to_string()),
+            None => break,
+        }
+    }
+    assert_eq!(texts, vec!["c", "b", "a"]);
+
+    let query = serde_json::from_value(json!({ "cursor": "yesterday" })).unwrap();
+    let error = posts_handler(State(app_state.clone()), None, Query(query))
+        .await
+        .unwrap_err();
+    assert_eq!(error.0, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,180 @@
+//! Derive macros for `firefly_api::par::{FromPar, ToPar}`.
+//!
+//! Named structs map to a Rholang map keyed by field name (override with `#[par(rename = "..")]`),
+//! tuple structs map to a tuple, newtypes to their only field and unit structs to `Nil`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    Data,
+    DeriveInput,
+    Fields,
+    GenericParam,
+    Generics,
+    LitStr,
+    Path,
+    parse_macro_input,
+    parse_quote,
+};
+
+#[proc_macro_derive(FromPar, attributes(par))]
+pub fn derive_from_par(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_par(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(ToPar, attributes(par))]
+pub fn derive_to_par(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_par(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_from_par(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = struct_fields(&input, "FromPar")?;
+    let name = &input.ident;
+    let type_name = LitStr::new(&name.to_string(), Span::call_site());
+    let generics = add_trait_bounds(
+        input.generics.clone(),
+        parse_quote!(::firefly_api::par::FromPar),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match fields {
+        Fields::Named(fields) => {
+            let values = fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().unwrap();
+                    let key = field_key(field)?;
+                    Ok(quote!(#ident: fields.field(#key)?))
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! {
+                let mut fields = ::firefly_api::par::StructFields::new(par, #type_name)?;
+                ::core::result::Result::Ok(Self { #(#values),* })
+            }
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote! {
+            ::core::result::Result::Ok(Self(::firefly_api::par::FromPar::from_par(par)?))
+        },
+        Fields::Unnamed(fields) => {
+            let len = Literal::usize_unsuffixed(fields.unnamed.len());
+            let items = (0..fields.unnamed.len())
+                .map(|i| format_ident!("item{i}"))
+                .collect::<Vec<_>>();
+            quote! {
+                let [#(#items),*] = ::firefly_api::par::tuple_items::<#len>(par)?;
+                ::core::result::Result::Ok(Self(#(::firefly_api::par::FromPar::from_par(#items)?),*))
+            }
+        }
+        Fields::Unit => quote! {
+            <() as ::firefly_api::par::FromPar>::from_par(par)?;
+            ::core::result::Result::Ok(Self)
+        },
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::firefly_api::par::FromPar for #name #ty_generics #where_clause {
+            fn from_par(
+                par: ::firefly_api::models::rhoapi::Par,
+            ) -> ::firefly_api::par::__private::Result<Self> {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_to_par(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = struct_fields(&input, "ToPar")?;
+    let name = &input.ident;
+    let generics = add_trait_bounds(
+        input.generics.clone(),
+        parse_quote!(::firefly_api::par::ToPar),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match fields {
+        Fields::Named(fields) => {
+            let entries = fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().unwrap();
+                    let key = field_key(field)?;
+                    Ok(quote! {
+                        (
+                            ::firefly_api::par::ToPar::to_par(#key),
+                            ::firefly_api::par::ToPar::to_par(&self.#ident),
+                        )
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote!(::firefly_api::par::map_par([#(#entries),*]))
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote! {
+            ::firefly_api::par::ToPar::to_par(&self.0)
+        },
+        Fields::Unnamed(fields) => {
+            let indexes = (0..fields.unnamed.len()).map(syn::Index::from);
+            quote! {
+                ::firefly_api::par::tuple_par(vec![
+                    #(::firefly_api::par::ToPar::to_par(&self.#indexes)),*
+                ])
+            }
+        }
+        Fields::Unit => quote!(::firefly_api::models::rhoapi::Par::default()),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::firefly_api::par::ToPar for #name #ty_generics #where_clause {
+            fn to_par(&self) -> ::firefly_api::models::rhoapi::Par {
+                #body
+            }
+        }
+    })
+}
+
+fn struct_fields<'a>(input: &'a DeriveInput, derive: &str) -> syn::Result<&'a Fields> {
+    match &input.data {
+        Data::Struct(data) => Ok(&data.fields),
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("{derive} can only be derived for structs"),
+        )),
+    }
+}
+
+fn add_trait_bounds(mut generics: Generics, bound: Path) -> Generics {
+    for param in &mut generics.params {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(parse_quote!(#bound));
+        }
+    }
+    generics
+}
+
+fn field_key(field: &syn::Field) -> syn::Result<LitStr> {
+    let mut key = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("par"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                key = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported par attribute"))
+            }
+        })?;
+    }
+    let ident = field.ident.as_ref().unwrap();
+    Ok(key.unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span())))
+}
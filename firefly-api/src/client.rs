@@ -1,20 +1,41 @@
+use std::fmt;
+
 use anyhow::{Context, anyhow};
-use helpers::{FromExpr, build_deploy_msg};
+use helpers::{DeployOptions, PhloLimit, build_deploy_msg, estimate_phlo_limit};
 use secp256k1::SecretKey;
 
 use crate::models::casper::v1::deploy_service_client::DeployServiceClient;
 use crate::models::casper::v1::propose_service_client::ProposeServiceClient;
 use crate::models::casper::v1::{deploy_response, propose_response, rho_data_response};
-use crate::models::casper::{DataAtNameByBlockQuery, ProposeQuery};
+use crate::models::casper::{DataAtNameByBlockQuery, DeployDataProto, ProposeQuery};
 use crate::models::rhoapi::expr::ExprInstance;
-use crate::models::rhoapi::{Expr, Par};
+use crate::par::{FromPar, expr_par};
+use crate::read_node_client::ReadNodeClient;
 
+#[cfg(feature = "cli")]
+pub mod args;
 pub mod helpers;
+pub mod tracker;
+
+/// The node answered a deploy with an error, so it was not added to the deploy pool. Other
+/// errors of a deploy leave it unknown whether the node received it.
+#[derive(Debug, Clone)]
+pub struct DeployRejected(pub String);
+
+impl fmt::Display for DeployRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "do_deploy error: {}", self.0)
+    }
+}
+
+impl std::error::Error for DeployRejected {}
 
 pub struct Client {
     wallet_key: SecretKey,
     deploy_client: DeployServiceClient<tonic::transport::Channel>,
     propose_client: ProposeServiceClient<tonic::transport::Channel>,
+    deploy_options: DeployOptions,
+    read_node: Option<ReadNodeClient>,
 }
 
 impl Client {
@@ -36,12 +57,88 @@ impl Client {
             wallet_key,
             deploy_client,
             propose_client,
+            deploy_options: DeployOptions::default(),
+            read_node: None,
         })
     }
 
+    /// Sets the defaults used by every deploy which doesn't pass its own options
+    pub fn with_deploy_options(mut self, deploy_options: DeployOptions) -> Self {
+        self.deploy_options = deploy_options;
+        self
+    }
+
+    /// Sets the read node used to estimate the cost of deploys with [`PhloLimit::Estimate`]
+    pub fn with_read_node(mut self, read_node: ReadNodeClient) -> Self {
+        self.read_node = Some(read_node);
+        self
+    }
+
+    pub fn deploy_options(&self) -> &DeployOptions {
+        &self.deploy_options
+    }
+
     pub async fn deploy(&mut self, code: String) -> anyhow::Result<String> {
-        let msg = build_deploy_msg(&self.wallet_key, code);
+        let options = self.deploy_options.clone();
+        self.deploy_with_options(code, &options).await
+    }
 
+    /// Deploys `code`, the node only accepts it into blocks created within its deploy lifespan
+    /// after `valid_after_block_number`
+    pub async fn deploy_after_block(
+        &mut self,
+        code: String,
+        valid_after_block_number: i64,
+    ) -> anyhow::Result<String> {
+        let options = DeployOptions {
+            valid_after_block_number,
+            ..self.deploy_options.clone()
+        };
+        self.deploy_with_options(code, &options).await
+    }
+
+    pub async fn deploy_with_options(
+        &mut self,
+        code: String,
+        options: &DeployOptions,
+    ) -> anyhow::Result<String> {
+        let msg = self.sign_deploy_msg(code, options).await?;
+        self.send_deploy_msg(msg).await
+    }
+
+    /// Signs `code` without sending it, the deploy id is the hex encoded signature
+    async fn sign_deploy_msg(
+        &mut self,
+        code: String,
+        options: &DeployOptions,
+    ) -> anyhow::Result<DeployDataProto> {
+        let phlo_limit = match options.phlo_limit {
+            PhloLimit::Fixed(phlo_limit) => phlo_limit,
+            PhloLimit::Estimate {
+                margin_percent,
+                max,
+            } => {
+                let read_node = self
+                    .read_node
+                    .clone()
+                    .context("phlo limit estimation requires a read node")?;
+                let cost = read_node
+                    .estimate_cost(code.clone())
+                    .await
+                    .context("failed to estimate deploy cost")?;
+                estimate_phlo_limit(cost, margin_percent, max)?
+            }
+        };
+        Ok(build_deploy_msg(
+            &self.wallet_key,
+            code,
+            phlo_limit,
+            options,
+        ))
+    }
+
+    /// Sends a signed deploy, a [`DeployRejected`] error means the node refused it
+    async fn send_deploy_msg(&mut self, msg: DeployDataProto) -> anyhow::Result<String> {
         let deploy_response = self
             .deploy_client
             .do_deploy(msg)
@@ -56,7 +153,7 @@ impl Client {
         let message = match resp_message {
             deploy_response::Message::Result(message) => message,
             deploy_response::Message::Error(err) => {
-                return Err(anyhow!("do_deploy error: {err:?}"));
+                return Err(DeployRejected(format!("{err:?}")).into());
             }
         };
         let sig = message
@@ -88,20 +185,25 @@ impl Client {
         Ok(block_hash)
     }
 
-    pub async fn full_deploy(&mut self, code: String) -> anyhow::Result<String> {
-        let _response_hash = self.deploy(code).await.context("deploy error")?;
-        let block_hash = self.propose().await.context("propose error")?;
-        Ok(block_hash)
+    pub async fn get_channel_value<T>(&mut self, hash: String, channel: String) -> anyhow::Result<T>
+    where
+        T: FromPar,
+    {
+        self.find_channel_value(hash, channel)
+            .await?
+            .context("missing par in get_data_at_name")
     }
 
-    pub async fn get_channel_value<T>(&mut self, hash: String, channel: String) -> anyhow::Result<T>
+    /// Like [`Client::get_channel_value`], but `None` when nothing was sent on the channel
+    pub async fn find_channel_value<T>(
+        &mut self,
+        hash: String,
+        channel: String,
+    ) -> anyhow::Result<Option<T>>
     where
-        T: FromExpr,
+        T: FromPar,
     {
-        let mut par = Par::default();
-        par.exprs.push(Expr {
-            expr_instance: Some(ExprInstance::GString(channel)),
-        });
+        let par = expr_par(ExprInstance::GString(channel));
 
         let resp = self
             .deploy_client
@@ -123,20 +225,6 @@ impl Client {
             }
         };
 
-        let par = payload
-            .par
-            .into_iter()
-            .last()
-            .context("missing par in get_data_at_name")?;
-        let expr = par
-            .exprs
-            .into_iter()
-            .next()
-            .context("missing exprs in get_data_at_name")?;
-        let expr = expr
-            .expr_instance
-            .context("missing expr_instance in get_data_at_name")?;
-
-        T::from(expr)
+        payload.par.into_iter().last().map(T::from_par).transpose()
     }
 }
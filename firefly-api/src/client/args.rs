@@ -0,0 +1,94 @@
+use super::Client;
+use super::helpers::{DEFAULT_PHLO_LIMIT, DeployOptions, PhloLimit};
+use crate::read_node_client::ReadNodeClient;
+
+/// Command line options of the deploys made by a service, flattened into its arguments
+#[derive(Debug, Clone, clap::Args)]
+pub struct DeployArgs {
+    /// Phlo price of every deploy
+    #[arg(long, default_value_t = 1)]
+    pub phlo_price: i64,
+
+    /// Phlo limit of every deploy, the upper bound when estimating
+    #[arg(long, default_value_t = DEFAULT_PHLO_LIMIT)]
+    pub phlo_limit: i64,
+
+    /// Firefly shard id
+    #[arg(long, default_value = "root")]
+    pub shard_id: String,
+
+    /// Size the phlo limit from the cost observed on the read node, increased by this percentage
+    #[arg(long, requires = "read_node_url")]
+    pub estimate_phlo_margin: Option<u32>,
+
+    /// Firefly read node url, used to estimate deploy costs
+    #[arg(long)]
+    pub read_node_url: Option<String>,
+}
+
+impl DeployArgs {
+    pub fn deploy_options(&self) -> DeployOptions {
+        let phlo_limit = match self.estimate_phlo_margin {
+            Some(margin_percent) => PhloLimit::Estimate {
+                margin_percent,
+                max: self.phlo_limit,
+            },
+            None => PhloLimit::Fixed(self.phlo_limit),
+        };
+        DeployOptions {
+            phlo_price: self.phlo_price,
+            phlo_limit,
+            shard_id: self.shard_id.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Applies the deploy options to `client`, along with the read node when one is given
+    pub fn configure(&self, client: Client) -> Client {
+        let client = client.with_deploy_options(self.deploy_options());
+        match &self.read_node_url {
+            Some(read_node_url) => client.with_read_node(ReadNodeClient::new(read_node_url)),
+            None => client,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct Args {
+        #[command(flatten)]
+        deploy: DeployArgs,
+    }
+
+    #[test]
+    fn estimates_phlo_limit_only_when_asked() {
+        let args = Args::parse_from(["test", "--phlo-limit", "1000"]);
+        let options = args.deploy.deploy_options();
+        assert_eq!(options.phlo_price, 1);
+        assert_eq!(options.shard_id, "root");
+        assert_eq!(options.phlo_limit, PhloLimit::Fixed(1000));
+
+        let args = Args::parse_from([
+            "test",
+            "--estimate-phlo-margin",
+            "20",
+            "--read-node-url",
+            "http://localhost:40413",
+        ]);
+        assert_eq!(
+            args.deploy.deploy_options().phlo_limit,
+            PhloLimit::Estimate {
+                margin_percent: 20,
+                max: DEFAULT_PHLO_LIMIT
+            }
+        );
+
+        // estimating needs a read node to run the deploy on
+        assert!(Args::try_parse_from(["test", "--estimate-phlo-margin", "20"]).is_err());
+    }
+}
@@ -1,23 +1,84 @@
-use std::collections::HashMap;
-
-use anyhow::{anyhow, Context};
+use anyhow::bail;
 use blake2::digest::consts::U32;
 use blake2::{Blake2b, Digest};
 use prost::Message as _;
-use secp256k1::{Message, Secp256k1, SecretKey};
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use sha3::Keccak256;
 
 use crate::models::casper::DeployDataProto;
-use crate::models::rhoapi::expr::ExprInstance;
 
-pub fn build_deploy_msg(key: &SecretKey, code: String) -> DeployDataProto {
+pub const DEFAULT_PHLO_LIMIT: i64 = 500000;
+
+/// How the phlo limit of a deploy is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhloLimit {
+    Fixed(i64),
+    /// Evaluate the term on the read node first and use the observed cost increased by
+    /// `margin_percent`, never going above `max`
+    ///
+    /// The term is evaluated as an explore-deploy, which runs without the deployer's identity.
+    /// Terms that depend on it, such as vault transfers, stop early there and cost more once
+    /// deployed, so they must use [`PhloLimit::fixed`].
+    Estimate {
+        margin_percent: u32,
+        max: i64,
+    },
+}
+
+impl PhloLimit {
+    /// A fixed limit at the upper bound of an estimate
+    pub fn fixed(self) -> Self {
+        match self {
+            PhloLimit::Fixed(_) => self,
+            PhloLimit::Estimate { max, .. } => PhloLimit::Fixed(max),
+        }
+    }
+}
+
+/// Parameters signed into every deploy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeployOptions {
+    pub phlo_price: i64,
+    pub phlo_limit: PhloLimit,
+    pub shard_id: String,
+    pub valid_after_block_number: i64,
+}
+
+impl Default for DeployOptions {
+    fn default() -> Self {
+        Self {
+            phlo_price: 1,
+            phlo_limit: PhloLimit::Fixed(DEFAULT_PHLO_LIMIT),
+            shard_id: "root".into(),
+            valid_after_block_number: 0,
+        }
+    }
+}
+
+/// Sizes the phlo limit from the cost observed on the read node
+pub fn estimate_phlo_limit(cost: u64, margin_percent: u32, max: i64) -> anyhow::Result<i64> {
+    let cost = i64::try_from(cost)?;
+    if cost > max {
+        bail!("estimated deploy cost {cost} exceeds the phlo limit {max}");
+    }
+    let limit = cost.saturating_add(cost.saturating_mul(margin_percent.into()) / 100);
+    Ok(limit.clamp(1, max))
+}
+
+pub fn build_deploy_msg(
+    key: &SecretKey,
+    code: String,
+    phlo_limit: i64,
+    options: &DeployOptions,
+) -> DeployDataProto {
     let timestamp = chrono::Utc::now().timestamp_millis();
     let mut msg = DeployDataProto {
         term: code,
         timestamp,
-        phlo_price: 1,
-        phlo_limit: 500000,
-        valid_after_block_number: 0,
-        shard_id: "root".into(),
+        phlo_price: options.phlo_price,
+        phlo_limit,
+        valid_after_block_number: options.valid_after_block_number,
+        shard_id: options.shard_id.clone(),
         ..Default::default()
     };
 
@@ -37,82 +98,36 @@ pub fn build_deploy_msg(key: &SecretKey, code: String) -> DeployDataProto {
     msg
 }
 
-pub trait FromExpr: Sized {
-    fn from(val: ExprInstance) -> anyhow::Result<Self>;
-}
+/// Prefix of a REV address payload: coin id `000000` followed by version `00`
+const REV_ADDRESS_PREFIX: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
 
-impl FromExpr for String {
-    fn from(val: ExprInstance) -> anyhow::Result<Self> {
-        match val {
-            ExprInstance::GString(value) => Ok(value),
-            other => Err(anyhow!("unexpected expr type: {other:?} expected GString")),
-        }
-    }
-}
+/// Derives the REV address owned by a secp256k1 public key
+///
+/// The address is built the same way as in RNode: keccak256 of the uncompressed
+/// public key gives an ETH address (last 20 bytes), whose keccak256 hash is prefixed
+/// and suffixed with a 4 byte blake2b checksum before base58 encoding.
+pub fn rev_address_from_public_key(public_key: &PublicKey) -> String {
+    let uncompressed = public_key.serialize_uncompressed();
+    let pk_hash = Keccak256::digest(&uncompressed[1..]);
+    let eth_address = &pk_hash[pk_hash.len() - 20..];
+    let eth_hash = Keccak256::digest(eth_address);
 
-impl<T> FromExpr for Vec<T>
-where
-    T: FromExpr,
-{
-    fn from(val: ExprInstance) -> anyhow::Result<Self> {
-        match val {
-            ExprInstance::EListBody(list) => list
-                .ps
-                .into_iter()
-                .map(|par| {
-                    let expr = par.exprs.into_iter().next().context("missing exprs")?;
-                    let expr = expr.expr_instance.context("missing expr_instance")?;
-                    T::from(expr)
-                })
-                .collect(),
-            other => Err(anyhow!(
-                "unexpected expr type: {other:?} expected EListBody"
-            )),
-        }
-    }
-}
+    let mut payload = REV_ADDRESS_PREFIX.to_vec();
+    payload.extend_from_slice(&eth_hash);
 
-impl<T> FromExpr for HashMap<String, T>
-where
-    T: FromExpr,
-{
-    fn from(val: ExprInstance) -> anyhow::Result<Self> {
-        match val {
-            ExprInstance::EMapBody(map) => map
-                .kvs
-                .into_iter()
-                .map(|pair| {
-                    let key = pair
-                        .key
-                        .and_then(|key| key.exprs.into_iter().next())
-                        .and_then(|expr| expr.expr_instance)
-                        .context("missing key")?;
-                    let key = FromExpr::from(key)?;
-
-                    let value = pair
-                        .value
-                        .and_then(|value| value.exprs.into_iter().next())
-                        .and_then(|expr| expr.expr_instance)
-                        .context("missing value")?;
-                    let value = FromExpr::from(value)?;
-
-                    Ok((key, value))
-                })
-                .collect(),
-            other => Err(anyhow!("unexpected expr type: {other:?} expected EMapBody")),
-        }
-    }
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(&payload);
+    let checksum = hasher.finalize();
+
+    payload.extend_from_slice(&checksum[..4]);
+    bs58::encode(payload).into_string()
 }
 
-impl FromExpr for Vec<u8> {
-    fn from(val: ExprInstance) -> anyhow::Result<Self> {
-        match val {
-            ExprInstance::GByteArray(list) => Ok(list),
-            other => Err(anyhow!(
-                "unexpected expr type: {other:?} expected GByteArray"
-            )),
-        }
-    }
+/// Derives the REV address owned by a hex encoded secp256k1 private key
+pub fn rev_address_from_private_key(wallet_key: &str) -> anyhow::Result<String> {
+    let secret_key = SecretKey::from_slice(&hex::decode(wallet_key)?)?;
+    let public_key = secret_key.public_key(&Secp256k1::new());
+    Ok(rev_address_from_public_key(&public_key))
 }
 
 pub fn verify_rev_addr(rev_addr: &str) -> bool {
@@ -147,3 +162,39 @@ pub fn verify_rev_addr(rev_addr: &str) -> bool {
 
     checksum == checksum_calc
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_phlo_limit_with_margin() {
+        assert_eq!(estimate_phlo_limit(1000, 20, 500000).unwrap(), 1200);
+        assert_eq!(estimate_phlo_limit(1000, 20, 1100).unwrap(), 1100);
+        assert_eq!(estimate_phlo_limit(0, 20, 1100).unwrap(), 1);
+        assert!(estimate_phlo_limit(2000, 20, 1100).is_err());
+    }
+
+    #[test]
+    fn fixes_estimates_at_their_upper_bound() {
+        let estimate = PhloLimit::Estimate {
+            margin_percent: 20,
+            max: 1100,
+        };
+        assert_eq!(estimate.fixed(), PhloLimit::Fixed(1100));
+        assert_eq!(PhloLimit::Fixed(1000).fixed(), PhloLimit::Fixed(1000));
+    }
+
+    #[test]
+    fn derives_rev_address_from_private_key() {
+        let address = rev_address_from_private_key(
+            "6a786ec387aff99fcce1bd6faa35916bfad3686d5c98e90a89f77670f535607c",
+        )
+        .unwrap();
+        assert_eq!(
+            address,
+            "1111EjdAxnKb5zKUc8ikuxfdi3kwSGH7BJCHKWjnVzfAF3SjCBvjh"
+        );
+        assert!(verify_rev_addr(&address));
+    }
+}
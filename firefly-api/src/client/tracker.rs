@@ -0,0 +1,501 @@
+use std::time::Duration;
+
+use anyhow::{Context, anyhow, bail};
+use tokio::time::Instant;
+
+use super::Client;
+use super::helpers::DeployOptions;
+use crate::models::casper::v1::{
+    block_response,
+    find_deploy_response,
+    is_finalized_response,
+    last_finalized_block_response,
+};
+use crate::models::casper::{
+    BlockQuery,
+    DeployDataProto,
+    FindDeployQuery,
+    IsFinalizedQuery,
+    LastFinalizedBlockQuery,
+    LightBlockInfo,
+};
+
+/// Deploy submitted to the node, used to poll its status and to resubmit it once expired
+#[derive(Debug, Clone)]
+pub struct DeployHandle {
+    /// Hex encoded deploy signature
+    pub deploy_id: String,
+    pub code: String,
+    pub valid_after_block_number: i64,
+    /// Number of times the deploy was submitted
+    pub attempts: u32,
+}
+
+/// Deploy signed with [`Client::sign_deploy`] and not sent yet
+#[derive(Debug, Clone)]
+pub struct SignedDeploy {
+    pub handle: DeployHandle,
+    msg: DeployDataProto,
+}
+
+/// Block a deploy was added to, with the outcome of its execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeployBlock {
+    pub block_hash: String,
+    pub block_number: i64,
+    pub cost: u64,
+    pub errored: bool,
+    pub system_deploy_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeployStatus {
+    /// Waiting in the deploy pool of the node
+    Pending,
+    /// Added to a block that is not finalized yet, the block can still be orphaned
+    Included(DeployBlock),
+    Finalized(DeployBlock),
+    /// The deploy lifespan has passed without the deploy being added to a block
+    Expired,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitFor {
+    Inclusion,
+    Finalization,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeployTrackerOptions {
+    pub wait_for: WaitFor,
+    pub poll_interval: Duration,
+    pub timeout: Duration,
+    /// How many times an expired deploy is resubmitted
+    pub max_retries: u32,
+    /// `deploy-lifespan` of the node, in blocks
+    pub deploy_lifespan: i64,
+    /// Propose a block after every submission, for nodes which don't propose on their own
+    pub propose: bool,
+}
+
+impl Default for DeployTrackerOptions {
+    fn default() -> Self {
+        Self {
+            wait_for: WaitFor::Finalization,
+            poll_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(300),
+            max_retries: 3,
+            deploy_lifespan: 50,
+            propose: true,
+        }
+    }
+}
+
+/// Whether a deploy that isn't in a block yet can no longer be added to one
+pub fn deploy_expired(
+    last_finalized_block_number: i64,
+    valid_after_block_number: i64,
+    deploy_lifespan: i64,
+) -> bool {
+    last_finalized_block_number >= valid_after_block_number + deploy_lifespan
+}
+
+/// What [`Client::wait_for_deploy`] does after polling a deploy
+#[derive(Debug, PartialEq, Eq)]
+enum Next {
+    Done(DeployBlock),
+    Resubmit,
+    Wait,
+}
+
+/// Progress of a deploy being waited on, apart from the node calls
+struct DeployWait<'a> {
+    options: &'a DeployTrackerOptions,
+    deadline: Instant,
+    included_in: Option<String>,
+}
+
+impl<'a> DeployWait<'a> {
+    fn new(options: &'a DeployTrackerOptions, started: Instant) -> Self {
+        Self {
+            options,
+            deadline: started + options.timeout,
+            included_in: None,
+        }
+    }
+
+    fn next(
+        &mut self,
+        handle: &DeployHandle,
+        status: DeployStatus,
+        now: Instant,
+    ) -> anyhow::Result<Next> {
+        match status {
+            DeployStatus::Finalized(block) => return Ok(Next::Done(block)),
+            DeployStatus::Included(block) if self.options.wait_for == WaitFor::Inclusion => {
+                return Ok(Next::Done(block));
+            }
+            DeployStatus::Included(block) => {
+                self.included_in = Some(block.block_hash);
+            }
+            DeployStatus::Pending => {
+                if let Some(block_hash) = self.included_in.take() {
+                    tracing::warn!(
+                        "deploy {} dropped from orphaned block {block_hash}",
+                        handle.deploy_id
+                    );
+                }
+            }
+            DeployStatus::Expired => {
+                if handle.attempts > self.options.max_retries {
+                    bail!(
+                        "deploy {} expired after {} attempts",
+                        handle.deploy_id,
+                        handle.attempts
+                    );
+                }
+                self.included_in = None;
+                return Ok(Next::Resubmit);
+            }
+        }
+
+        if now >= self.deadline {
+            bail!("timed out waiting for deploy {}", handle.deploy_id);
+        }
+        Ok(Next::Wait)
+    }
+}
+
+impl Client {
+    pub async fn last_finalized_block_number(&mut self) -> anyhow::Result<i64> {
+        self.last_finalized_block_info()
+            .await
+            .map(|block_info| block_info.block_number)
+    }
+
+    pub async fn last_finalized_block_hash(&mut self) -> anyhow::Result<String> {
+        self.last_finalized_block_info()
+            .await
+            .map(|block_info| block_info.block_hash)
+    }
+
+    async fn last_finalized_block_info(&mut self) -> anyhow::Result<LightBlockInfo> {
+        let resp = self
+            .deploy_client
+            .last_finalized_block(LastFinalizedBlockQuery {})
+            .await
+            .context("last_finalized_block grpc error")?
+            .into_inner()
+            .message
+            .context("missing last_finalized_block responce")?;
+
+        let block = match resp {
+            last_finalized_block_response::Message::BlockInfo(block) => block,
+            last_finalized_block_response::Message::Error(err) => {
+                return Err(anyhow!("last_finalized_block error: {err:?}"));
+            }
+        };
+        block
+            .block_info
+            .context("missing block_info in last_finalized_block")
+    }
+
+    /// Deploys `code` valid after the last finalized block, so it can be retried once expired
+    pub async fn submit_deploy(&mut self, code: String) -> anyhow::Result<DeployHandle> {
+        let deploy = self.sign_deploy(code).await?;
+        self.send_deploy(deploy).await
+    }
+
+    /// Signs `code` like [`Client::submit_deploy`] without sending it, so that its deploy id can
+    /// be recorded before the node may see it
+    pub async fn sign_deploy(&mut self, code: String) -> anyhow::Result<SignedDeploy> {
+        let valid_after_block_number = self.last_finalized_block_number().await?;
+        let options = DeployOptions {
+            valid_after_block_number,
+            ..self.deploy_options.clone()
+        };
+        let msg = self.sign_deploy_msg(code.clone(), &options).await?;
+        Ok(SignedDeploy {
+            handle: DeployHandle {
+                deploy_id: hex::encode(&msg.sig),
+                code,
+                valid_after_block_number,
+                attempts: 1,
+            },
+            msg,
+        })
+    }
+
+    /// Sends a deploy signed with [`Client::sign_deploy`]. If this fails with anything but
+    /// [`DeployRejected`](super::DeployRejected) the node may still have received it.
+    pub async fn send_deploy(&mut self, deploy: SignedDeploy) -> anyhow::Result<DeployHandle> {
+        self.send_deploy_msg(deploy.msg).await?;
+        Ok(deploy.handle)
+    }
+
+    /// Resubmits the deploy with a fresh `valid_after_block_number`, the deploy id changes
+    pub async fn resubmit_deploy(&mut self, handle: DeployHandle) -> anyhow::Result<DeployHandle> {
+        let attempts = handle.attempts;
+        let handle = self.submit_deploy(handle.code).await?;
+        Ok(DeployHandle {
+            attempts: attempts + 1,
+            ..handle
+        })
+    }
+
+    /// Status of the deploy `deploy_id`, submitted valid after `valid_after_block_number`
+    pub async fn deploy_status(
+        &mut self,
+        deploy_id: &str,
+        valid_after_block_number: i64,
+        deploy_lifespan: i64,
+    ) -> anyhow::Result<DeployStatus> {
+        let resp = self
+            .deploy_client
+            .find_deploy(FindDeployQuery {
+                deploy_id: hex::decode(deploy_id)?,
+            })
+            .await
+            .context("find_deploy grpc error")?
+            .into_inner()
+            .message
+            .context("missing find_deploy responce")?;
+
+        let block_hash = match resp {
+            find_deploy_response::Message::BlockInfo(block) => block.block_hash,
+            // the node answers with an error until the deploy is added to a block
+            find_deploy_response::Message::Error(_) => {
+                let last_finalized = self.last_finalized_block_number().await?;
+                if deploy_expired(last_finalized, valid_after_block_number, deploy_lifespan) {
+                    return Ok(DeployStatus::Expired);
+                }
+                return Ok(DeployStatus::Pending);
+            }
+        };
+
+        let block = self.deploy_block(&block_hash, deploy_id).await?;
+
+        let resp = self
+            .deploy_client
+            .is_finalized(IsFinalizedQuery { hash: block_hash })
+            .await
+            .context("is_finalized grpc error")?
+            .into_inner()
+            .message
+            .context("missing is_finalized responce")?;
+
+        match resp {
+            is_finalized_response::Message::IsFinalized(true) => Ok(DeployStatus::Finalized(block)),
+            is_finalized_response::Message::IsFinalized(false) => Ok(DeployStatus::Included(block)),
+            is_finalized_response::Message::Error(err) => {
+                Err(anyhow!("is_finalized error: {err:?}"))
+            }
+        }
+    }
+
+    async fn deploy_block(
+        &mut self,
+        block_hash: &str,
+        deploy_id: &str,
+    ) -> anyhow::Result<DeployBlock> {
+        let resp = self
+            .deploy_client
+            .get_block(BlockQuery {
+                hash: block_hash.to_string(),
+            })
+            .await
+            .context("get_block grpc error")?
+            .into_inner()
+            .message
+            .context("missing get_block responce")?;
+
+        let block = match resp {
+            block_response::Message::BlockInfo(block) => block,
+            block_response::Message::Error(err) => return Err(anyhow!("get_block error: {err:?}")),
+        };
+        let block_number = block
+            .block_info
+            .map(|block_info| block_info.block_number)
+            .context("missing block_info in get_block")?;
+        let deploy = block
+            .deploys
+            .into_iter()
+            .find(|deploy| deploy.sig == deploy_id)
+            .context("deploy is missing from its block")?;
+
+        Ok(DeployBlock {
+            block_hash: block_hash.to_string(),
+            block_number,
+            cost: deploy.cost,
+            errored: deploy.errored,
+            system_deploy_error: Some(deploy.system_deploy_error).filter(|err| !err.is_empty()),
+        })
+    }
+
+    /// Polls the deploy until it reaches `options.wait_for`, resubmitting it whenever it expires.
+    ///
+    /// Returns the handle of the last submission together with the block the deploy landed in. A
+    /// deploy that failed during execution is still returned, check `DeployBlock::errored`.
+    pub async fn wait_for_deploy(
+        &mut self,
+        handle: DeployHandle,
+        options: &DeployTrackerOptions,
+    ) -> anyhow::Result<(DeployHandle, DeployBlock)> {
+        self.wait_for_deploy_with(handle, options, |_| ()).await
+    }
+
+    /// [`Client::wait_for_deploy`], calling `resubmitted` with the handle of every resubmission
+    /// so that callers can keep track of the current deploy id
+    pub async fn wait_for_deploy_with(
+        &mut self,
+        mut handle: DeployHandle,
+        options: &DeployTrackerOptions,
+        mut resubmitted: impl FnMut(&DeployHandle),
+    ) -> anyhow::Result<(DeployHandle, DeployBlock)> {
+        let mut wait = DeployWait::new(options, Instant::now());
+        loop {
+            let status = self
+                .deploy_status(
+                    &handle.deploy_id,
+                    handle.valid_after_block_number,
+                    options.deploy_lifespan,
+                )
+                .await?;
+            match wait.next(&handle, status, Instant::now())? {
+                Next::Done(block) => return Ok((handle, block)),
+                Next::Resubmit => {
+                    tracing::warn!("deploy {} expired, resubmitting", handle.deploy_id);
+                    handle = self.resubmit_deploy(handle).await?;
+                    resubmitted(&handle);
+                    if options.propose {
+                        self.try_propose().await;
+                    }
+                }
+                Next::Wait => tokio::time::sleep(options.poll_interval).await,
+            }
+        }
+    }
+
+    /// Submits `code` and waits for it with [`Client::wait_for_deploy`]
+    pub async fn deploy_and_wait(
+        &mut self,
+        code: String,
+        options: &DeployTrackerOptions,
+    ) -> anyhow::Result<DeployBlock> {
+        let handle = self.submit_deploy(code).await.context("deploy error")?;
+        if options.propose {
+            self.try_propose().await;
+        }
+        let (_, block) = self.wait_for_deploy(handle, options).await?;
+        Ok(block)
+    }
+
+    // a failed propose is not fatal, the deploy stays in the pool until another block is created
+    async fn try_propose(&mut self) {
+        if let Err(err) = self.propose().await {
+            tracing::warn!("propose failed: {err:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(attempts: u32) -> DeployHandle {
+        DeployHandle {
+            deploy_id: "deploy".to_string(),
+            code: "Nil".to_string(),
+            valid_after_block_number: 10,
+            attempts,
+        }
+    }
+
+    fn block(block_hash: &str) -> DeployBlock {
+        DeployBlock {
+            block_hash: block_hash.to_string(),
+            block_number: 11,
+            cost: 100,
+            errored: false,
+            system_deploy_error: None,
+        }
+    }
+
+    fn options(wait_for: WaitFor) -> DeployTrackerOptions {
+        DeployTrackerOptions {
+            wait_for,
+            max_retries: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn deploys_expire_once_their_lifespan_is_finalized() {
+        assert!(!deploy_expired(59, 10, 50));
+        assert!(deploy_expired(60, 10, 50));
+        assert!(deploy_expired(61, 10, 50));
+    }
+
+    #[test]
+    fn waits_for_finalization_after_inclusion() {
+        let options = options(WaitFor::Finalization);
+        let now = Instant::now();
+        let mut wait = DeployWait::new(&options, now);
+        let next = wait.next(&handle(1), DeployStatus::Included(block("a")), now);
+        assert_eq!(next.unwrap(), Next::Wait);
+        assert_eq!(wait.included_in.as_deref(), Some("a"));
+        // the block was orphaned and the deploy went back to the pool
+        let next = wait.next(&handle(1), DeployStatus::Pending, now);
+        assert_eq!(next.unwrap(), Next::Wait);
+        assert_eq!(wait.included_in, None);
+        let next = wait.next(&handle(1), DeployStatus::Finalized(block("b")), now);
+        assert_eq!(next.unwrap(), Next::Done(block("b")));
+    }
+
+    #[test]
+    fn returns_on_inclusion_when_asked() {
+        let options = options(WaitFor::Inclusion);
+        let now = Instant::now();
+        let mut wait = DeployWait::new(&options, now);
+        let next = wait.next(&handle(1), DeployStatus::Included(block("a")), now);
+        assert_eq!(next.unwrap(), Next::Done(block("a")));
+    }
+
+    #[test]
+    fn resubmits_expired_deploys_until_out_of_retries() {
+        let options = options(WaitFor::Inclusion);
+        let now = Instant::now();
+        let mut wait = DeployWait::new(&options, now);
+        for attempts in 1..=2 {
+            let next = wait.next(&handle(attempts), DeployStatus::Expired, now);
+            assert_eq!(next.unwrap(), Next::Resubmit);
+        }
+        let error = wait
+            .next(&handle(3), DeployStatus::Expired, now)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "deploy deploy expired after 3 attempts");
+    }
+
+    #[test]
+    fn times_out_while_the_deploy_is_not_done() {
+        let options = options(WaitFor::Finalization);
+        let started = Instant::now();
+        let mut wait = DeployWait::new(&options, started);
+        let late = started + options.timeout;
+        let error = wait
+            .next(&handle(1), DeployStatus::Pending, late)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "timed out waiting for deploy deploy");
+        let error = wait
+            .next(&handle(1), DeployStatus::Included(block("a")), late)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "timed out waiting for deploy deploy");
+        // a deploy that made it is returned however late it's seen
+        let next = wait.next(&handle(1), DeployStatus::Finalized(block("a")), late);
+        assert_eq!(next.unwrap(), Next::Done(block("a")));
+        assert_eq!(
+            wait.next(&handle(1), DeployStatus::Expired, late).unwrap(),
+            Next::Resubmit
+        );
+    }
+}
@@ -1,27 +1,39 @@
 use anyhow::Context;
 use sailfish::TemplateSimple;
 
+use crate::models::rhoapi::Par;
+use crate::par::FromPar;
+use crate::rholang::ToRholang;
+use crate::transaction::OperationHeader;
+
+// Template fields hold already encoded Rholang literals and are rendered raw with `<%- %>`
+
 #[derive(TemplateSimple)]
 #[template(path = "check_balance.rho")]
 #[template(rm_whitespace = true)]
-struct CheckBallanceTemplate<'a> {
-    wallet_address: &'a str,
+struct CheckBallanceTemplate {
+    header: String,
+    wallet_address: String,
 }
 
 #[derive(TemplateSimple)]
 #[template(path = "set_transfer.rho")]
 #[template(rm_whitespace = true)]
-struct SetTransferTemplate<'a> {
-    wallet_address_from: &'a str,
-    wallet_address_to: &'a str,
-    amount: u128,
-    description: &'a str,
+struct SetTransferTemplate {
+    header: String,
+    wallet_address_from: String,
+    wallet_address_to: String,
+    amount: String,
+    description: String,
 }
 
 pub fn check_balance_rho(wallet_address: &str) -> anyhow::Result<String> {
-    CheckBallanceTemplate { wallet_address }
-        .render_once()
-        .context("failed to render check_balance_rho")
+    CheckBallanceTemplate {
+        header: OperationHeader::new("CHECK_BALANCE", vec![wallet_address.to_string()]).encode(),
+        wallet_address: wallet_address.to_rholang(),
+    }
+    .render_once()
+    .context("failed to render check_balance_rho")
 }
 
 pub fn set_transfer_rho(
@@ -30,12 +42,53 @@ pub fn set_transfer_rho(
     amount: u128,
     description: Option<String>,
 ) -> anyhow::Result<String> {
+    let description = description.unwrap_or_default();
+    let rho_amount = i64::try_from(amount).context("transfer amount is too large")?;
     SetTransferTemplate {
-        wallet_address_from,
-        wallet_address_to,
-        amount,
-        description: &description.unwrap_or_default(),
+        header: OperationHeader::new(
+            "SET_TRANSFER",
+            vec![
+                wallet_address_from.to_string(),
+                wallet_address_to.to_string(),
+                amount.to_string(),
+                description.clone(),
+            ],
+        )
+        .encode(),
+        wallet_address_from: wallet_address_from.to_rholang(),
+        wallet_address_to: wallet_address_to.to_rholang(),
+        amount: rho_amount.to_rholang(),
+        description: description.to_rholang(),
     }
     .render_once()
     .context("failed to render set_transfer_rho")
 }
+
+/// Whether a `set_transfer_rho` deploy moved the funds, given what it sent on its deploy id.
+///
+/// The deploy lands in a block even when the vault refuses the transfer, the outcome is sent as
+/// `(true, description)` or `(false, error)`. Anything else, including nothing at all, didn't move
+/// funds.
+pub fn set_transfer_succeeded(result: Option<Par>) -> bool {
+    result
+        .and_then(|result| <(bool, Par)>::from_par(result).ok())
+        .is_some_and(|(succeeded, _)| succeeded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::par::ToPar;
+
+    #[test]
+    fn only_transfers_reported_as_successful_succeeded() {
+        assert!(set_transfer_succeeded(Some(
+            (true, "rent".to_string()).to_par()
+        )));
+        assert!(!set_transfer_succeeded(Some(
+            (false, "Insufficient funds".to_string()).to_par()
+        )));
+        assert!(!set_transfer_succeeded(Some("ok".to_string().to_par())));
+        assert!(!set_transfer_succeeded(None));
+    }
+}
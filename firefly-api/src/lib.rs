@@ -1,13 +1,19 @@
+// lets `#[derive(FromPar, ToPar)]` refer to `::firefly_api` from inside this crate
+extern crate self as firefly_api;
+
 pub mod client;
 pub mod communication_service;
 mod contracts;
 pub mod models;
+pub mod par;
 pub mod providers;
 pub mod read_node_client;
 pub mod repositories;
+pub mod rholang;
 pub mod transaction;
 pub mod write_node_client;
 
 pub use client::Client;
 pub use communication_service::CommunicationService;
+pub use contracts::set_transfer_succeeded;
 pub use read_node_client::ReadNodeClient;
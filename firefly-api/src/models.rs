@@ -4,9 +4,9 @@
     clippy::enum_variant_names
 )]
 
-use anyhow::Context;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+
+use crate::client::tracker::DeployBlock;
 
 pub mod servicemodelapi {
     tonic::include_proto!("servicemodelapi");
@@ -29,26 +29,20 @@ pub struct TransferResult {
     pub cost: u64,
     pub errored: bool,
     pub system_deploy_error: Option<String>,
+    /// Whether the vault moved the funds, a refused transfer doesn't make the deploy error
+    pub succeeded: bool,
 }
 
 impl TransferResult {
-    pub fn new(block_data: Value) -> anyhow::Result<Self> {
-        let cost = block_data
-            .get("cost")
-            .and_then(Value::as_u64)
-            .context("cost not found")?;
-        let errored = block_data
-            .get("errored")
-            .and_then(Value::as_bool)
-            .context("errored not found")?;
-        let system_deploy_error = block_data
-            .get("system_deploy_error")
-            .and_then(Value::as_str)
-            .map(ToString::to_string);
-        Ok(Self {
-            cost,
-            errored,
-            system_deploy_error,
-        })
+    /// `succeeded` is the outcome the transfer reported, see [`set_transfer_succeeded`]
+    ///
+    /// [`set_transfer_succeeded`]: crate::set_transfer_succeeded
+    pub fn new(block: DeployBlock, succeeded: bool) -> Self {
+        Self {
+            cost: block.cost,
+            errored: block.errored,
+            system_deploy_error: block.system_deploy_error,
+            succeeded: succeeded && !block.errored,
+        }
     }
 }
@@ -0,0 +1,699 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+
+use anyhow::{Context, anyhow, bail};
+pub use firefly_api_derive::{FromPar, ToPar};
+use uuid::Uuid;
+
+use crate::models::rhoapi::expr::ExprInstance;
+use crate::models::rhoapi::{EList, EMap, ESet, ETuple, Expr, KeyValuePair, Par};
+use crate::rholang::{ByteArray, ToRholang, Uri};
+
+/// Converts a Rholang process read from the chain into a Rust value.
+///
+/// Derive it for structs with `#[derive(FromPar)]`: named structs are read from a map with string
+/// keys, tuple structs from a tuple and newtypes from their only field.
+pub trait FromPar: Sized {
+    fn from_par(par: Par) -> anyhow::Result<Self>;
+}
+
+/// Converts a Rust value into a Rholang process, the inverse of [`FromPar`].
+pub trait ToPar {
+    fn to_par(&self) -> Par;
+}
+
+/// Returns true if the process has nothing but expressions
+fn only_exprs(par: &Par) -> bool {
+    par.sends.is_empty()
+        && par.receives.is_empty()
+        && par.news.is_empty()
+        && par.matches.is_empty()
+        && par.unforgeables.is_empty()
+        && par.bundles.is_empty()
+        && par.connectives.is_empty()
+}
+
+pub fn is_nil(par: &Par) -> bool {
+    par.exprs.is_empty() && only_exprs(par)
+}
+
+pub fn expr_par(expr: ExprInstance) -> Par {
+    Par {
+        exprs: vec![Expr {
+            expr_instance: Some(expr),
+        }],
+        ..Default::default()
+    }
+}
+
+/// Unwraps a process consisting of exactly one expression
+pub fn into_expr(mut par: Par) -> anyhow::Result<ExprInstance> {
+    let exprs = std::mem::take(&mut par.exprs);
+    if exprs.len() != 1 || !only_exprs(&par) {
+        bail!("unexpected par: {exprs:?} expected a single expression");
+    }
+    exprs
+        .into_iter()
+        .next()
+        .and_then(|expr| expr.expr_instance)
+        .context("missing expr_instance")
+}
+
+impl FromPar for Par {
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        Ok(par)
+    }
+}
+
+impl ToPar for Par {
+    fn to_par(&self) -> Par {
+        self.clone()
+    }
+}
+
+impl<T> ToPar for &T
+where
+    T: ToPar + ?Sized,
+{
+    fn to_par(&self) -> Par {
+        (**self).to_par()
+    }
+}
+
+impl FromPar for () {
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        if !is_nil(&par) {
+            bail!("unexpected par: {par:?} expected Nil");
+        }
+        Ok(())
+    }
+}
+
+impl ToPar for () {
+    fn to_par(&self) -> Par {
+        Par::default()
+    }
+}
+
+impl<T> FromPar for Option<T>
+where
+    T: FromPar,
+{
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        if is_nil(&par) {
+            return Ok(None);
+        }
+        T::from_par(par).map(Some)
+    }
+}
+
+impl<T> ToPar for Option<T>
+where
+    T: ToPar,
+{
+    fn to_par(&self) -> Par {
+        match self {
+            Some(value) => value.to_par(),
+            None => Par::default(),
+        }
+    }
+}
+
+impl<T> FromPar for Box<T>
+where
+    T: FromPar,
+{
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        T::from_par(par).map(Box::new)
+    }
+}
+
+impl<T> ToPar for Box<T>
+where
+    T: ToPar,
+{
+    fn to_par(&self) -> Par {
+        (**self).to_par()
+    }
+}
+
+impl FromPar for bool {
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        match into_expr(par)? {
+            ExprInstance::GBool(value) => Ok(value),
+            other => Err(anyhow!("unexpected expr type: {other:?} expected GBool")),
+        }
+    }
+}
+
+impl ToPar for bool {
+    fn to_par(&self) -> Par {
+        expr_par(ExprInstance::GBool(*self))
+    }
+}
+
+impl FromPar for i64 {
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        match into_expr(par)? {
+            ExprInstance::GInt(value) => Ok(value),
+            other => Err(anyhow!("unexpected expr type: {other:?} expected GInt")),
+        }
+    }
+}
+
+impl ToPar for i64 {
+    fn to_par(&self) -> Par {
+        expr_par(ExprInstance::GInt(*self))
+    }
+}
+
+// u8 is left out on purpose: `Vec<u8>` maps to GByteArray rather than a list of ints
+macro_rules! impl_from_par_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl FromPar for $ty {
+                fn from_par(par: Par) -> anyhow::Result<Self> {
+                    let value = i64::from_par(par)?;
+                    <$ty>::try_from(value)
+                        .with_context(|| format!("{value} is out of range for {}", stringify!($ty)))
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_to_par_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl ToPar for $ty {
+                fn to_par(&self) -> Par {
+                    i64::from(*self).to_par()
+                }
+            }
+        )*
+    };
+}
+
+impl_from_par_for_int!(i8, i16, i32, u16, u32, u64, u128, i128, isize, usize);
+impl_to_par_for_int!(i8, i16, i32, u16, u32);
+
+impl FromPar for String {
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        match into_expr(par)? {
+            ExprInstance::GString(value) => Ok(value),
+            other => Err(anyhow!("unexpected expr type: {other:?} expected GString")),
+        }
+    }
+}
+
+impl ToPar for str {
+    fn to_par(&self) -> Par {
+        expr_par(ExprInstance::GString(self.to_string()))
+    }
+}
+
+impl ToPar for String {
+    fn to_par(&self) -> Par {
+        self.as_str().to_par()
+    }
+}
+
+impl FromPar for Uri {
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        match into_expr(par)? {
+            ExprInstance::GUri(value) => Uri::new(value),
+            other => Err(anyhow!("unexpected expr type: {other:?} expected GUri")),
+        }
+    }
+}
+
+impl ToPar for Uri {
+    fn to_par(&self) -> Par {
+        expr_par(ExprInstance::GUri(self.as_str().to_string()))
+    }
+}
+
+impl FromPar for Uuid {
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        String::from_par(par)?
+            .parse()
+            .context("failed to parse uuid")
+    }
+}
+
+impl ToPar for Uuid {
+    fn to_par(&self) -> Par {
+        self.to_string().to_par()
+    }
+}
+
+impl FromPar for Vec<u8> {
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        match into_expr(par)? {
+            ExprInstance::GByteArray(value) => Ok(value),
+            other => Err(anyhow!(
+                "unexpected expr type: {other:?} expected GByteArray"
+            )),
+        }
+    }
+}
+
+impl ToPar for Vec<u8> {
+    fn to_par(&self) -> Par {
+        expr_par(ExprInstance::GByteArray(self.clone()))
+    }
+}
+
+impl FromPar for ByteArray<Vec<u8>> {
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        Vec::from_par(par).map(ByteArray)
+    }
+}
+
+impl<T> ToPar for ByteArray<T>
+where
+    T: AsRef<[u8]>,
+{
+    fn to_par(&self) -> Par {
+        expr_par(ExprInstance::GByteArray(self.0.as_ref().to_vec()))
+    }
+}
+
+fn from_pars<C, T>(pars: Vec<Par>) -> anyhow::Result<C>
+where
+    C: FromIterator<T>,
+    T: FromPar,
+{
+    pars.into_iter().map(T::from_par).collect()
+}
+
+fn list_items(par: Par) -> anyhow::Result<Vec<Par>> {
+    match into_expr(par)? {
+        ExprInstance::EListBody(list) => Ok(list.ps),
+        other => Err(anyhow!(
+            "unexpected expr type: {other:?} expected EListBody"
+        )),
+    }
+}
+
+fn set_items(par: Par) -> anyhow::Result<Vec<Par>> {
+    match into_expr(par)? {
+        ExprInstance::ESetBody(set) => Ok(set.ps),
+        other => Err(anyhow!("unexpected expr type: {other:?} expected ESetBody")),
+    }
+}
+
+fn map_items(par: Par) -> anyhow::Result<Vec<(Par, Par)>> {
+    match into_expr(par)? {
+        ExprInstance::EMapBody(map) => map
+            .kvs
+            .into_iter()
+            .map(|pair| {
+                let key = pair.key.context("missing key")?;
+                let value = pair.value.context("missing value")?;
+                Ok((key, value))
+            })
+            .collect(),
+        other => Err(anyhow!("unexpected expr type: {other:?} expected EMapBody")),
+    }
+}
+
+/// Returns the elements of a tuple, checking its arity
+pub fn tuple_items<const N: usize>(par: Par) -> anyhow::Result<[Par; N]> {
+    match into_expr(par)? {
+        ExprInstance::ETupleBody(tuple) => {
+            let len = tuple.ps.len();
+            tuple
+                .ps
+                .try_into()
+                .map_err(|_| anyhow!("unexpected tuple size: {len} expected {N}"))
+        }
+        other => Err(anyhow!(
+            "unexpected expr type: {other:?} expected ETupleBody"
+        )),
+    }
+}
+
+pub fn list_par(ps: Vec<Par>) -> Par {
+    expr_par(ExprInstance::EListBody(EList {
+        ps,
+        ..Default::default()
+    }))
+}
+
+pub fn tuple_par(ps: Vec<Par>) -> Par {
+    expr_par(ExprInstance::ETupleBody(ETuple {
+        ps,
+        ..Default::default()
+    }))
+}
+
+pub fn set_par(ps: Vec<Par>) -> Par {
+    expr_par(ExprInstance::ESetBody(ESet {
+        ps,
+        ..Default::default()
+    }))
+}
+
+pub fn map_par(kvs: impl IntoIterator<Item = (Par, Par)>) -> Par {
+    expr_par(ExprInstance::EMapBody(EMap {
+        kvs: kvs
+            .into_iter()
+            .map(|(key, value)| KeyValuePair {
+                key: Some(key),
+                value: Some(value),
+            })
+            .collect(),
+        ..Default::default()
+    }))
+}
+
+impl<T> FromPar for Vec<T>
+where
+    T: FromPar,
+{
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        from_pars(list_items(par)?)
+    }
+}
+
+impl<T> ToPar for [T]
+where
+    T: ToPar,
+{
+    fn to_par(&self) -> Par {
+        list_par(self.iter().map(ToPar::to_par).collect())
+    }
+}
+
+impl<T> ToPar for Vec<T>
+where
+    T: ToPar,
+{
+    fn to_par(&self) -> Par {
+        self.as_slice().to_par()
+    }
+}
+
+impl<T> FromPar for HashSet<T>
+where
+    T: FromPar + Eq + Hash,
+{
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        from_pars(set_items(par)?)
+    }
+}
+
+impl<T> ToPar for HashSet<T>
+where
+    T: ToPar,
+{
+    fn to_par(&self) -> Par {
+        set_par(self.iter().map(ToPar::to_par).collect())
+    }
+}
+
+impl<T> FromPar for BTreeSet<T>
+where
+    T: FromPar + Ord,
+{
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        from_pars(set_items(par)?)
+    }
+}
+
+impl<T> ToPar for BTreeSet<T>
+where
+    T: ToPar,
+{
+    fn to_par(&self) -> Par {
+        set_par(self.iter().map(ToPar::to_par).collect())
+    }
+}
+
+impl<K, V> FromPar for HashMap<K, V>
+where
+    K: FromPar + Eq + Hash,
+    V: FromPar,
+{
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        map_items(par)?
+            .into_iter()
+            .map(|(key, value)| Ok((K::from_par(key)?, V::from_par(value)?)))
+            .collect()
+    }
+}
+
+impl<K, V> ToPar for HashMap<K, V>
+where
+    K: ToPar,
+    V: ToPar,
+{
+    fn to_par(&self) -> Par {
+        map_par(
+            self.iter()
+                .map(|(key, value)| (key.to_par(), value.to_par())),
+        )
+    }
+}
+
+impl<K, V> FromPar for BTreeMap<K, V>
+where
+    K: FromPar + Ord,
+    V: FromPar,
+{
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        map_items(par)?
+            .into_iter()
+            .map(|(key, value)| Ok((K::from_par(key)?, V::from_par(value)?)))
+            .collect()
+    }
+}
+
+impl<K, V> ToPar for BTreeMap<K, V>
+where
+    K: ToPar,
+    V: ToPar,
+{
+    fn to_par(&self) -> Par {
+        map_par(
+            self.iter()
+                .map(|(key, value)| (key.to_par(), value.to_par())),
+        )
+    }
+}
+
+macro_rules! impl_par_for_tuple {
+    ($len:literal; $($name:ident),+) => {
+        impl<$($name),+> FromPar for ($($name,)+)
+        where
+            $($name: FromPar),+
+        {
+            #[allow(non_snake_case)]
+            fn from_par(par: Par) -> anyhow::Result<Self> {
+                let [$($name),+] = tuple_items::<$len>(par)?;
+                Ok(($(<$name as FromPar>::from_par($name)?,)+))
+            }
+        }
+
+        impl<$($name),+> ToPar for ($($name,)+)
+        where
+            $($name: ToPar),+
+        {
+            #[allow(non_snake_case)]
+            fn to_par(&self) -> Par {
+                let ($($name,)+) = self;
+                tuple_par(vec![$($name.to_par()),+])
+            }
+        }
+    };
+}
+
+impl_par_for_tuple!(1; A);
+impl_par_for_tuple!(2; A, B);
+impl_par_for_tuple!(3; A, B, C);
+impl_par_for_tuple!(4; A, B, C, D);
+impl_par_for_tuple!(5; A, B, C, D, E);
+
+#[doc(hidden)]
+pub mod __private {
+    pub use anyhow::Result;
+}
+
+/// Renders a ground process, such as the ones built by [`ToPar`], as Rholang source
+pub fn to_rholang(par: &Par) -> anyhow::Result<String> {
+    let mut out = String::new();
+    write_par(par, &mut out)?;
+    Ok(out)
+}
+
+fn write_par(par: &Par, out: &mut String) -> anyhow::Result<()> {
+    if !only_exprs(par) {
+        bail!("only ground values can be rendered as rholang: {par:?}");
+    }
+    if par.exprs.is_empty() {
+        out.push_str("Nil");
+    }
+    for (i, expr) in par.exprs.iter().enumerate() {
+        if i > 0 {
+            out.push_str(" | ");
+        }
+        write_expr(expr, out)?;
+    }
+    Ok(())
+}
+
+fn write_pars(ps: &[Par], open: &str, close: &str, out: &mut String) -> anyhow::Result<()> {
+    out.push_str(open);
+    for (i, par) in ps.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_par(par, out)?;
+    }
+    out.push_str(close);
+    Ok(())
+}
+
+fn write_expr(expr: &Expr, out: &mut String) -> anyhow::Result<()> {
+    match expr
+        .expr_instance
+        .as_ref()
+        .context("missing expr_instance")?
+    {
+        ExprInstance::GBool(value) => value.write_rholang(out),
+        ExprInstance::GInt(value) => value.write_rholang(out),
+        ExprInstance::GString(value) => value.write_rholang(out),
+        ExprInstance::GUri(value) => Uri::new(value.as_str())?.write_rholang(out),
+        ExprInstance::GByteArray(value) => ByteArray(value).write_rholang(out),
+        ExprInstance::EListBody(list) => write_pars(&list.ps, "[", "]", out)?,
+        ExprInstance::ETupleBody(tuple) if tuple.ps.len() == 1 => {
+            write_pars(&tuple.ps, "(", ",)", out)?
+        }
+        ExprInstance::ETupleBody(tuple) => write_pars(&tuple.ps, "(", ")", out)?,
+        ExprInstance::ESetBody(set) => write_pars(&set.ps, "Set(", ")", out)?,
+        ExprInstance::EMapBody(map) => {
+            out.push('{');
+            for (i, pair) in map.kvs.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_par(pair.key.as_ref().context("missing key")?, out)?;
+                out.push_str(": ");
+                write_par(pair.value.as_ref().context("missing value")?, out)?;
+            }
+            out.push('}');
+        }
+        other => bail!("only ground values can be rendered as rholang: {other:?}"),
+    }
+    Ok(())
+}
+
+/// Fields of a Rholang map being decoded into a struct, used by `#[derive(FromPar)]`
+#[doc(hidden)]
+pub struct StructFields {
+    type_name: &'static str,
+    fields: HashMap<String, Par>,
+}
+
+impl StructFields {
+    pub fn new(par: Par, type_name: &'static str) -> anyhow::Result<Self> {
+        let fields = HashMap::from_par(par).with_context(|| format!("invalid {type_name}"))?;
+        Ok(Self { type_name, fields })
+    }
+
+    /// Missing fields are decoded from `Nil`, so optional fields can be left out of the map
+    pub fn field<T>(&mut self, name: &str) -> anyhow::Result<T>
+    where
+        T: FromPar,
+    {
+        let type_name = self.type_name;
+        match self.fields.remove(name) {
+            Some(par) => {
+                T::from_par(par).with_context(|| format!("invalid field `{name}` of {type_name}"))
+            }
+            None => T::from_par(Par::default())
+                .map_err(|_| anyhow!("missing field `{name}` of {type_name}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, FromPar, ToPar)]
+    struct Entry {
+        #[par(rename = "block_hash")]
+        hash: String,
+        height: u32,
+        tags: BTreeSet<String>,
+        parent: Option<Box<Entry>>,
+    }
+
+    #[derive(Debug, PartialEq, FromPar, ToPar)]
+    struct Pair(bool, Option<i64>);
+
+    fn round_trip<T>(value: T)
+    where
+        T: FromPar + ToPar + PartialEq + std::fmt::Debug,
+    {
+        assert_eq!(T::from_par(value.to_par()).unwrap(), value);
+    }
+
+    #[test]
+    fn round_trips_values() {
+        round_trip((true, -3i64, "x".to_string()));
+        round_trip(vec![Some(1u32), None]);
+        round_trip(BTreeMap::from([(1i32, vec![0u8, 255]), (2, vec![])]));
+        round_trip(HashSet::from([(1i64,), (2,)]));
+        round_trip(Uri::new("rho:registry:lookup").unwrap());
+        round_trip(Pair(false, None));
+    }
+
+    #[test]
+    fn derives_struct_conversion() {
+        let entry = Entry {
+            hash: "abc".into(),
+            height: 2,
+            tags: BTreeSet::from(["a".into()]),
+            parent: Some(Box::new(Entry {
+                hash: "def".into(),
+                height: 1,
+                tags: BTreeSet::new(),
+                parent: None,
+            })),
+        };
+        round_trip(entry);
+
+        let par = map_par([
+            ("block_hash".to_par(), "abc".to_par()),
+            ("height".to_par(), 1i64.to_par()),
+            ("tags".to_par(), BTreeSet::<String>::new().to_par()),
+        ]);
+        assert_eq!(Entry::from_par(par).unwrap().parent, None);
+
+        let error = Entry::from_par(map_par([("height".to_par(), 1i64.to_par())])).unwrap_err();
+        assert_eq!(error.to_string(), "missing field `block_hash` of Entry");
+    }
+
+    #[test]
+    fn renders_ground_values_as_rholang() {
+        let value = (
+            BTreeMap::from([("a", vec![1i64, 2])]),
+            None::<bool>,
+            ByteArray([0xde, 0xad]),
+        );
+        assert_eq!(
+            to_rholang(&value.to_par()).unwrap(),
+            r#"({"a": [1, 2]}, Nil, "dead".hexToBytes())"#
+        );
+        assert_eq!(to_rholang(&(1i64,).to_par()).unwrap(), "(1,)");
+    }
+
+    #[test]
+    fn rejects_out_of_range_ints() {
+        assert!(u32::from_par((-1i64).to_par()).is_err());
+        assert!(u128::from_par("1".to_par()).is_err());
+    }
+}
@@ -1,8 +1,10 @@
+use anyhow::Context;
+
 use crate::client::Client;
+use crate::client::helpers::DeployOptions;
 use crate::read_node_client::ReadNodeClient;
 use crate::repositories::FireflyRepository;
 use crate::write_node_client::BlocksClient;
-use anyhow::Context;
 
 #[derive(Debug, Clone)]
 pub struct FireflyProvider {
@@ -10,8 +12,7 @@ pub struct FireflyProvider {
     read_node_url: String,
     deploy_service_url: String,
     propose_service_url: String,
-    wallet_address: String,
-    wallet_key: String,
+    deploy_options: DeployOptions,
 }
 
 impl FireflyProvider {
@@ -20,27 +21,32 @@ impl FireflyProvider {
         read_node_url: String,
         deploy_service_url: String,
         propose_service_url: String,
-        wallet_address: String,
-        wallet_key: String,
     ) -> Self {
         Self {
             write_node_url,
             read_node_url,
             deploy_service_url,
             propose_service_url,
-            wallet_address,
-            wallet_key,
+            deploy_options: DeployOptions::default(),
         }
     }
 
+    pub fn with_deploy_options(mut self, deploy_options: DeployOptions) -> Self {
+        self.deploy_options = deploy_options;
+        self
+    }
+
     pub async fn client(&self, wallet_key: &str) -> Result<Client, anyhow::Error> {
         let client = Client::new(
             wallet_key,
             &self.deploy_service_url,
             &self.propose_service_url,
         )
-        .await;
-        client.context("Failed to create Firefly client: ")
+        .await
+        .context("Failed to create Firefly client: ")?;
+        Ok(client
+            .with_deploy_options(self.deploy_options.clone())
+            .with_read_node(self.read_client()))
     }
 
     pub fn read_client(&self) -> ReadNodeClient {
@@ -51,7 +57,11 @@ impl FireflyProvider {
         BlocksClient::new(&self.write_node_url)
     }
 
-    pub fn firefly(&self) -> FireflyRepository {
-        FireflyRepository::new(self.clone(), &self.wallet_address, &self.wallet_key)
+    pub fn firefly<'a>(
+        &self,
+        wallet_address: &'a str,
+        wallet_key: &'a str,
+    ) -> FireflyRepository<'a> {
+        FireflyRepository::new(self.clone(), wallet_address, wallet_key)
     }
 }
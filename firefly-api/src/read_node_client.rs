@@ -1,6 +1,13 @@
-use anyhow::{anyhow, Context};
+use std::collections::BTreeMap;
+
+use anyhow::{Context, anyhow};
 use reqwest::Client as HttpClient;
-use serde_json::Value;
+use serde::Deserialize;
+
+use crate::models::rhoapi::expr::ExprInstance;
+use crate::models::rhoapi::g_unforgeable::UnfInstance;
+use crate::models::rhoapi::{GDeployId, GDeployerId, GPrivate, GUnforgeable, Par};
+use crate::par::{self, FromPar};
 
 #[derive(Debug, Clone)]
 pub struct ReadNodeClient {
@@ -19,7 +26,7 @@ impl ReadNodeClient {
         format!("{}/api/{}", self.read_node_url, read_node_method)
     }
 
-    async fn get_value(self, code: String) -> anyhow::Result<Value> {
+    async fn get_value(self, code: String) -> anyhow::Result<ExploreDeployResponse> {
         // Get the URL from the `read_node_api` function
         let url = self.read_node_api();
 
@@ -48,22 +55,160 @@ impl ReadNodeClient {
         }
     }
 
-    fn extract_data_from_response(mut json: Value) -> Option<Value> {
-        json.pointer_mut("/expr/0/ExprInt/data").map(|v| v.take())
-    }
-
+    /// Evaluates `rholang_code` on the read node and converts the first value sent to its return
+    /// channel
     pub async fn get_data<T>(self, rholang_code: String) -> anyhow::Result<T>
     where
-        T: serde::de::DeserializeOwned,
+        T: FromPar,
     {
-        let response_json: Value = self.get_value(rholang_code).await?;
+        let response = self.get_value(rholang_code).await?;
+
+        let data = response
+            .expr
+            .into_iter()
+            .next()
+            .context("explore-deploy returned no data")?;
+
+        T::from_par(data.try_into()?).context("Failed to convert response data into target type")
+    }
+
+    /// Evaluates `rholang_code` on the read node and returns the phlo it consumed
+    ///
+    /// Nothing is persisted, the cost of terms that depend on the deployer identity (such as
+    /// vault transfers) is only approximate.
+    pub async fn estimate_cost(self, rholang_code: String) -> anyhow::Result<u64> {
+        let response = self.get_value(rholang_code).await?;
+        response
+            .cost
+            .context("explore-deploy response is missing the cost")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExploreDeployResponse {
+    expr: Vec<RhoExpr>,
+    cost: Option<u64>,
+}
+
+/// JSON representation of a Rholang value used by the RNode web API
+#[derive(Debug, Deserialize)]
+pub(crate) enum RhoExpr {
+    ExprPar { data: Vec<RhoExpr> },
+    ExprTuple { data: Vec<RhoExpr> },
+    ExprList { data: Vec<RhoExpr> },
+    ExprSet { data: Vec<RhoExpr> },
+    ExprMap { data: BTreeMap<String, RhoExpr> },
+    ExprBool { data: bool },
+    ExprInt { data: i64 },
+    ExprString { data: String },
+    ExprUri { data: String },
+    ExprBytes { data: String },
+    ExprUnforg { data: RhoUnforg },
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) enum RhoUnforg {
+    UnforgPrivate { data: String },
+    UnforgDeploy { data: String },
+    UnforgDeployer { data: String },
+}
+
+fn try_into_pars(data: Vec<RhoExpr>) -> anyhow::Result<Vec<Par>> {
+    data.into_iter().map(TryInto::try_into).collect()
+}
+
+impl TryFrom<RhoExpr> for Par {
+    type Error = anyhow::Error;
+
+    fn try_from(value: RhoExpr) -> anyhow::Result<Self> {
+        let par = match value {
+            RhoExpr::ExprPar { data } => {
+                try_into_pars(data)?
+                    .into_iter()
+                    .fold(Par::default(), |mut acc, par| {
+                        acc.sends.extend(par.sends);
+                        acc.receives.extend(par.receives);
+                        acc.news.extend(par.news);
+                        acc.exprs.extend(par.exprs);
+                        acc.matches.extend(par.matches);
+                        acc.unforgeables.extend(par.unforgeables);
+                        acc.bundles.extend(par.bundles);
+                        acc.connectives.extend(par.connectives);
+                        acc
+                    })
+            }
+            RhoExpr::ExprTuple { data } => par::tuple_par(try_into_pars(data)?),
+            RhoExpr::ExprList { data } => par::list_par(try_into_pars(data)?),
+            RhoExpr::ExprSet { data } => par::set_par(try_into_pars(data)?),
+            // the web API renders map keys as strings
+            RhoExpr::ExprMap { data } => par::map_par(
+                data.into_iter()
+                    .map(|(key, value)| {
+                        Ok((par::expr_par(ExprInstance::GString(key)), value.try_into()?))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            ),
+            RhoExpr::ExprBool { data } => par::expr_par(ExprInstance::GBool(data)),
+            RhoExpr::ExprInt { data } => par::expr_par(ExprInstance::GInt(data)),
+            RhoExpr::ExprString { data } => par::expr_par(ExprInstance::GString(data)),
+            RhoExpr::ExprUri { data } => par::expr_par(ExprInstance::GUri(data)),
+            RhoExpr::ExprBytes { data } => {
+                par::expr_par(ExprInstance::GByteArray(hex::decode(data)?))
+            }
+            RhoExpr::ExprUnforg { data } => {
+                let unf_instance = match data {
+                    RhoUnforg::UnforgPrivate { data } => UnfInstance::GPrivateBody(GPrivate {
+                        id: hex::decode(data)?,
+                    }),
+                    RhoUnforg::UnforgDeploy { data } => UnfInstance::GDeployIdBody(GDeployId {
+                        sig: hex::decode(data)?,
+                    }),
+                    RhoUnforg::UnforgDeployer { data } => {
+                        UnfInstance::GDeployerIdBody(GDeployerId {
+                            public_key: hex::decode(data)?,
+                        })
+                    }
+                };
+                Par {
+                    unforgeables: vec![GUnforgeable {
+                        unf_instance: Some(unf_instance),
+                    }],
+                    ..Default::default()
+                }
+            }
+        };
+        Ok(par)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
 
-        let data_value = Self::extract_data_from_response(response_json)
-            .context("Failed to extract data from response structure")?;
+    use super::*;
 
-        let parsed_data: T = serde_json::from_value(data_value)
-            .context("Failed to deserialize response data into target type")?;
+    #[test]
+    fn converts_explore_deploy_response() {
+        let response: ExploreDeployResponse = serde_json::from_str(
+            r#"{"expr":[{"ExprMap":{"data":{
+                "balance":{"ExprInt":{"data":100}},
+                "owner":{"ExprTuple":{"data":[{"ExprString":{"data":"a"}},{"ExprBytes":{"data":"dead"}}]}}
+            }}}],"block":{}}"#,
+        )
+        .unwrap();
+        let par: Par = response
+            .expr
+            .into_iter()
+            .next()
+            .unwrap()
+            .try_into()
+            .unwrap();
 
-        Ok(parsed_data)
+        let mut map: HashMap<String, Par> = FromPar::from_par(par).unwrap();
+        assert_eq!(u128::from_par(map.remove("balance").unwrap()).unwrap(), 100);
+        assert_eq!(
+            <(String, Vec<u8>)>::from_par(map.remove("owner").unwrap()).unwrap(),
+            ("a".to_string(), vec![0xde, 0xad])
+        );
     }
 }
@@ -1,7 +1,15 @@
-use crate::contracts::{check_balance_rho, set_transfer_rho};
+use crate::client::helpers::DeployOptions;
+use crate::client::tracker::{
+    DeployHandle,
+    DeployStatus,
+    DeployTrackerOptions,
+    SignedDeploy,
+    WaitFor,
+};
+use crate::client::Client;
+use crate::contracts::{check_balance_rho, set_transfer_rho, set_transfer_succeeded};
 use crate::models::TransferResult;
 use crate::providers::FireflyProvider;
-use crate::transaction::Transaction;
 use anyhow::Context;
 
 /// Repository for interacting with the Firefly blockchain
@@ -56,7 +64,7 @@ impl<'a> FireflyRepository<'a> {
         Ok(data)
     }
 
-    /// Initiates a transfer request to another wallet
+    /// Initiates a transfer request to another wallet and waits for it to be added to a block
     ///
     /// # Arguments
     /// * `wallet_address_to` - The recipient's wallet address
@@ -72,39 +80,129 @@ impl<'a> FireflyRepository<'a> {
         amount: u128,
         description: Option<String>,
     ) -> anyhow::Result<TransferResult> {
+        let handle = self
+            .submit_transfer(wallet_address_to, amount, description)
+            .await?;
+        self.wait_for_transfer(handle).await
+    }
+
+    /// Submits a transfer to another wallet without waiting for it
+    ///
+    /// # Returns
+    /// * `Ok(DeployHandle)` - The submitted deploy, to poll with [`Self::transfer_status`]
+    /// * `Err` - If the transfer couldn't be submitted
+    pub async fn submit_transfer(
+        &self,
+        wallet_address_to: &str,
+        amount: u128,
+        description: Option<String>,
+    ) -> anyhow::Result<DeployHandle> {
+        let deploy = self
+            .sign_transfer(wallet_address_to, amount, description)
+            .await?;
+        self.send_transfer(deploy).await
+    }
+
+    /// Signs a transfer without sending it, so its deploy id can be recorded before the node may
+    /// see it. Send it with [`Self::send_transfer`].
+    pub async fn sign_transfer(
+        &self,
+        wallet_address_to: &str,
+        amount: u128,
+        description: Option<String>,
+    ) -> anyhow::Result<SignedDeploy> {
         let set_transfer = set_transfer_rho(
             &self.get_wallet_address(),
             wallet_address_to,
             amount,
             description,
         )?;
-        let wallet_key = self.get_wallet_key();
-        let mut client = self.provider.client(&wallet_key).await?;
-        let block_client = self.provider.write_client();
+        let mut client = self.transfer_client().await?;
+        client
+            .sign_deploy(set_transfer)
+            .await
+            .context("Failed to sign transfer code: ")
+    }
 
-        let deploy_response = client.deploy(set_transfer).await;
-        let sid = deploy_response.context("Failed to deploy transfer code: ")?;
+    /// Sends a transfer signed with [`Self::sign_transfer`]. Unless the error is a
+    /// [`DeployRejected`](crate::client::DeployRejected), the node may have received the transfer
+    /// anyway.
+    pub async fn send_transfer(&self, deploy: SignedDeploy) -> anyhow::Result<DeployHandle> {
+        let mut client = self.transfer_client().await?;
+        let handle = client
+            .send_deploy(deploy)
+            .await
+            .context("Failed to deploy transfer code: ")?;
+        if transfer_tracker_options().propose {
+            if let Err(err) = client.propose().await {
+                tracing::warn!("propose failed: {err:#}");
+            }
+        }
+        Ok(handle)
+    }
 
-        let block_hash = client.propose().await;
-        let block_hash = block_hash.context("Failed to propose transfer code: ")?;
+    /// Waits for a submitted transfer to be added to a block, resubmitting it if it expires
+    pub async fn wait_for_transfer(&self, handle: DeployHandle) -> anyhow::Result<TransferResult> {
+        self.wait_for_transfer_with(handle, |_| ()).await
+    }
 
-        let response_block = block_client.get_deploy_results(&block_hash, &sid).await?;
+    /// [`Self::wait_for_transfer`], calling `resubmitted` with every resubmission of an expired
+    /// transfer, the deploy id changes each time
+    pub async fn wait_for_transfer_with(
+        &self,
+        handle: DeployHandle,
+        resubmitted: impl FnMut(&DeployHandle),
+    ) -> anyhow::Result<TransferResult> {
+        let mut client = self.transfer_client().await?;
+        let (handle, block) = client
+            .wait_for_deploy_with(handle, &transfer_tracker_options(), resubmitted)
+            .await
+            .context("Failed to deploy transfer code: ")?;
+        // the vault reports a refused transfer on the deploy id, the deploy itself doesn't error
+        let result = self
+            .provider
+            .write_client()
+            .get_deploy_data(&block.block_hash, &handle.deploy_id)
+            .await
+            .context("Failed to read transfer result: ")?;
+        Ok(TransferResult::new(block, set_transfer_succeeded(result)))
+    }
 
-        TransferResult::new(response_block)
+    /// Current status of a transfer submitted with [`Self::submit_transfer`]
+    pub async fn transfer_status(
+        &self,
+        deploy_id: &str,
+        valid_after_block_number: i64,
+    ) -> anyhow::Result<DeployStatus> {
+        let wallet_key = self.get_wallet_key();
+        let mut client = self.provider.client(&wallet_key).await?;
+        client
+            .deploy_status(
+                deploy_id,
+                valid_after_block_number,
+                transfer_tracker_options().deploy_lifespan,
+            )
+            .await
     }
 
-    /// Retrieves all transactions for the wallet
-    ///
-    /// # Returns
-    /// * `Ok(Vec<Transaction>)` - List of transactions associated with the wallet
-    /// * `Err` - If retrieving transactions fails
-    pub async fn get_transactions(&self) -> anyhow::Result<Vec<Transaction>> {
-        let client = self.provider.write_client();
-        let raw_transactions = client.get_transactions().await?;
-        let transactions = raw_transactions
-            .into_iter()
-            .map(|data| Transaction::new(data))
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(transactions)
+    /// Client deploying from this wallet. Transfers move funds out of the deployer's vault, which
+    /// a cost estimate can't see, so they always get the fixed phlo limit.
+    async fn transfer_client(&self) -> anyhow::Result<Client> {
+        let client = self.provider.client(self.get_wallet_key()).await?;
+        let options = client.deploy_options();
+        let options = DeployOptions {
+            phlo_limit: options.phlo_limit.fixed(),
+            ..options.clone()
+        };
+        Ok(client.with_deploy_options(options))
+    }
+}
+
+// the transfer result is known as soon as the deploy is in a block, finalization can take much
+// longer
+fn transfer_tracker_options() -> DeployTrackerOptions {
+    DeployTrackerOptions {
+        wait_for: WaitFor::Inclusion,
+        ..Default::default()
     }
 }
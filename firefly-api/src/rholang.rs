@@ -0,0 +1,302 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use anyhow::anyhow;
+
+/// Encodes a Rust value as a Rholang literal that can be spliced into contract source.
+///
+/// Every implementation produces a self-delimiting expression, so user supplied values can never
+/// terminate the surrounding term or inject code.
+pub trait ToRholang {
+    fn write_rholang(&self, out: &mut String);
+
+    fn to_rholang(&self) -> String {
+        let mut out = String::new();
+        self.write_rholang(&mut out);
+        out
+    }
+}
+
+impl<T> ToRholang for &T
+where
+    T: ToRholang + ?Sized,
+{
+    fn write_rholang(&self, out: &mut String) {
+        (**self).write_rholang(out)
+    }
+}
+
+impl ToRholang for () {
+    fn write_rholang(&self, out: &mut String) {
+        out.push_str("Nil");
+    }
+}
+
+impl ToRholang for bool {
+    fn write_rholang(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+}
+
+impl ToRholang for i64 {
+    fn write_rholang(&self, out: &mut String) {
+        if *self == i64::MIN {
+            // the positive part of i64::MIN does not fit into a Rholang Int literal
+            out.push_str("(-9223372036854775807 - 1)");
+        } else {
+            out.push_str(&self.to_string());
+        }
+    }
+}
+
+// Rholang Int is a signed 64 bit integer, wider types must be converted with `i64::try_from`
+macro_rules! impl_to_rholang_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl ToRholang for $ty {
+                fn write_rholang(&self, out: &mut String) {
+                    i64::from(*self).write_rholang(out)
+                }
+            }
+        )*
+    };
+}
+
+impl_to_rholang_for_int!(i8, i16, i32, u8, u16, u32);
+
+impl ToRholang for str {
+    fn write_rholang(&self, out: &mut String) {
+        out.reserve(self.len() + 2);
+        out.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+}
+
+impl ToRholang for String {
+    fn write_rholang(&self, out: &mut String) {
+        self.as_str().write_rholang(out)
+    }
+}
+
+/// Byte array literal, rendered as `"<hex>".hexToBytes()` since Rholang has no native syntax for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteArray<T>(pub T);
+
+impl<T> ToRholang for ByteArray<T>
+where
+    T: AsRef<[u8]>,
+{
+    fn write_rholang(&self, out: &mut String) {
+        out.push('"');
+        out.push_str(&hex::encode(self.0.as_ref()));
+        out.push_str("\".hexToBytes()");
+    }
+}
+
+/// Rholang URI literal such as `` `rho:registry:lookup` ``
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uri(String);
+
+impl Uri {
+    /// URI literals have no escape sequences, so backticks and backslashes are rejected
+    pub fn new(uri: impl Into<String>) -> anyhow::Result<Self> {
+        let uri = uri.into();
+        if uri.is_empty() || uri.contains(['`', '\\']) || uri.contains(char::is_control) {
+            return Err(anyhow!("invalid rholang uri: {uri:?}"));
+        }
+        Ok(Self(uri))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl ToRholang for Uri {
+    fn write_rholang(&self, out: &mut String) {
+        out.push('`');
+        out.push_str(&self.0);
+        out.push('`');
+    }
+}
+
+impl<T> ToRholang for Option<T>
+where
+    T: ToRholang,
+{
+    fn write_rholang(&self, out: &mut String) {
+        match self {
+            Some(value) => value.write_rholang(out),
+            None => out.push_str("Nil"),
+        }
+    }
+}
+
+fn write_sequence<I, T>(out: &mut String, open: &str, close: &str, items: I)
+where
+    I: IntoIterator<Item = T>,
+    T: ToRholang,
+{
+    out.push_str(open);
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        item.write_rholang(out);
+    }
+    out.push_str(close);
+}
+
+fn write_map<'a, I, K, V>(out: &mut String, entries: I)
+where
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+    K: ToRholang + 'a,
+    V: ToRholang + 'a,
+{
+    out.push('{');
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        key.write_rholang(out);
+        out.push_str(": ");
+        value.write_rholang(out);
+    }
+    out.push('}');
+}
+
+impl<T> ToRholang for [T]
+where
+    T: ToRholang,
+{
+    fn write_rholang(&self, out: &mut String) {
+        write_sequence(out, "[", "]", self)
+    }
+}
+
+impl<T> ToRholang for Vec<T>
+where
+    T: ToRholang,
+{
+    fn write_rholang(&self, out: &mut String) {
+        self.as_slice().write_rholang(out)
+    }
+}
+
+impl<T> ToRholang for HashSet<T>
+where
+    T: ToRholang,
+{
+    fn write_rholang(&self, out: &mut String) {
+        write_sequence(out, "Set(", ")", self)
+    }
+}
+
+impl<T> ToRholang for BTreeSet<T>
+where
+    T: ToRholang,
+{
+    fn write_rholang(&self, out: &mut String) {
+        write_sequence(out, "Set(", ")", self)
+    }
+}
+
+impl<K, V> ToRholang for HashMap<K, V>
+where
+    K: ToRholang,
+    V: ToRholang,
+{
+    fn write_rholang(&self, out: &mut String) {
+        write_map(out, self)
+    }
+}
+
+impl<K, V> ToRholang for BTreeMap<K, V>
+where
+    K: ToRholang,
+    V: ToRholang,
+{
+    fn write_rholang(&self, out: &mut String) {
+        write_map(out, self)
+    }
+}
+
+macro_rules! impl_to_rholang_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name),+> ToRholang for ($($name,)+)
+        where
+            $($name: ToRholang),+
+        {
+            #[allow(non_snake_case)]
+            fn write_rholang(&self, out: &mut String) {
+                let ($($name,)+) = self;
+                write_sequence(out, "(", ")", [$($name as &dyn ToRholang),+])
+            }
+        }
+    };
+}
+
+impl_to_rholang_for_tuple!(A, B);
+impl_to_rholang_for_tuple!(A, B, C);
+impl_to_rholang_for_tuple!(A, B, C, D);
+impl_to_rholang_for_tuple!(A, B, C, D, E);
+
+// a single element tuple needs a trailing comma to not be parsed as parentheses
+impl<A> ToRholang for (A,)
+where
+    A: ToRholang,
+{
+    fn write_rholang(&self, out: &mut String) {
+        out.push('(');
+        self.0.write_rholang(out);
+        out.push_str(",)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!("say \"hi\";\n\\ //".to_rholang(), r#""say \"hi\";\n\\ //""#);
+    }
+
+    #[test]
+    fn encodes_numbers_and_bytes() {
+        assert_eq!(42u32.to_rholang(), "42");
+        assert_eq!((-7i64).to_rholang(), "-7");
+        assert_eq!(i64::MIN.to_rholang(), "(-9223372036854775807 - 1)");
+        assert_eq!(
+            ByteArray([0xde, 0xad]).to_rholang(),
+            r#""dead".hexToBytes()"#
+        );
+    }
+
+    #[test]
+    fn encodes_collections() {
+        let map = BTreeMap::from([("a", vec![1, 2]), ("b", vec![])]);
+        assert_eq!(map.to_rholang(), r#"{"a": [1, 2], "b": []}"#);
+        assert_eq!((true, "x", None::<i32>).to_rholang(), r#"(true, "x", Nil)"#);
+        assert_eq!((1,).to_rholang(), "(1,)");
+        assert_eq!(BTreeSet::from([1, 2]).to_rholang(), "Set(1, 2)");
+    }
+
+    #[test]
+    fn validates_uris() {
+        assert_eq!(
+            Uri::new("rho:registry:lookup").unwrap().to_rholang(),
+            "`rho:registry:lookup`"
+        );
+        assert!(Uri::new("rho:`injected`").is_err());
+    }
+}
@@ -1,6 +1,63 @@
 use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use csv::ReaderBuilder;
+use serde::{Deserialize, Serialize};
+
+const OPERATION_PREFIX: &str = "//FIREFLY_OPERATION";
+const OPERATION_V2_PREFIX: &str = "//FIREFLY_OPERATION_V2;";
+
+/// Operation description stored as the first line of every Firefly deploy
+///
+/// The header is written as `//FIREFLY_OPERATION_V2;<base64 json>`, so arguments can contain any
+/// text (quotes, semicolons, new lines) without leaking out of the Rholang comment. Headers in the
+/// legacy `//FIREFLY_OPERATION;NAME;"arg";...` CSV form are still decoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationHeader {
+    pub name: String,
+    pub arguments: Vec<String>,
+}
+
+impl OperationHeader {
+    pub fn new(name: impl Into<String>, arguments: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            arguments,
+        }
+    }
+
+    /// Renders the header as a single line Rholang comment
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("operation header is always serializable");
+        format!("{OPERATION_V2_PREFIX}{}", BASE64_STANDARD.encode(json))
+    }
+
+    /// Parses a deploy term line, returns `None` if it is not an operation header
+    pub fn decode(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if let Some(payload) = line.strip_prefix(OPERATION_V2_PREFIX) {
+            let json = BASE64_STANDARD.decode(payload).ok()?;
+            return serde_json::from_slice(&json).ok();
+        }
+
+        if !line.starts_with(OPERATION_PREFIX) {
+            return None;
+        }
+        let mut csv_reader = ReaderBuilder::new()
+            .delimiter(b';')
+            .has_headers(false)
+            .from_reader(line.as_bytes());
+        let record = csv_reader.records().next()?.ok()?;
+        let mut values = record.iter().skip(1).map(ToString::to_string);
+        let name = values.next()?;
+
+        Some(Self {
+            name,
+            arguments: values.collect(),
+        })
+    }
+}
 
 /// Represents a transaction with timestamp, name, and arguments
 #[derive(Debug, Serialize)]
@@ -13,29 +70,52 @@ pub struct Transaction {
 }
 
 impl Transaction {
-    /// Creates a new Transaction from a tuple of DateTime and arguments
+    /// Creates a new Transaction from a tuple of deploy data
     ///
     /// # Arguments
-    /// * `data` - Tuple containing (DateTime<Utc>, Vec<String>)
+    /// * `data` - Tuple containing (deploy signature, DateTime<Utc>, OperationHeader, cost)
     ///
     /// # Returns
     /// * `Result<Self>` - Result containing the Transaction or an error
-    pub fn new(data: (String, DateTime<Utc>, Vec<String>, u64)) -> Result<Self> {
-        let (id, date_time, arguments, cost) = data;
-
-        if arguments.len() < 2 {
-            return Err(anyhow!(
-                "Operation signature requires at least 2 arguments, but got {}",
-                arguments.len()
-            ));
+    pub fn new(data: (String, DateTime<Utc>, OperationHeader, u64)) -> Result<Self> {
+        let (id, date_time, header, cost) = data;
+
+        if header.name.is_empty() {
+            return Err(anyhow!("Operation signature requires a name"));
         }
 
         Ok(Self {
             id,
             date_time,
-            name: arguments[1].clone(),
-            arguments: arguments[2..].to_vec(),
+            name: header.name,
+            arguments: header.arguments,
             cost: cost.to_string(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operation_header_round_trips_arbitrary_text() {
+        let header = OperationHeader::new(
+            "SET_TRANSFER",
+            vec!["a;b".into(), "\"quoted\"\n}|evil!(1)".into()],
+        );
+        let encoded = header.encode();
+        assert!(!encoded.contains('\n'));
+        assert_eq!(OperationHeader::decode(&encoded), Some(header));
+    }
+
+    #[test]
+    fn decodes_legacy_operation_header() {
+        let header =
+            OperationHeader::decode(r#"//FIREFLY_OPERATION;SET_TRANSFER;"from";"to";100;"desc""#)
+                .unwrap();
+        assert_eq!(header.name, "SET_TRANSFER");
+        assert_eq!(header.arguments, vec!["from", "to", "100", "desc"]);
+        assert_eq!(OperationHeader::decode("new x in { Nil }"), None);
+    }
+}
@@ -2,12 +2,16 @@ use std::collections::HashSet;
 
 use anyhow::{anyhow, Context};
 use chrono::{DateTime, Utc};
-use csv::ReaderBuilder;
 use reqwest::Client as HttpClient;
-use serde_json::Value;
+use serde_json::{Value, json};
+
+use crate::par::FromPar;
+use crate::read_node_client::RhoExpr;
+use crate::transaction::OperationHeader;
 
 /// Extracts and filters deploy information from a vector of JSON Values.
-/// Only processes non-errored deploys that start with "//FIREFLY_OPERATION".
+/// Only processes non-errored deploys that start with a Firefly operation header. Anyone can
+/// deploy, so deploys with a missing signature or timestamp are skipped as well.
 ///
 /// # Arguments
 /// * `deploys` - Vector of JSON Values containing deploy information
@@ -16,15 +20,17 @@ use serde_json::Value;
 /// Vector of tuples containing:
 /// * Deploy signature (String)
 /// * Timestamp (DateTime<Utc>)
-/// * CSV values extracted from the first line (Vec<String>)
+/// * Operation header decoded from the first line (OperationHeader)
+/// * Deploy cost (u64)
 ///
 /// # Processing Steps
 /// 1. Filters out errored deploys
-/// 2. Checks if term starts with "//FIREFLY_OPERATION"
-/// 3. Parses first line as CSV
-/// 4. Extracts signature and timestamp
-/// 5. Returns tuple of (signature, timestamp, csv_values)
-fn extract_filtered_deploys(deploys: Vec<Value>) -> Vec<(String, DateTime<Utc>, Vec<String>, u64)> {
+/// 2. Decodes the first non-empty line as an OperationHeader
+/// 3. Extracts signature, timestamp and cost
+/// 4. Returns tuple of (signature, timestamp, header, cost)
+fn extract_filtered_deploys(
+    deploys: Vec<Value>,
+) -> Vec<(String, DateTime<Utc>, OperationHeader, u64)> {
     deploys
         .into_iter()
         .filter_map(|deploy| {
@@ -34,47 +40,28 @@ fn extract_filtered_deploys(deploys: Vec<Value>) -> Vec<(String, DateTime<Utc>,
 
             let term = deploy["term"].as_str()?;
             let first_line = term.lines().find(|line| !line.trim().is_empty())?;
+            let header = OperationHeader::decode(first_line)?;
 
-            if !first_line.starts_with("//FIREFLY_OPERATION") {
-                return None;
-            }
-
-            let mut csv_reader = ReaderBuilder::new()
-                .delimiter(b';')
-                .has_headers(false)
-                .from_reader(first_line.as_bytes());
-            let csv_values: Vec<String> = csv_reader
-                .records()
-                .next()
-                .and_then(|record| record.ok())
-                .map(|record| record.iter().map(|s| s.to_string()).collect())
-                .unwrap_or_default();
-
-            // Usage
-            let unix_timestamp_ms = deploy["timestamp"]
-                .as_i64()
-                .expect("invalid timestamp format");
-            let datetime = DateTime::from_timestamp_millis(unix_timestamp_ms)
-                .expect("invalid unix timestamp value");
+            let unix_timestamp_ms = deploy["timestamp"].as_i64()?;
+            let datetime = DateTime::from_timestamp_millis(unix_timestamp_ms)?;
             let sig = deploy["sig"].as_str()?.to_string();
             let cost = deploy["cost"].as_u64().unwrap_or(0);
-            return Some((sig, datetime, csv_values, cost));
+            Some((sig, datetime, header, cost))
         })
         .collect()
 }
 
-/// Processes a vector of tuples containing signatures, timestamps, and CSV values.
-/// Filters out duplicate entries based on signatures, sorts by timestamp,
-/// and returns only the CSV values portion of each tuple.
+/// Processes a vector of tuples containing signatures, timestamps, and operation headers.
+/// Filters out duplicate entries based on signatures and sorts by timestamp.
 ///
 /// # Arguments
-/// * `tuples` - Vector of tuples containing (signature, timestamp, csv_values)
+/// * `tuples` - Vector of tuples containing (signature, timestamp, header, cost)
 ///
 /// # Returns
-/// Vector of tuples with timestamp as Datetime and CSV value vectors, sorted by timestamp with duplicates removed
+/// Vector of tuples sorted by timestamp with duplicates removed
 fn process_tuples(
-    tuples: Vec<(String, DateTime<Utc>, Vec<String>, u64)>,
-) -> Vec<(String, DateTime<Utc>, Vec<String>, u64)> {
+    tuples: Vec<(String, DateTime<Utc>, OperationHeader, u64)>,
+) -> Vec<(String, DateTime<Utc>, OperationHeader, u64)> {
     let mut seen_sigs = HashSet::new();
     let mut unique_tuples: Vec<_> = tuples
         .into_iter()
@@ -84,6 +71,12 @@ fn process_tuples(
     unique_tuples
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    pub block_hash: String,
+    pub block_number: i64,
+}
+
 #[derive(Debug, Clone)]
 pub struct BlocksClient {
     http_client: HttpClient,
@@ -141,45 +134,91 @@ impl BlocksClient {
         }
     }
 
-    async fn first_block_hash(&self) -> Result<String, anyhow::Error> {
+    async fn api_post(&self, method: &str, body: Value) -> Result<Value, anyhow::Error> {
+        let response = self
+            .http_client
+            .post(self.api_url(method))
+            .json(&body)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let json_data: Value = response
+                .json()
+                .await
+                .context("failed to parse response as JSON")?;
+            Ok(json_data)
+        } else {
+            Err(anyhow!(
+                "HTTP request failed with status: {}",
+                response.status()
+            ))
+        }
+    }
+
+    fn parse_block_info(block: &Value) -> anyhow::Result<BlockInfo> {
+        let block_hash = block["blockHash"]
+            .as_str()
+            .context("Failed to extract block hash value.")?;
+        let block_number = block["blockNumber"]
+            .as_i64()
+            .context("Failed to extract block number value.")?;
+        Ok(BlockInfo {
+            block_hash: block_hash.to_string(),
+            block_number,
+        })
+    }
+
+    /// Returns the most recent block known to the node
+    pub async fn get_latest_block(&self) -> anyhow::Result<BlockInfo> {
         let response = self.api_get("blocks", None, None).await?;
 
-        response
+        let block = response
             .as_array()
-            .and_then(|blocks| blocks.get(0))
-            .and_then(|block| block["blockHash"].as_str())
-            .map(|s| s.to_string())
-            .context("Failed to extract block hash value.")
+            .and_then(|blocks| blocks.first())
+            .context("Failed to extract latest block.")?;
+        Self::parse_block_info(block)
     }
 
-    async fn walk_deploys_values(
+    /// Returns every block with a height in `start..=end`
+    ///
+    /// The node caps the size of the range (50 blocks by default), callers should page through
+    /// long ranges.
+    pub async fn get_blocks_by_heights(
         &self,
-        hash: &str,
-    ) -> Result<
-        (
-            Option<Vec<String>>,
-            Vec<(String, DateTime<Utc>, Vec<String>, u64)>,
-        ),
-        anyhow::Error,
-    > {
+        start: i64,
+        end: i64,
+    ) -> anyhow::Result<Vec<BlockInfo>> {
         let response = self
-            .api_get("block", Some(hash), None)
+            .api_get("blocks", Some(&format!("{start}/{end}")), None)
             .await
-            .context("failed to get blocks")?;
+            .context("failed to get blocks by heights")?;
 
-        let parents_hash_list = response["blockInfo"]["parentsHashList"]
+        let mut blocks = response
             .as_array()
-            .map(|list| {
-                list.iter()
-                    .filter_map(|item| item.as_str().map(|s| s.to_string()))
-                    .collect::<Vec<_>>()
-            });
+            .context("missing blocks")?
+            .iter()
+            .map(Self::parse_block_info)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        blocks.sort_by_key(|block| block.block_number);
+        Ok(blocks)
+    }
+
+    /// Returns the Firefly operations deployed in a single block, sorted by deploy timestamp
+    pub async fn get_block_transactions(
+        &self,
+        hash: &str,
+    ) -> anyhow::Result<Vec<(String, DateTime<Utc>, OperationHeader, u64)>> {
+        let response = self
+            .get_block_by_hash(hash)
+            .await
+            .context("failed to get block transactions")?;
 
         let deploys = response["deploys"].as_array().context("missing deploys")?;
 
         let filtered_deploys = extract_filtered_deploys(deploys.to_vec());
 
-        Ok((parents_hash_list, filtered_deploys))
+        Ok(process_tuples(filtered_deploys))
     }
 
     pub async fn get_block_by_hash(&self, hash: &str) -> anyhow::Result<Value> {
@@ -188,6 +227,34 @@ impl BlocksClient {
             .context("failed to get block")
     }
 
+    /// Last value the deploy `deploy_id` sent on its `rho:rchain:deployId` channel in the block
+    /// `hash`, `None` if it sent nothing
+    pub async fn get_deploy_data<T>(&self, hash: &str, deploy_id: &str) -> anyhow::Result<Option<T>>
+    where
+        T: FromPar,
+    {
+        let response = self
+            .api_post(
+                "data-at-name-by-block-hash",
+                json!({
+                    "name": { "UnforgDeploy": { "data": deploy_id } },
+                    "blockHash": hash,
+                    "usePreStateHash": false,
+                }),
+            )
+            .await
+            .context("failed to get deploy data")?;
+
+        // the node answers with a `[exprs, block]` pair
+        let (exprs, _): (Vec<RhoExpr>, Value) =
+            serde_json::from_value(response).context("malformed deploy data")?;
+        exprs
+            .into_iter()
+            .last()
+            .map(|expr| T::from_par(expr.try_into()?))
+            .transpose()
+    }
+
     pub async fn get_deploy_results(&self, hash: &str, sid: &str) -> anyhow::Result<Value> {
         let block = self.get_block_by_hash(hash).await?;
         let deploys = block["deploys"].as_array().context("missing deploys")?;
@@ -198,31 +265,30 @@ impl BlocksClient {
             .to_owned();
         Ok(deploy)
     }
+}
 
-    pub async fn get_transactions(
-        &self,
-    ) -> Result<Vec<(String, DateTime<Utc>, Vec<String>, u64)>, anyhow::Error> {
-        let mut current_hash = self.first_block_hash().await?;
-        let mut result = vec![];
-        let mut hash_list: Vec<String> = vec![];
-
-        loop {
-            let (next_hash_list, values) = self.walk_deploys_values(&current_hash).await?;
-            result.extend(values.into_iter().map(|v| v));
-
-            if let Some(hashes) = next_hash_list {
-                hash_list.extend(hashes);
-            }
-
-            if let Some(hash) = hash_list.pop() {
-                current_hash = hash;
-            } else {
-                break;
-            }
-        }
-
-        let processed_values = process_tuples(result);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(processed_values)
+    #[test]
+    fn skips_deploys_without_a_valid_timestamp() {
+        let header = OperationHeader::new("SET_TRANSFER", vec![]).encode();
+        let deploy = |timestamp: Value| {
+            json!({
+                "errored": false,
+                "term": format!("{header}\nNil"),
+                "timestamp": timestamp,
+                "sig": "sig",
+                "cost": 10,
+            })
+        };
+        let deploys = extract_filtered_deploys(vec![
+            deploy(json!("yesterday")),
+            deploy(json!(i64::MAX)),
+            deploy(json!(1742817600000i64)),
+        ]);
+        assert_eq!(deploys.len(), 1);
+        assert_eq!(deploys[0].1.timestamp_millis(), 1742817600000);
     }
 }
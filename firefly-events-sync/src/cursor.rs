@@ -0,0 +1,170 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Firehose frame header, followed by the event body in the same message
+#[derive(Debug, Deserialize)]
+struct FrameHeader {
+    op: i64,
+    t: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FrameBody {
+    seq: Option<i64>,
+}
+
+/// Returns the `seq` of a `subscribeRepos` frame, `None` for frames which are not part of the
+/// sequence (`#info`)
+pub fn frame_seq(frame: &[u8]) -> anyhow::Result<Option<i64>> {
+    let mut reader = Cursor::new(frame);
+    let header: FrameHeader =
+        ciborium::de::from_reader(&mut reader).context("invalid frame header")?;
+    if header.op != 1 {
+        return Err(anyhow::anyhow!("error frame: {:?}", header.t));
+    }
+    let body: FrameBody = ciborium::de::from_reader(&mut reader).context("invalid frame body")?;
+    Ok(body.seq)
+}
+
+/// Appends the `cursor` query parameter, so the source replays every event after `cursor`
+pub fn with_cursor(events_source_url: &str, cursor: Option<i64>) -> String {
+    match cursor {
+        Some(cursor) if events_source_url.contains('?') => {
+            format!("{events_source_url}&cursor={cursor}")
+        }
+        Some(cursor) => format!("{events_source_url}?cursor={cursor}"),
+        None => events_source_url.to_string(),
+    }
+}
+
+/// Last deployed `seq`, kept in a local file. The notification of a deployed batch is kept next to
+/// it until listeners were sent it, so a crash between the two deploys doesn't lose it.
+pub struct LocalCursor {
+    path: PathBuf,
+    pending_path: PathBuf,
+}
+
+impl LocalCursor {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        Self {
+            pending_path: path.with_extension("notify"),
+            path,
+        }
+    }
+
+    pub fn load(&self) -> anyhow::Result<Option<i64>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .map(Some)
+                .with_context(|| format!("invalid cursor in {}", self.path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Written through a temporary file, so a crash never leaves a truncated cursor behind
+    pub fn store(&self, seq: i64) -> anyhow::Result<()> {
+        write_atomic(&self.path, seq.to_string().as_bytes())
+    }
+
+    pub fn load_pending<T: DeserializeOwned>(&self) -> anyhow::Result<Option<T>> {
+        match std::fs::read(&self.pending_path) {
+            Ok(value) => serde_json::from_slice(&value).map(Some).with_context(|| {
+                format!("invalid notification in {}", self.pending_path.display())
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn store_pending<T: Serialize>(&self, notification: &T) -> anyhow::Result<()> {
+        write_atomic(&self.pending_path, &serde_json::to_vec(notification)?)
+    }
+
+    pub fn clear_pending(&self) -> anyhow::Result<()> {
+        match std::fs::remove_file(&self.pending_path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_seq_from_frame() {
+        #[derive(serde::Serialize)]
+        struct Header {
+            op: i64,
+            t: &'static str,
+        }
+        #[derive(serde::Serialize)]
+        struct Body {
+            seq: i64,
+            repo: &'static str,
+        }
+
+        let mut frame = vec![];
+        ciborium::ser::into_writer(
+            &Header {
+                op: 1,
+                t: "#commit",
+            },
+            &mut frame,
+        )
+        .unwrap();
+        ciborium::ser::into_writer(
+            &Body {
+                seq: 42,
+                repo: "did:plc:test",
+            },
+            &mut frame,
+        )
+        .unwrap();
+
+        assert_eq!(frame_seq(&frame).unwrap(), Some(42));
+        assert_eq!(
+            with_cursor("ws://pds/xrpc/com.atproto.sync.subscribeRepos", Some(42)),
+            "ws://pds/xrpc/com.atproto.sync.subscribeRepos?cursor=42"
+        );
+    }
+
+    #[test]
+    fn keeps_pending_notification_until_cleared() {
+        let dir = std::env::temp_dir().join(format!("events-sync-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let local_cursor = LocalCursor::new(dir.join("events-sync.cursor"));
+
+        assert_eq!(local_cursor.load_pending::<String>().unwrap(), None);
+        local_cursor.store(42).unwrap();
+        local_cursor.store_pending(&"block".to_string()).unwrap();
+        assert_eq!(local_cursor.load().unwrap(), Some(42));
+        assert_eq!(
+            local_cursor.load_pending::<String>().unwrap().as_deref(),
+            Some("block")
+        );
+        local_cursor.clear_pending().unwrap();
+        local_cursor.clear_pending().unwrap();
+        assert_eq!(local_cursor.load_pending::<String>().unwrap(), None);
+        assert_eq!(local_cursor.load().unwrap(), Some(42));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt::Display;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::time::Duration;
 
@@ -7,6 +8,11 @@ use anyhow::Context;
 use base64::Engine;
 use base64::prelude::BASE64_STANDARD;
 use clap::{Parser, Subcommand};
+use cursor::LocalCursor;
+use firefly_api::client::args::DeployArgs;
+use firefly_api::client::tracker::{DeployTrackerOptions, WaitFor};
+use firefly_api::models::rhoapi::Par;
+use firefly_api::rholang::{ByteArray, ToRholang};
 use futures::stream::select_all;
 use futures::{FutureExt, SinkExt, Stream, StreamExt, TryStreamExt, future};
 use serde::{Deserialize, Serialize};
@@ -18,6 +24,11 @@ use tonic::transport::Server;
 use uuid::Uuid;
 use warp::Filter;
 
+mod cursor;
+
+/// Delay between attempts to deploy a batch of events
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Parser)]
 struct Args {
     /// Wallet key in hex format
@@ -36,6 +47,9 @@ struct Args {
     #[arg(long)]
     service_id: String,
 
+    #[command(flatten)]
+    deploy: DeployArgs,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -74,6 +88,10 @@ enum Commands {
         /// Max number of events in window
         #[arg(long)]
         size_threshold: usize,
+
+        /// File keeping the seq of the last deployed event, to resume from it after a restart
+        #[arg(long, default_value = "events-sync.cursor")]
+        cursor_file: PathBuf,
     },
 
     /// Initialize contract
@@ -84,13 +102,14 @@ enum Commands {
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    let mut client = firefly_api::Client::new(
+    let client = firefly_api::Client::new(
         &args.wallet_key,
         &args.deploy_service_url,
         &args.propose_service_url,
     )
     .await
     .context("failed to create firefly client")?;
+    let mut client = args.deploy.configure(client);
 
     match args.command {
         Commands::Listen {
@@ -144,82 +163,59 @@ async fn main() -> anyhow::Result<()> {
             events_source_url,
             time_threshold,
             size_threshold,
+            cursor_file,
         } => {
-            let mut index = 0;
-
-            let task = async move {
-                let binary_stream =
-                    subscribe_to_event_source(&events_source_url)
-                        .await
-                        .map_ok(|msg| {
-                            let self_index = index;
-                            index += 1;
-
-                            Entry {
-                                msg,
-                                index: self_index,
-                            }
-                        });
-
-                let binary_stream = tokio_stream::StreamExt::chunks_timeout(
-                    binary_stream,
-                    size_threshold,
-                    Duration::from_secs(time_threshold),
-                );
-
-                tokio::pin!(binary_stream);
-
-                while let Some(events) = binary_stream.next().await {
-                    let events = events.into_iter().collect::<Result<Vec<_>, _>>()?;
-                    let channel_name = Uuid::new_v4();
-                    println!("events: {}", events.len());
-                    let rho_code = rho_save_events(channel_name, events);
-                    let hash = client
-                        .full_deploy(rho_code)
-                        .await
-                        .context("failed save events")?;
-                    println!("events deployed");
-
-                    let rho_code = rho_notify_listeners(
-                        &args.service_id,
-                        &NotifyMsg {
-                            block_hash: hash,
-                            channel_name: channel_name.to_string(),
-                        },
-                    );
-                    client
-                        .full_deploy(rho_code)
-                        .await
-                        .context("failed to notify listeners")?;
-                    println!("notified");
-                }
-
-                anyhow::Ok(())
-            };
+            let local_cursor = LocalCursor::new(cursor_file);
+            let task = push_events(
+                client,
+                &args.service_id,
+                &events_source_url,
+                size_threshold,
+                Duration::from_secs(time_threshold),
+                local_cursor,
+            );
 
             select! {
-                _ = task => (),
+                result = task => result?,
                 _ = ctrl_c() => (),
             };
         }
         Commands::Init => {
-            let rho_code = rho_init_events_channels(&args.service_id);
-            let hash = client
-                .full_deploy(rho_code)
+            // push waits for the channels to be finalized, so return only once they are
+            let options = DeployTrackerOptions {
+                wait_for: WaitFor::Finalization,
+                ..Default::default()
+            };
+            let block = client
+                .deploy_and_wait(rho_init_events_channels(&args.service_id), &options)
                 .await
                 .context("failed to init channels")?;
-            println!("{hash}");
+            if block.errored {
+                anyhow::bail!(
+                    "failed to init channels: deploy errored in {}",
+                    block.block_hash
+                );
+            }
+            println!("{}", block.block_hash);
         }
     }
 
     Ok(())
 }
 
+fn rho_init_cursor(service_id: &str) -> String {
+    format!("@{}!(-1)", format!("{service_id}-cursor").to_rholang())
+}
+
 fn rho_init_events_channels(service_id: &str) -> String {
+    let listeners = format!("{service_id}-listeners").to_rholang();
+    let init_cursor = rho_init_cursor(service_id);
+    let notify_listeners = format!("{service_id}-notify-listeners").to_rholang();
     format!(
         r#"
-        @"{service_id}-listeners"!({{}})|
-        contract @"{service_id}-notify-listeners"(@payload) = {{
+        @{listeners}!({{}})|
+        {init_cursor}|
+        contract @{notify_listeners}(@payload) = {{
             new loop, grpcTell(`rho:io:grpcTell`) in {{
                 contract loop(@listeners, @payload) = {{
                     match listeners {{
@@ -230,7 +226,7 @@ fn rho_init_events_channels(service_id: &str) -> String {
                         }}
                     }}
                 }}|
-                for(@listeners <<- @"{service_id}-listeners") {{
+                for(@listeners <<- @{listeners}) {{
                     loop!(listeners.toList(), payload)
                 }}
             }}
@@ -245,11 +241,15 @@ fn rho_subscribe_to_service(
     hostname: &str,
     port: u16,
 ) -> String {
+    let listeners = format!("{service_id}-listeners").to_rholang();
+    let self_id = self_id.to_string().to_rholang();
+    let hostname = hostname.to_rholang();
+    let port = port.to_rholang();
     format!(
         r#"
-        for(@listeners <- @"{service_id}-listeners") {{
-            @"{service_id}-listeners"!(listeners.set("{self_id}", {{
-                "hostname": "{hostname}",
+        for(@listeners <- @{listeners}) {{
+            @{listeners}!(listeners.set({self_id}, {{
+                "hostname": {hostname},
                 "port": {port},
             }}))
         }}
@@ -258,35 +258,64 @@ fn rho_subscribe_to_service(
 }
 
 fn rho_unsubscribe_from_service(service_id: &str, self_id: impl Display) -> String {
+    let listeners = format!("{service_id}-listeners").to_rholang();
+    let self_id = self_id.to_string().to_rholang();
     format!(
         r#"
-        for(@listeners <- @"{service_id}-listeners") {{
-            @"{service_id}-listeners"!(listeners.delete("{self_id}"))
+        for(@listeners <- @{listeners}) {{
+            @{listeners}!(listeners.delete({self_id}))
         }}
         "#
     )
 }
 
-fn rho_save_events(channel_name: impl Display, entries: Vec<Entry>) -> String {
-    let data = bitcode::serialize(&entries).unwrap();
+/// Events are only written if the on chain cursor is before them, so a batch deployed twice (a
+/// retry of a deploy which landed after all) is stored once. A batch overlapping the cursor is
+/// not written at all, [`save_events`] trims it and deploys the rest again.
+fn rho_save_events(service_id: &str, channel_name: impl Display, entries: &[Entry]) -> String {
+    let cursor = format!("{service_id}-cursor").to_rholang();
+    let first = entries.first().map_or(0, |entry| entry.index as i64);
+    let last = entries.last().map_or(0, |entry| entry.index as i64);
+    let data = bitcode::serialize(entries).unwrap();
     format!(
-        r#"@"{channel_name}"!("{}".hexToBytes())"#,
-        hex::encode(data)
+        r#"
+        for(@cursor <- @{cursor}) {{
+            if (cursor < {first}) {{
+                @{cursor}!({last})|
+                @{}!({})
+            }} else {{
+                @{cursor}!(cursor)
+            }}
+        }}
+        "#,
+        channel_name.to_string().to_rholang(),
+        ByteArray(data).to_rholang()
     )
 }
 
 fn rho_notify_listeners(service_id: &str, msg: &NotifyMsg) -> String {
     let json_msg = serde_json::to_string(msg).unwrap();
     let json_msg = BASE64_STANDARD.encode(json_msg);
-    format!(r#"@"{service_id}-notify-listeners"!("{json_msg}")"#)
+    format!(
+        "@{}!({})",
+        format!("{service_id}-notify-listeners").to_rholang(),
+        json_msg.to_rholang()
+    )
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Entry {
+    /// `seq` of the event on the source PDS
     index: u64,
     msg: Vec<u8>,
 }
 
+/// Drops the entries which are already on chain
+fn entries_after(mut entries: Vec<Entry>, cursor: i64) -> Vec<Entry> {
+    entries.retain(|entry| entry.index as i64 > cursor);
+    entries
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct NotifyMsg {
     block_hash: String,
@@ -355,6 +384,236 @@ fn spawn_grpc_server(
     })
 }
 
+/// Pushes events of the source PDS to firefly, resuming after the last deployed one
+async fn push_events(
+    mut client: firefly_api::Client,
+    service_id: &str,
+    events_source_url: &str,
+    size_threshold: usize,
+    time_threshold: Duration,
+    local_cursor: LocalCursor,
+) -> anyhow::Result<()> {
+    let mut cursor = resume_cursor(&mut client, service_id, &local_cursor).await?;
+    println!("resuming after seq {cursor:?}");
+    // the last run deployed a batch and stopped before listeners were notified of it
+    if let Some(msg) = local_cursor.load_pending::<NotifyMsg>()? {
+        println!("notifying listeners of block {}", msg.block_hash);
+        notify_listeners(&mut client, service_id, &msg).await;
+        local_cursor.clear_pending()?;
+    }
+
+    loop {
+        let url = cursor::with_cursor(events_source_url, cursor);
+        let after = cursor;
+        let binary_stream = subscribe_to_event_source(&url)
+            .await
+            .try_filter_map(move |msg| future::ready(entry_from_frame(msg, after)));
+
+        let binary_stream =
+            tokio_stream::StreamExt::chunks_timeout(binary_stream, size_threshold, time_threshold);
+
+        tokio::pin!(binary_stream);
+
+        while let Some(events) = binary_stream.next().await {
+            let mut entries = vec![];
+            let mut failed = None;
+            for event in events {
+                match event {
+                    Ok(entry) => entries.push(entry),
+                    Err(err) => {
+                        failed = Some(err);
+                        break;
+                    }
+                }
+            }
+
+            if let Some(last) = entries.last() {
+                let seq = last.index as i64;
+                println!("events: {}", entries.len());
+                save_events(&mut client, service_id, entries, &local_cursor).await?;
+                local_cursor.store(seq)?;
+                cursor = Some(seq);
+            }
+
+            // events after the failure are requested again from the cursor
+            if let Some(err) = failed {
+                println!("event source failed: {err:#}");
+                break;
+            }
+        }
+
+        println!("reconnecting to event source");
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Resumes from whichever cursor is further, the local one is behind after a crash right after a
+/// deploy and the on chain one lags behind until the block is finalized
+async fn resume_cursor(
+    client: &mut firefly_api::Client,
+    service_id: &str,
+    local_cursor: &LocalCursor,
+) -> anyhow::Result<Option<i64>> {
+    let local = local_cursor.load()?;
+    let on_chain = loop {
+        let hash = client.last_finalized_block_hash().await?;
+        let on_chain = client
+            .find_channel_value::<i64>(hash.clone(), format!("{service_id}-cursor"))
+            .await
+            .context("failed to read cursor")?;
+        if let Some(seq) = on_chain {
+            break seq;
+        }
+        let initialized = client
+            .find_channel_value::<Par>(hash, format!("{service_id}-listeners"))
+            .await
+            .context("failed to read listeners")?
+            .is_some();
+        if initialized {
+            init_cursor(client, service_id).await?;
+            break -1;
+        }
+        // init creates the cursor too, sending another one before it's finalized would leave two
+        // on the channel and listeners could be notified before the contract exists
+        println!("channels not initialized yet, waiting for init to be finalized");
+        tokio::time::sleep(RETRY_INTERVAL).await;
+    };
+    Ok(local.max(Some(on_chain).filter(|seq| *seq >= 0)))
+}
+
+/// Contracts initialized before the cursor existed don't have its channel, every batch would be
+/// stuck waiting on it. Waits for finalization so a restart can't send a second cursor.
+async fn init_cursor(client: &mut firefly_api::Client, service_id: &str) -> anyhow::Result<()> {
+    println!("cursor channel missing, initializing it");
+    let options = DeployTrackerOptions {
+        wait_for: WaitFor::Finalization,
+        ..Default::default()
+    };
+    let block = client
+        .deploy_and_wait(rho_init_cursor(service_id), &options)
+        .await
+        .context("failed to init cursor")?;
+    if block.errored {
+        anyhow::bail!(
+            "failed to init cursor: deploy errored in {}",
+            block.block_hash
+        );
+    }
+    Ok(())
+}
+
+fn entry_from_frame(msg: Vec<u8>, after: Option<i64>) -> anyhow::Result<Option<Entry>> {
+    match cursor::frame_seq(&msg)? {
+        Some(seq) if after.is_none_or(|after| seq > after) => Ok(Some(Entry {
+            index: seq as u64,
+            msg,
+        })),
+        Some(_) => Ok(None),
+        None => {
+            println!("skipping frame without seq");
+            Ok(None)
+        }
+    }
+}
+
+/// Deploys the batch and notifies listeners, retrying until both succeed. The notification is kept
+/// in `local_cursor` until it's sent, a restart sends it if the batch is already on chain.
+async fn save_events(
+    client: &mut firefly_api::Client,
+    service_id: &str,
+    mut entries: Vec<Entry>,
+    local_cursor: &LocalCursor,
+) -> anyhow::Result<()> {
+    let options = DeployTrackerOptions {
+        wait_for: WaitFor::Inclusion,
+        ..Default::default()
+    };
+
+    let (block, channel_name) = loop {
+        let channel_name = Uuid::new_v4().to_string();
+        let rho_code = rho_save_events(service_id, &channel_name, &entries);
+        let block = loop {
+            match client.deploy_and_wait(rho_code.clone(), &options).await {
+                Ok(block) if !block.errored => break block,
+                Ok(block) => println!("failed save events: deploy errored in {}", block.block_hash),
+                Err(err) => println!("failed save events: {err:#}"),
+            }
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        };
+        println!("events deployed");
+
+        // only a batch missing from the block is unwritten, failed reads are retried
+        let written = loop {
+            match client
+                .find_channel_value::<Par>(block.block_hash.clone(), channel_name.clone())
+                .await
+            {
+                Ok(par) => break par.is_some(),
+                Err(err) => println!("failed to read events: {err:#}"),
+            }
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        };
+        if written {
+            break (block, channel_name);
+        }
+
+        // the batch overlapped the cursor, it was built from a lagging one or a previous deploy
+        // of it landed after all
+        let on_chain = loop {
+            match client
+                .get_channel_value::<i64>(block.block_hash.clone(), format!("{service_id}-cursor"))
+                .await
+            {
+                Ok(seq) => break seq,
+                Err(err) => println!("failed to read cursor: {err:#}"),
+            }
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        };
+        entries = entries_after(entries, on_chain);
+        // the deploy which moved the cursor notified listeners of the rest, or left its
+        // notification pending for the restart
+        if entries.is_empty() {
+            println!("events already deployed");
+            return Ok(());
+        }
+        println!(
+            "{} events after cursor {on_chain}, deploying again",
+            entries.len()
+        );
+    };
+
+    let msg = NotifyMsg {
+        block_hash: block.block_hash,
+        channel_name,
+    };
+    local_cursor.store_pending(&msg)?;
+    notify_listeners(client, service_id, &msg).await;
+    local_cursor.clear_pending()?;
+
+    Ok(())
+}
+
+/// Retries until listeners are notified of a deployed batch
+async fn notify_listeners(client: &mut firefly_api::Client, service_id: &str, msg: &NotifyMsg) {
+    let options = DeployTrackerOptions {
+        wait_for: WaitFor::Inclusion,
+        ..Default::default()
+    };
+    let rho_code = rho_notify_listeners(service_id, msg);
+    loop {
+        match client.deploy_and_wait(rho_code.clone(), &options).await {
+            Ok(block) if !block.errored => break,
+            Ok(block) => println!(
+                "failed to notify listeners: deploy errored in {}",
+                block.block_hash
+            ),
+            Err(err) => println!("failed to notify listeners: {err:#}"),
+        }
+        tokio::time::sleep(RETRY_INTERVAL).await;
+    }
+    println!("notified");
+}
+
 async fn subscribe_to_event_source(
     events_source_url: &str,
 ) -> Pin<Box<dyn Stream<Item = anyhow::Result<Vec<u8>>> + Send>> {
@@ -378,6 +637,23 @@ async fn subscribe_to_event_source(
     }
 }
 
+/// Deploys `code` and waits for it to be added to a block, a deploy that errored is an error too
+async fn deploy_included(client: &mut firefly_api::Client, code: String) -> anyhow::Result<()> {
+    let options = DeployTrackerOptions {
+        wait_for: WaitFor::Inclusion,
+        ..Default::default()
+    };
+    let block = client.deploy_and_wait(code, &options).await?;
+    if block.errored {
+        anyhow::bail!(
+            "deploy errored in {}: {:?}",
+            block.block_hash,
+            block.system_deploy_error
+        );
+    }
+    Ok(())
+}
+
 async fn subscribe_to_firefly(
     mut client: firefly_api::Client,
     service_id: String,
@@ -388,16 +664,14 @@ async fn subscribe_to_firefly(
     let self_id = Uuid::new_v4();
 
     let rho_code = rho_subscribe_to_service(&service_id, self_id, external_hostname, grpc_port);
-    client
-        .full_deploy(rho_code)
+    deploy_included(&mut client, rho_code)
         .await
         .context("failed to subscribe to service")?;
 
     let mut client = scopeguard::guard(client, move |mut client| {
         tokio::spawn(async move {
             let rho_code = rho_unsubscribe_from_service(&service_id, self_id);
-            client
-                .full_deploy(rho_code)
+            deploy_included(&mut client, rho_code)
                 .await
                 .context("failed to unsubscribe from service")?;
             println!("unsubscribed");
@@ -435,3 +709,31 @@ async fn subscribe_to_atproto_sync(
 
     select_all(streams).boxed()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(indexes: impl IntoIterator<Item = u64>) -> Vec<Entry> {
+        indexes
+            .into_iter()
+            .map(|index| Entry { index, msg: vec![] })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_entries_after_cursor() {
+        let indexes = |entries: Vec<Entry>| entries.iter().map(|e| e.index).collect::<Vec<_>>();
+
+        // a batch built from a lagging cursor keeps the events the chain doesn't have yet
+        assert_eq!(
+            indexes(entries_after(entries(51..=150), 100)),
+            (101..=150).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            indexes(entries_after(entries(51..=150), -1)),
+            (51..=150).collect::<Vec<_>>()
+        );
+        assert!(entries_after(entries(51..=150), 150).is_empty());
+    }
+}
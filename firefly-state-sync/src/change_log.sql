@@ -0,0 +1,80 @@
+-- Records every row change of the synced schemas as a replayable statement.
+-- Safe to run repeatedly, tables created after the last run get their trigger on the next one.
+
+CREATE SCHEMA IF NOT EXISTS firefly_sync;
+
+CREATE TABLE IF NOT EXISTS firefly_sync.change_log (
+    id bigserial PRIMARY KEY,
+    statement text NOT NULL
+);
+
+CREATE OR REPLACE FUNCTION firefly_sync.record_change() RETURNS trigger AS $$
+DECLARE
+    target text := format('%I.%I', TG_TABLE_SCHEMA, TG_TABLE_NAME);
+    columns text;
+    new_values text;
+    key_match text;
+    statement text;
+BEGIN
+    SELECT string_agg(quote_ident(attname), ', ' ORDER BY attnum),
+           string_agg('r.' || quote_ident(attname), ', ' ORDER BY attnum)
+    INTO columns, new_values
+    FROM pg_attribute
+    WHERE attrelid = TG_RELID AND attnum > 0 AND NOT attisdropped AND attgenerated = '';
+
+    SELECT string_agg(format('t.%1$I IS NOT DISTINCT FROM o.%1$I', a.attname), ' AND ')
+    INTO key_match
+    FROM pg_index i
+    JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
+    WHERE i.indrelid = TG_RELID AND i.indisprimary;
+
+    -- tables without a primary key are matched on every column, compared as text since some
+    -- types (json) have no equality operator
+    IF key_match IS NULL THEN
+        SELECT string_agg(format('t.%1$I::text IS NOT DISTINCT FROM o.%1$I::text', attname), ' AND ')
+        INTO key_match
+        FROM pg_attribute
+        WHERE attrelid = TG_RELID AND attnum > 0 AND NOT attisdropped;
+    END IF;
+
+    -- statements are idempotent, a change replayed on top of a base snapshot which already
+    -- contains it is a no-op
+    IF TG_OP = 'INSERT' THEN
+        statement := format(
+            'INSERT INTO %s (%s) SELECT %s FROM json_populate_record(NULL::%s, %L) ON CONFLICT DO NOTHING',
+            target, columns, columns, target, row_to_json(NEW)
+        );
+    ELSIF TG_OP = 'UPDATE' THEN
+        statement := format(
+            'UPDATE %s t SET (%s) = ROW(%s) FROM json_populate_record(NULL::%s, %L) r, json_populate_record(NULL::%s, %L) o WHERE %s',
+            target, columns, new_values, target, row_to_json(NEW), target, row_to_json(OLD), key_match
+        );
+    ELSE
+        statement := format(
+            'DELETE FROM %s t USING json_populate_record(NULL::%s, %L) o WHERE %s',
+            target, target, row_to_json(OLD), key_match
+        );
+    END IF;
+
+    INSERT INTO firefly_sync.change_log (statement) VALUES (statement);
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    synced record;
+BEGIN
+    FOR synced IN
+        SELECT schemaname, tablename
+        FROM pg_tables
+        WHERE schemaname NOT IN ('public', 'firefly_sync', 'information_schema')
+          AND schemaname NOT LIKE 'pg\_%'
+    LOOP
+        EXECUTE format(
+            'CREATE OR REPLACE TRIGGER firefly_sync_change_log AFTER INSERT OR UPDATE OR DELETE ON %I.%I FOR EACH ROW EXECUTE FUNCTION firefly_sync.record_change()',
+            synced.schemaname, synced.tablename
+        );
+    END LOOP;
+END
+$$;
@@ -1,17 +1,25 @@
 use std::collections::HashMap;
-use std::fmt::Display;
-use std::process::Command;
-use std::time::Duration;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Ok, anyhow};
+use anyhow::{Context, Ok, anyhow, bail};
 use base64::Engine;
 use base64::prelude::BASE64_STANDARD;
+use change_log::Changes;
 use clap::{Parser, Subcommand};
-use firefly_api::client::helpers::FromExpr;
-use firefly_api::models::rhoapi::expr::ExprInstance;
-use serde::{Deserialize, Serialize};
+use firefly_api::Client;
+use firefly_api::client::args::DeployArgs;
+use firefly_api::client::tracker::{DeployTrackerOptions, WaitFor};
+use firefly_api::models::rhoapi::Par;
+use firefly_api::par::{self, ToPar};
+use firefly_api::rholang::{ByteArray, ToRholang};
+use snapshot::{Chunk, ChunkRef, SnapshotEntry, SnapshotManifest};
 use tokio::select;
-use uuid::Uuid;
+
+mod change_log;
+mod snapshot;
 
 #[derive(Debug, Parser)]
 struct Args {
@@ -31,6 +39,9 @@ struct Args {
     #[arg(long)]
     service_id: String,
 
+    #[command(flatten)]
+    deploy: DeployArgs,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -46,6 +57,16 @@ enum Commands {
         /// Sync interval in seconds
         #[arg(long)]
         interval: u64,
+
+        /// Maximum size of an uncompressed snapshot chunk in bytes, each chunk is a separate deploy
+        #[arg(long, default_value_t = 1024 * 1024)]
+        chunk_size: usize,
+
+        /// Switch to incremental snapshots: every `interval` only the rows changed since the
+        /// previous snapshot are uploaded, and a full base snapshot is taken every `base_interval`
+        /// seconds
+        #[arg(long)]
+        base_interval: Option<u64>,
     },
 
     /// Download db snaphot
@@ -53,6 +74,22 @@ enum Commands {
         /// Block hash
         #[arg(long)]
         hash: String,
+
+        /// Write the SQL to this file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Apply db snapshot to a database, the latest base snapshot is replayed together with the
+    /// deltas taken after it
+    Restore {
+        /// Block hash
+        #[arg(long)]
+        hash: String,
+
+        /// Postgres connection string of the target database
+        #[arg(long)]
+        db_url: String,
     },
 
     /// Initialize contract
@@ -63,62 +100,102 @@ enum Commands {
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    let mut client = firefly_api::Client::new(
+    let client = firefly_api::Client::new(
         &args.wallet_key,
         &args.deploy_service_url,
         &args.propose_service_url,
     )
     .await?;
+    let mut client = args.deploy.configure(client);
 
     match args.command {
-        Commands::Upload { db_url, interval } => {
+        Commands::Upload {
+            db_url,
+            interval,
+            chunk_size,
+            base_interval,
+        } => {
             let mut interval = tokio::time::interval(Duration::from_secs(interval));
+            let base_interval = base_interval.map(Duration::from_secs);
 
             let mut exit = tokio::spawn(tokio::signal::ctrl_c());
 
+            // chunks already on chain, by sha256, unchanged tables are not uploaded again
+            let mut uploaded = HashMap::new();
+            let mut last_base: Option<Instant> = None;
+
             loop {
                 select! {
                     _ = interval.tick() => (),
                     _ = &mut exit => break,
                 };
 
-                let channel_name = Uuid::new_v4();
-                let sql = run_pg_dump(&db_url)?;
-
-                let rho_code = rho_sql_dump_template(channel_name, sql);
-                let hash = client.full_deploy(rho_code).await?;
-                println!("dump hash: {hash}");
+                let (sql, delta, changes) = match base_interval {
+                    None => (run_pg_dump(&db_url)?, false, Changes::default()),
+                    Some(base_interval)
+                        if last_base.is_none_or(|at| at.elapsed() >= base_interval) =>
+                    {
+                        change_log::install(&db_url)?;
+                        // read before dumping, so every change read is already part of the dump,
+                        // changes past the first page are replayed on top of it as deltas
+                        let changes = change_log::pending(&db_url, change_log::PAGE_SIZE)?;
+                        last_base = Some(Instant::now());
+                        (run_pg_dump(&db_url)?, false, changes)
+                    }
+                    Some(_) => {
+                        let changes = change_log::pending(&db_url, change_log::PAGE_SIZE)?;
+                        if changes.is_empty() {
+                            continue;
+                        }
+                        if changes.ids.len() == change_log::PAGE_SIZE {
+                            // more are waiting, upload the next page right after this one
+                            interval.reset_immediately();
+                        }
+                        (changes.to_sql(), true, changes)
+                    }
+                };
 
-                let rho_code = rho_save_hash_template(
+                let manifest = upload_snapshot(
+                    &mut client,
                     &args.service_id,
-                    ServiceHash {
-                        block_hash: hash,
-                        channel_name,
-                    },
+                    sql.as_bytes(),
+                    chunk_size,
+                    delta,
+                    &mut uploaded,
+                )
+                .await?;
+
+                // captured changes are only deleted once a finalized block lists their manifest,
+                // otherwise a restore couldn't replay them
+                let rho_code = rho_save_manifest_template(&args.service_id, &manifest)?;
+                let hash = deploy_finalized(&mut client, rho_code)
+                    .await
+                    .context("failed to save snapshot manifest")?;
+                change_log::acknowledge(&db_url, &changes)?;
+                println!(
+                    "{} snapshot of {} chunks saved in block {hash}",
+                    if delta { "delta" } else { "base" },
+                    manifest.chunks.len()
                 );
-                let hash = client.full_deploy(rho_code).await?;
-                println!("save hash: {hash}");
             }
         }
-        Commands::Download { hash } => {
-            let entries: Vec<ServiceHash> = client
-                .get_channel_value(hash, format!("{}-hashes", args.service_id))
-                .await?;
-
-            let Some(entry) = entries.into_iter().last() else {
-                return Err(anyhow!("no data"));
-            };
-
-            let sql: String = client
-                .get_channel_value(entry.block_hash, entry.channel_name.to_string())
-                .await?;
-            let sql = BASE64_STANDARD.decode(sql)?;
-            let sql = String::from_utf8(sql)?;
-            println!("{sql}");
+        Commands::Download { hash, output } => {
+            let sql = download_snapshot(&mut client, &args.service_id, hash).await?;
+            match output {
+                Some(path) => std::fs::write(path, sql)?,
+                None => std::io::stdout().write_all(&sql)?,
+            }
+        }
+        Commands::Restore { hash, db_url } => {
+            let sql = download_snapshot(&mut client, &args.service_id, hash).await?;
+            run_psql(&db_url, &sql)?;
+            println!("snapshot restored");
         }
         Commands::Init => {
             let rho_code = rho_save_hash_contract(args.service_id);
-            let hash = client.full_deploy(rho_code).await?;
+            let hash = deploy_finalized(&mut client, rho_code)
+                .await
+                .context("failed to init snapshot contract")?;
             println!("{hash}");
         }
     }
@@ -126,51 +203,182 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn rho_sql_dump_template(channel_name: impl Display, sql: String) -> String {
-    format!(r#"@"{channel_name}"!("{}")"#, BASE64_STANDARD.encode(sql))
+/// Deploys `code` and returns the hash of the finalized block it landed in
+async fn deploy_finalized(client: &mut Client, code: String) -> anyhow::Result<String> {
+    let options = DeployTrackerOptions {
+        wait_for: WaitFor::Finalization,
+        ..Default::default()
+    };
+    let block = client.deploy_and_wait(code, &options).await?;
+    if block.errored {
+        bail!(
+            "deploy errored in {}: {:?}",
+            block.block_hash,
+            block.system_deploy_error
+        );
+    }
+    Ok(block.block_hash)
 }
 
-fn rho_save_hash_template(service_id: impl Display, service_hash: ServiceHash) -> String {
-    format!(
-        r#"@"{service_id}-hash"!({})"#,
-        serde_json::to_string(&service_hash).unwrap()
-    )
+fn rho_chunk_template(service_id: &str, chunk: &Chunk) -> anyhow::Result<String> {
+    Ok(format!(
+        "@{}!({})",
+        snapshot::chunk_channel(service_id, &chunk.sha256).to_rholang(),
+        ByteArray(chunk.compress()?).to_rholang()
+    ))
+}
+
+fn rho_save_manifest_template(
+    service_id: &str,
+    manifest: &SnapshotManifest,
+) -> anyhow::Result<String> {
+    Ok(format!(
+        "@{}!({})",
+        format!("{service_id}-hash").to_rholang(),
+        par::to_rholang(&manifest.to_par())?
+    ))
 }
 
 fn rho_save_hash_contract(service_id: String) -> String {
+    let hashes = format!("{service_id}-hashes").to_rholang();
+    let hash = format!("{service_id}-hash").to_rholang();
     format!(
         r#"
-        @"{service_id}-hashes"!([])
+        @{hashes}!([])
         |
-        contract @"{service_id}-hash"(@data) = {{
-            for(@hashes <- @"{service_id}-hashes") {{
-                @"{service_id}-hashes"!(hashes ++ [data])
+        contract @{hash}(@data) = {{
+            for(@hashes <- @{hashes}) {{
+                @{hashes}!(hashes ++ [data])
             }}
         }}
         "#
     )
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ServiceHash {
-    block_hash: String,
-    channel_name: Uuid,
+async fn upload_snapshot(
+    client: &mut Client,
+    service_id: &str,
+    sql: &[u8],
+    chunk_size: usize,
+    delta: bool,
+    uploaded: &mut HashMap<String, String>,
+) -> anyhow::Result<SnapshotManifest> {
+    let options = DeployTrackerOptions {
+        wait_for: WaitFor::Inclusion,
+        ..Default::default()
+    };
+
+    let mut chunks = vec![];
+    for chunk in snapshot::split_dump(sql, chunk_size) {
+        let block_hash = match uploaded.get(&chunk.sha256) {
+            Some(block_hash) => block_hash.clone(),
+            None => {
+                let rho_code = rho_chunk_template(service_id, &chunk)?;
+                let block = client.deploy_and_wait(rho_code, &options).await?;
+                if block.errored {
+                    bail!(
+                        "chunk {} failed to deploy: {:?}",
+                        chunk.sha256,
+                        block.system_deploy_error
+                    );
+                }
+                uploaded.insert(chunk.sha256.clone(), block.block_hash.clone());
+                block.block_hash
+            }
+        };
+        chunks.push(ChunkRef {
+            sha256: chunk.sha256,
+            size: chunk.data.len() as i64,
+            block_hash,
+        });
+    }
+
+    Ok(SnapshotManifest {
+        created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64,
+        sha256: snapshot::sha256_hex(sql),
+        size: sql.len() as i64,
+        chunks,
+        delta,
+    })
 }
 
-impl FromExpr for ServiceHash {
-    fn from(val: ExprInstance) -> anyhow::Result<Self> {
-        let mut map: HashMap<String, String> = FromExpr::from(val)?;
+/// Fetches the latest base snapshot known at block `hash` followed by the deltas taken after it,
+/// every chunk is verified. The script ends by moving the sequences past the restored ids.
+async fn download_snapshot(
+    client: &mut Client,
+    service_id: &str,
+    hash: String,
+) -> anyhow::Result<Vec<u8>> {
+    let entries: Vec<Par> = client
+        .get_channel_value(hash, format!("{service_id}-hashes"))
+        .await?;
+    let entries = snapshot::decode_entries(entries)?;
 
-        let block_hash = map.remove("block_hash").context("block_hash is missing")?;
-        let channel_name = map
-            .remove("channel_name")
-            .context("channel_name is missing")?;
-        let channel_name: Uuid = channel_name.parse()?;
+    let Some(entries) = snapshot::since_latest_base(&entries) else {
+        return Err(anyhow!("no data"));
+    };
 
-        Ok(ServiceHash {
-            block_hash,
-            channel_name,
-        })
+    let mut sql = vec![];
+    for entry in entries {
+        match entry {
+            SnapshotEntry::Manifest(manifest) => {
+                let mut chunks = vec![];
+                for chunk_ref in &manifest.chunks {
+                    let compressed: Vec<u8> = client
+                        .get_channel_value(
+                            chunk_ref.block_hash.clone(),
+                            snapshot::chunk_channel(service_id, &chunk_ref.sha256),
+                        )
+                        .await
+                        .with_context(|| format!("failed to get chunk {}", chunk_ref.sha256))?;
+                    chunks.push(Chunk::decompress(chunk_ref, &compressed)?);
+                }
+                sql.extend(snapshot::assemble_dump(manifest, chunks)?);
+            }
+            SnapshotEntry::Legacy(legacy) => {
+                let dump: String = client
+                    .get_channel_value(legacy.block_hash.clone(), legacy.channel_name.clone())
+                    .await
+                    .with_context(|| {
+                        format!("failed to get legacy snapshot {}", legacy.channel_name)
+                    })?;
+                sql.extend(BASE64_STANDARD.decode(dump).with_context(|| {
+                    format!("legacy snapshot {} is corrupted", legacy.channel_name)
+                })?);
+            }
+        }
+    }
+    sql.extend(change_log::SYNC_SEQUENCES_SQL.as_bytes());
+    Ok(sql)
+}
+
+fn run_psql(db_url: &str, sql: &[u8]) -> anyhow::Result<String> {
+    let mut child = Command::new("psql")
+        .arg("--single-transaction")
+        .arg("--quiet")
+        .arg("--no-align")
+        .arg("--tuples-only")
+        .arg("--set=ON_ERROR_STOP=1")
+        .arg(db_url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    child
+        .stdin
+        .take()
+        .context("missing psql stdin")?
+        .write_all(sql)?;
+
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        String::from_utf8(output.stdout).map_err(Into::into)
+    } else {
+        Err(anyhow::anyhow!(
+            "error from psql: {:?}",
+            String::from_utf8(output.stderr)
+        ))
     }
 }
 
@@ -180,6 +388,7 @@ fn run_pg_dump(db_url: &str) -> anyhow::Result<String> {
     command.arg("--data-only");
     command.arg("--column-inserts");
     command.arg("--exclude-schema=public");
+    command.arg("--exclude-schema=firefly_sync");
     command.arg(db_url);
 
     let output = command.output()?;
@@ -0,0 +1,295 @@
+use anyhow::{Context, bail};
+use firefly_api::models::rhoapi::Par;
+use firefly_api::par::{FromPar, ToPar};
+use sha2::{Digest, Sha256};
+
+/// pg_dump starts the data of every table with this comment, chunks are cut there first so that
+/// unchanged tables produce identical chunks in consecutive snapshots
+const TABLE_DATA_HEADER: &str = "-- Data for Name: ";
+
+const COMPRESSION_LEVEL: i32 = 19;
+
+/// Snapshot stored in `{service_id}-hashes`, chunks are concatenated in order to get the dump back
+#[derive(Debug, Clone, PartialEq, Eq, FromPar, ToPar)]
+pub struct SnapshotManifest {
+    /// Unix timestamp in milliseconds
+    pub created_at: i64,
+    /// Hex encoded sha256 of the whole dump
+    pub sha256: String,
+    pub size: i64,
+    pub chunks: Vec<ChunkRef>,
+    /// Changes since the previous snapshot rather than a full dump, replayed on top of the
+    /// preceding base snapshot
+    pub delta: bool,
+}
+
+/// Snapshot written before dumps were split into chunks: the whole dump, base64 encoded, sent on
+/// the `channel_name` channel in block `block_hash`
+#[derive(Debug, Clone, PartialEq, Eq, FromPar, ToPar)]
+pub struct LegacySnapshot {
+    pub block_hash: String,
+    pub channel_name: String,
+}
+
+/// Entry of `{service_id}-hashes`, services deployed before chunking still hold legacy ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotEntry {
+    Manifest(SnapshotManifest),
+    Legacy(LegacySnapshot),
+}
+
+impl SnapshotEntry {
+    /// Legacy snapshots are always full dumps
+    pub fn is_delta(&self) -> bool {
+        match self {
+            Self::Manifest(manifest) => manifest.delta,
+            Self::Legacy(_) => false,
+        }
+    }
+}
+
+impl FromPar for SnapshotEntry {
+    fn from_par(par: Par) -> anyhow::Result<Self> {
+        match SnapshotManifest::from_par(par.clone()) {
+            Ok(manifest) => Ok(Self::Manifest(manifest)),
+            Err(error) => LegacySnapshot::from_par(par)
+                .map(Self::Legacy)
+                .map_err(|_| error.context("neither a snapshot manifest nor a legacy snapshot")),
+        }
+    }
+}
+
+/// Decodes the entries of `{service_id}-hashes` one by one, so an error names the entry
+pub fn decode_entries(entries: Vec<Par>) -> anyhow::Result<Vec<SnapshotEntry>> {
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            SnapshotEntry::from_par(entry)
+                .with_context(|| format!("invalid snapshot entry {index}"))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromPar, ToPar)]
+pub struct ChunkRef {
+    /// Hex encoded sha256 of the uncompressed chunk, also names the channel holding it
+    pub sha256: String,
+    pub size: i64,
+    /// Block the chunk was deployed in
+    pub block_hash: String,
+}
+
+/// Uncompressed piece of a dump
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub sha256: String,
+    pub data: Vec<u8>,
+}
+
+impl Chunk {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            sha256: sha256_hex(&data),
+            data,
+        }
+    }
+
+    pub fn compress(&self) -> anyhow::Result<Vec<u8>> {
+        zstd::encode_all(self.data.as_slice(), COMPRESSION_LEVEL)
+            .context("failed to compress chunk")
+    }
+
+    /// Decompresses a chunk read from the chain and checks it against its manifest entry
+    pub fn decompress(chunk_ref: &ChunkRef, compressed: &[u8]) -> anyhow::Result<Self> {
+        let data = zstd::decode_all(compressed).context("failed to decompress chunk")?;
+        let chunk = Self::new(data);
+        if chunk.sha256 != chunk_ref.sha256 || chunk.data.len() as i64 != chunk_ref.size {
+            bail!("chunk {} is corrupted", chunk_ref.sha256);
+        }
+        Ok(chunk)
+    }
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+pub fn chunk_channel(service_id: &str, sha256: &str) -> String {
+    format!("{service_id}-chunk-{sha256}")
+}
+
+/// Splits a dump at table boundaries, and at line boundaries once a chunk reaches `max_size`
+///
+/// A single line longer than `max_size` is split as well, the chunks only have to be concatenated
+/// to restore the dump.
+pub fn split_dump(dump: &[u8], max_size: usize) -> Vec<Chunk> {
+    let mut chunks = vec![];
+    let mut current: Vec<u8> = vec![];
+
+    for line in dump.split_inclusive(|byte| *byte == b'\n') {
+        let starts_table = line.starts_with(TABLE_DATA_HEADER.as_bytes());
+        if !current.is_empty() && (starts_table || current.len() + line.len() > max_size) {
+            chunks.push(Chunk::new(std::mem::take(&mut current)));
+        }
+        for piece in line.chunks(max_size) {
+            if current.len() + piece.len() > max_size {
+                chunks.push(Chunk::new(std::mem::take(&mut current)));
+            }
+            current.extend_from_slice(piece);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(Chunk::new(current));
+    }
+    chunks
+}
+
+/// Concatenates verified chunks and checks the result against the manifest
+pub fn assemble_dump(manifest: &SnapshotManifest, chunks: Vec<Chunk>) -> anyhow::Result<Vec<u8>> {
+    let dump: Vec<u8> = chunks.into_iter().flat_map(|chunk| chunk.data).collect();
+    if dump.len() as i64 != manifest.size || sha256_hex(&dump) != manifest.sha256 {
+        bail!("snapshot does not match its manifest");
+    }
+    Ok(dump)
+}
+
+/// Snapshots a restore replays: the latest base snapshot followed by the deltas taken after it
+pub fn since_latest_base(entries: &[SnapshotEntry]) -> Option<&[SnapshotEntry]> {
+    let base = entries.iter().rposition(|entry| !entry.is_delta())?;
+    Some(&entries[base..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(created_at: i64, delta: bool) -> SnapshotEntry {
+        SnapshotEntry::Manifest(SnapshotManifest {
+            created_at,
+            sha256: String::new(),
+            size: 0,
+            chunks: vec![],
+            delta,
+        })
+    }
+
+    /// Legacy entries have no timestamp, they're told apart by -1
+    fn created_at(entry: &SnapshotEntry) -> i64 {
+        match entry {
+            SnapshotEntry::Manifest(manifest) => manifest.created_at,
+            SnapshotEntry::Legacy(_) => -1,
+        }
+    }
+
+    #[test]
+    fn splits_dump_at_tables_and_size() {
+        let dump =
+            b"SET x;\n-- Data for Name: a\nINSERT 1;\nINSERT 2;\n-- Data for Name: b\nINSERT 3;\n";
+        let chunks = split_dump(dump, 20);
+        let parts: Vec<&[u8]> = chunks.iter().map(|chunk| chunk.data.as_slice()).collect();
+        assert_eq!(
+            parts,
+            vec![
+                &b"SET x;\n"[..],
+                b"-- Data for Name: a\n",
+                b"INSERT 1;\nINSERT 2;\n",
+                b"-- Data for Name: b\n",
+                b"INSERT 3;\n",
+            ]
+        );
+        assert!(
+            split_dump(&[b'x'; 50], 20)
+                .iter()
+                .all(|chunk| chunk.data.len() <= 20)
+        );
+    }
+
+    #[test]
+    fn round_trips_chunks() {
+        let dump = b"-- Data for Name: a\nINSERT 1;\n".repeat(100);
+        let chunks = split_dump(&dump, 256);
+        let manifest = SnapshotManifest {
+            created_at: 0,
+            sha256: sha256_hex(&dump),
+            size: dump.len() as i64,
+            chunks: chunks
+                .iter()
+                .map(|chunk| ChunkRef {
+                    sha256: chunk.sha256.clone(),
+                    size: chunk.data.len() as i64,
+                    block_hash: "block".into(),
+                })
+                .collect(),
+            delta: false,
+        };
+        assert_eq!(
+            SnapshotManifest::from_par(manifest.to_par()).unwrap(),
+            manifest
+        );
+
+        let restored = manifest
+            .chunks
+            .iter()
+            .zip(&chunks)
+            .map(|(chunk_ref, chunk)| Chunk::decompress(chunk_ref, &chunk.compress().unwrap()))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(assemble_dump(&manifest, restored).unwrap(), dump);
+
+        let tampered = Chunk::new(b"INSERT 2;\n".to_vec()).compress().unwrap();
+        assert!(Chunk::decompress(&manifest.chunks[0], &tampered).is_err());
+    }
+
+    #[test]
+    fn replays_deltas_since_the_latest_base() {
+        let replayed = |entries: Option<&[SnapshotEntry]>| {
+            entries.map(|entries| entries.iter().map(created_at).collect::<Vec<_>>())
+        };
+
+        let manifests = [
+            manifest(1, false),
+            manifest(2, true),
+            manifest(3, false),
+            manifest(4, true),
+            manifest(5, true),
+        ];
+        assert_eq!(replayed(since_latest_base(&manifests)), Some(vec![3, 4, 5]));
+        assert_eq!(replayed(since_latest_base(&manifests[..3])), Some(vec![3]));
+        assert_eq!(replayed(since_latest_base(&[manifest(1, true)])), None);
+        assert_eq!(replayed(since_latest_base(&[])), None);
+    }
+
+    #[test]
+    fn decodes_legacy_entries_next_to_manifests() {
+        let legacy = LegacySnapshot {
+            block_hash: "block".into(),
+            channel_name: "5b7d1b9c-3c6e-4a8e-9d0b-2f1e8a4c6d3f".into(),
+        };
+        let SnapshotEntry::Manifest(delta) = manifest(2, true) else {
+            unreachable!()
+        };
+        let entries =
+            decode_entries(vec![legacy.to_par(), delta.to_par(), legacy.to_par()]).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                SnapshotEntry::Legacy(legacy.clone()),
+                SnapshotEntry::Manifest(delta.clone()),
+                SnapshotEntry::Legacy(legacy),
+            ]
+        );
+        assert_eq!(
+            since_latest_base(&entries[..2])
+                .unwrap()
+                .iter()
+                .map(created_at)
+                .collect::<Vec<_>>(),
+            vec![-1, 2]
+        );
+        assert_eq!(since_latest_base(&entries).unwrap().len(), 1);
+
+        let error = decode_entries(vec![delta.to_par(), "garbage".to_par()]).unwrap_err();
+        assert!(format!("{error:#}").starts_with("invalid snapshot entry 1"));
+    }
+}
@@ -0,0 +1,28 @@
+-- Moves every sequence owned by a column of the synced schemas past the highest value restored.
+-- Rows replayed from deltas carry their ids, so they never advance the sequences themselves.
+
+DO $$
+DECLARE
+    owned record;
+BEGIN
+    FOR owned IN
+        SELECT n.nspname AS schemaname,
+               c.relname AS tablename,
+               a.attname AS columnname,
+               pg_get_serial_sequence(format('%I.%I', n.nspname, c.relname), a.attname) AS sequence
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped
+        WHERE c.relkind IN ('r', 'p')
+          AND n.nspname NOT IN ('public', 'firefly_sync', 'information_schema')
+          AND n.nspname NOT LIKE 'pg\_%'
+    LOOP
+        CONTINUE WHEN owned.sequence IS NULL;
+        -- the next value handed out is one past the highest restored, 1 for an empty table
+        EXECUTE format(
+            'SELECT setval(%L, coalesce((SELECT max(%I) FROM %I.%I), 0) + 1, false)',
+            owned.sequence, owned.columnname, owned.schemaname, owned.tablename
+        );
+    END LOOP;
+END
+$$;
@@ -70,6 +70,16 @@ pub fn encode_uri_component(input: &String) -> String {
     encode(input).to_string()
 }
 
+/// Escapes text for use in HTML content or a quoted attribute value
+pub fn escape_html(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
 // based on did-doc.ts
 pub fn get_did(doc: &DidDocument) -> String {
     doc.id.clone()
@@ -178,7 +188,7 @@ pub mod time;
 
 #[cfg(test)]
 mod tests {
-    use crate::{get_service_endpoint, validate_url, GetServiceEndpointOpts};
+    use crate::{escape_html, get_service_endpoint, validate_url, GetServiceEndpointOpts};
     use rsky_identity::types::{DidDocument, Service};
 
     #[test]
@@ -195,6 +205,14 @@ mod tests {
         assert_eq!(result.is_some(), true);
     }
 
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+
     #[test]
     fn test_get_service_endpoint() {
         let text = "https://rsky.com".to_string();
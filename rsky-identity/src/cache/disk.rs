@@ -0,0 +1,197 @@
+use crate::cache::memory::CacheVal;
+use crate::cache::{now_micros, CacheResult, CacheTtls, DidCache, HandleCacheResult};
+use crate::common::encode_uri_component;
+use crate::types::DidDocument;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::fs;
+
+const DID_DIR: &str = "did";
+const HANDLE_DIR: &str = "handle";
+
+// makes temp files unique between concurrent writes of the same key
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Cache kept as one JSON file per DID or handle under `dir`, for services without a database
+#[derive(Clone, Debug)]
+pub struct DiskCache {
+    pub dir: PathBuf,
+    pub ttls: CacheTtls,
+}
+
+impl DiskCache {
+    pub async fn new(dir: PathBuf, ttls: CacheTtls) -> Result<Self> {
+        fs::create_dir_all(dir.join(DID_DIR)).await?;
+        fs::create_dir_all(dir.join(HANDLE_DIR)).await?;
+        Ok(Self { dir, ttls })
+    }
+
+    fn path(&self, kind: &str, key: &String) -> PathBuf {
+        self.dir
+            .join(kind)
+            .join(format!("{}.json", encode_uri_component(key)))
+    }
+
+    async fn write<T: Serialize + Send>(&self, path: PathBuf, val: Option<T>) -> Result<()> {
+        let bytes = serde_json::to_vec(&CacheVal {
+            val,
+            updated_at: now_micros(),
+        })?;
+        // written aside and renamed so a reader never sees a partial file
+        let tmp = path.with_extension(format!(
+            "json.{}-{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&tmp, bytes).await?;
+        fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn read<T: DeserializeOwned + Send>(&self, path: PathBuf) -> Result<Option<CacheVal<T>>> {
+        match fs::read(&path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn remove(&self, path: PathBuf) -> Result<()> {
+        match fs::remove_file(&path).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Removes expired and unreadable entries of one kind
+    async fn purge(&self, kind: &str) -> Result<()> {
+        let mut entries = fs::read_dir(self.dir.join(kind)).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let expired = match self.read::<Value>(path.clone()).await {
+                Ok(Some(CacheVal { val, updated_at })) => {
+                    self.ttls.check(updated_at, val.is_none()).1
+                }
+                Ok(None) => false,
+                Err(_) => true,
+            };
+            if expired {
+                self.remove(path).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DidCache for DiskCache {
+    async fn cache_did(&self, did: String, doc: Option<DidDocument>) -> Result<()> {
+        self.write(self.path(DID_DIR, &did), doc).await
+    }
+
+    async fn check_cache(&self, did: String) -> Result<Option<CacheResult>> {
+        let path = self.path(DID_DIR, &did);
+        match self.read::<DidDocument>(path).await? {
+            None => Ok(None),
+            Some(CacheVal { val, updated_at }) => {
+                let (stale, expired) = self.ttls.check(updated_at, val.is_none());
+                Ok(Some(CacheResult {
+                    did,
+                    doc: val,
+                    updated_at,
+                    stale,
+                    expired,
+                }))
+            }
+        }
+    }
+
+    async fn cache_handle(&self, handle: String, did: Option<String>) -> Result<()> {
+        self.write(self.path(HANDLE_DIR, &handle), did).await
+    }
+
+    async fn check_handle_cache(&self, handle: String) -> Result<Option<HandleCacheResult>> {
+        let path = self.path(HANDLE_DIR, &handle);
+        match self.read::<String>(path).await? {
+            None => Ok(None),
+            Some(CacheVal { val, updated_at }) => {
+                let (stale, expired) = self.ttls.check(updated_at, val.is_none());
+                Ok(Some(HandleCacheResult {
+                    handle,
+                    did: val,
+                    updated_at,
+                    stale,
+                    expired,
+                }))
+            }
+        }
+    }
+
+    async fn clear_entry(&self, did: String) -> Result<()> {
+        self.remove(self.path(DID_DIR, &did)).await
+    }
+
+    async fn clear_handle(&self, handle: String) -> Result<()> {
+        self.remove(self.path(HANDLE_DIR, &handle)).await
+    }
+
+    async fn purge_expired(&self) -> Result<()> {
+        for kind in [DID_DIR, HANDLE_DIR] {
+            self.purge(kind).await?;
+        }
+        Ok(())
+    }
+
+    async fn clear(&self) -> Result<()> {
+        for kind in [DID_DIR, HANDLE_DIR] {
+            let dir = self.dir.join(kind);
+            match fs::remove_dir_all(&dir).await {
+                Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+                _ => (),
+            }
+            fs::create_dir_all(&dir).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn purges_expired_entries() {
+        let dir = std::env::temp_dir().join(format!("did-cache-{}", now_micros()));
+        let ttls = CacheTtls::new(None, None, Some(Duration::ZERO));
+        let cache = DiskCache::new(dir.clone(), ttls).await.unwrap();
+        let (cached, missing) = ("alice.test".to_string(), "missing.test".to_string());
+        cache
+            .cache_handle(cached.clone(), Some("did:plc:a".to_string()))
+            .await
+            .unwrap();
+        cache.cache_handle(missing.clone(), None).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        cache.purge_expired().await.unwrap();
+        assert!(cache.check_handle_cache(cached).await.unwrap().is_some());
+        assert!(cache.check_handle_cache(missing).await.unwrap().is_none());
+        // nothing but the remaining entry is left behind, temp files included
+        let mut entries = fs::read_dir(dir.join(HANDLE_DIR)).await.unwrap();
+        let mut count = 0;
+        while entries.next_entry().await.unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 1);
+        fs::remove_dir_all(dir).await.unwrap();
+    }
+}
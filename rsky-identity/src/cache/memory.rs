@@ -0,0 +1,206 @@
+use crate::cache::{now_micros, CacheResult, CacheTtls, DidCache, HandleCacheResult};
+use crate::types::DidDocument;
+use anyhow::Result;
+use async_trait::async_trait;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+/// DIDs and handles the memory cache holds each, before the least recently used are dropped
+pub const DEFAULT_MAX_ENTRIES: usize = 100_000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CacheVal<T> {
+    pub val: Option<T>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: u128,
+}
+
+/// In-process cache, lost on restart
+#[derive(Debug)]
+pub struct MemoryCache {
+    pub ttls: CacheTtls,
+    dids: Mutex<LruCache<String, CacheVal<DidDocument>>>,
+    handles: Mutex<LruCache<String, CacheVal<String>>>,
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new(CacheTtls::default())
+    }
+}
+
+impl MemoryCache {
+    pub fn new(ttls: CacheTtls) -> Self {
+        Self::with_max_entries(ttls, DEFAULT_MAX_ENTRIES)
+    }
+
+    pub fn with_max_entries(ttls: CacheTtls, max_entries: usize) -> Self {
+        let cap = NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            ttls,
+            dids: Mutex::new(LruCache::new(cap)),
+            handles: Mutex::new(LruCache::new(cap)),
+        }
+    }
+}
+
+fn purge<T>(ttls: &CacheTtls, entries: &mut LruCache<String, CacheVal<T>>) {
+    let expired = entries
+        .iter()
+        .filter(|(_, entry)| ttls.check(entry.updated_at, entry.val.is_none()).1)
+        .map(|(key, _)| key.clone())
+        .collect::<Vec<String>>();
+    for key in expired {
+        entries.pop(&key);
+    }
+}
+
+#[async_trait]
+impl DidCache for MemoryCache {
+    async fn cache_did(&self, did: String, doc: Option<DidDocument>) -> Result<()> {
+        let mut dids = self.dids.lock().expect("did cache lock poisoned");
+        dids.put(
+            did,
+            CacheVal {
+                val: doc,
+                updated_at: now_micros(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn check_cache(&self, did: String) -> Result<Option<CacheResult>> {
+        let mut dids = self.dids.lock().expect("did cache lock poisoned");
+        match dids.get(&did) {
+            None => Ok(None),
+            Some(CacheVal { val, updated_at }) => {
+                let (stale, expired) = self.ttls.check(*updated_at, val.is_none());
+                Ok(Some(CacheResult {
+                    did,
+                    doc: val.clone(),
+                    updated_at: *updated_at,
+                    stale,
+                    expired,
+                }))
+            }
+        }
+    }
+
+    async fn cache_handle(&self, handle: String, did: Option<String>) -> Result<()> {
+        let mut handles = self.handles.lock().expect("handle cache lock poisoned");
+        handles.put(
+            handle,
+            CacheVal {
+                val: did,
+                updated_at: now_micros(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn check_handle_cache(&self, handle: String) -> Result<Option<HandleCacheResult>> {
+        let mut handles = self.handles.lock().expect("handle cache lock poisoned");
+        match handles.get(&handle) {
+            None => Ok(None),
+            Some(CacheVal { val, updated_at }) => {
+                let (stale, expired) = self.ttls.check(*updated_at, val.is_none());
+                Ok(Some(HandleCacheResult {
+                    handle,
+                    did: val.clone(),
+                    updated_at: *updated_at,
+                    stale,
+                    expired,
+                }))
+            }
+        }
+    }
+
+    async fn clear_entry(&self, did: String) -> Result<()> {
+        let mut dids = self.dids.lock().expect("did cache lock poisoned");
+        dids.pop(&did);
+        Ok(())
+    }
+
+    async fn clear_handle(&self, handle: String) -> Result<()> {
+        let mut handles = self.handles.lock().expect("handle cache lock poisoned");
+        handles.pop(&handle);
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<()> {
+        purge(
+            &self.ttls,
+            &mut self.dids.lock().expect("did cache lock poisoned"),
+        );
+        purge(
+            &self.ttls,
+            &mut self.handles.lock().expect("handle cache lock poisoned"),
+        );
+        Ok(())
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.dids.lock().expect("did cache lock poisoned").clear();
+        self.handles
+            .lock()
+            .expect("handle cache lock poisoned")
+            .clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn drops_least_recently_used_entries() {
+        let cache = MemoryCache::with_max_entries(CacheTtls::default(), 2);
+        for handle in ["a.test", "b.test"] {
+            cache.cache_handle(handle.to_string(), None).await.unwrap();
+        }
+        // reading a.test makes b.test the least recently used
+        assert!(cache
+            .check_handle_cache("a.test".to_string())
+            .await
+            .unwrap()
+            .is_some());
+        cache
+            .cache_handle("c.test".to_string(), Some("did:plc:c".to_string()))
+            .await
+            .unwrap();
+        for (handle, cached) in [("a.test", true), ("b.test", false), ("c.test", true)] {
+            let found = cache.check_handle_cache(handle.to_string()).await.unwrap();
+            assert_eq!(found.is_some(), cached, "{handle}");
+        }
+    }
+
+    #[tokio::test]
+    async fn purges_expired_entries() {
+        let ttls = CacheTtls::new(None, None, Some(Duration::ZERO));
+        let cache = MemoryCache::new(ttls);
+        cache
+            .cache_handle("missing.test".to_string(), None)
+            .await
+            .unwrap();
+        cache
+            .cache_handle("alice.test".to_string(), Some("did:plc:a".to_string()))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        cache.purge_expired().await.unwrap();
+        assert!(cache
+            .check_handle_cache("missing.test".to_string())
+            .await
+            .unwrap()
+            .is_none());
+        assert!(cache
+            .check_handle_cache("alice.test".to_string())
+            .await
+            .unwrap()
+            .is_some());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::common::{HOUR, MINUTE};
+use crate::types::DidDocument;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_STALE_TTL: Duration = Duration::from_millis(HOUR as u64);
+pub const DEFAULT_MAX_TTL: Duration = Duration::from_millis(24 * HOUR as u64);
+pub const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_millis(5 * MINUTE as u64);
+
+#[derive(Clone, Copy, Debug)]
+pub struct CacheTtls {
+    /// Entries older than this are served but refreshed in the background
+    pub stale_ttl: Duration,
+    /// Entries older than this are resolved again before being served
+    pub max_ttl: Duration,
+    /// How long a lookup that found nothing is remembered
+    pub negative_ttl: Duration,
+}
+
+impl Default for CacheTtls {
+    fn default() -> Self {
+        Self {
+            stale_ttl: DEFAULT_STALE_TTL,
+            max_ttl: DEFAULT_MAX_TTL,
+            negative_ttl: DEFAULT_NEGATIVE_TTL,
+        }
+    }
+}
+
+impl CacheTtls {
+    pub fn new(
+        stale_ttl: Option<Duration>,
+        max_ttl: Option<Duration>,
+        negative_ttl: Option<Duration>,
+    ) -> Self {
+        Self {
+            stale_ttl: stale_ttl.unwrap_or(DEFAULT_STALE_TTL),
+            max_ttl: max_ttl.unwrap_or(DEFAULT_MAX_TTL),
+            negative_ttl: negative_ttl.unwrap_or(DEFAULT_NEGATIVE_TTL),
+        }
+    }
+
+    /// Returns whether an entry written at `updated_at` is `(stale, expired)`. Negative entries
+    /// are never refreshed in the background, they just expire.
+    pub fn check(&self, updated_at: u128, negative: bool) -> (bool, bool) {
+        let now = now_micros();
+        if negative {
+            let expired = now > updated_at + self.negative_ttl.as_micros();
+            return (expired, expired);
+        }
+        (
+            now > updated_at + self.stale_ttl.as_micros(),
+            now > updated_at + self.max_ttl.as_micros(),
+        )
+    }
+}
+
+/// Keys with a background refresh running, so a stale entry is resolved again once however many
+/// requests are served from it in the meantime
+#[derive(Clone, Debug, Default)]
+pub struct InFlight(Arc<Mutex<HashSet<String>>>);
+
+impl InFlight {
+    /// Returns false if `key` is already being refreshed
+    pub fn start(&self, key: &str) -> bool {
+        self.0
+            .lock()
+            .expect("in flight lock poisoned")
+            .insert(key.to_string())
+    }
+
+    pub fn finish(&self, key: &str) {
+        self.0.lock().expect("in flight lock poisoned").remove(key);
+    }
+}
+
+pub fn now_micros() -> u128 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("timestamp in micros since UNIX epoch")
+        .as_micros()
+}
+
+/// A cached DID document. `doc` is `None` when the DID was positively not found.
+#[derive(Clone, Debug)]
+pub struct CacheResult {
+    pub did: String,
+    pub doc: Option<DidDocument>,
+    pub updated_at: u128,
+    pub stale: bool,
+    pub expired: bool,
+}
+
+/// A cached handle resolution. `did` is `None` when the handle didn't resolve.
+#[derive(Clone, Debug)]
+pub struct HandleCacheResult {
+    pub handle: String,
+    pub did: Option<String>,
+    pub updated_at: u128,
+    pub stale: bool,
+    pub expired: bool,
+}
+
+/// Storage for resolved DID documents and handles, shared by the DID and handle resolvers.
+/// Failed lookups are stored as entries without a value so they aren't retried on every request.
+#[async_trait]
+pub trait DidCache: Send + Sync + Debug {
+    async fn cache_did(&self, did: String, doc: Option<DidDocument>) -> Result<()>;
+
+    async fn check_cache(&self, did: String) -> Result<Option<CacheResult>>;
+
+    async fn cache_handle(&self, handle: String, did: Option<String>) -> Result<()>;
+
+    async fn check_handle_cache(&self, handle: String) -> Result<Option<HandleCacheResult>>;
+
+    async fn clear_entry(&self, did: String) -> Result<()>;
+
+    async fn clear_handle(&self, handle: String) -> Result<()>;
+
+    /// Drops expired entries. Lookups of keys that don't exist are cached too, so without this
+    /// the cache grows with every made up DID or handle it's asked about.
+    async fn purge_expired(&self) -> Result<()>;
+
+    async fn clear(&self) -> Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_entries_expire_sooner() {
+        let ttls = CacheTtls::new(
+            Some(Duration::from_secs(60)),
+            Some(Duration::from_secs(120)),
+            Some(Duration::from_secs(10)),
+        );
+        let written = now_micros() - Duration::from_secs(30).as_micros();
+        assert_eq!(ttls.check(written, false), (false, false));
+        assert_eq!(ttls.check(written, true), (true, true));
+
+        let written = now_micros() - Duration::from_secs(90).as_micros();
+        assert_eq!(ttls.check(written, false), (true, false));
+    }
+
+    #[test]
+    fn refreshes_a_key_once_at_a_time() {
+        let in_flight = InFlight::default();
+        assert!(in_flight.start("did:plc:a"));
+        assert!(!in_flight.clone().start("did:plc:a"));
+        assert!(in_flight.start("did:plc:b"));
+        in_flight.finish("did:plc:a");
+        assert!(in_flight.start("did:plc:a"));
+    }
+}
+
+#[cfg(feature = "disk")]
+pub mod disk;
+pub mod memory;
+#[cfg(feature = "postgres")]
+pub mod postgres;
@@ -0,0 +1,234 @@
+use crate::cache::{now_micros, CacheResult, CacheTtls, DidCache, HandleCacheResult};
+use crate::types::DidDocument;
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use native_tls::TlsConnector;
+use postgres_native_tls::MakeTlsConnector;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio_postgres::Client;
+
+/// Cache shared by every instance pointed at the same database. Reads and writes the
+/// `did_cache` and `handle_cache` tables in `schema`, which the service creates in its own
+/// migrations.
+pub struct PostgresCache {
+    pub ttls: CacheTtls,
+    database_url: String,
+    schema: String,
+    client: RwLock<Arc<Client>>,
+}
+
+impl fmt::Debug for PostgresCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresCache")
+            .field("ttls", &self.ttls)
+            .field("schema", &self.schema)
+            .finish()
+    }
+}
+
+/// Connects over TLS as the url's `sslmode` asks, `prefer` by default
+async fn connect(database_url: &str) -> Result<Client> {
+    let tls = MakeTlsConnector::new(TlsConnector::new()?);
+    let (client, connection) = tokio_postgres::connect(database_url, tls).await?;
+    tokio::spawn(async move {
+        if let Err(err) = connection.await {
+            tracing::warn!("DID cache connection closed: {err}");
+        }
+    });
+    Ok(client)
+}
+
+impl PostgresCache {
+    pub async fn connect(database_url: String, schema: String, ttls: CacheTtls) -> Result<Self> {
+        if schema.is_empty()
+            || !schema
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            bail!("Invalid DID cache schema: `{schema}`");
+        }
+        let client = connect(&database_url).await?;
+        Ok(Self {
+            ttls,
+            database_url,
+            schema,
+            client: RwLock::new(Arc::new(client)),
+        })
+    }
+
+    /// The connection, opened again if it dropped since the last query
+    async fn client(&self) -> Result<Arc<Client>> {
+        let client = self.client.read().await.clone();
+        if !client.is_closed() {
+            return Ok(client);
+        }
+        let mut lock = self.client.write().await;
+        if lock.is_closed() {
+            *lock = Arc::new(connect(&self.database_url).await?);
+        }
+        Ok(lock.clone())
+    }
+}
+
+#[async_trait]
+impl DidCache for PostgresCache {
+    async fn cache_did(&self, did: String, doc: Option<DidDocument>) -> Result<()> {
+        let doc = match doc {
+            Some(doc) => Some(serde_json::to_string(&doc)?),
+            None => None,
+        };
+        let updated_at = now_micros() as i64;
+        self.client()
+            .await?
+            .execute(
+                &format!(
+                    r#"INSERT INTO {}.did_cache (did, doc, "updatedAt") VALUES ($1, $2, $3)
+                    ON CONFLICT (did) DO UPDATE SET doc = EXCLUDED.doc, "updatedAt" = EXCLUDED."updatedAt""#,
+                    self.schema
+                ),
+                &[&did, &doc, &updated_at],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn check_cache(&self, did: String) -> Result<Option<CacheResult>> {
+        let row = self
+            .client()
+            .await?
+            .query_opt(
+                &format!(
+                    r#"SELECT doc, "updatedAt" FROM {}.did_cache WHERE did = $1"#,
+                    self.schema
+                ),
+                &[&did],
+            )
+            .await?;
+        match row {
+            None => Ok(None),
+            Some(row) => {
+                let doc = match row.get::<_, Option<String>>(0) {
+                    Some(doc) => Some(serde_json::from_str::<DidDocument>(&doc)?),
+                    None => None,
+                };
+                let updated_at = row.get::<_, i64>(1) as u128;
+                let (stale, expired) = self.ttls.check(updated_at, doc.is_none());
+                Ok(Some(CacheResult {
+                    did,
+                    doc,
+                    updated_at,
+                    stale,
+                    expired,
+                }))
+            }
+        }
+    }
+
+    async fn cache_handle(&self, handle: String, did: Option<String>) -> Result<()> {
+        let updated_at = now_micros() as i64;
+        self.client()
+            .await?
+            .execute(
+                &format!(
+                    r#"INSERT INTO {}.handle_cache (handle, did, "updatedAt") VALUES ($1, $2, $3)
+                    ON CONFLICT (handle) DO UPDATE SET did = EXCLUDED.did, "updatedAt" = EXCLUDED."updatedAt""#,
+                    self.schema
+                ),
+                &[&handle, &did, &updated_at],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn check_handle_cache(&self, handle: String) -> Result<Option<HandleCacheResult>> {
+        let row = self
+            .client()
+            .await?
+            .query_opt(
+                &format!(
+                    r#"SELECT did, "updatedAt" FROM {}.handle_cache WHERE handle = $1"#,
+                    self.schema
+                ),
+                &[&handle],
+            )
+            .await?;
+        match row {
+            None => Ok(None),
+            Some(row) => {
+                let did = row.get::<_, Option<String>>(0);
+                let updated_at = row.get::<_, i64>(1) as u128;
+                let (stale, expired) = self.ttls.check(updated_at, did.is_none());
+                Ok(Some(HandleCacheResult {
+                    handle,
+                    did,
+                    updated_at,
+                    stale,
+                    expired,
+                }))
+            }
+        }
+    }
+
+    async fn clear_entry(&self, did: String) -> Result<()> {
+        self.client()
+            .await?
+            .execute(
+                &format!("DELETE FROM {}.did_cache WHERE did = $1", self.schema),
+                &[&did],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn clear_handle(&self, handle: String) -> Result<()> {
+        self.client()
+            .await?
+            .execute(
+                &format!("DELETE FROM {}.handle_cache WHERE handle = $1", self.schema),
+                &[&handle],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<()> {
+        let now = now_micros();
+        let max_cutoff = (now - self.ttls.max_ttl.as_micros()) as i64;
+        let negative_cutoff = (now - self.ttls.negative_ttl.as_micros()) as i64;
+        let client = self.client().await?;
+        client
+            .execute(
+                &format!(
+                    r#"DELETE FROM {}.did_cache WHERE "updatedAt" < $1
+                    OR (doc IS NULL AND "updatedAt" < $2)"#,
+                    self.schema
+                ),
+                &[&max_cutoff, &negative_cutoff],
+            )
+            .await?;
+        client
+            .execute(
+                &format!(
+                    r#"DELETE FROM {}.handle_cache WHERE "updatedAt" < $1
+                    OR (did IS NULL AND "updatedAt" < $2)"#,
+                    self.schema
+                ),
+                &[&max_cutoff, &negative_cutoff],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.client()
+            .await?
+            .batch_execute(&format!(
+                "TRUNCATE {0}.did_cache; TRUNCATE {0}.handle_cache;",
+                self.schema
+            ))
+            .await?;
+        Ok(())
+    }
+}
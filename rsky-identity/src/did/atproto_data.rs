@@ -1,7 +1,10 @@
-use anyhow::Result;
+use crate::errors::Error;
+use crate::types::{AtprotoData, DidDocument, VerificationMethod};
+use anyhow::{bail, Result};
 use rsky_crypto::constants::{P256_JWT_ALG, SECP256K1_JWT_ALG};
 use rsky_crypto::did::{format_did_key, parse_multikey};
 use rsky_crypto::multibase::multibase_to_bytes;
+use url::Url;
 
 #[derive(Clone)]
 pub struct VerificationMaterial {
@@ -26,3 +29,107 @@ pub fn get_did_key_from_multibase(key: VerificationMaterial) -> Result<Option<St
     };
     Ok(did_key)
 }
+
+pub fn get_did(doc: &DidDocument) -> String {
+    doc.id.clone()
+}
+
+/// The first `at://` entry of `alsoKnownAs`, which still has to be checked against the handle's
+/// own resolution
+pub fn get_handle(doc: &DidDocument) -> Option<String> {
+    doc.also_known_as
+        .as_ref()?
+        .iter()
+        .find_map(|aka| aka.strip_prefix("at://"))
+        .map(|handle| handle.to_lowercase())
+}
+
+pub fn get_signing_key(doc: &DidDocument) -> Result<Option<String>> {
+    let key = doc.verification_method.as_ref().and_then(|methods| {
+        methods
+            .iter()
+            .find(|method| method.id == "#atproto" || method.id == format!("{}#atproto", doc.id))
+    });
+    match key {
+        Some(VerificationMethod {
+            r#type,
+            public_key_multibase: Some(public_key_multibase),
+            ..
+        }) => get_did_key_from_multibase(VerificationMaterial {
+            r#type: r#type.clone(),
+            public_key_multibase: public_key_multibase.clone(),
+        }),
+        _ => Ok(None),
+    }
+}
+
+pub fn get_pds(doc: &DidDocument) -> Option<String> {
+    let service = doc.service.as_ref()?.iter().find(|service| {
+        (service.id == "#atproto_pds" || service.id == format!("{}#atproto_pds", doc.id))
+            && service.r#type == "AtprotoPersonalDataServer"
+    })?;
+    match Url::parse(&service.service_endpoint) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+            Some(service.service_endpoint.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Pulls the did, handle, signing key and PDS out of a DID Document, failing on the first one
+/// that's missing. The handle isn't verified here.
+pub fn ensure_atp_document(doc: &DidDocument) -> Result<AtprotoData> {
+    let did = get_did(doc);
+    let handle = match get_handle(doc) {
+        Some(handle) => handle,
+        None => bail!(Error::MissingHandleError(did)),
+    };
+    let signing_key = match get_signing_key(doc)? {
+        Some(signing_key) => signing_key,
+        None => bail!(Error::MissingSigningKeyError(did)),
+    };
+    let pds = match get_pds(doc) {
+        Some(pds) => pds,
+        None => bail!(Error::MissingPdsEndpointError(did)),
+    };
+    Ok(AtprotoData {
+        did,
+        signing_key,
+        handle,
+        pds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Service;
+
+    fn doc() -> DidDocument {
+        DidDocument {
+            context: None,
+            id: "did:plc:ewvi7nxzyoun6zhxrhs64oiz".to_string(),
+            also_known_as: Some(vec!["at://Alice.example.com".to_string()]),
+            verification_method: None,
+            service: Some(vec![Service {
+                id: "#atproto_pds".to_string(),
+                r#type: "AtprotoPersonalDataServer".to_string(),
+                service_endpoint: "https://pds.example.com".to_string(),
+            }]),
+        }
+    }
+
+    #[test]
+    fn reads_atproto_fields() {
+        let doc = doc();
+        assert_eq!(get_handle(&doc), Some("alice.example.com".to_string()));
+        assert_eq!(get_pds(&doc), Some("https://pds.example.com".to_string()));
+        assert_eq!(get_signing_key(&doc).unwrap(), None);
+
+        let error = ensure_atp_document(&doc).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::MissingSigningKeyError(_))
+        ));
+    }
+}
@@ -1,10 +1,12 @@
+use crate::cache::{DidCache, InFlight};
 use crate::did::plc_resolver::DidPlcResolver;
 use crate::did::web_resolver::DidWebResolver;
 use crate::errors::Error;
-use crate::types::{CacheResult, DidCache, DidDocument, DidResolverOpts};
+use crate::types::{DidDocument, DidResolverOpts};
 use anyhow::{bail, Result};
 use serde_json::Value;
 use std::collections::BTreeMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(Clone, Debug)]
@@ -24,14 +26,18 @@ impl ResolverKind {
 
 #[derive(Clone, Debug)]
 pub struct DidResolver {
-    pub cache: Option<DidCache>,
+    pub cache: Option<Arc<dyn DidCache>>,
     pub methods: BTreeMap<String, ResolverKind>,
+    refreshing: InFlight,
 }
 
 impl DidResolver {
     pub fn new(opts: DidResolverOpts) -> Self {
         let DidResolverOpts {
-            timeout, plc_url, ..
+            timeout,
+            plc_url,
+            did_cache,
+            allow_local_http,
         } = opts;
         let timeout = timeout.unwrap_or_else(|| Duration::new(3, 0));
         let plc_url = plc_url.unwrap_or_else(|| "https://plc.directory".to_string());
@@ -43,13 +49,14 @@ impl DidResolver {
         );
         methods.insert(
             "web".to_string(),
-            ResolverKind::Web(DidWebResolver::new(timeout, None)),
+            ResolverKind::Web(DidWebResolver::new(timeout, None, allow_local_http)),
         );
 
         // do not pass cache to sub-methods, or we will be double caching
         Self {
-            cache: Some(opts.did_cache),
+            cache: did_cache,
             methods,
+            refreshing: InFlight::default(),
         }
     }
 
@@ -73,7 +80,7 @@ impl DidResolver {
                 Ok(doc)
             }
             Err(err) => {
-                eprintln!("Failed at parsing: {err}");
+                tracing::debug!("Failed to parse DID document of `{did}`: {err}");
                 bail!(Error::PoorlyFormattedDidDocumentError(val))
             }
         }
@@ -86,14 +93,13 @@ impl DidResolver {
         }
     }
 
-    pub async fn refresh_cache(&mut self, did: String) -> Result<()> {
-        let resolver = self.clone();
+    /// Resolves `did` again and stores the result, including a DID that's positively not found
+    pub async fn refresh_cache(&self, did: String) -> Result<()> {
         match self.cache {
             None => Ok(()),
-            Some(ref mut cache) => {
-                cache
-                    .refresh_cache(did.clone(), || resolver.resolve_no_cache(&did))
-                    .await
+            Some(ref cache) => {
+                let doc = self.resolve_no_cache(&did).await?;
+                cache.cache_did(did, doc).await
             }
         }
     }
@@ -103,40 +109,37 @@ impl DidResolver {
         did: String,
         force_refresh: Option<bool>,
     ) -> Result<Option<DidDocument>> {
-        let from_cache: Option<CacheResult>;
         let force_refresh = force_refresh.unwrap_or(false);
         match self.cache {
-            None => (),
-            Some(ref cache) if !force_refresh => {
-                from_cache = cache.check_cache(did.clone())?;
-                match from_cache {
-                    None => (),
-                    Some(from_cache) if !from_cache.expired => {
-                        if from_cache.stale {
-                            self.refresh_cache(did).await?;
-                        }
-                        return Ok(Some(from_cache.doc));
+            Some(ref cache) if !force_refresh => match cache.check_cache(did.clone()).await {
+                Ok(Some(from_cache)) if !from_cache.expired => {
+                    if from_cache.stale && self.refreshing.start(&did) {
+                        // served stale while it's resolved again in the background
+                        let resolver = self.clone();
+                        let did = did.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = resolver.refresh_cache(did.clone()).await {
+                                tracing::warn!("Failed to refresh cached DID `{did}`: {err}");
+                            }
+                            resolver.refreshing.finish(&did);
+                        });
                     }
-                    _ => (),
+                    return Ok(from_cache.doc);
                 }
-            }
+                // an unreadable cache is treated as a miss
+                Err(err) => tracing::warn!("Failed to read DID cache: {err}"),
+                _ => (),
+            },
             _ => (),
         }
 
-        match self.resolve_no_cache(&did).await? {
-            None => {
-                if let Some(ref mut cache) = self.cache {
-                    cache.clear_entry(did)?;
-                }
-                Ok(None)
-            }
-            Some(got) => {
-                if let Some(ref mut cache) = self.cache {
-                    cache.cache_did(did, got.clone()).await?;
-                }
-                Ok(Some(got))
+        let got = self.resolve_no_cache(&did).await?;
+        if let Some(ref cache) = self.cache {
+            if let Err(err) = cache.cache_did(did, got.clone()).await {
+                tracing::warn!("Failed to write DID cache: {err}");
             }
         }
+        Ok(got)
     }
 
     pub async fn ensure_resolve(
@@ -1,47 +1,37 @@
+use crate::cache::DidCache;
 use crate::common::decode_uri_component;
 use crate::errors::Error;
-use crate::types::DidCache;
 use anyhow::{bail, Result};
 use serde_json::Value;
+use std::sync::Arc;
 use std::time::Duration;
-use url::Url;
+use url::{Host, Url};
 
 pub const DOC_PATH: &str = "/.well-known/did.json";
 
 #[derive(Clone, Debug)]
 pub struct DidWebResolver {
     pub timeout: Duration,
-    pub cache: Option<DidCache>,
+    pub cache: Option<Arc<dyn DidCache>>,
+    /// Fetch local hosts over plain http, for development only
+    pub allow_local_http: bool,
 }
 
 impl DidWebResolver {
-    pub fn new(timeout: Duration, cache: Option<DidCache>) -> Self {
-        Self { timeout, cache }
+    pub fn new(
+        timeout: Duration,
+        cache: Option<Arc<dyn DidCache>>,
+        allow_local_http: bool,
+    ) -> Self {
+        Self {
+            timeout,
+            cache,
+            allow_local_http,
+        }
     }
 
     pub async fn resolve_no_check(&self, did: String) -> Result<Option<Value>> {
-        let parsed_id: String = did.split(":").collect::<Vec<&str>>()[2..].join(":");
-        let parts = parsed_id
-            .split(":")
-            .into_iter()
-            .map(|part| decode_uri_component(part))
-            .collect::<Result<Vec<String>>>()?;
-        let path: String;
-        if parts.len() < 1 {
-            bail!(Error::PoorlyFormattedDidError(did))
-        } else if parts.len() == 1 {
-            path = parts[0].clone() + DOC_PATH;
-        } else {
-            // how we *would* resolve a did:web with path, if atproto supported it
-            // path = parts.join('/') + "/did.json";
-            bail!(Error::UnsupportedDidWebPathError(did))
-        }
-
-        let mut url = Url::parse(&format!("https://{path}"))?;
-
-        if url.host_str() == Some("localhost") {
-            let _ = url.set_scheme("http");
-        }
+        let url = did_web_to_url(&did, self.allow_local_http)?;
 
         let client = reqwest::Client::new();
         let response = client
@@ -60,3 +50,82 @@ impl DidWebResolver {
         }
     }
 }
+
+/// Where the DID Document of a `did:web` is served. A port is percent encoded into the DID
+/// (`did:web:localhost%3A2583`), and with `allow_local_http` local hosts are fetched over plain
+/// http for testing.
+pub fn did_web_to_url(did: &str, allow_local_http: bool) -> Result<Url> {
+    let parts = match did.strip_prefix("did:web:") {
+        Some(parsed_id) if !parsed_id.is_empty() => parsed_id
+            .split(":")
+            .map(decode_uri_component)
+            .collect::<Result<Vec<String>>>()?,
+        _ => bail!(Error::PoorlyFormattedDidError(did.to_string())),
+    };
+    if parts.len() > 1 {
+        // how we *would* resolve a did:web with path, if atproto supported it
+        // path = parts.join('/') + "/did.json";
+        bail!(Error::UnsupportedDidWebPathError(did.to_string()))
+    }
+    let host = &parts[0];
+    if host.is_empty() || host.contains(['/', '?', '#', '@']) {
+        bail!(Error::PoorlyFormattedDidError(did.to_string()))
+    }
+
+    let mut url = match Url::parse(&format!("https://{host}{DOC_PATH}")) {
+        Ok(url) => url,
+        Err(_) => bail!(Error::PoorlyFormattedDidError(did.to_string())),
+    };
+    let is_local = match url.host() {
+        Some(Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    };
+    if allow_local_http && is_local {
+        let _ = url.set_scheme("http");
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn did_web_urls() {
+        let url = |did: &str| did_web_to_url(did, false).map(|url| url.to_string());
+        let dev_url = |did: &str| did_web_to_url(did, true).map(|url| url.to_string());
+        assert_eq!(
+            url("did:web:example.com").unwrap(),
+            "https://example.com/.well-known/did.json"
+        );
+        assert_eq!(
+            url("did:web:example.com%3A8443").unwrap(),
+            "https://example.com:8443/.well-known/did.json"
+        );
+        assert_eq!(
+            url("did:web:localhost%3A2583").unwrap(),
+            "https://localhost:2583/.well-known/did.json"
+        );
+        assert_eq!(
+            dev_url("did:web:localhost%3A2583").unwrap(),
+            "http://localhost:2583/.well-known/did.json"
+        );
+        assert_eq!(
+            dev_url("did:web:pds.localhost").unwrap(),
+            "http://pds.localhost/.well-known/did.json"
+        );
+        assert_eq!(
+            dev_url("did:web:127.0.0.1%3A2583").unwrap(),
+            "http://127.0.0.1:2583/.well-known/did.json"
+        );
+        assert_eq!(
+            dev_url("did:web:example.com").unwrap(),
+            "https://example.com/.well-known/did.json"
+        );
+        assert!(url("did:web:example.com:user:alice").is_err());
+        assert!(url("did:web:").is_err());
+        assert!(url("did:web:example.com%2Fpath").is_err());
+    }
+}
@@ -13,4 +13,22 @@ pub enum Error {
     PoorlyFormattedDidDocumentError(Value),
     #[error("Unsupported did:web paths: `{0}`")]
     UnsupportedDidWebPathError(String),
+    #[error("Poorly formatted handle: `{0}`")]
+    PoorlyFormattedHandleError(String),
+    #[error("Could not resolve handle: `{0}`")]
+    HandleNotFoundError(String),
+    #[error("DID Document of `{0}` does not claim a handle")]
+    MissingHandleError(String),
+    #[error("DID Document of `{0}` has no atproto signing key")]
+    MissingSigningKeyError(String),
+    #[error("DID Document of `{0}` has no valid PDS endpoint")]
+    MissingPdsEndpointError(String),
+    #[error("Handle `{handle}` resolves to `{did}`, whose DID Document does not claim it")]
+    HandleNotClaimedError { handle: String, did: String },
+    #[error("Handle `{handle}` claimed by `{did}` resolves to `{resolved}` instead")]
+    HandleMismatchError {
+        handle: String,
+        did: String,
+        resolved: String,
+    },
 }
@@ -1,3 +1,4 @@
+use crate::cache::{DidCache, InFlight};
 use crate::types::HandleResolverOpts;
 use anyhow::Result;
 use hickory_resolver::config::*;
@@ -5,6 +6,7 @@ use hickory_resolver::error::ResolveResult;
 use hickory_resolver::lookup_ip::LookupIp;
 use hickory_resolver::Resolver;
 use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
 use std::time::Duration;
 use url::Url;
 
@@ -16,6 +18,8 @@ pub struct HandleResolver {
     pub timeout: Duration,
     backup_nameservers: Option<Vec<String>>,
     backup_nameserver_ips: Option<Vec<IpAddr>>,
+    pub cache: Option<Arc<dyn DidCache>>,
+    refreshing: InFlight,
 }
 
 impl HandleResolver {
@@ -24,20 +28,61 @@ impl HandleResolver {
             timeout: opts.timeout.unwrap_or(Duration::from_millis(3000)),
             backup_nameservers: opts.backup_nameservers,
             backup_nameserver_ips: None,
+            cache: opts.cache,
+            refreshing: InFlight::default(),
         }
     }
 
     pub async fn resolve(&mut self, handle: &String) -> Result<Option<String>> {
-        let dns_future = self.resolve_dns(handle);
-        let http_future = self.resolve_http(handle);
-
-        match dns_future.await {
-            Ok(dns_res) => Ok(dns_res),
-            Err(_) => match http_future.await {
-                Ok(http_res) => Ok(http_res),
-                Err(_) => self.resolve_backup_dns(handle).await,
-            },
+        if let Some(cache) = self.cache.clone() {
+            match cache.check_handle_cache(handle.clone()).await {
+                Ok(Some(from_cache)) if !from_cache.expired => {
+                    if from_cache.stale && self.refreshing.start(handle) {
+                        // served stale while it's resolved again in the background
+                        let mut resolver = self.clone();
+                        let handle = handle.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = resolver.refresh_cache(&handle).await {
+                                tracing::warn!("Failed to refresh cached handle `{handle}`: {err}");
+                            }
+                            resolver.refreshing.finish(&handle);
+                        });
+                    }
+                    return Ok(from_cache.did);
+                }
+                // an unreadable cache is treated as a miss
+                Err(err) => tracing::warn!("Failed to read handle cache: {err}"),
+                _ => (),
+            }
+        }
+
+        let did = self.resolve_no_cache(handle).await?;
+        if let Some(ref cache) = self.cache {
+            if let Err(err) = cache.cache_handle(handle.clone(), did.clone()).await {
+                tracing::warn!("Failed to write handle cache: {err}");
+            }
+        }
+        Ok(did)
+    }
+
+    /// Resolves `handle` again and stores the result, including a handle that didn't resolve
+    pub async fn refresh_cache(&mut self, handle: &String) -> Result<()> {
+        let did = self.resolve_no_cache(handle).await?;
+        match self.cache {
+            None => Ok(()),
+            Some(ref cache) => cache.cache_handle(handle.clone(), did).await,
+        }
+    }
+
+    pub async fn resolve_no_cache(&mut self, handle: &String) -> Result<Option<String>> {
+        // a handle without a TXT record can still be served over http
+        if let Ok(Some(did)) = self.resolve_dns(handle).await {
+            return Ok(Some(did));
+        }
+        if let Ok(Some(did)) = self.resolve_http(handle).await {
+            return Ok(Some(did));
         }
+        self.resolve_backup_dns(handle).await
     }
 
     pub async fn resolve_dns(&self, handle: &String) -> Result<Option<String>> {
@@ -61,12 +106,13 @@ impl HandleResolver {
 
         let res = client
             .get(url.as_str())
+            .timeout(self.timeout)
             .header("Connection", "Keep-Alive")
             .header("Keep-Alive", "timeout=5, max=1000")
             .send()
             .await?;
 
-        let res = res.text().await?;
+        let res = res.error_for_status()?.text().await?;
 
         let did = match res.split("\n").collect::<Vec<&str>>().first() {
             None => return Ok(None),
@@ -1,8 +1,11 @@
 extern crate url;
 
+use crate::did::atproto_data::ensure_atp_document;
 use crate::did::did_resolver::DidResolver;
+use crate::errors::Error;
 use crate::handle::HandleResolver;
-use crate::types::{DidCache, DidResolverOpts, HandleResolverOpts, IdentityResolverOpts};
+use crate::types::{AtprotoData, DidResolverOpts, HandleResolverOpts, IdentityResolverOpts};
+use anyhow::{bail, Result};
 use std::time::Duration;
 
 #[derive(Clone, Debug)]
@@ -18,28 +21,218 @@ impl IdResolver {
             plc_url,
             did_cache,
             backup_nameservers,
+            allow_local_http,
         } = opts;
         let timeout = timeout.unwrap_or_else(|| Duration::from_millis(3000));
-        let did_cache = did_cache.unwrap_or_else(|| DidCache {
-            stale_ttl: Default::default(),
-            max_ttl: Default::default(),
-            cache: Default::default(),
-        });
 
         Self {
             handle: HandleResolver::new(HandleResolverOpts {
                 timeout: Some(timeout),
                 backup_nameservers,
+                cache: did_cache.clone(),
             }),
             did: DidResolver::new(DidResolverOpts {
                 timeout: Some(timeout),
                 plc_url,
                 did_cache,
+                allow_local_http,
             }),
         }
     }
+
+    /// Resolves a handle or DID to an identity whose DID Document claims the handle and whose
+    /// handle resolves back to the same DID. `force_refresh` skips the cache for both lookups.
+    pub async fn resolve_identity(
+        &mut self,
+        handle_or_did: &String,
+        force_refresh: Option<bool>,
+    ) -> Result<AtprotoData> {
+        let force_refresh = force_refresh.unwrap_or(false);
+        if handle_or_did.starts_with("did:") {
+            let doc = self
+                .did
+                .ensure_resolve(handle_or_did, Some(force_refresh))
+                .await?;
+            let data = ensure_atp_document(&doc)?;
+            match self.resolve_handle(&data.handle, force_refresh).await? {
+                Some(resolved) if resolved == data.did => Ok(data),
+                Some(resolved) => bail!(Error::HandleMismatchError {
+                    handle: data.handle,
+                    did: data.did,
+                    resolved,
+                }),
+                None => bail!(Error::HandleNotFoundError(data.handle)),
+            }
+        } else {
+            let handle = handle_or_did.to_lowercase();
+            if handle.is_empty() || !handle.contains(".") {
+                bail!(Error::PoorlyFormattedHandleError(handle));
+            }
+            let did = match self.resolve_handle(&handle, force_refresh).await? {
+                Some(did) => did,
+                None => bail!(Error::HandleNotFoundError(handle)),
+            };
+            let doc = self.did.ensure_resolve(&did, Some(force_refresh)).await?;
+            let data = ensure_atp_document(&doc)?;
+            if data.handle != handle {
+                bail!(Error::HandleNotClaimedError { handle, did });
+            }
+            Ok(data)
+        }
+    }
+
+    async fn resolve_handle(
+        &mut self,
+        handle: &String,
+        force_refresh: bool,
+    ) -> Result<Option<String>> {
+        match force_refresh {
+            true => self.handle.resolve_no_cache(handle).await,
+            false => self.handle.resolve(handle).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::memory::MemoryCache;
+    use crate::cache::DidCache;
+    use crate::types::{DidDocument, Service, VerificationMethod};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    const DID: &str = "did:plc:ewvi7nxzyoun6zhxrhs64oiz";
+    const HANDLE: &str = "alice.test";
+
+    fn doc(handle: &str) -> DidDocument {
+        DidDocument {
+            context: None,
+            id: DID.to_string(),
+            also_known_as: Some(vec![format!("at://{handle}")]),
+            verification_method: Some(vec![VerificationMethod {
+                id: "#atproto".to_string(),
+                r#type: "Multikey".to_string(),
+                controller: DID.to_string(),
+                public_key_multibase: Some(
+                    "zQ3shZc2QzApp2oymGvQbzP8eKheVshBHbU4ZYjeXqwSKEn6N".to_string(),
+                ),
+            }]),
+            service: Some(vec![Service {
+                id: "#atproto_pds".to_string(),
+                r#type: "AtprotoPersonalDataServer".to_string(),
+                service_endpoint: "https://pds.example.com".to_string(),
+            }]),
+        }
+    }
+
+    /// A resolver whose cache maps `handle` to `handle_did` and `DID` to a document claiming
+    /// `claimed`, so nothing is looked up over the network
+    async fn resolver(handle: &str, handle_did: &str, claimed: &str) -> IdResolver {
+        resolver_with_plc(handle, handle_did, claimed, None).await
+    }
+
+    async fn resolver_with_plc(
+        handle: &str,
+        handle_did: &str,
+        claimed: &str,
+        plc_url: Option<String>,
+    ) -> IdResolver {
+        let cache = Arc::new(MemoryCache::default());
+        cache
+            .cache_handle(handle.to_string(), Some(handle_did.to_string()))
+            .await
+            .unwrap();
+        cache
+            .cache_did(DID.to_string(), Some(doc(claimed)))
+            .await
+            .unwrap();
+        IdResolver::new(IdentityResolverOpts {
+            timeout: Some(Duration::from_millis(100)),
+            plc_url,
+            did_cache: Some(cache),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn resolves_both_directions() {
+        let mut resolver = resolver(HANDLE, DID, HANDLE).await;
+        for handle_or_did in [DID, HANDLE, "Alice.TEST"] {
+            let data = resolver
+                .resolve_identity(&handle_or_did.to_string(), None)
+                .await
+                .unwrap();
+            assert_eq!(data.did, DID);
+            assert_eq!(data.handle, HANDLE);
+            assert_eq!(data.pds, "https://pds.example.com");
+            assert_eq!(
+                data.signing_key,
+                "did:key:zQ3shZc2QzApp2oymGvQbzP8eKheVshBHbU4ZYjeXqwSKEn6N"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_a_handle_resolving_to_another_did() {
+        let mut resolver = resolver(HANDLE, "did:plc:someoneelse", HANDLE).await;
+        let error = resolver
+            .resolve_identity(&DID.to_string(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::HandleMismatchError { resolved, .. }) if resolved == "did:plc:someoneelse"
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_handle_the_did_does_not_claim() {
+        let mut resolver = resolver(HANDLE, DID, "bob.test").await;
+        let error = resolver
+            .resolve_identity(&HANDLE.to_string(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::HandleNotClaimedError { handle, did }) if handle == HANDLE && did == DID
+        ));
+    }
+
+    /// A PLC directory that doesn't know any DID
+    fn empty_plc() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 4096]);
+                let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n");
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn force_refresh_skips_the_cache() {
+        let mut resolver = resolver_with_plc(HANDLE, DID, HANDLE, Some(empty_plc())).await;
+        assert!(resolver
+            .resolve_identity(&DID.to_string(), Some(false))
+            .await
+            .is_ok());
+        // only the cached document knows the DID
+        let error = resolver
+            .resolve_identity(&DID.to_string(), Some(true))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::DidNotFoundError(did)) if did == DID
+        ));
+    }
 }
 
+pub mod cache;
 pub mod common;
 pub mod did;
 pub mod errors;
@@ -73,6 +73,11 @@ pub struct SendMailOutput {
     pub sent: bool,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GetAccountInfosOutput {
+    pub infos: Vec<AccountView>,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct GetInviteCodesOutput {
     #[serde(skip_serializing_if = "Option::is_none")]
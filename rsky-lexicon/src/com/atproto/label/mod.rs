@@ -8,6 +8,15 @@ pub struct SubscribeLabels {
     pub labels: Vec<Label>,
 }
 
+/// Find labels relevant to the provided AT-URI patterns. Public endpoint for moderation services,
+/// though may return different or additional results with auth.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryLabelsOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub labels: Vec<Label>,
+}
+
 /// Metadata tag on an atproto resource (eg, repo or record).
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Label {
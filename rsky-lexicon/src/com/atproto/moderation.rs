@@ -0,0 +1,27 @@
+use crate::com::atproto::admin::Subject;
+use serde::{Deserialize, Serialize};
+
+/// Submit a moderation report regarding an atproto account or record. Implemented by moderation
+/// services (with PDS proxying), and requires auth.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReportInput {
+    /// Indicates the broad category of violation the report is for.
+    pub reason_type: String,
+    /// Additional context about the content and violation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub subject: Subject,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReportOutput {
+    pub id: i64,
+    pub reason_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub subject: Subject,
+    pub reported_by: String,
+    pub created_at: String,
+}
@@ -47,6 +47,20 @@ pub struct GetLatestCommitOutput {
     pub rev: String,
 }
 
+/// DEPRECATED - please use com.atproto.sync.getLatestCommit instead
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetHeadOutput {
+    pub root: String,
+}
+
+/// Notify a crawling service of a recent update, and that crawling should resume. Intended use
+/// is after a gap between repo stream events caused the crawling service to disconnect.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NotifyOfUpdateInput {
+    /// Hostname of the current service (usually a PDS) that is notifying of update.
+    pub hostname: String,
+}
+
 /// Get the hosting status for a repository, on this server.
 /// Expected to be implemented by PDS and Relay.
 #[derive(Clone, Debug, Serialize, Deserialize)]
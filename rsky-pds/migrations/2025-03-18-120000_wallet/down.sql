@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS pds.wallet_address_idx;
+DROP TABLE IF EXISTS pds.wallet;
@@ -0,0 +1,9 @@
+-- Create Wallet Table
+CREATE TABLE IF NOT EXISTS pds.wallet (
+    did character varying PRIMARY KEY,
+    address character varying NOT NULL,
+    "encryptedKey" character varying NOT NULL,
+    "createdAt" character varying NOT NULL
+);
+CREATE UNIQUE INDEX wallet_address_idx
+    ON pds.wallet(address);
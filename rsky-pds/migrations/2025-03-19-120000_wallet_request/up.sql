@@ -0,0 +1,13 @@
+-- Create Wallet Request Table
+CREATE TABLE IF NOT EXISTS pds.wallet_request (
+    id character varying PRIMARY KEY,
+    did character varying NOT NULL,
+    amount character varying NOT NULL,
+    description character varying NOT NULL,
+    status character varying NOT NULL,
+    "fulfilledBy" character varying,
+    "createdAt" character varying NOT NULL,
+    "updatedAt" character varying NOT NULL
+);
+CREATE INDEX wallet_request_did_idx
+    ON pds.wallet_request(did, "createdAt");
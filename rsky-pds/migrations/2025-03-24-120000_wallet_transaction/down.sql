@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS pds.wallet_index_state;
+DROP INDEX IF EXISTS pds.wallet_transaction_cursor_idx;
+DROP TABLE IF EXISTS pds.wallet_transaction;
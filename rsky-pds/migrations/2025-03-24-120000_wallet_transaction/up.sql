@@ -0,0 +1,30 @@
+-- Create Wallet Transaction Table
+-- One row per wallet address involved in a Firefly transfer
+CREATE TABLE IF NOT EXISTS pds.wallet_transaction (
+    id character varying NOT NULL,
+    address character varying NOT NULL,
+    direction character varying NOT NULL,
+    counterparty character varying NOT NULL,
+    amount character varying NOT NULL,
+    description character varying NOT NULL,
+    cost character varying NOT NULL,
+    "blockHash" character varying NOT NULL,
+    "blockNumber" bigint NOT NULL,
+    "createdAt" bigint NOT NULL
+);
+
+ALTER TABLE ONLY pds.wallet_transaction
+    DROP CONSTRAINT IF EXISTS wallet_transaction_pkey;
+ALTER TABLE ONLY pds.wallet_transaction
+    ADD CONSTRAINT wallet_transaction_pkey PRIMARY KEY (id, address);
+CREATE INDEX wallet_transaction_cursor_idx
+    ON pds.wallet_transaction(address, "createdAt", id);
+
+-- Create Wallet Index State Table
+-- Tracks the last Firefly block the transaction index has caught up with
+CREATE TABLE IF NOT EXISTS pds.wallet_index_state (
+    id character varying PRIMARY KEY,
+    "lastBlockHash" character varying NOT NULL,
+    "lastBlockNumber" bigint NOT NULL,
+    "updatedAt" character varying NOT NULL
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS pds.oauth_authorized_client;
+DROP INDEX IF EXISTS pds.oauth_token_did_idx;
+DROP TABLE IF EXISTS pds.oauth_token;
+DROP TABLE IF EXISTS pds.oauth_request;
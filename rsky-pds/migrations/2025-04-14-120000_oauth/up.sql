@@ -0,0 +1,39 @@
+-- Create OAuth Request Table
+-- Pushed authorization requests, holding the authorization code once the user approved them
+CREATE TABLE IF NOT EXISTS pds.oauth_request (
+    id character varying PRIMARY KEY,
+    "clientId" character varying NOT NULL,
+    parameters character varying NOT NULL,
+    "dpopJkt" character varying NOT NULL,
+    did character varying,
+    code character varying UNIQUE,
+    "expiresAt" character varying NOT NULL,
+    "createdAt" character varying NOT NULL
+);
+
+-- Create OAuth Token Table
+-- One row per authorized session, the refresh token rotates on every use
+CREATE TABLE IF NOT EXISTS pds.oauth_token (
+    id character varying PRIMARY KEY,
+    did character varying NOT NULL,
+    "clientId" character varying NOT NULL,
+    scope character varying NOT NULL,
+    "dpopJkt" character varying NOT NULL,
+    "refreshToken" character varying NOT NULL UNIQUE,
+    "expiresAt" character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    "updatedAt" character varying NOT NULL
+);
+CREATE INDEX oauth_token_did_idx
+    ON pds.oauth_token(did);
+
+-- Create OAuth Authorized Client Table
+-- Clients a user consented to, along with the scope they were granted
+CREATE TABLE IF NOT EXISTS pds.oauth_authorized_client (
+    did character varying NOT NULL,
+    "clientId" character varying NOT NULL,
+    scope character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    "updatedAt" character varying NOT NULL,
+    PRIMARY KEY (did, "clientId")
+);
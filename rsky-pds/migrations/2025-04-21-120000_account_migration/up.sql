@@ -0,0 +1,14 @@
+-- Create Account Migration Table
+-- Progress of moving an account between two PDS instances, so an interrupted migration resumes
+-- at the step it stopped at. Credentials are never stored and have to be supplied again.
+CREATE TABLE IF NOT EXISTS pds.account_migration (
+    did character varying PRIMARY KEY,
+    "oldPds" character varying NOT NULL,
+    "newPds" character varying NOT NULL,
+    step character varying NOT NULL,
+    status character varying NOT NULL,
+    "blobsImported" integer NOT NULL DEFAULT 0,
+    error character varying,
+    "createdAt" character varying NOT NULL,
+    "updatedAt" character varying NOT NULL
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS pds.job_status_run_at_idx;
+DROP TABLE IF EXISTS pds.job;
@@ -0,0 +1,19 @@
+-- Create Job Table
+-- Deferred work run by the job worker. Failed jobs are retried with backoff until they run out
+-- of attempts and are left as `dead` for inspection. Scheduled jobs carry a unique key so only
+-- one of each is queued at a time.
+CREATE TABLE IF NOT EXISTS pds.job (
+    id bigserial PRIMARY KEY,
+    kind character varying NOT NULL,
+    payload character varying NOT NULL,
+    status character varying NOT NULL,
+    attempts integer NOT NULL DEFAULT 0,
+    "lastError" character varying,
+    "uniqueKey" character varying UNIQUE,
+    "runAt" character varying NOT NULL,
+    "lockedAt" character varying,
+    "createdAt" character varying NOT NULL,
+    "updatedAt" character varying NOT NULL
+);
+CREATE INDEX job_status_run_at_idx
+    ON pds.job(status, "runAt");
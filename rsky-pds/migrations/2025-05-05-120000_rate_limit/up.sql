@@ -0,0 +1,8 @@
+-- Create Rate Limit Table
+-- Fixed window counters for the postgres rate limit store, keyed by limit name and ip or did.
+-- A window starts over once "resetAt" (ms since epoch) has passed.
+CREATE TABLE IF NOT EXISTS pds.rate_limit (
+    key character varying PRIMARY KEY,
+    points bigint NOT NULL,
+    "resetAt" bigint NOT NULL
+);
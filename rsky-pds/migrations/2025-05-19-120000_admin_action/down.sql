@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS pds.admin_action_did_idx;
+DROP INDEX IF EXISTS pds.admin_action_batch_id_idx;
+DROP TABLE IF EXISTS pds.admin_action;
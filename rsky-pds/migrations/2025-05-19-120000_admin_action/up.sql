@@ -0,0 +1,18 @@
+-- Create Admin Action Table
+-- Audit trail of bulk moderation actions. Every account touched by a batch gets a row with the
+-- outcome, so a partially failed batch can be inspected and retried for the failed accounts.
+CREATE TABLE IF NOT EXISTS pds.admin_action (
+    id bigserial PRIMARY KEY,
+    "batchId" character varying NOT NULL,
+    action character varying NOT NULL,
+    did character varying NOT NULL,
+    params character varying,
+    "performedBy" character varying NOT NULL,
+    outcome character varying NOT NULL,
+    error character varying,
+    "createdAt" character varying NOT NULL
+);
+CREATE INDEX admin_action_batch_id_idx
+    ON pds.admin_action("batchId");
+CREATE INDEX admin_action_did_idx
+    ON pds.admin_action(did);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS pds.job_unique_key_idx;
+ALTER TABLE pds.job ADD CONSTRAINT "job_uniqueKey_key" UNIQUE ("uniqueKey");
@@ -0,0 +1,5 @@
+-- Only one pending or running job per unique key. A dead job with the same key no longer keeps
+-- it from being queued again.
+ALTER TABLE pds.job DROP CONSTRAINT IF EXISTS "job_uniqueKey_key";
+CREATE UNIQUE INDEX IF NOT EXISTS job_unique_key_idx
+    ON pds.job("uniqueKey") WHERE status IN ('pending', 'running');
@@ -0,0 +1,8 @@
+-- Create OAuth Used Jti Table
+-- DPoP proofs and client assertions that were already presented, shared between PDS instances
+-- so one can't be replayed against another. A jti is remembered until "expiresAt" (seconds since
+-- epoch), after which the proof would be rejected as expired anyway.
+CREATE TABLE IF NOT EXISTS pds.oauth_used_jti (
+    jti character varying PRIMARY KEY,
+    "expiresAt" bigint NOT NULL
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS pds.handle_cache;
+DROP TABLE IF EXISTS pds.did_cache;
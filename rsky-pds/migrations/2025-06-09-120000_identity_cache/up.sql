@@ -0,0 +1,16 @@
+-- Create Identity Cache Tables
+-- Resolved DID documents and handles shared between PDS instances. "updatedAt" is microseconds
+-- since epoch; a null doc or did remembers a lookup that found nothing.
+CREATE TABLE IF NOT EXISTS pds.did_cache (
+    did character varying PRIMARY KEY,
+    doc text,
+    "updatedAt" bigint NOT NULL
+);
+CREATE INDEX IF NOT EXISTS did_cache_updated_at_idx ON pds.did_cache ("updatedAt");
+
+CREATE TABLE IF NOT EXISTS pds.handle_cache (
+    handle character varying PRIMARY KEY,
+    did character varying,
+    "updatedAt" bigint NOT NULL
+);
+CREATE INDEX IF NOT EXISTS handle_cache_updated_at_idx ON pds.handle_cache ("updatedAt");
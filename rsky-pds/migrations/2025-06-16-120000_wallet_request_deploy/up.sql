@@ -0,0 +1,3 @@
+-- Deploy paying a transfer request, replaced whenever an expired deploy is submitted again. A
+-- pending request is settled by the indexed transaction of this deploy only.
+ALTER TABLE pds.wallet_request ADD COLUMN IF NOT EXISTS "deployId" character varying;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE pds.wallet_transaction DROP COLUMN IF EXISTS succeeded;
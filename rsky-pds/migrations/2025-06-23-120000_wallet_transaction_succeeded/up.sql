@@ -0,0 +1,3 @@
+-- Transfers refused by the vault still land in a block. Rows indexed before this column existed
+-- are assumed to have succeeded.
+ALTER TABLE pds.wallet_transaction ADD COLUMN IF NOT EXISTS succeeded boolean NOT NULL DEFAULT true;
@@ -0,0 +1,10 @@
+-- Create Wallet Retired Table
+-- Keys an imported wallet replaced, kept so funds left at their addresses can still be moved
+CREATE TABLE IF NOT EXISTS pds.wallet_retired (
+    did character varying NOT NULL,
+    address character varying NOT NULL,
+    "encryptedKey" character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    "retiredAt" character varying NOT NULL,
+    PRIMARY KEY (did, address)
+);
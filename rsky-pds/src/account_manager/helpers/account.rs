@@ -14,6 +14,7 @@ use diesel::*;
 use rsky_common;
 use rsky_common::RFC3339_VARIANT;
 use rsky_lexicon::com::atproto::admin::StatusAttr;
+use std::collections::BTreeMap;
 use std::ops::Add;
 use std::time::SystemTime;
 use thiserror::Error;
@@ -147,6 +148,59 @@ pub async fn get_account(
     Ok(found)
 }
 
+pub async fn get_accounts(
+    dids: &Vec<String>,
+    flags: Option<AvailabilityFlags>,
+) -> Result<BTreeMap<String, ActorAccount>> {
+    if dids.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let conn = &mut establish_connection()?;
+    let found = select_account_qb(flags)
+        .filter(ActorSchema::did.eq_any(dids))
+        .select((
+            ActorSchema::did,
+            ActorSchema::handle,
+            ActorSchema::createdAt,
+            ActorSchema::takedownRef,
+            ActorSchema::deactivatedAt,
+            ActorSchema::deleteAfter,
+            AccountSchema::email.nullable(),
+            AccountSchema::emailConfirmedAt.nullable(),
+            AccountSchema::invitesDisabled.nullable(),
+        ))
+        .load::<(
+            String,
+            Option<String>,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<i16>,
+        )>(conn)?;
+    Ok(found
+        .into_iter()
+        .map(|res| {
+            (
+                res.0.clone(),
+                ActorAccount {
+                    did: res.0,
+                    handle: res.1,
+                    created_at: res.2,
+                    takedown_ref: res.3,
+                    deactivated_at: res.4,
+                    delete_after: res.5,
+                    email: res.6,
+                    email_confirmed_at: res.7,
+                    invites_disabled: res.8,
+                },
+            )
+        })
+        .collect())
+}
+
 pub async fn get_account_by_email(
     email: &String,
     flags: Option<AvailabilityFlags>,
@@ -245,6 +299,8 @@ pub fn register_account(did: String, email: String, password: String) -> Result<
 
 pub async fn delete_account(did: &String) -> Result<()> {
     use crate::schema::pds::email_token::dsl as EmailTokenSchema;
+    use crate::schema::pds::oauth_authorized_client::dsl as AuthorizedClientSchema;
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
     use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
     use crate::schema::pds::repo_root::dsl as RepoRootSchema;
 
@@ -258,6 +314,12 @@ pub async fn delete_account(did: &String) -> Result<()> {
     delete(RefreshTokenSchema::refresh_token)
         .filter(RefreshTokenSchema::did.eq(did))
         .execute(conn)?;
+    delete(OAuthTokenSchema::oauth_token)
+        .filter(OAuthTokenSchema::did.eq(did))
+        .execute(conn)?;
+    delete(AuthorizedClientSchema::oauth_authorized_client)
+        .filter(AuthorizedClientSchema::did.eq(did))
+        .execute(conn)?;
     delete(AccountSchema::account)
         .filter(AccountSchema::did.eq(did))
         .execute(conn)?;
@@ -167,6 +167,41 @@ pub async fn get_account_invite_codes(did: &String) -> Result<Vec<CodeDetail>> {
         .collect::<Vec<CodeDetail>>())
 }
 
+pub async fn get_accounts_invite_codes(
+    dids: &Vec<String>,
+) -> Result<BTreeMap<String, Vec<CodeDetail>>> {
+    if dids.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    use crate::schema::pds::invite_code::dsl as InviteCodeSchema;
+    let conn = &mut establish_connection()?;
+
+    let res: Vec<models::InviteCode> = InviteCodeSchema::invite_code
+        .filter(InviteCodeSchema::forAccount.eq_any(dids))
+        .select(models::InviteCode::as_select())
+        .get_results(conn)?;
+    let codes: Vec<String> = res.iter().map(|row| row.code.clone()).collect();
+    let mut uses = get_invite_codes_uses(codes).await?;
+    Ok(res.into_iter().fold(
+        BTreeMap::new(),
+        |mut acc: BTreeMap<String, Vec<CodeDetail>>, row| {
+            let code_uses = mem::take(uses.get_mut(&row.code).unwrap_or(&mut Vec::new()));
+            acc.entry(row.for_account.clone())
+                .or_default()
+                .push(CodeDetail {
+                    code: row.code,
+                    available: row.available_uses,
+                    disabled: row.disabled == 1,
+                    for_account: row.for_account,
+                    created_by: row.created_by,
+                    created_at: row.created_at,
+                    uses: code_uses,
+                });
+            acc
+        },
+    ))
+}
+
 pub async fn get_invite_codes_uses(codes: Vec<String>) -> Result<BTreeMap<String, Vec<CodeUse>>> {
     use crate::schema::pds::invite_code_use::dsl as InviteCodeUseSchema;
     let conn = &mut establish_connection()?;
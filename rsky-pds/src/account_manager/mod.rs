@@ -9,6 +9,7 @@ use crate::account_manager::helpers::password::UpdateUserPasswordOpts;
 use crate::account_manager::helpers::repo;
 use crate::auth_verifier::AuthScope;
 use crate::models::models::EmailTokenPurpose;
+use crate::oauth;
 use anyhow::Result;
 use chrono::offset::Utc as UtcOffset;
 use chrono::DateTime;
@@ -73,6 +74,13 @@ impl AccountManager {
         account::get_account(handle_or_did, flags).await
     }
 
+    pub async fn get_accounts(
+        dids: &Vec<String>,
+        flags: Option<AvailabilityFlags>,
+    ) -> Result<BTreeMap<String, ActorAccount>> {
+        account::get_accounts(dids, flags).await
+    }
+
     pub async fn get_account_by_email(
         email: &String,
         flags: Option<AvailabilityFlags>,
@@ -166,9 +174,10 @@ impl AccountManager {
     }
 
     pub async fn takedown_account(did: &String, takedown: StatusAttr) -> Result<()> {
-        (_, _) = try_join!(
+        (_, _, _) = try_join!(
             account::update_account_takedown_status(did, takedown),
-            auth::revoke_refresh_tokens_by_did(did)
+            auth::revoke_refresh_tokens_by_did(did),
+            oauth::store::revoke_tokens_by_did(did)
         )?;
         Ok(())
     }
@@ -332,6 +341,12 @@ impl AccountManager {
         invite::get_account_invite_codes(did).await
     }
 
+    pub async fn get_accounts_invite_codes(
+        dids: &Vec<String>,
+    ) -> Result<BTreeMap<String, Vec<CodeDetail>>> {
+        invite::get_accounts_invite_codes(dids).await
+    }
+
     pub async fn get_invited_by_for_accounts(
         dids: Vec<&String>,
     ) -> Result<BTreeMap<String, CodeDetail>> {
@@ -1,9 +1,11 @@
 use std::str::FromStr;
 // based on https://github.com/bluesky-social/atproto/blob/main/packages/aws/src/s3.ts
+use crate::actor_store::blobstore::BlobStore;
 use anyhow::Result;
 use aws_config::SdkConfig;
 use aws_sdk_s3 as s3;
 use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::get_object::GetObjectError;
 use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::types::{Delete, ObjectCannedAcl, ObjectIdentifier};
 use lexicon_cid::Cid;
@@ -54,7 +56,95 @@ impl S3BlobStore {
         format!("quarantine/{0}/{1}", self.bucket, cid.to_string())
     }
 
-    pub async fn put_temp(&self, bytes: Vec<u8>) -> Result<String> {
+    fn get_cache_path(&self, cid: Cid) -> String {
+        format!("cache/{0}/{1}", self.bucket, cid.to_string())
+    }
+
+    async fn get_object(&self, cid: Cid) -> Result<ByteStream> {
+        self.get_key(self.get_stored_path(cid)).await
+    }
+
+    async fn get_key(&self, key: String) -> Result<ByteStream> {
+        let res = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await;
+        match res {
+            Ok(res) => Ok(res.body),
+            Err(SdkError::ServiceError(s)) => Err(anyhow::Error::new(s.into_err())),
+            Err(e) => Err(anyhow::Error::new(e.into_service_error())),
+        }
+    }
+
+    async fn has_key(&self, key: String) -> bool {
+        let res = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await;
+        match res {
+            Ok(_) => true,
+            Err(_) => false,
+        }
+    }
+
+    async fn delete_key(&self, key: String) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_many_keys(&self, keys: Vec<String>) -> Result<()> {
+        let objects: Vec<ObjectIdentifier> = keys
+            .into_iter()
+            .map(|key| Ok(ObjectIdentifier::builder().key(key).build()?))
+            .collect::<Result<Vec<ObjectIdentifier>>>()?;
+        let deletes = Delete::builder().set_objects(Some(objects)).build()?;
+        self.client
+            .delete_objects()
+            .bucket(&self.bucket)
+            .delete(deletes)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn move_object(&self, keys: MoveObject) -> Result<()> {
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(format!(
+                "{0}/{1}/{2}",
+                env_str("AWS_ENDPOINT_BUCKET").unwrap(),
+                self.bucket,
+                keys.from
+            ))
+            .key(keys.to)
+            .acl(ObjectCannedAcl::PublicRead)
+            .send()
+            .await?;
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(keys.from)
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put_temp(&self, bytes: Vec<u8>) -> Result<String> {
         let key = self.gen_key();
         let body = ByteStream::from(bytes);
         self.client
@@ -68,7 +158,7 @@ impl S3BlobStore {
         Ok(key)
     }
 
-    pub async fn make_permanent(&self, key: String, cid: Cid) -> Result<()> {
+    async fn make_permanent(&self, key: String, cid: Cid) -> Result<()> {
         let already_has = self.has_stored(cid).await?;
         if !already_has {
             Ok(self
@@ -83,7 +173,7 @@ impl S3BlobStore {
         }
     }
 
-    pub async fn put_permanent(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+    async fn put_permanent(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
         let body = ByteStream::from(bytes);
         self.client
             .put_object()
@@ -96,7 +186,8 @@ impl S3BlobStore {
         Ok(())
     }
 
-    pub async fn quarantine(&self, cid: Cid) -> Result<()> {
+    async fn quarantine(&self, cid: Cid) -> Result<()> {
+        self.delete_cached(cid).await?;
         Ok(self
             .move_object(MoveObject {
                 from: self.get_stored_path(cid),
@@ -105,7 +196,7 @@ impl S3BlobStore {
             .await?)
     }
 
-    pub async fn unquarantine(&self, cid: Cid) -> Result<()> {
+    async fn unquarantine(&self, cid: Cid) -> Result<()> {
         Ok(self
             .move_object(MoveObject {
                 from: self.get_quarantined_path(cid),
@@ -114,38 +205,26 @@ impl S3BlobStore {
             .await?)
     }
 
-    async fn get_object(&self, cid: Cid) -> Result<ByteStream> {
-        let res = self
-            .client
-            .get_object()
-            .bucket(&self.bucket)
-            .key(self.get_stored_path(cid))
-            .send()
-            .await;
-        match res {
-            Ok(res) => Ok(res.body),
-            Err(SdkError::ServiceError(s)) => Err(anyhow::Error::new(s.into_err())),
-            Err(e) => Err(anyhow::Error::new(e.into_service_error())),
-        }
-    }
-
-    pub async fn get_bytes(&self, cid: Cid) -> Result<Vec<u8>> {
+    async fn get_bytes(&self, cid: Cid) -> Result<Vec<u8>> {
         let res = self.get_object(cid).await?;
         let bytes = res.collect().await.map(|data| data.into_bytes())?;
         Ok(bytes.to_vec())
     }
 
-    pub async fn get_stream(&self, cid: Cid) -> Result<ByteStream> {
+    async fn get_stream(&self, cid: Cid) -> Result<ByteStream> {
         Ok(self.get_object(cid).await?)
     }
 
-    pub async fn delete(&self, cid: String) -> Result<()> {
-        Ok(self
-            .delete_key(self.get_stored_path(Cid::from_str(&cid)?))
-            .await?)
+    async fn delete(&self, cid: String) -> Result<()> {
+        let cid = Cid::from_str(&cid)?;
+        self.delete_cached(cid).await?;
+        Ok(self.delete_key(self.get_stored_path(cid)).await?)
     }
 
-    pub async fn delete_many(&self, cids: Vec<Cid>) -> Result<()> {
+    async fn delete_many(&self, cids: Vec<Cid>) -> Result<()> {
+        for cid in cids.iter() {
+            self.delete_cached(*cid).await?;
+        }
         let keys: Vec<String> = cids
             .into_iter()
             .map(|cid| self.get_stored_path(cid))
@@ -153,73 +232,57 @@ impl S3BlobStore {
         Ok(self.delete_many_keys(keys).await?)
     }
 
-    pub async fn has_stored(&self, cid: Cid) -> Result<bool> {
+    async fn has_stored(&self, cid: Cid) -> Result<bool> {
         Ok(self.has_key(self.get_stored_path(cid)).await)
     }
 
-    pub async fn has_temp(&self, key: String) -> Result<bool> {
+    async fn has_temp(&self, key: String) -> Result<bool> {
         Ok(self.has_key(self.get_tmp_path(&key)).await)
     }
 
-    async fn has_key(&self, key: String) -> bool {
-        let res = self
-            .client
-            .head_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .send()
-            .await;
-        match res {
-            Ok(_) => true,
-            Err(_) => false,
-        }
+    async fn delete_temp(&self, key: String) -> Result<()> {
+        self.delete_key(self.get_tmp_path(&key)).await
     }
 
-    async fn delete_key(&self, key: String) -> Result<()> {
-        self.client
-            .delete_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .send()
-            .await?;
-        Ok(())
+    async fn get_cached(&self, cid: Cid, variant: String) -> Result<Option<Vec<u8>>> {
+        let key = format!("{0}/{1}", self.get_cache_path(cid), variant);
+        match self.get_key(key).await {
+            Ok(res) => Ok(Some(res.collect().await?.into_bytes().to_vec())),
+            Err(e) => match e.downcast_ref() {
+                Some(GetObjectError::NoSuchKey(_)) => Ok(None),
+                _ => Err(e),
+            },
+        }
     }
 
-    async fn delete_many_keys(&self, keys: Vec<String>) -> Result<()> {
-        let objects: Vec<ObjectIdentifier> = keys
-            .into_iter()
-            .map(|key| Ok(ObjectIdentifier::builder().key(key).build()?))
-            .collect::<Result<Vec<ObjectIdentifier>>>()?;
-        let deletes = Delete::builder().set_objects(Some(objects)).build()?;
+    async fn put_cached(&self, cid: Cid, variant: String, bytes: Vec<u8>) -> Result<()> {
         self.client
-            .delete_objects()
+            .put_object()
+            .body(ByteStream::from(bytes))
             .bucket(&self.bucket)
-            .delete(deletes)
+            .key(format!("{0}/{1}", self.get_cache_path(cid), variant))
+            .acl(ObjectCannedAcl::PublicRead)
             .send()
             .await?;
         Ok(())
     }
 
-    async fn move_object(&self, keys: MoveObject) -> Result<()> {
-        self.client
-            .copy_object()
-            .bucket(&self.bucket)
-            .copy_source(format!(
-                "{0}/{1}/{2}",
-                env_str("AWS_ENDPOINT_BUCKET").unwrap(),
-                self.bucket,
-                keys.from
-            ))
-            .key(keys.to)
-            .acl(ObjectCannedAcl::PublicRead)
-            .send()
-            .await?;
-        self.client
-            .delete_object()
+    async fn delete_cached(&self, cid: Cid) -> Result<()> {
+        let res = self
+            .client
+            .list_objects_v2()
             .bucket(&self.bucket)
-            .key(keys.from)
+            .prefix(format!("{0}/", self.get_cache_path(cid)))
             .send()
             .await?;
-        Ok(())
+        let keys: Vec<String> = res
+            .contents()
+            .iter()
+            .filter_map(|object| object.key().map(|key| key.to_string()))
+            .collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        self.delete_many_keys(keys).await
     }
 }
@@ -1,6 +1,7 @@
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStore;
 use crate::db::establish_connection;
 use crate::image;
+use crate::jobs::{self, JobKind};
 use crate::models::models;
 use anyhow::{bail, Result};
 use aws_sdk_s3::operation::get_object::GetObjectError;
@@ -32,7 +33,7 @@ pub struct BlobMetadata {
 }
 
 pub struct BlobReader {
-    pub blobstore: S3BlobStore,
+    pub blobstore: Box<dyn BlobStore>,
     pub did: String,
 }
 
@@ -60,11 +61,8 @@ pub struct GetBlobMetadataOutput {
 
 // Basically handles getting blob records from db
 impl BlobReader {
-    pub fn new(blobstore: S3BlobStore) -> Self {
-        BlobReader {
-            did: blobstore.bucket.clone(),
-            blobstore,
-        }
+    pub fn new(did: String, blobstore: Box<dyn BlobStore>) -> Self {
+        BlobReader { did, blobstore }
     }
 
     pub async fn get_blob_metadata(&self, cid: Cid) -> Result<GetBlobMetadataOutput> {
@@ -97,6 +95,7 @@ impl BlobReader {
                     Some(GetObjectError::NoSuchKey(key)) => {
                         Err(anyhow::Error::new(GetObjectError::NoSuchKey(key.clone())))
                     }
+                    _ if e.is::<BlobError>() => Err(e),
                     _ => bail!(e.to_string()),
                 }
             }
@@ -291,8 +290,8 @@ impl BlobReader {
         let cids_to_delete = deleted_repo_blob_cids
             .into_iter()
             .filter_map(|cid: String| match cids_to_keep.contains(&cid) {
-                true => Some(cid),
-                false => None,
+                true => None,
+                false => Some(cid),
             })
             .collect::<Vec<String>>();
         if cids_to_delete.len() < 1 {
@@ -303,14 +302,11 @@ impl BlobReader {
             .filter(BlobSchema::cid.eq_any(&cids_to_delete))
             .execute(conn)?;
 
-        // Original code queues a background job to delete by CID from S3 compatible blobstore
-        let _ = stream::iter(cids_to_delete)
-            .then(|cid| async { Ok::<(), anyhow::Error>(self.blobstore.delete(cid).await?) })
-            .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(())
+        jobs::enqueue(JobKind::DeleteBlobs {
+            did: self.did.clone(),
+            cids: cids_to_delete,
+        })
+        .await
     }
 
     pub async fn verify_blob_and_make_permanent(&self, blob: PreparedBlobRef) -> Result<()> {
@@ -0,0 +1,81 @@
+use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::disk::DiskBlobStore;
+use crate::config::BlobStoreConfig;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use lexicon_cid::Cid;
+
+/// Storage for the blobs of a single repo. Uploads land in a temporary location and are only made
+/// permanent once a record references them.
+#[rocket::async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put_temp(&self, bytes: Vec<u8>) -> Result<String>;
+
+    async fn make_permanent(&self, key: String, cid: Cid) -> Result<()>;
+
+    async fn put_permanent(&self, cid: Cid, bytes: Vec<u8>) -> Result<()>;
+
+    async fn quarantine(&self, cid: Cid) -> Result<()>;
+
+    async fn unquarantine(&self, cid: Cid) -> Result<()>;
+
+    async fn get_bytes(&self, cid: Cid) -> Result<Vec<u8>>;
+
+    async fn get_stream(&self, cid: Cid) -> Result<ByteStream>;
+
+    async fn has_stored(&self, cid: Cid) -> Result<bool>;
+
+    async fn has_temp(&self, key: String) -> Result<bool>;
+
+    /// Drops an upload that was never made permanent
+    async fn delete_temp(&self, key: String) -> Result<()>;
+
+    async fn delete(&self, cid: String) -> Result<()>;
+
+    async fn delete_many(&self, cids: Vec<Cid>) -> Result<()>;
+
+    /// Variants generated from a stored blob, e.g. image thumbnails. `None` when the variant
+    /// hasn't been cached yet.
+    async fn get_cached(&self, cid: Cid, variant: String) -> Result<Option<Vec<u8>>>;
+
+    async fn put_cached(&self, cid: Cid, variant: String, bytes: Vec<u8>) -> Result<()>;
+
+    /// Drops every cached variant of a blob, done whenever the blob is deleted or quarantined
+    async fn delete_cached(&self, cid: Cid) -> Result<()>;
+}
+
+/// Builds the configured `BlobStore` for a given `did`
+#[derive(Debug, Clone)]
+pub enum BlobStoreCreator {
+    S3(SdkConfig),
+    Disk {
+        location: String,
+        tmp_location: String,
+    },
+}
+
+impl BlobStoreCreator {
+    pub fn new(cfg: &BlobStoreConfig, sdk_config: SdkConfig) -> Self {
+        match cfg {
+            BlobStoreConfig::S3 => BlobStoreCreator::S3(sdk_config),
+            BlobStoreConfig::Disk {
+                location,
+                tmp_location,
+            } => BlobStoreCreator::Disk {
+                location: location.clone(),
+                tmp_location: tmp_location.clone(),
+            },
+        }
+    }
+
+    pub fn create(&self, did: String) -> Box<dyn BlobStore> {
+        match self {
+            BlobStoreCreator::S3(sdk_config) => Box::new(S3BlobStore::new(did, sdk_config)),
+            BlobStoreCreator::Disk {
+                location,
+                tmp_location,
+            } => Box::new(DiskBlobStore::new(did, location, tmp_location)),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// based on https://github.com/bluesky-social/atproto/blob/main/packages/pds/src/disk-blobstore.ts
+use crate::actor_store::blobstore::BlobStore;
+use anyhow::Result;
+use aws_sdk_s3::primitives::ByteStream;
+use lexicon_cid::Cid;
+use rsky_common::get_random_str;
+use rsky_repo::error::BlobError;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::fs;
+
+/// Keeps blobs on the local filesystem, for single-box deployments and tests.
+/// `tmp_location` must be on the same filesystem as `location` so making an upload permanent is
+/// a single atomic rename.
+#[derive(Debug, Clone)]
+pub struct DiskBlobStore {
+    pub did: String,
+    location: PathBuf,
+    tmp_location: PathBuf,
+}
+
+impl DiskBlobStore {
+    pub fn new(did: String, location: &String, tmp_location: &String) -> Self {
+        DiskBlobStore {
+            did,
+            location: PathBuf::from(location),
+            tmp_location: PathBuf::from(tmp_location),
+        }
+    }
+
+    fn did_dir(&self) -> String {
+        self.did.replace(":", "-")
+    }
+
+    fn get_tmp_path(&self, key: &String) -> PathBuf {
+        self.tmp_location.join(self.did_dir()).join(key)
+    }
+
+    // blobs are sharded on the last characters of the cid, the leading ones are the same for
+    // every blob
+    fn get_stored_path(&self, cid: Cid) -> PathBuf {
+        let cid = cid.to_string();
+        let shard = &cid[cid.len() - 2..];
+        self.location
+            .join("blocks")
+            .join(self.did_dir())
+            .join(shard)
+            .join(cid)
+    }
+
+    fn get_quarantined_path(&self, cid: Cid) -> PathBuf {
+        self.location
+            .join("quarantine")
+            .join(self.did_dir())
+            .join(cid.to_string())
+    }
+
+    fn get_cache_path(&self, cid: Cid) -> PathBuf {
+        self.location
+            .join("cache")
+            .join(self.did_dir())
+            .join(cid.to_string())
+    }
+
+    async fn move_file(&self, from: &Path, to: &Path) -> Result<()> {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        match fs::rename(from, to).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(BlobError::BlobNotFoundError.into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete_file(&self, path: &Path) -> Result<()> {
+        match fs::remove_file(path).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl BlobStore for DiskBlobStore {
+    async fn put_temp(&self, bytes: Vec<u8>) -> Result<String> {
+        let key = get_random_str();
+        let path = self.get_tmp_path(&key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(path, bytes).await?;
+        Ok(key)
+    }
+
+    async fn make_permanent(&self, key: String, cid: Cid) -> Result<()> {
+        let tmp_path = self.get_tmp_path(&key);
+        if self.has_stored(cid).await? {
+            // already saved, so we no-op & just delete the temp
+            return self.delete_file(&tmp_path).await;
+        }
+        self.move_file(&tmp_path, &self.get_stored_path(cid)).await
+    }
+
+    async fn put_permanent(&self, cid: Cid, bytes: Vec<u8>) -> Result<()> {
+        // written through a temp file so readers never see a partial blob
+        let key = self.put_temp(bytes).await?;
+        self.move_file(&self.get_tmp_path(&key), &self.get_stored_path(cid))
+            .await
+    }
+
+    async fn quarantine(&self, cid: Cid) -> Result<()> {
+        self.delete_cached(cid).await?;
+        self.move_file(&self.get_stored_path(cid), &self.get_quarantined_path(cid))
+            .await
+    }
+
+    async fn unquarantine(&self, cid: Cid) -> Result<()> {
+        self.move_file(&self.get_quarantined_path(cid), &self.get_stored_path(cid))
+            .await
+    }
+
+    async fn get_bytes(&self, cid: Cid) -> Result<Vec<u8>> {
+        match fs::read(self.get_stored_path(cid)).await {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(BlobError::BlobNotFoundError.into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_stream(&self, cid: Cid) -> Result<ByteStream> {
+        Ok(ByteStream::from(self.get_bytes(cid).await?))
+    }
+
+    async fn has_stored(&self, cid: Cid) -> Result<bool> {
+        Ok(fs::try_exists(self.get_stored_path(cid)).await?)
+    }
+
+    async fn has_temp(&self, key: String) -> Result<bool> {
+        Ok(fs::try_exists(self.get_tmp_path(&key)).await?)
+    }
+
+    async fn delete_temp(&self, key: String) -> Result<()> {
+        self.delete_file(&self.get_tmp_path(&key)).await
+    }
+
+    async fn delete(&self, cid: String) -> Result<()> {
+        let cid = Cid::from_str(&cid)?;
+        self.delete_cached(cid).await?;
+        self.delete_file(&self.get_stored_path(cid)).await
+    }
+
+    async fn delete_many(&self, cids: Vec<Cid>) -> Result<()> {
+        for cid in cids {
+            self.delete_cached(cid).await?;
+            self.delete_file(&self.get_stored_path(cid)).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_cached(&self, cid: Cid, variant: String) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.get_cache_path(cid).join(variant)).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn put_cached(&self, cid: Cid, variant: String, bytes: Vec<u8>) -> Result<()> {
+        let key = self.put_temp(bytes).await?;
+        self.move_file(
+            &self.get_tmp_path(&key),
+            &self.get_cache_path(cid).join(variant),
+        )
+        .await
+    }
+
+    async fn delete_cached(&self, cid: Cid) -> Result<()> {
+        match fs::remove_dir_all(self.get_cache_path(cid)).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn moves_blobs_between_locations() {
+        let root = std::env::temp_dir().join(format!("rsky-disk-blobstore-{}", get_random_str()));
+        let location = root.join("blobs").to_string_lossy().to_string();
+        let tmp_location = root.join("tmp").to_string_lossy().to_string();
+        let store = DiskBlobStore::new("did:plc:test".to_string(), &location, &tmp_location);
+        let cid =
+            Cid::from_str("bafkreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm").unwrap();
+
+        let key = store.put_temp(b"blob".to_vec()).await.unwrap();
+        assert!(store.has_temp(key.clone()).await.unwrap());
+        store.make_permanent(key.clone(), cid).await.unwrap();
+        assert!(!store.has_temp(key).await.unwrap());
+        assert_eq!(store.get_bytes(cid).await.unwrap(), b"blob".to_vec());
+
+        store
+            .put_cached(cid, "avatar@jpeg".to_string(), b"thumb".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .get_cached(cid, "avatar@jpeg".to_string())
+                .await
+                .unwrap(),
+            Some(b"thumb".to_vec())
+        );
+
+        store.quarantine(cid).await.unwrap();
+        assert!(store
+            .get_cached(cid, "avatar@jpeg".to_string())
+            .await
+            .unwrap()
+            .is_none());
+        assert!(store.get_bytes(cid).await.is_err());
+        store.unquarantine(cid).await.unwrap();
+        assert!(store.has_stored(cid).await.unwrap());
+
+        store.delete(cid.to_string()).await.unwrap();
+        assert!(!store.has_stored(cid).await.unwrap());
+        fs::remove_dir_all(root).await.unwrap();
+    }
+}
@@ -1,12 +1,13 @@
 // based on https://github.com/bluesky-social/atproto/blob/main/packages/repo/src/repo.ts
 // also adds components from https://github.com/bluesky-social/atproto/blob/main/packages/pds/src/actor-store/repo/transactor.ts
 
-use crate::actor_store::aws::s3::S3BlobStore;
 use crate::actor_store::blob::BlobReader;
+use crate::actor_store::blobstore::BlobStore;
 use crate::actor_store::preference::PreferenceReader;
 use crate::actor_store::record::RecordReader;
 use crate::actor_store::repo::sql_repo::SqlRepoReader;
 use crate::db::DbConn;
+use crate::jobs::{self, JobKind};
 use anyhow::{bail, Result};
 use diesel::*;
 use futures::stream::{self, StreamExt};
@@ -36,14 +37,14 @@ pub struct ActorStore {
 
 // Combination of RepoReader/Transactor, BlobReader/Transactor, SqlRepoReader/Transactor
 impl ActorStore {
-    /// Concrete reader of an individual repo (hence S3BlobStore which takes `did` param)
-    pub fn new(did: String, blobstore: S3BlobStore, conn: DbConn) -> Self {
+    /// Concrete reader of an individual repo (hence a BlobStore created for its `did`)
+    pub fn new(did: String, blobstore: Box<dyn BlobStore>, conn: DbConn) -> Self {
         ActorStore {
             storage: Arc::new(RwLock::new(SqlRepoReader::new(did.clone(), None, conn))),
             record: RecordReader::new(did.clone()),
             pref: PreferenceReader::new(did.clone()),
+            blob: BlobReader::new(did.clone(), blobstore), // Unlike TS impl, just use blob reader vs generator
             did,
-            blob: BlobReader::new(blobstore), // Unlike TS impl, just use blob reader vs generator
         }
     }
 
@@ -301,18 +302,14 @@ impl ActorStore {
                     .get_results(conn)
             })
             .await?;
-        let cids = blob_rows
-            .into_iter()
-            .map(|row| Ok(Cid::from_str(&row)?))
-            .collect::<Result<Vec<Cid>>>()?;
-        let _ = stream::iter(cids.chunks(500))
-            .then(|chunk| async {
-                Ok::<(), anyhow::Error>(self.blob.blobstore.delete_many(chunk.to_vec()).await?)
+        // the blobs are removed from storage by the job worker
+        for chunk in blob_rows.chunks(500) {
+            jobs::enqueue(JobKind::DeleteBlobs {
+                did: self.did.clone(),
+                cids: chunk.to_vec(),
             })
-            .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?;
+            .await?;
+        }
         Ok(())
     }
 
@@ -350,6 +347,8 @@ impl ActorStore {
 
 pub mod aws;
 pub mod blob;
+pub mod blobstore;
+pub mod disk;
 pub mod preference;
 pub mod record;
 pub mod repo;
@@ -0,0 +1,389 @@
+use crate::account_manager::helpers::account::{ActorAccount, AvailabilityFlags};
+use crate::account_manager::AccountManager;
+use crate::mailer::moderation::{HtmlMailOpts, ModerationMailer};
+use crate::models::AdminAction;
+use crate::SharedSequencer;
+use anyhow::{bail, Result};
+use chrono::DateTime;
+use rsky_common::{get_random_str, RFC3339_VARIANT};
+use rsky_lexicon::com::atproto::admin::StatusAttr;
+use std::str::FromStr;
+
+/// Most accounts a single bulk action touches, larger result sets are worked through page by
+/// page with the returned cursor
+pub const MAX_BULK_ACCOUNTS: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountStatusFilter {
+    Active,
+    Takendown,
+    Deactivated,
+}
+
+impl FromStr for AccountStatusFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(status: &str) -> Result<Self> {
+        match status {
+            "active" => Ok(AccountStatusFilter::Active),
+            "takendown" => Ok(AccountStatusFilter::Takendown),
+            "deactivated" => Ok(AccountStatusFilter::Deactivated),
+            _ => bail!(InvalidAdminRequest(format!(
+                "Invalid account status: `{status}`"
+            ))),
+        }
+    }
+}
+
+/// The request itself was wrong, as opposed to the PDS failing to carry it out
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct InvalidAdminRequest(pub String);
+
+/// Filters for finding accounts, all of them optional and combined with AND
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSearch {
+    pub handle_prefix: Option<String>,
+    /// Case insensitive substring of the account email
+    pub email: Option<String>,
+    /// RFC3339 timestamp in any offset, inclusive
+    pub created_after: Option<String>,
+    /// RFC3339 timestamp in any offset, exclusive
+    pub created_before: Option<String>,
+    pub status: Option<AccountStatusFilter>,
+    /// Accounts that signed up with this invite code
+    pub invite_code: Option<String>,
+    /// Accounts that signed up with an invite code belonging to this did
+    pub invited_by: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+impl AccountSearch {
+    pub fn has_filters(&self) -> bool {
+        self.handle_prefix.is_some()
+            || self.email.is_some()
+            || self.created_after.is_some()
+            || self.created_before.is_some()
+            || self.status.is_some()
+            || self.invite_code.is_some()
+            || self.invited_by.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountSearchOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub accounts: Vec<ActorAccount>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BulkAction {
+    Takedown {
+        /// Recorded as the takedown reference, the batch id when left out
+        #[serde(rename = "takedownRef")]
+        takedown_ref: Option<String>,
+    },
+    /// Lifts a takedown and deactivation
+    Reactivate,
+    DisableInvites,
+    SendEmail {
+        subject: Option<String>,
+        content: String,
+    },
+}
+
+impl BulkAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BulkAction::Takedown { .. } => "takedown",
+            BulkAction::Reactivate => "reactivate",
+            BulkAction::DisableInvites => "disable_invites",
+            BulkAction::SendEmail { .. } => "send_email",
+        }
+    }
+}
+
+/// A bulk action applies either to an explicit list of dids or to one page of a search
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkActionInput {
+    pub action: BulkAction,
+    pub dids: Option<Vec<String>>,
+    pub search: Option<AccountSearch>,
+    /// Who to attribute the action to in the audit trail
+    pub performed_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkActionOutput {
+    pub batch_id: String,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Cursor of the next search page when the action was applied to a search
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Audit rows of the batch, an account is missing only if recording its outcome failed
+    pub actions: Vec<AdminAction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionOutcome {
+    Succeeded,
+    Failed,
+}
+
+impl ActionOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActionOutcome::Succeeded => "succeeded",
+            ActionOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// Rewrites an RFC3339 timestamp in the format account creation times are stored in, so they
+/// compare in time order
+pub fn normalize_time(time: &str) -> Result<String> {
+    match DateTime::parse_from_rfc3339(time) {
+        Ok(time) => Ok(time.to_utc().format(RFC3339_VARIANT).to_string()),
+        Err(error) => bail!(InvalidAdminRequest(format!(
+            "Invalid timestamp `{time}`: {error}"
+        ))),
+    }
+}
+
+pub async fn search_accounts(mut search: AccountSearch) -> Result<AccountSearchOutput> {
+    search.limit = Some(search.limit.unwrap_or(50).clamp(1, MAX_BULK_ACCOUNTS));
+    search.created_after = search
+        .created_after
+        .as_deref()
+        .map(normalize_time)
+        .transpose()?;
+    search.created_before = search
+        .created_before
+        .as_deref()
+        .map(normalize_time)
+        .transpose()?;
+    let (accounts, cursor) = store::search_accounts(&search).await?;
+    Ok(AccountSearchOutput { cursor, accounts })
+}
+
+/// Applies `action` to every targeted account one at a time, recording the outcome of each in
+/// the audit trail. A failure on one account doesn't stop the rest of the batch.
+pub async fn apply_bulk_action(
+    input: BulkActionInput,
+    sequencer: &SharedSequencer,
+) -> Result<BulkActionOutput> {
+    let BulkActionInput {
+        action,
+        dids,
+        search,
+        performed_by,
+    } = input;
+    let (dids, cursor) = match (dids, search) {
+        (Some(dids), None) => {
+            if dids.len() as i64 > MAX_BULK_ACCOUNTS {
+                bail!(InvalidAdminRequest(format!(
+                    "At most {MAX_BULK_ACCOUNTS} accounts can be targeted at once"
+                )));
+            }
+            (dids, None)
+        }
+        (None, Some(search)) => {
+            // an empty search would match every account
+            if !search.has_filters() {
+                bail!(InvalidAdminRequest(
+                    "A search target needs at least one filter".to_string()
+                ));
+            }
+            let AccountSearchOutput { cursor, accounts } = search_accounts(search).await?;
+            (
+                accounts.into_iter().map(|account| account.did).collect(),
+                cursor,
+            )
+        }
+        _ => bail!(InvalidAdminRequest(
+            "Exactly one of `dids` or `search` is required".to_string()
+        )),
+    };
+
+    let batch_id = format!("batch-{}", get_random_str());
+    let performed_by = performed_by.unwrap_or("admin".to_string());
+    let params = serde_json::to_string(&action)?;
+    let mut actions = Vec::with_capacity(dids.len());
+    let (mut succeeded, mut failed) = (0, 0);
+    for did in dids {
+        let (outcome, error) = match apply_action(&action, &did, &batch_id, sequencer).await {
+            Ok(()) => {
+                succeeded += 1;
+                (ActionOutcome::Succeeded, None)
+            }
+            Err(error) => {
+                tracing::error!(
+                    "@LOG: ERROR: bulk {} of {did} failed; err: {error}",
+                    action.as_str()
+                );
+                failed += 1;
+                (ActionOutcome::Failed, Some(error.to_string()))
+            }
+        };
+        // the action was already applied, so losing its audit row mustn't stop the batch
+        let recorded = store::record_action(
+            &batch_id,
+            &action,
+            &did,
+            &params,
+            &performed_by,
+            outcome,
+            error,
+        )
+        .await;
+        match recorded {
+            Ok(recorded) => actions.push(recorded),
+            Err(error) => tracing::error!(
+                "@LOG: ERROR: failed to record bulk {} of {did} in batch {batch_id} ({}); err: {error}",
+                action.as_str(),
+                outcome.as_str()
+            ),
+        }
+    }
+
+    Ok(BulkActionOutput {
+        batch_id,
+        succeeded,
+        failed,
+        cursor,
+        actions,
+    })
+}
+
+/// Explicit dids may name accounts this PDS doesn't host, those fail without being touched
+async fn apply_action(
+    action: &BulkAction,
+    did: &String,
+    batch_id: &String,
+    sequencer: &SharedSequencer,
+) -> Result<()> {
+    let account = AccountManager::get_account(
+        did,
+        Some(AvailabilityFlags {
+            include_deactivated: Some(true),
+            include_taken_down: Some(true),
+        }),
+    )
+    .await?;
+    let Some(account) = account else {
+        bail!("account is not hosted on this PDS");
+    };
+    match action {
+        BulkAction::Takedown { takedown_ref } => {
+            AccountManager::takedown_account(
+                did,
+                StatusAttr {
+                    applied: true,
+                    r#ref: Some(takedown_ref.clone().unwrap_or(batch_id.clone())),
+                },
+            )
+            .await?;
+            sequence_account_status(did, sequencer).await
+        }
+        BulkAction::Reactivate => {
+            AccountManager::takedown_account(
+                did,
+                StatusAttr {
+                    applied: false,
+                    r#ref: None,
+                },
+            )
+            .await?;
+            AccountManager::activate_account(did).await?;
+            sequence_account_status(did, sequencer).await
+        }
+        BulkAction::DisableInvites => AccountManager::set_account_invites_disabled(did, true).await,
+        BulkAction::SendEmail { subject, content } => {
+            let email = match account.email {
+                Some(email) => email,
+                None => bail!("account does not have an email address"),
+            };
+            ModerationMailer::send_html(HtmlMailOpts {
+                to: email,
+                subject: subject
+                    .clone()
+                    .unwrap_or("Message via your PDS".to_string()),
+                html: content.clone(),
+            })
+            .await
+        }
+    }
+}
+
+async fn sequence_account_status(did: &String, sequencer: &SharedSequencer) -> Result<()> {
+    let status = AccountManager::get_account_status(did).await?;
+    let mut lock = sequencer.sequencer.write().await;
+    lock.sequence_account_evt(did.clone(), status).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tagged_bulk_actions() {
+        let action: BulkAction =
+            serde_json::from_str(r#"{"type":"takedown","takedownRef":"spam-wave"}"#).unwrap();
+        assert_eq!(action.as_str(), "takedown");
+        let action: BulkAction = serde_json::from_str(r#"{"type":"disableInvites"}"#).unwrap();
+        assert_eq!(action.as_str(), "disable_invites");
+        assert!(serde_json::from_str::<BulkAction>(r#"{"type":"sendEmail"}"#).is_err());
+    }
+
+    #[test]
+    fn parses_account_status_filters() {
+        assert_eq!(
+            "takendown".parse::<AccountStatusFilter>().unwrap(),
+            AccountStatusFilter::Takendown
+        );
+        let error = "banned".parse::<AccountStatusFilter>().unwrap_err();
+        assert!(error.downcast_ref::<InvalidAdminRequest>().is_some());
+    }
+
+    #[test]
+    fn normalizes_search_times_to_the_stored_format() {
+        assert_eq!(
+            normalize_time("2025-03-01T02:30:00+02:00").unwrap(),
+            "2025-03-01T00:30:00.000Z"
+        );
+        assert_eq!(
+            normalize_time("2025-03-01T00:30:00.5Z").unwrap(),
+            "2025-03-01T00:30:00.500Z"
+        );
+        for time in ["2025-03-01", "yesterday", "2025-03-01 00:30:00"] {
+            let error = normalize_time(time).unwrap_err();
+            assert!(
+                error.downcast_ref::<InvalidAdminRequest>().is_some(),
+                "{time}"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_search_has_no_filters() {
+        let mut search = AccountSearch {
+            limit: Some(10),
+            ..Default::default()
+        };
+        assert!(!search.has_filters());
+        search.status = Some(AccountStatusFilter::Takendown);
+        assert!(search.has_filters());
+    }
+}
+
+pub mod routes;
+pub mod store;
@@ -0,0 +1,80 @@
+use crate::admin::{
+    apply_bulk_action, search_accounts, store, AccountSearch, AccountSearchOutput, BulkActionInput,
+    BulkActionOutput, InvalidAdminRequest,
+};
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::models::AdminAction;
+use crate::SharedSequencer;
+use rocket::serde::json::Json;
+use rocket::State;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListAdminActionsOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub actions: Vec<AdminAction>,
+}
+
+/// Bad input is reported back to the caller, anything else is logged as an internal error
+fn api_error(error: anyhow::Error) -> ApiError {
+    match error.downcast_ref::<InvalidAdminRequest>() {
+        Some(invalid) => ApiError::InvalidRequest(invalid.to_string()),
+        None => {
+            tracing::error!("@LOG: ERROR: {error}");
+            ApiError::RuntimeError
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+#[rocket::post("/accounts/search", format = "json", data = "<body>")]
+pub async fn search(
+    body: Json<AccountSearch>,
+    _auth: AdminToken,
+) -> Result<Json<AccountSearchOutput>, ApiError> {
+    match search_accounts(body.into_inner()).await {
+        Ok(output) => Ok(Json(output)),
+        Err(error) => Err(api_error(error)),
+    }
+}
+
+/// Applies a takedown, reactivation, invite disabling or email to a list of accounts or a page
+/// of search results
+#[tracing::instrument(skip_all)]
+#[rocket::post("/accounts/bulk", format = "json", data = "<body>")]
+pub async fn bulk_action(
+    body: Json<BulkActionInput>,
+    sequencer: &State<SharedSequencer>,
+    _auth: AdminToken,
+) -> Result<Json<BulkActionOutput>, ApiError> {
+    match apply_bulk_action(body.into_inner(), sequencer).await {
+        Ok(output) => Ok(Json(output)),
+        Err(error) => Err(api_error(error)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+#[rocket::get("/actions?<batch_id>&<did>&<limit>&<cursor>")]
+pub async fn list_actions(
+    batch_id: Option<String>,
+    did: Option<String>,
+    limit: Option<i64>,
+    cursor: Option<i64>,
+    _auth: AdminToken,
+) -> Result<Json<ListAdminActionsOutput>, ApiError> {
+    let limit = limit.unwrap_or(50).clamp(1, 100);
+    match store::list_actions(batch_id, did, limit, cursor).await {
+        Ok(actions) => Ok(Json(ListAdminActionsOutput {
+            cursor: match actions.len() as i64 == limit {
+                true => actions.last().map(|action| action.id.to_string()),
+                false => None,
+            },
+            actions,
+        })),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
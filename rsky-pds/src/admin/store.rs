@@ -0,0 +1,268 @@
+use crate::account_manager::helpers::account::{
+    select_account_qb, ActorAccount, AvailabilityFlags, BoxedQuery,
+};
+use crate::admin::{
+    AccountSearch, AccountStatusFilter, ActionOutcome, BulkAction, InvalidAdminRequest,
+};
+use crate::db::establish_connection;
+use crate::models::AdminAction;
+use anyhow::Result;
+use diesel::*;
+use rsky_common::now;
+
+/// Finds accounts matching `search`, newest first.
+///
+/// Cursors are packed as `<createdAt>::<did>`.
+pub async fn search_accounts(
+    search: &AccountSearch,
+) -> Result<(Vec<ActorAccount>, Option<String>)> {
+    use crate::schema::pds::account::dsl as AccountSchema;
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    let conn = &mut establish_connection()?;
+
+    let limit = search.limit.unwrap_or(50);
+    let builder = search_query(search)?;
+    let accounts = builder
+        .order((ActorSchema::createdAt.desc(), ActorSchema::did.desc()))
+        .limit(limit)
+        .select((
+            ActorSchema::did,
+            ActorSchema::handle,
+            ActorSchema::createdAt,
+            ActorSchema::takedownRef,
+            ActorSchema::deactivatedAt,
+            ActorSchema::deleteAfter,
+            AccountSchema::email.nullable(),
+            AccountSchema::emailConfirmedAt.nullable(),
+            AccountSchema::invitesDisabled.nullable(),
+        ))
+        .load::<(
+            String,
+            Option<String>,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<i16>,
+        )>(conn)?
+        .into_iter()
+        .map(|res| ActorAccount {
+            did: res.0,
+            handle: res.1,
+            created_at: res.2,
+            takedown_ref: res.3,
+            deactivated_at: res.4,
+            delete_after: res.5,
+            email: res.6,
+            email_confirmed_at: res.7,
+            invites_disabled: res.8,
+        })
+        .collect::<Vec<ActorAccount>>();
+
+    let cursor = match accounts.last() {
+        Some(last) if accounts.len() as i64 == limit => {
+            Some(format_cursor(&last.created_at, &last.did))
+        }
+        _ => None,
+    };
+    Ok((accounts, cursor))
+}
+
+/// Accounts matching every filter of `search` that sort after its cursor. Times are expected
+/// already normalized to the stored format.
+fn search_query(search: &AccountSearch) -> Result<BoxedQuery<'static>> {
+    use crate::schema::pds::account::dsl as AccountSchema;
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    use crate::schema::pds::invite_code::dsl as InviteCodeSchema;
+    use crate::schema::pds::invite_code_use::dsl as InviteCodeUseSchema;
+
+    let mut builder = select_account_qb(Some(AvailabilityFlags {
+        include_taken_down: Some(true),
+        include_deactivated: Some(true),
+    }));
+    if let Some(handle_prefix) = &search.handle_prefix {
+        builder = builder.filter(
+            ActorSchema::handle.like(format!("{}%", escape_like(&handle_prefix.to_lowercase()))),
+        );
+    }
+    if let Some(email) = &search.email {
+        builder = builder.filter(AccountSchema::email.ilike(format!("%{}%", escape_like(email))));
+    }
+    if let Some(created_after) = &search.created_after {
+        builder = builder.filter(ActorSchema::createdAt.ge(created_after.clone()));
+    }
+    if let Some(created_before) = &search.created_before {
+        builder = builder.filter(ActorSchema::createdAt.lt(created_before.clone()));
+    }
+    match search.status {
+        Some(AccountStatusFilter::Active) => {
+            builder = builder
+                .filter(ActorSchema::takedownRef.is_null())
+                .filter(ActorSchema::deactivatedAt.is_null());
+        }
+        Some(AccountStatusFilter::Takendown) => {
+            builder = builder.filter(ActorSchema::takedownRef.is_not_null());
+        }
+        Some(AccountStatusFilter::Deactivated) => {
+            builder = builder.filter(ActorSchema::deactivatedAt.is_not_null());
+        }
+        None => (),
+    }
+    if let Some(invite_code) = &search.invite_code {
+        builder = builder.filter(
+            ActorSchema::did.eq_any(
+                InviteCodeUseSchema::invite_code_use
+                    .filter(InviteCodeUseSchema::code.eq(invite_code.clone()))
+                    .select(InviteCodeUseSchema::usedBy),
+            ),
+        );
+    }
+    if let Some(invited_by) = &search.invited_by {
+        builder = builder.filter(
+            ActorSchema::did.eq_any(
+                InviteCodeUseSchema::invite_code_use
+                    .filter(
+                        InviteCodeUseSchema::code.eq_any(
+                            InviteCodeSchema::invite_code
+                                .filter(InviteCodeSchema::forAccount.eq(invited_by.clone()))
+                                .select(InviteCodeSchema::code),
+                        ),
+                    )
+                    .select(InviteCodeUseSchema::usedBy),
+            ),
+        );
+    }
+    if let Some(cursor) = &search.cursor {
+        let (created_at, did) = parse_cursor(cursor)?;
+        builder = builder.filter(
+            ActorSchema::createdAt
+                .lt(created_at.clone())
+                .or(ActorSchema::createdAt
+                    .eq(created_at)
+                    .and(ActorSchema::did.lt(did))),
+        );
+    }
+    Ok(builder)
+}
+
+pub fn format_cursor(created_at: &str, did: &str) -> String {
+    format!("{created_at}::{did}")
+}
+
+pub fn parse_cursor(cursor: &str) -> Result<(String, String)> {
+    match cursor.split_once("::") {
+        Some((created_at, did)) if !created_at.is_empty() && did.starts_with("did:") => {
+            Ok((created_at.to_string(), did.to_string()))
+        }
+        _ => Err(InvalidAdminRequest(format!("Malformed cursor: `{cursor}`")).into()),
+    }
+}
+
+pub async fn record_action(
+    batch_id: &String,
+    action: &BulkAction,
+    did: &String,
+    params: &String,
+    performed_by: &String,
+    outcome: ActionOutcome,
+    error: Option<String>,
+) -> Result<AdminAction> {
+    use crate::schema::pds::admin_action::dsl as AdminActionSchema;
+    let conn = &mut establish_connection()?;
+
+    Ok(insert_into(AdminActionSchema::admin_action)
+        .values((
+            AdminActionSchema::batchId.eq(batch_id),
+            AdminActionSchema::action.eq(action.as_str()),
+            AdminActionSchema::did.eq(did),
+            AdminActionSchema::params.eq(params),
+            AdminActionSchema::performedBy.eq(performed_by),
+            AdminActionSchema::outcome.eq(outcome.as_str()),
+            AdminActionSchema::error.eq(error),
+            AdminActionSchema::createdAt.eq(now()),
+        ))
+        .returning(AdminAction::as_returning())
+        .get_result(conn)?)
+}
+
+/// Audit trail of bulk actions, newest first, optionally narrowed to one batch or account
+pub async fn list_actions(
+    batch_id: Option<String>,
+    did: Option<String>,
+    limit: i64,
+    cursor: Option<i64>,
+) -> Result<Vec<AdminAction>> {
+    use crate::schema::pds::admin_action::dsl as AdminActionSchema;
+    let conn = &mut establish_connection()?;
+
+    let mut builder = AdminActionSchema::admin_action.into_boxed();
+    if let Some(batch_id) = batch_id {
+        builder = builder.filter(AdminActionSchema::batchId.eq(batch_id));
+    }
+    if let Some(did) = did {
+        builder = builder.filter(AdminActionSchema::did.eq(did));
+    }
+    if let Some(cursor) = cursor {
+        builder = builder.filter(AdminActionSchema::id.lt(cursor));
+    }
+    Ok(builder
+        .order(AdminActionSchema::id.desc())
+        .limit(limit)
+        .select(AdminAction::as_select())
+        .load(conn)?)
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+
+    #[test]
+    fn cursors_round_trip() {
+        let cursor = format_cursor("2025-01-01T00:00:00.000Z", "did:plc:alice");
+        assert_eq!(
+            parse_cursor(&cursor).unwrap(),
+            (
+                "2025-01-01T00:00:00.000Z".to_string(),
+                "did:plc:alice".to_string()
+            )
+        );
+        for cursor in ["", "2025-01-01T00:00:00.000Z", "::did:plc:alice", "a::b"] {
+            let error = parse_cursor(cursor).unwrap_err();
+            assert!(error.downcast_ref::<InvalidAdminRequest>().is_some());
+        }
+    }
+
+    #[test]
+    fn search_query_applies_every_filter() {
+        let search = AccountSearch {
+            handle_prefix: Some("Spam_".to_string()),
+            created_after: Some("2025-01-01T00:00:00.000Z".to_string()),
+            status: Some(AccountStatusFilter::Takendown),
+            cursor: Some(format_cursor("2025-02-01T00:00:00.000Z", "did:plc:bob")),
+            ..Default::default()
+        };
+        let sql = debug_query::<Pg, _>(&search_query(&search).unwrap()).to_string();
+        assert!(sql.contains(r#""actor"."handle" LIKE $"#), "{sql}");
+        assert!(sql.contains(r#""actor"."createdAt" >= $"#), "{sql}");
+        assert!(
+            sql.contains(r#""actor"."takedownRef" IS NOT NULL"#),
+            "{sql}"
+        );
+        assert!(sql.contains(r#""actor"."did" < $"#), "{sql}");
+        assert!(!sql.contains(r#""account"."email" ILIKE"#), "{sql}");
+        // the underscore is escaped so it only matches itself
+        assert!(sql.contains(r#""spam\\_%""#), "{sql}");
+        assert!(sql.contains(r#""did:plc:bob""#), "{sql}");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandard;
 use crate::config::ServerConfig;
@@ -8,7 +9,6 @@ use crate::read_after_write::viewer::LocalViewer;
 use crate::xrpc_server::types::HandlerPipeThrough;
 use crate::SharedLocalViewer;
 use anyhow::Result;
-use aws_config::SdkConfig;
 use rocket::State;
 use rsky_lexicon::app::bsky::actor::ProfileViewDetailed;
 
@@ -19,7 +19,7 @@ pub async fn inner_get_profile(
     _actor: String,
     auth: AccessStandard,
     res: HandlerPipeThrough,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     db: DbConn,
 ) -> Result<ReadAfterWriteResponse<ProfileViewDetailed>, ApiError> {
@@ -35,7 +35,7 @@ pub async fn inner_get_profile(
                 requester,
                 res,
                 get_profile_munge,
-                s3_config,
+                blobstore,
                 state_local_viewer,
                 db,
             )
@@ -54,7 +54,7 @@ pub async fn get_profile(
     actor: String,
     auth: AccessStandard,
     res: HandlerPipeThrough,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     cfg: &State<ServerConfig>,
     db: DbConn,
@@ -62,7 +62,7 @@ pub async fn get_profile(
     match cfg.bsky_app_view {
         None => Err(ApiError::AccountNotFound),
         Some(_) => {
-            match inner_get_profile(actor, auth, res, s3_config, state_local_viewer, db).await {
+            match inner_get_profile(actor, auth, res, blobstore, state_local_viewer, db).await {
                 Ok(response) => Ok(response),
                 Err(error) => Err(error),
             }
@@ -1,3 +1,4 @@
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandard;
 use crate::config::ServerConfig;
@@ -8,7 +9,6 @@ use crate::read_after_write::viewer::LocalViewer;
 use crate::xrpc_server::types::HandlerPipeThrough;
 use crate::SharedLocalViewer;
 use anyhow::Result;
-use aws_config::SdkConfig;
 use rocket::State;
 use rsky_lexicon::app::bsky::actor::{GetProfilesOutput, ProfileViewDetailed};
 
@@ -18,7 +18,7 @@ pub async fn inner_get_profiles(
     _actors: Vec<String>,
     auth: AccessStandard,
     res: HandlerPipeThrough,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     db: DbConn,
 ) -> Result<ReadAfterWriteResponse<GetProfilesOutput>, ApiError> {
@@ -31,7 +31,7 @@ pub async fn inner_get_profiles(
         requester,
         res,
         get_profiles_munge,
-        s3_config,
+        blobstore,
         state_local_viewer,
         db,
     )
@@ -46,7 +46,7 @@ pub async fn get_profiles(
     actors: Vec<String>,
     auth: AccessStandard,
     res: HandlerPipeThrough,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     cfg: &State<ServerConfig>,
     db: DbConn,
@@ -54,7 +54,7 @@ pub async fn get_profiles(
     match cfg.bsky_app_view {
         None => Err(ApiError::AccountNotFound),
         Some(_) => {
-            match inner_get_profiles(actors, auth, res, s3_config, state_local_viewer, db).await {
+            match inner_get_profiles(actors, auth, res, blobstore, state_local_viewer, db).await {
                 Ok(response) => Ok(response),
                 Err(error) => Err(error),
             }
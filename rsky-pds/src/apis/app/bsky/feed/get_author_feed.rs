@@ -1,3 +1,4 @@
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandard;
 use crate::config::ServerConfig;
@@ -8,7 +9,6 @@ use crate::read_after_write::viewer::LocalViewer;
 use crate::xrpc_server::types::HandlerPipeThrough;
 use crate::SharedLocalViewer;
 use anyhow::Result;
-use aws_config::SdkConfig;
 use rocket::form::validate::Contains;
 use rocket::State;
 use rsky_lexicon::app::bsky::feed::{AuthorFeed, FeedViewPost, PostView};
@@ -22,7 +22,7 @@ pub async fn inner_get_author_feed(
     _filter: Option<String>,
     auth: AccessStandard,
     res: HandlerPipeThrough,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     db: DbConn,
 ) -> Result<ReadAfterWriteResponse<AuthorFeed>> {
@@ -38,7 +38,7 @@ pub async fn inner_get_author_feed(
                 requester,
                 res,
                 get_author_munge,
-                s3_config,
+                blobstore,
                 state_local_viewer,
                 db,
             )
@@ -58,7 +58,7 @@ pub async fn get_author_feed(
     filter: Option<String>, // Combinations of post/repost types to include in response.
     auth: AccessStandard,
     res: HandlerPipeThrough,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     cfg: &State<ServerConfig>,
     db: DbConn,
@@ -100,7 +100,7 @@ pub async fn get_author_feed(
             filter,
             auth,
             res,
-            s3_config,
+            blobstore,
             state_local_viewer,
             db,
         )
@@ -1,4 +1,4 @@
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandard;
@@ -21,7 +21,6 @@ use atrium_api::client::AtpServiceClient;
 use atrium_api::types::LimitedU16;
 use atrium_ipld::ipld::Ipld as AtriumIpld;
 use atrium_xrpc_client::reqwest::ReqwestClientBuilder;
-use aws_config::SdkConfig;
 use futures::stream::{self, StreamExt};
 use reqwest::header::HeaderMap;
 use rocket::State;
@@ -49,7 +48,7 @@ pub async fn inner_get_post_thread(
     parentHeight: u16,
     auth: AccessStandard,
     res: Result<HandlerPipeThrough>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     cfg: &State<ServerConfig>,
     db: DbConn,
@@ -65,7 +64,7 @@ pub async fn inner_get_post_thread(
                 requester,
                 res,
                 get_post_thread_munge,
-                s3_config,
+                blobstore,
                 state_local_viewer,
                 db,
             )
@@ -85,7 +84,7 @@ pub async fn inner_get_post_thread(
                         Some(error) if error == "NotFound" => {
                             let actor_store = ActorStore::new(
                                 requester.clone(),
-                                S3BlobStore::new(requester.clone(), s3_config),
+                                blobstore.create(requester.clone()),
                                 db,
                             );
                             let local_viewer_lock = state_local_viewer.local_viewer.read().await;
@@ -129,7 +128,7 @@ pub async fn get_post_thread(
     parentHeight: Option<u16>, // How many levels of parent (and grandparent, etc.) post to include.
     auth: AccessStandard,
     res: Result<HandlerPipeThrough>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     cfg: &State<ServerConfig>,
     db: DbConn,
@@ -149,7 +148,7 @@ pub async fn get_post_thread(
             parentHeight,
             auth,
             res,
-            s3_config,
+            blobstore,
             state_local_viewer,
             cfg,
             db,
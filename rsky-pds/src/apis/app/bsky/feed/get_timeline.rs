@@ -1,3 +1,4 @@
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandard;
 use crate::config::ServerConfig;
@@ -8,7 +9,6 @@ use crate::read_after_write::viewer::LocalViewer;
 use crate::xrpc_server::types::HandlerPipeThrough;
 use crate::SharedLocalViewer;
 use anyhow::Result;
-use aws_config::SdkConfig;
 use rocket::State;
 use rsky_lexicon::app::bsky::feed::AuthorFeed;
 
@@ -20,7 +20,7 @@ pub async fn inner_get_timeline(
     _cursor: Option<String>,
     auth: AccessStandard,
     res: HandlerPipeThrough,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     db: DbConn,
 ) -> Result<ReadAfterWriteResponse<AuthorFeed>> {
@@ -36,7 +36,7 @@ pub async fn inner_get_timeline(
                 requester,
                 res,
                 get_timeline_munge,
-                s3_config,
+                blobstore,
                 state_local_viewer,
                 db,
             )
@@ -56,7 +56,7 @@ pub async fn get_timeline(
     cursor: Option<String>,
     auth: AccessStandard,
     res: HandlerPipeThrough,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     cfg: &State<ServerConfig>,
     db: DbConn,
@@ -74,7 +74,7 @@ pub async fn get_timeline(
             cursor,
             auth,
             res,
-            s3_config,
+            blobstore,
             state_local_viewer,
             db,
         )
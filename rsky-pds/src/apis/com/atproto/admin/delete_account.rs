@@ -1,13 +1,12 @@
 use crate::account_manager::helpers::account::AccountStatus;
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::auth_verifier::AdminToken;
 use crate::db::DbConn;
 use crate::{sequencer, SharedSequencer};
 use anyhow::Result;
-use aws_config::SdkConfig;
 use rocket::serde::json::Json;
 use rocket::State;
 use rsky_lexicon::com::atproto::admin::DeleteAccountInput;
@@ -15,13 +14,12 @@ use rsky_lexicon::com::atproto::admin::DeleteAccountInput;
 async fn inner_delete_account(
     body: Json<DeleteAccountInput>,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<()> {
     let DeleteAccountInput { did } = body.into_inner();
 
-    let mut actor_store =
-        ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let mut actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
     actor_store.destroy().await?;
     AccountManager::delete_account(&did).await?;
     let mut lock = sequencer.sequencer.write().await;
@@ -43,11 +41,11 @@ async fn inner_delete_account(
 pub async fn delete_account(
     body: Json<DeleteAccountInput>,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     _auth: AdminToken,
     db: DbConn,
 ) -> Result<(), ApiError> {
-    match inner_delete_account(body, sequencer, s3_config, db).await {
+    match inner_delete_account(body, sequencer, blobstore, db).await {
         Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
@@ -0,0 +1,150 @@
+use crate::account_manager::helpers::account::{ActorAccount, AvailabilityFlags};
+use crate::account_manager::helpers::invite::CodeDetail;
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::Moderator;
+use anyhow::Result;
+use futures::try_join;
+use rocket::serde::json::Json;
+use rsky_common::env::env_str;
+use rsky_lexicon::com::atproto::admin::{AccountView, GetAccountInfosOutput};
+use rsky_syntax::handle::INVALID_HANDLE;
+use std::collections::BTreeMap;
+
+async fn inner_get_account_infos(dids: Vec<String>) -> Result<GetAccountInfosOutput> {
+    let (accounts, invites, invited_by) = try_join!(
+        AccountManager::get_accounts(
+            &dids,
+            Some(AvailabilityFlags {
+                include_deactivated: Some(true),
+                include_taken_down: Some(true)
+            })
+        ),
+        AccountManager::get_accounts_invite_codes(&dids),
+        AccountManager::get_invited_by_for_accounts(dids.iter().collect())
+    )?;
+    let manages_own_invites = env_str("PDS_ENTRYWAY_URL").is_none();
+    Ok(GetAccountInfosOutput {
+        infos: account_views(&dids, &accounts, invites, &invited_by, manages_own_invites),
+    })
+}
+
+/// Keeps the order of the requested dids, skipping ones that aren't hosted here. Invites are
+/// left out when an entryway manages them.
+fn account_views(
+    dids: &[String],
+    accounts: &BTreeMap<String, ActorAccount>,
+    mut invites: BTreeMap<String, Vec<CodeDetail>>,
+    invited_by: &BTreeMap<String, CodeDetail>,
+    manages_own_invites: bool,
+) -> Vec<AccountView> {
+    dids.iter()
+        .filter_map(|did| accounts.get(did))
+        .map(|account| AccountView {
+            did: account.did.clone(),
+            handle: account.handle.clone().unwrap_or(INVALID_HANDLE.to_string()),
+            email: account.email.clone(),
+            indexed_at: account.created_at.clone(),
+            email_confirmed_at: account.email_confirmed_at.clone(),
+            invited_by: match invited_by.get(&account.did) {
+                Some(code_detail) if manages_own_invites => Some(code_detail.clone()),
+                _ => None,
+            },
+            invites: if manages_own_invites {
+                Some(invites.remove(&account.did).unwrap_or_default())
+            } else {
+                None
+            },
+            invites_disabled: if manages_own_invites {
+                Some(account.invites_disabled == Some(1))
+            } else {
+                None
+            },
+            related_records: None,
+            invite_note: None,
+        })
+        .collect()
+}
+
+/// Get details about some accounts.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/com.atproto.admin.getAccountInfos?<dids>")]
+pub async fn get_account_infos(
+    dids: Vec<String>,
+    _auth: Moderator,
+) -> Result<Json<GetAccountInfosOutput>, ApiError> {
+    match inner_get_account_infos(dids).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(did: &str, handle: Option<&str>) -> ActorAccount {
+        ActorAccount {
+            did: did.to_string(),
+            handle: handle.map(str::to_string),
+            created_at: "2025-01-01T00:00:00.000Z".to_string(),
+            takedown_ref: None,
+            deactivated_at: None,
+            delete_after: None,
+            email: None,
+            invites_disabled: Some(1),
+            email_confirmed_at: None,
+        }
+    }
+
+    fn invite(code: &str, for_account: &str) -> CodeDetail {
+        CodeDetail {
+            code: code.to_string(),
+            available: 1,
+            disabled: false,
+            for_account: for_account.to_string(),
+            created_by: "admin".to_string(),
+            created_at: "2025-01-01T00:00:00.000Z".to_string(),
+            uses: vec![],
+        }
+    }
+
+    #[test]
+    fn keeps_requested_order_and_skips_missing_accounts() {
+        let accounts = BTreeMap::from([
+            (
+                "did:plc:a".to_string(),
+                account("did:plc:a", Some("a.test")),
+            ),
+            ("did:plc:b".to_string(), account("did:plc:b", None)),
+        ]);
+        let invites =
+            BTreeMap::from([("did:plc:b".to_string(), vec![invite("code-b", "did:plc:b")])]);
+        let invited_by = BTreeMap::from([("did:plc:a".to_string(), invite("code-a", "did:plc:a"))]);
+        let dids = vec![
+            "did:plc:b".to_string(),
+            "did:plc:missing".to_string(),
+            "did:plc:a".to_string(),
+        ];
+
+        let views = account_views(&dids, &accounts, invites.clone(), &invited_by, true);
+        let view_dids: Vec<_> = views.iter().map(|view| view.did.as_str()).collect();
+        assert_eq!(view_dids, vec!["did:plc:b", "did:plc:a"]);
+        assert_eq!(views[0].handle, INVALID_HANDLE);
+        assert_eq!(views[0].invites.as_ref().unwrap()[0].code, "code-b");
+        assert!(views[0].invited_by.is_none());
+        assert!(views[1].invites.as_ref().unwrap().is_empty());
+        assert_eq!(views[1].invited_by.as_ref().unwrap().code, "code-a");
+        assert_eq!(views[1].invites_disabled, Some(true));
+
+        // an entryway manages invites, so none are reported
+        let views = account_views(&dids, &accounts, invites, &invited_by, false);
+        assert!(views
+            .iter()
+            .all(|view| view.invites.is_none() && view.invited_by.is_none()));
+        assert!(views.iter().all(|view| view.invites_disabled.is_none()));
+    }
+}
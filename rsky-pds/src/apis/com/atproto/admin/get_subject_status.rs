@@ -1,11 +1,10 @@
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::auth_verifier::Moderator;
 use crate::db::DbConn;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use futures::try_join;
 use libipld::Cid;
 use rocket::serde::json::Json;
@@ -18,7 +17,7 @@ async fn inner_get_subject_status(
     did: Option<String>,
     uri: Option<String>,
     blob: Option<String>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<SubjectStatus> {
     let mut body: Option<SubjectStatus> = None;
@@ -26,8 +25,7 @@ async fn inner_get_subject_status(
         match did {
             None => bail!("Must provide a did to request blob state"),
             Some(did) => {
-                let actor_store =
-                    ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+                let actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
 
                 let takedown = actor_store
                     .blob
@@ -52,7 +50,7 @@ async fn inner_get_subject_status(
         if let (Some(uri_hostname), Some(_), Some(_)) = (parts.get(0), parts.get(1), parts.get(2)) {
             let actor_store = ActorStore::new(
                 uri_hostname.to_string(),
-                S3BlobStore::new(uri_hostname.to_string(), s3_config),
+                blobstore.create(uri_hostname.to_string()),
                 db,
             );
             let (takedown, cid) = try_join!(
@@ -94,11 +92,11 @@ pub async fn get_subject_status(
     did: Option<String>,
     uri: Option<String>,
     blob: Option<String>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
     _auth: Moderator,
 ) -> Result<Json<SubjectStatus>, ApiError> {
-    match inner_get_subject_status(did, uri, blob, s3_config, db).await {
+    match inner_get_subject_status(did, uri, blob, blobstore, db).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
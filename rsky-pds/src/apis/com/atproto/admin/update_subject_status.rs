@@ -1,12 +1,11 @@
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::auth_verifier::Moderator;
 use crate::db::DbConn;
 use crate::SharedSequencer;
 use anyhow::Result;
-use aws_config::SdkConfig;
 use libipld::Cid;
 use rocket::serde::json::Json;
 use rocket::State;
@@ -17,7 +16,7 @@ use std::str::FromStr;
 async fn inner_update_subject_status(
     body: Json<SubjectStatus>,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<UpdateSubjectStatusOutput> {
     let SubjectStatus {
@@ -35,7 +34,7 @@ async fn inner_update_subject_status(
                 let subject_at_uri: AtUri = subject.uri.clone().try_into()?;
                 let actor_store = ActorStore::new(
                     subject_at_uri.get_hostname().to_string(),
-                    S3BlobStore::new(subject_at_uri.get_hostname().to_string(), s3_config),
+                    blobstore.create(subject_at_uri.get_hostname().to_string()),
                     db,
                 );
                 actor_store
@@ -46,7 +45,7 @@ async fn inner_update_subject_status(
             Subject::RepoBlobRef(subject) => {
                 let actor_store = ActorStore::new(
                     subject.did.clone(),
-                    S3BlobStore::new(subject.did.clone(), s3_config),
+                    blobstore.create(subject.did.clone()),
                     db,
                 );
                 actor_store
@@ -86,11 +85,11 @@ async fn inner_update_subject_status(
 pub async fn update_subject_status(
     body: Json<SubjectStatus>,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
     _auth: Moderator,
 ) -> Result<Json<UpdateSubjectStatusOutput>, ApiError> {
-    match inner_update_subject_status(body, sequencer, s3_config, db).await {
+    match inner_update_subject_status(body, sequencer, blobstore, db).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
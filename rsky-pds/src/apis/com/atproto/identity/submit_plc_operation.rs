@@ -234,7 +234,7 @@ pub async fn submit_plc_operation(
 
     //Refresh DID after PLC update
     let mut id_lock = id_resolver.id_resolver.write().await;
-    if let Err(error) = id_lock.did.ensure_resolve(&did, None).await {
+    if let Err(error) = id_lock.did.ensure_resolve(&did, Some(true)).await {
         tracing::error!("Failed to fresh did after plc update\n{error}")
     };
 
@@ -4,7 +4,10 @@ use crate::apis::com::atproto::server::get_keys_from_private_key_str;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardCheckTakedown;
 use crate::config::ServerConfig;
-use crate::handle::{normalize_and_validate_handle, HandleValidationContext, HandleValidationOpts};
+use crate::handle::{
+    clear_cached_identity, normalize_and_validate_handle, HandleValidationContext,
+    HandleValidationOpts,
+};
 use crate::{plc, SharedIdResolver, SharedSequencer};
 use anyhow::{bail, Result};
 use rocket::serde::json::Json;
@@ -55,7 +58,19 @@ async fn inner_update_handle(
             plc_client
                 .update_handle(&requester, &signing_key, &handle)
                 .await?;
+            let old_handle = AccountManager::get_account(
+                &requester,
+                Some(AvailabilityFlags {
+                    include_deactivated: Some(true),
+                    include_taken_down: Some(true),
+                }),
+            )
+            .await?
+            .and_then(|account| account.handle);
             AccountManager::update_handle(&requester, &handle).await?;
+            let mut handles = vec![handle.as_str()];
+            handles.extend(old_handle.as_deref());
+            clear_cached_identity(id_resolver, &requester, &handles).await;
         }
     }
     let mut lock = sequencer.sequencer.write().await;
@@ -0,0 +1,76 @@
+use crate::apis::ApiError;
+use crate::config::{ServerConfig, ServiceConfig};
+use crate::APP_USER_AGENT;
+use anyhow::Result;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::atproto::label::QueryLabelsOutput;
+
+async fn inner_query_labels(
+    uri_patterns: Vec<String>,
+    sources: Vec<String>,
+    limit: u16,
+    cursor: Option<String>,
+    mod_service: &ServiceConfig,
+) -> Result<QueryLabelsOutput> {
+    let mut query: Vec<(&str, String)> = uri_patterns
+        .into_iter()
+        .map(|pattern| ("uriPatterns", pattern))
+        .chain(sources.into_iter().map(|source| ("sources", source)))
+        .collect();
+    query.push(("limit", limit.to_string()));
+    if let Some(cursor) = cursor {
+        query.push(("cursor", cursor));
+    }
+    let client = reqwest::Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .build()?;
+    let res = client
+        .get(format!(
+            "{}/xrpc/com.atproto.label.queryLabels",
+            mod_service.url
+        ))
+        .query(&query)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(res.json::<QueryLabelsOutput>().await?)
+}
+
+/// Find labels relevant to the provided AT-URI patterns. The PDS keeps no labels of its own, so
+/// this is answered by the configured moderation service, or is empty without one.
+#[tracing::instrument(skip_all)]
+#[allow(non_snake_case)]
+#[rocket::get("/xrpc/com.atproto.label.queryLabels?<uriPatterns>&<sources>&<limit>&<cursor>")]
+pub async fn query_labels(
+    // List of AT URI patterns to match (boolean 'OR'). Can be a prefix ending in '*'.
+    uriPatterns: Vec<String>,
+    // Optional list of label sources (DIDs) to filter on.
+    sources: Vec<String>,
+    limit: Option<u16>,
+    cursor: Option<String>,
+    cfg: &State<ServerConfig>,
+) -> Result<Json<QueryLabelsOutput>, ApiError> {
+    let limit = limit.unwrap_or(50);
+    if uriPatterns.is_empty() || !(1..=250).contains(&limit) {
+        return Err(ApiError::InvalidRequest(
+            "uriPatterns is required and limit must be between 1 and 250".to_string(),
+        ));
+    }
+    let mod_service = match cfg.mod_service {
+        Some(ref mod_service) => mod_service,
+        None => {
+            return Ok(Json(QueryLabelsOutput {
+                cursor: None,
+                labels: Vec::new(),
+            }))
+        }
+    };
+    match inner_query_labels(uriPatterns, sources, limit, cursor, mod_service).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
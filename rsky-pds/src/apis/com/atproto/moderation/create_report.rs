@@ -0,0 +1,62 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessStandard;
+use crate::config::{ServerConfig, ServiceConfig};
+use crate::context::service_auth_headers;
+use crate::APP_USER_AGENT;
+use anyhow::Result;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::atproto::moderation::{CreateReportInput, CreateReportOutput};
+
+const CREATE_REPORT_NSID: &str = "com.atproto.moderation.createReport";
+
+async fn inner_create_report(
+    body: CreateReportInput,
+    did: String,
+    report_service: &ServiceConfig,
+) -> Result<CreateReportOutput> {
+    let headers =
+        service_auth_headers(&did, &report_service.did, &CREATE_REPORT_NSID.to_string()).await?;
+    let client = reqwest::Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .build()?;
+    let res = client
+        .post(format!("{}/xrpc/{CREATE_REPORT_NSID}", report_service.url))
+        .headers(headers)
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(res.json::<CreateReportOutput>().await?)
+}
+
+/// Submit a moderation report regarding an atproto account or record. Forwarded to the
+/// configured report service, authenticated as the reporting account.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/com.atproto.moderation.createReport",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn create_report(
+    body: Json<CreateReportInput>,
+    auth: AccessStandard,
+    cfg: &State<ServerConfig>,
+) -> Result<Json<CreateReportOutput>, ApiError> {
+    let report_service = match cfg.report_service {
+        Some(ref report_service) => report_service,
+        None => {
+            return Err(ApiError::InvalidRequest(
+                "No report service configured".to_string(),
+            ))
+        }
+    };
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    match inner_create_report(body.into_inner(), did, report_service).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
@@ -1,22 +1,27 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
+use crate::apis::com::atproto::repo::write_error;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
 use crate::db::DbConn;
+use crate::rate_limit::guard::RepoWriteLimit;
+use crate::rate_limit::{CREATE_POINTS, DELETE_POINTS, UPDATE_POINTS};
 use crate::repo::prepare::{
     prepare_create, prepare_delete, prepare_update, PrepareCreateOpts, PrepareDeleteOpts,
     PrepareUpdateOpts,
 };
 use crate::SharedSequencer;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use futures::stream::{self, StreamExt};
 use libipld::Cid;
 use rocket::serde::json::Json;
 use rocket::State;
-use rsky_lexicon::com::atproto::repo::{ApplyWritesInput, ApplyWritesInputRefWrite};
+use rsky_lexicon::com::atproto::repo::{
+    ApplyWritesInput, ApplyWritesInputRefWrite, ApplyWritesOutput, ApplyWritesResult,
+    RefWriteResult,
+};
 use rsky_repo::types::PreparedWrite;
 use std::str::FromStr;
 
@@ -24,9 +29,9 @@ async fn inner_apply_writes(
     body: Json<ApplyWritesInput>,
     auth: AccessStandardIncludeChecks,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
-) -> Result<()> {
+) -> Result<ApplyWritesOutput> {
     let tx: ApplyWritesInput = body.into_inner();
     let ApplyWritesInput {
         repo,
@@ -101,18 +106,34 @@ async fn inner_apply_writes(
             None => None,
         };
 
-        let mut actor_store =
-            ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+        let mut actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
 
         let commit = actor_store
             .process_writes(writes.clone(), swap_commit_cid)
             .await?;
 
         let mut lock = sequencer.sequencer.write().await;
-        lock.sequence_commit(did.clone(), commit.clone(), writes)
+        lock.sequence_commit(did.clone(), commit.clone(), writes.clone())
             .await?;
         AccountManager::update_repo_root(did.to_string(), commit.cid, commit.rev)?;
-        Ok(())
+
+        let results = writes
+            .into_iter()
+            .map(|write| match write {
+                PreparedWrite::Create(write) => ApplyWritesResult::Create(RefWriteResult {
+                    uri: write.uri,
+                    cid: write.cid.to_string(),
+                    validation_status: write.validation_status.map(|status| status.to_string()),
+                }),
+                PreparedWrite::Update(write) => ApplyWritesResult::Update(RefWriteResult {
+                    uri: write.uri,
+                    cid: write.cid.to_string(),
+                    validation_status: write.validation_status.map(|status| status.to_string()),
+                }),
+                PreparedWrite::Delete(_) => ApplyWritesResult::Delete {},
+            })
+            .collect();
+        Ok(ApplyWritesOutput { results })
     } else {
         bail!("Could not find repo: `{repo}`")
     }
@@ -123,16 +144,24 @@ async fn inner_apply_writes(
 pub async fn apply_writes(
     body: Json<ApplyWritesInput>,
     auth: AccessStandardIncludeChecks,
+    write_limit: RepoWriteLimit<'_>,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
-) -> Result<(), ApiError> {
+) -> Result<Json<ApplyWritesOutput>, ApiError> {
     tracing::debug!("@LOG: debug apply_writes {body:#?}");
-    match inner_apply_writes(body, auth, sequencer, s3_config, db).await {
-        Ok(()) => Ok(()),
-        Err(error) => {
-            tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
-        }
+    let points: i64 = body
+        .writes
+        .iter()
+        .map(|write| match write {
+            ApplyWritesInputRefWrite::Create(_) => CREATE_POINTS,
+            ApplyWritesInputRefWrite::Update(_) => UPDATE_POINTS,
+            ApplyWritesInputRefWrite::Delete(_) => DELETE_POINTS,
+        })
+        .sum();
+    write_limit.consume(&auth.access, points).await?;
+    match inner_apply_writes(body, auth, sequencer, blobstore, db).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(write_error(error)),
     }
 }
@@ -1,14 +1,16 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
+use crate::apis::com::atproto::repo::write_error;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
 use crate::db::DbConn;
+use crate::rate_limit::guard::RepoWriteLimit;
+use crate::rate_limit::CREATE_POINTS;
 use crate::repo::prepare::{prepare_create, prepare_delete, PrepareCreateOpts, PrepareDeleteOpts};
 use crate::SharedSequencer;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use libipld::Cid;
 use rocket::serde::json::Json;
 use rocket::State;
@@ -21,7 +23,7 @@ async fn inner_create_record(
     body: Json<CreateRecordInput>,
     auth: AccessStandardIncludeChecks,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<CreateRecordOutput> {
     let CreateRecordInput {
@@ -62,8 +64,7 @@ async fn inner_create_record(
         })
         .await?;
 
-        let mut actor_store =
-            ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+        let mut actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
         let backlink_conflicts: Vec<AtUri> = match validate {
             Some(true) => {
                 let write_at_uri: AtUri = write.uri.clone().try_into()?;
@@ -102,6 +103,7 @@ async fn inner_create_record(
         Ok(CreateRecordOutput {
             uri: write.uri.clone(),
             cid: write.cid.to_string(),
+            validation_status: write.validation_status.map(|status| status.to_string()),
         })
     } else {
         bail!("Could not find repo: `{repo}`")
@@ -117,16 +119,15 @@ async fn inner_create_record(
 pub async fn create_record(
     body: Json<CreateRecordInput>,
     auth: AccessStandardIncludeChecks,
+    write_limit: RepoWriteLimit<'_>,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<Json<CreateRecordOutput>, ApiError> {
     tracing::debug!("@LOG: debug create_record {body:#?}");
-    match inner_create_record(body, auth, sequencer, s3_config, db).await {
+    write_limit.consume(&auth.access, CREATE_POINTS).await?;
+    match inner_create_record(body, auth, sequencer, blobstore, db).await {
         Ok(res) => Ok(Json(res)),
-        Err(error) => {
-            tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
-        }
+        Err(error) => Err(write_error(error)),
     }
 }
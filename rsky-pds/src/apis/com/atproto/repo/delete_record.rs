@@ -1,14 +1,15 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
 use crate::db::DbConn;
+use crate::rate_limit::guard::RepoWriteLimit;
+use crate::rate_limit::DELETE_POINTS;
 use crate::repo::prepare::{prepare_delete, PrepareDeleteOpts};
 use crate::SharedSequencer;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use libipld::Cid;
 use rocket::serde::json::Json;
 use rocket::State;
@@ -21,7 +22,7 @@ async fn inner_delete_record(
     body: Json<DeleteRecordInput>,
     auth: AccessStandardIncludeChecks,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<()> {
     let DeleteRecordInput {
@@ -63,8 +64,7 @@ async fn inner_delete_record(
                 rkey,
                 swap_cid: swap_record_cid,
             })?;
-            let mut actor_store =
-                ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+            let mut actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
             let write_at_uri: AtUri = write.uri.clone().try_into()?;
             let record = actor_store
                 .record
@@ -102,11 +102,13 @@ async fn inner_delete_record(
 pub async fn delete_record(
     body: Json<DeleteRecordInput>,
     auth: AccessStandardIncludeChecks,
+    write_limit: RepoWriteLimit<'_>,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<(), ApiError> {
-    match inner_delete_record(body, auth, sequencer, s3_config, db).await {
+    write_limit.consume(&auth.access, DELETE_POINTS).await?;
+    match inner_delete_record(body, auth, sequencer, blobstore, db).await {
         Ok(()) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
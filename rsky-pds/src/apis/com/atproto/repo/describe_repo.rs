@@ -1,11 +1,10 @@
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::db::DbConn;
 use crate::SharedIdResolver;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use rocket::serde::json::Json;
 use rocket::State;
 use rsky_identity::types::DidDocument;
@@ -15,7 +14,7 @@ use rsky_syntax::handle::INVALID_HANDLE;
 async fn inner_describe_repo(
     repo: String,
     id_resolver: &State<SharedIdResolver>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<DescribeRepoOutput> {
     let account = AccountManager::get_account(&repo, None).await?;
@@ -33,7 +32,7 @@ async fn inner_describe_repo(
 
             let mut actor_store = ActorStore::new(
                 account.did.clone(),
-                S3BlobStore::new(account.did.clone(), s3_config),
+                blobstore.create(account.did.clone()),
                 db,
             );
             let collections = actor_store.record.list_collections().await?;
@@ -54,10 +53,10 @@ async fn inner_describe_repo(
 pub async fn describe_repo(
     repo: String,
     id_resolver: &State<SharedIdResolver>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<Json<DescribeRepoOutput>, ApiError> {
-    match inner_describe_repo(repo, id_resolver, s3_config, db).await {
+    match inner_describe_repo(repo, id_resolver, blobstore, db).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("{error:?}");
@@ -1,11 +1,10 @@
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::db::DbConn;
 use crate::pipethrough::{pipethrough, OverrideOpts, ProxyRequest};
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use rocket::serde::json::Json;
 use rocket::State;
 use rsky_lexicon::com::atproto::repo::GetRecordOutput;
@@ -17,7 +16,7 @@ async fn inner_get_record(
     collection: String,
     rkey: String,
     cid: Option<String>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
     req: ProxyRequest<'_>,
 ) -> Result<GetRecordOutput> {
@@ -27,8 +26,7 @@ async fn inner_get_record(
     if let Some(did) = did {
         let uri = AtUri::make(did.clone(), Some(collection), Some(rkey))?;
 
-        let mut actor_store =
-            ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+        let mut actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
 
         match actor_store.record.get_record(&uri, cid, None).await {
             Ok(Some(record)) if record.takedown_ref.is_none() => Ok(GetRecordOutput {
@@ -71,11 +69,11 @@ pub async fn get_record(
     collection: String,
     rkey: String,
     cid: Option<String>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
     req: ProxyRequest<'_>,
 ) -> Result<Json<GetRecordOutput>, ApiError> {
-    match inner_get_record(repo, collection, rkey, cid, s3_config, db, req).await {
+    match inner_get_record(repo, collection, rkey, cid, blobstore, db, req).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
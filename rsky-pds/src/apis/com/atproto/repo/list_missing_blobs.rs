@@ -1,11 +1,10 @@
-use crate::actor_store::aws::s3::S3BlobStore;
 use crate::actor_store::blob::ListMissingBlobsOpts;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessFull;
 use crate::db::DbConn;
 use anyhow::Result;
-use aws_config::SdkConfig;
 use rocket::serde::json::Json;
 use rocket::State;
 use rsky_lexicon::com::atproto::repo::ListMissingBlobsOutput;
@@ -17,12 +16,12 @@ pub async fn list_missing_blobs(
     cursor: Option<String>,
     auth: AccessFull,
     db: DbConn,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
 ) -> Result<Json<ListMissingBlobsOutput>, ApiError> {
     let did = auth.access.credentials.unwrap().did.unwrap();
     let limit: u16 = limit.unwrap_or(500);
 
-    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
 
     match actor_store
         .blob
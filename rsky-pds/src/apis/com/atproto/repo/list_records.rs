@@ -1,10 +1,9 @@
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::db::DbConn;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use rocket::serde::json::Json;
 use rocket::State;
 use rsky_lexicon::com::atproto::repo::{ListRecordsOutput, Record};
@@ -25,7 +24,7 @@ async fn inner_list_records(
     rkeyEnd: Option<String>,
     // Flag to reverse the order of the returned records.
     reverse: bool,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<ListRecordsOutput> {
     if limit > 100 {
@@ -33,8 +32,7 @@ async fn inner_list_records(
     }
     let did = AccountManager::get_did_for_actor(&repo, None).await?;
     if let Some(did) = did {
-        let mut actor_store =
-            ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+        let mut actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
 
         let records: Vec<Record> = actor_store
             .record
@@ -89,14 +87,14 @@ pub async fn list_records(
     rkeyEnd: Option<String>,
     // Flag to reverse the order of the returned records.
     reverse: Option<bool>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<Json<ListRecordsOutput>, ApiError> {
     let limit = limit.unwrap_or(50);
     let reverse = reverse.unwrap_or(false);
 
     match inner_list_records(
-        repo, collection, limit, cursor, rkeyStart, rkeyEnd, reverse, s3_config, db,
+        repo, collection, limit, cursor, rkeyStart, rkeyEnd, reverse, blobstore, db,
     )
     .await
     {
@@ -1,5 +1,7 @@
 use crate::account_manager::helpers::account::{ActorAccount, AvailabilityFlags};
 use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::repo::prepare::InvalidRecordError;
 use anyhow::{bail, Result};
 
 pub async fn assert_repo_availability(
@@ -31,6 +33,20 @@ pub async fn assert_repo_availability(
     }
 }
 
+/// Invalid records are the client's fault and answered with `400 InvalidRecord`, anything else
+/// is logged as a server error
+pub fn write_error(error: anyhow::Error) -> ApiError {
+    match error.downcast_ref::<InvalidRecordError>() {
+        Some(InvalidRecordError(message)) => {
+            ApiError::BadRequest("InvalidRecord".to_string(), message.clone())
+        }
+        None => {
+            tracing::error!("@LOG: ERROR: {error}");
+            ApiError::RuntimeError
+        }
+    }
+}
+
 pub mod apply_writes;
 pub mod create_record;
 pub mod delete_record;
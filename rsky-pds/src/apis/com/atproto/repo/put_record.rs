@@ -1,14 +1,16 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
+use crate::apis::com::atproto::repo::write_error;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
 use crate::db::DbConn;
+use crate::rate_limit::guard::RepoWriteLimit;
+use crate::rate_limit::CREATE_POINTS;
 use crate::repo::prepare::{prepare_create, prepare_update, PrepareCreateOpts, PrepareUpdateOpts};
 use crate::SharedSequencer;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use libipld::Cid;
 use rocket::serde::json::Json;
 use rocket::State;
@@ -22,7 +24,7 @@ async fn inner_put_record(
     body: Json<PutRecordInput>,
     auth: AccessStandardIncludeChecks,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<PutRecordOutput> {
     let PutRecordInput {
@@ -60,8 +62,7 @@ async fn inner_put_record(
             None => None,
         };
         let (commit, write): (Option<CommitData>, PreparedWrite) = {
-            let mut actor_store =
-                ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+            let mut actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
 
             let current = actor_store
                 .record
@@ -114,6 +115,7 @@ async fn inner_put_record(
         Ok(PutRecordOutput {
             uri: write.uri().to_string(),
             cid: write.cid().unwrap().to_string(),
+            validation_status: write.validation_status().map(|status| status.to_string()),
         })
     } else {
         bail!("Could not find repo: `{repo}`")
@@ -125,16 +127,16 @@ async fn inner_put_record(
 pub async fn put_record(
     body: Json<PutRecordInput>,
     auth: AccessStandardIncludeChecks,
+    write_limit: RepoWriteLimit<'_>,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<Json<PutRecordOutput>, ApiError> {
     tracing::debug!("@LOG: debug put_record {body:#?}");
-    match inner_put_record(body, auth, sequencer, s3_config, db).await {
+    // may create the record, so it's charged as a create
+    write_limit.consume(&auth.access, CREATE_POINTS).await?;
+    match inner_put_record(body, auth, sequencer, blobstore, db).await {
         Ok(res) => Ok(Json(res)),
-        Err(error) => {
-            tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
-        }
+        Err(error) => Err(write_error(error)),
     }
 }
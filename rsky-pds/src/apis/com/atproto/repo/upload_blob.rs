@@ -1,10 +1,9 @@
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
 use crate::db::DbConn;
 use anyhow::Result;
-use aws_config::SdkConfig;
 use rocket::data::Data;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome};
@@ -41,16 +40,12 @@ async fn inner_upload_blob(
     auth: AccessStandardIncludeChecks,
     blob: Data<'_>,
     content_type: ContentType,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<BlobOutput> {
     let requester = auth.access.credentials.unwrap().did.unwrap();
 
-    let actor_store = ActorStore::new(
-        requester.clone(),
-        S3BlobStore::new(requester.clone(), s3_config),
-        db,
-    );
+    let actor_store = ActorStore::new(requester.clone(), blobstore.create(requester.clone()), db);
 
     let metadata = actor_store
         .blob
@@ -96,10 +91,10 @@ pub async fn upload_blob(
     auth: AccessStandardIncludeChecks,
     blob: Data<'_>,
     content_type: ContentType,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<Json<BlobOutput>, ApiError> {
-    match inner_upload_blob(auth, blob, content_type, s3_config, db).await {
+    match inner_upload_blob(auth, blob, content_type, blobstore, db).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("{error:?}");
@@ -1,13 +1,12 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::com::atproto::server::assert_valid_did_documents_for_service;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessFull;
 use crate::db::DbConn;
 use crate::SharedSequencer;
-use aws_config::SdkConfig;
 use rocket::State;
 use rsky_repo::cid_set::CidSet;
 use rsky_repo::storage::readable_blockstore::ReadableBlockstore;
@@ -18,7 +17,7 @@ use rsky_syntax::handle::INVALID_HANDLE;
 async fn inner_activate_account(
     auth: AccessFull,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<(), ApiError> {
     let requester = auth.access.credentials.unwrap().did.unwrap();
@@ -36,11 +35,8 @@ async fn inner_activate_account(
     if let Some(account) = account {
         AccountManager::activate_account(&requester).await?;
 
-        let actor_store = ActorStore::new(
-            requester.clone(),
-            S3BlobStore::new(requester.clone(), s3_config),
-            db,
-        );
+        let actor_store =
+            ActorStore::new(requester.clone(), blobstore.create(requester.clone()), db);
         let storage_guard = actor_store.storage.read().await;
         let root = storage_guard.get_root_detailed().await?;
         let blocks = storage_guard.get_blocks(vec![root.cid]).await?;
@@ -76,10 +72,10 @@ async fn inner_activate_account(
 pub async fn activate_account(
     auth: AccessFull,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<(), ApiError> {
-    match inner_activate_account(auth, sequencer, s3_config, db).await {
+    match inner_activate_account(auth, sequencer, blobstore, db).await {
         Ok(_) => Ok(()),
         Err(error) => Err(error),
     }
@@ -1,6 +1,6 @@
 use crate::account_manager::helpers::account::AccountStatus;
 use crate::account_manager::{AccountManager, CreateAccountOpts};
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::com::atproto::server::safe_resolve_did_doc;
 use crate::apis::ApiError;
@@ -12,7 +12,6 @@ use crate::plc::operations::{create_op, CreateAtprotoOpInput};
 use crate::plc::types::{OpOrTombstone, Operation};
 use crate::SharedSequencer;
 use crate::{plc, SharedIdResolver};
-use aws_config::SdkConfig;
 use email_address::*;
 use rocket::serde::json::Json;
 use rocket::State;
@@ -45,7 +44,7 @@ pub async fn server_create_account(
     body: Json<CreateAccountInput>,
     auth: UserDidAuthOptional,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     cfg: &State<ServerConfig>,
     id_resolver: &State<SharedIdResolver>,
     db: DbConn,
@@ -67,8 +66,7 @@ pub async fn server_create_account(
     } = validate_inputs_for_local_pds(cfg, id_resolver, body.into_inner(), requester).await?;
 
     // Create new actor repo TODO: Proper rollback
-    let mut actor_store =
-        ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let mut actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
     let commit = match actor_store.create_repo(signing_key, Vec::new()).await {
         Ok(commit) => commit,
         Err(error) => {
@@ -286,11 +284,7 @@ pub async fn validate_inputs_for_local_pds(
 
     match input.did {
         Some(input_did) => {
-            if input_did == requester.unwrap_or("n/a".to_string()) {
-                return Err(ApiError::AuthRequiredError(format!(
-                    "Missing auth to create account with did: {input_did}"
-                )));
-            }
+            check_did_auth(&input_did, requester.as_ref())?;
             did = input_did;
             plc_op = None;
             deactivated = true;
@@ -315,6 +309,17 @@ pub async fn validate_inputs_for_local_pds(
     })
 }
 
+/// An account brought in with an existing DID, by a migration, must be created with service auth
+/// issued by that same DID
+fn check_did_auth(input_did: &String, requester: Option<&String>) -> Result<(), ApiError> {
+    if requester != Some(input_did) {
+        return Err(ApiError::AuthRequiredError(format!(
+            "Missing auth to create account with did: {input_did}"
+        )));
+    }
+    Ok(())
+}
+
 #[tracing::instrument(skip_all)]
 async fn format_did_and_plc_op(
     input: CreateAccountInput,
@@ -355,3 +360,16 @@ async fn format_did_and_plc_op(
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn existing_did_requires_its_own_service_auth() {
+        let did = "did:plc:migrating".to_string();
+        assert!(check_did_auth(&did, Some(&did)).is_ok());
+        assert!(check_did_auth(&did, Some(&"did:plc:someone-else".to_string())).is_err());
+        assert!(check_did_auth(&did, None).is_err());
+    }
+}
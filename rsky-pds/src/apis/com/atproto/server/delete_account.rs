@@ -1,6 +1,6 @@
 use crate::account_manager::helpers::account::{AccountStatus, AvailabilityFlags};
 use crate::account_manager::AccountManager;
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::auth_verifier::AdminToken;
@@ -8,7 +8,6 @@ use crate::db::DbConn;
 use crate::models::models::EmailTokenPurpose;
 use crate::sequencer;
 use crate::SharedSequencer;
-use aws_config::SdkConfig;
 use rocket::serde::json::Json;
 use rocket::State;
 use rsky_lexicon::com::atproto::server::DeleteAccountInput;
@@ -17,7 +16,7 @@ use rsky_lexicon::com::atproto::server::DeleteAccountInput;
 async fn inner_delete_account(
     body: Json<DeleteAccountInput>,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<(), ApiError> {
     let DeleteAccountInput {
@@ -45,8 +44,7 @@ async fn inner_delete_account(
         )
         .await?;
 
-        let mut actor_store =
-            ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+        let mut actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
         actor_store.destroy().await?;
         AccountManager::delete_account(&did).await?;
         let mut lock = sequencer.sequencer.write().await;
@@ -72,11 +70,11 @@ async fn inner_delete_account(
 pub async fn delete_account(
     body: Json<DeleteAccountInput>,
     sequencer: &State<SharedSequencer>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
     _auth: AdminToken,
 ) -> Result<(), ApiError> {
-    match inner_delete_account(body, sequencer, s3_config, db).await {
+    match inner_delete_account(body, sequencer, blobstore, db).await {
         Ok(_) => Ok(()),
         Err(error) => Err(error),
     }
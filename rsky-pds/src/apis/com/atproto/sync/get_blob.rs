@@ -1,4 +1,4 @@
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::com::atproto::repo::assert_repo_availability;
 use crate::apis::ApiError;
@@ -6,12 +6,12 @@ use crate::auth_verifier;
 use crate::auth_verifier::OptionalAccessOrAdminToken;
 use crate::db::DbConn;
 use anyhow::Result;
-use aws_config::SdkConfig;
 use aws_sdk_s3::operation::get_object::GetObjectError;
 use aws_sdk_s3::primitives::AggregatedBytes;
 use libipld::Cid;
 use rocket::http::Header;
 use rocket::{Responder, State};
+use rsky_repo::error::BlobError;
 use std::str::FromStr;
 
 #[derive(Responder)]
@@ -21,7 +21,7 @@ pub struct BlobResponder(Vec<u8>, Header<'static>, Header<'static>, Header<'stat
 async fn inner_get_blob(
     did: String,
     cid: String,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
 ) -> Result<(Vec<u8>, Option<String>)> {
@@ -33,7 +33,7 @@ async fn inner_get_blob(
     let _ = assert_repo_availability(&did, is_user_or_admin).await?;
 
     let cid = Cid::from_str(&cid)?;
-    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
 
     let found = actor_store.blob.get_blob(cid).await?;
     let buf: AggregatedBytes = found.stream.collect().await?;
@@ -47,11 +47,11 @@ async fn inner_get_blob(
 pub async fn get_blob(
     did: String,
     cid: String,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
 ) -> Result<BlobResponder, ApiError> {
-    match inner_get_blob(did, cid, s3_config, auth, db).await {
+    match inner_get_blob(did, cid, blobstore, auth, db).await {
         Ok(res) => {
             let (bytes, mime_type) = res;
             Ok(BlobResponder(
@@ -70,6 +70,10 @@ pub async fn get_blob(
                     tracing::error!("Error: {}", error);
                     Err(ApiError::BlobNotFound)
                 }
+                _ if error.is::<BlobError>() => {
+                    tracing::error!("Error: {}", error);
+                    Err(ApiError::BlobNotFound)
+                }
                 _ => {
                     tracing::error!("Error: {}", error);
                     Err(ApiError::RuntimeError)
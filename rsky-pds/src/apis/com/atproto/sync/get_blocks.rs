@@ -1,4 +1,4 @@
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::com::atproto::repo::assert_repo_availability;
 use crate::apis::ApiError;
@@ -6,7 +6,6 @@ use crate::auth_verifier;
 use crate::auth_verifier::OptionalAccessOrAdminToken;
 use crate::db::DbConn;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use libipld::Cid;
 use rocket::{Responder, State};
 use rsky_repo::car::blocks_to_car_file;
@@ -20,7 +19,7 @@ pub struct BlockResponder(Vec<u8>);
 async fn inner_get_blocks(
     did: String,
     cids: Vec<String>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
 ) -> Result<Vec<u8>> {
@@ -36,7 +35,7 @@ async fn inner_get_blocks(
         .map(|c| Cid::from_str(&c).map_err(anyhow::Error::new))
         .collect::<Result<Vec<Cid>>>()?;
 
-    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
     let storage_guard = actor_store.storage.read().await;
     let got = storage_guard.get_blocks(cids).await?;
 
@@ -60,11 +59,11 @@ async fn inner_get_blocks(
 pub async fn get_blocks(
     did: String,
     cids: Vec<String>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
 ) -> Result<BlockResponder, ApiError> {
-    match inner_get_blocks(did, cids, s3_config, auth, db).await {
+    match inner_get_blocks(did, cids, blobstore, auth, db).await {
         Ok(res) => Ok(BlockResponder(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
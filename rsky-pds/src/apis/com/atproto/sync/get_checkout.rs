@@ -0,0 +1,49 @@
+use crate::actor_store::blobstore::BlobStoreCreator;
+use crate::actor_store::ActorStore;
+use crate::apis::com::atproto::repo::assert_repo_availability;
+use crate::apis::com::atproto::sync::get_repo::BlockResponder;
+use crate::apis::ApiError;
+use crate::auth_verifier;
+use crate::auth_verifier::OptionalAccessOrAdminToken;
+use crate::db::DbConn;
+use anyhow::{bail, Result};
+use rocket::State;
+
+async fn inner_get_checkout(
+    did: String,
+    blobstore: &State<BlobStoreCreator>,
+    auth: OptionalAccessOrAdminToken,
+    db: DbConn,
+) -> Result<Vec<u8>> {
+    let is_user_or_admin = if let Some(access) = auth.access {
+        auth_verifier::is_user_or_admin(access, &did)
+    } else {
+        false
+    };
+    let _ = assert_repo_availability(&did, is_user_or_admin).await?;
+
+    let actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
+    let storage_guard = actor_store.storage.read().await;
+    match storage_guard.get_car_stream(None).await {
+        Err(_) => bail!("Could not find repo for DID: {did}"),
+        Ok(carstream) => Ok(carstream),
+    }
+}
+
+/// DEPRECATED - please use com.atproto.sync.getRepo instead
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/com.atproto.sync.getCheckout?<did>")]
+pub async fn get_checkout(
+    did: String,
+    blobstore: &State<BlobStoreCreator>,
+    auth: OptionalAccessOrAdminToken,
+    db: DbConn,
+) -> Result<BlockResponder, ApiError> {
+    match inner_get_checkout(did, blobstore, auth, db).await {
+        Ok(res) => Ok(BlockResponder(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
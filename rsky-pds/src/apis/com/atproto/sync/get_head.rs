@@ -0,0 +1,52 @@
+use crate::actor_store::blobstore::BlobStoreCreator;
+use crate::actor_store::ActorStore;
+use crate::apis::com::atproto::repo::assert_repo_availability;
+use crate::apis::ApiError;
+use crate::auth_verifier;
+use crate::auth_verifier::OptionalAccessOrAdminToken;
+use crate::db::DbConn;
+use anyhow::{bail, Result};
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::atproto::sync::GetHeadOutput;
+
+async fn inner_get_head(
+    did: String,
+    blobstore: &State<BlobStoreCreator>,
+    auth: OptionalAccessOrAdminToken,
+    db: DbConn,
+) -> Result<GetHeadOutput> {
+    let is_user_or_admin = if let Some(access) = auth.access {
+        auth_verifier::is_user_or_admin(access, &did)
+    } else {
+        false
+    };
+    let _ = assert_repo_availability(&did, is_user_or_admin).await?;
+
+    let actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
+    let storage_guard = actor_store.storage.read().await;
+    match storage_guard.get_root_detailed().await {
+        Ok(res) => Ok(GetHeadOutput {
+            root: res.cid.to_string(),
+        }),
+        Err(_) => bail!("Could not find root for DID: {did}"),
+    }
+}
+
+/// DEPRECATED - please use com.atproto.sync.getLatestCommit instead
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/com.atproto.sync.getHead?<did>")]
+pub async fn get_head(
+    did: String,
+    blobstore: &State<BlobStoreCreator>,
+    auth: OptionalAccessOrAdminToken,
+    db: DbConn,
+) -> Result<Json<GetHeadOutput>, ApiError> {
+    match inner_get_head(did, blobstore, auth, db).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::com::atproto::repo::assert_repo_availability;
 use crate::apis::ApiError;
@@ -6,14 +6,13 @@ use crate::auth_verifier;
 use crate::auth_verifier::OptionalAccessOrAdminToken;
 use crate::db::DbConn;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use rocket::serde::json::Json;
 use rocket::State;
 use rsky_lexicon::com::atproto::sync::GetLatestCommitOutput;
 
 async fn inner_get_latest_commit(
     did: String,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
 ) -> Result<GetLatestCommitOutput> {
@@ -24,7 +23,7 @@ async fn inner_get_latest_commit(
     };
     let _ = assert_repo_availability(&did, is_user_or_admin).await?;
 
-    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
     let storage_guard = actor_store.storage.read().await;
     match storage_guard.get_root_detailed().await {
         Ok(res) => Ok(GetLatestCommitOutput {
@@ -39,11 +38,11 @@ async fn inner_get_latest_commit(
 #[rocket::get("/xrpc/com.atproto.sync.getLatestCommit?<did>")]
 pub async fn get_latest_commit(
     did: String,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
 ) -> Result<Json<GetLatestCommitOutput>, ApiError> {
-    match inner_get_latest_commit(did, s3_config, auth, db).await {
+    match inner_get_latest_commit(did, blobstore, auth, db).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
@@ -1,4 +1,4 @@
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::com::atproto::repo::assert_repo_availability;
 use crate::apis::ApiError;
@@ -6,7 +6,6 @@ use crate::auth_verifier;
 use crate::auth_verifier::OptionalAccessOrAdminToken;
 use crate::db::DbConn;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use libipld::Cid;
 use rocket::{Responder, State};
 use rsky_repo::storage::types::RepoStorage;
@@ -22,7 +21,7 @@ async fn inner_get_record(
     collection: String,
     rkey: String,
     commit: Option<String>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
 ) -> Result<Vec<u8>> {
@@ -32,7 +31,7 @@ async fn inner_get_record(
         false
     };
     let _ = assert_repo_availability(&did, is_user_or_admin).await?;
-    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
     let storage_guard = actor_store.storage.read().await;
     let commit: Option<Cid> = match commit {
         Some(commit) => Some(Cid::from_str(&commit)?),
@@ -61,11 +60,11 @@ pub async fn get_record(
     collection: String,
     rkey: String,
     commit: Option<String>, // DEPRECATED: referenced a repo commit by CID, and retrieved record as of that commit
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
 ) -> Result<BlockResponder, ApiError> {
-    match inner_get_record(did, collection, rkey, commit, s3_config, auth, db).await {
+    match inner_get_record(did, collection, rkey, commit, blobstore, auth, db).await {
         Ok(res) => Ok(BlockResponder(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
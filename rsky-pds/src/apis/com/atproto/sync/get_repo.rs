@@ -1,4 +1,4 @@
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::com::atproto::repo::assert_repo_availability;
 use crate::apis::ApiError;
@@ -6,20 +6,19 @@ use crate::auth_verifier;
 use crate::auth_verifier::OptionalAccessOrAdminToken;
 use crate::db::DbConn;
 use anyhow::{bail, Result};
-use aws_config::SdkConfig;
 use rocket::{Responder, State};
 
 #[derive(Responder)]
 #[response(status = 200, content_type = "application/vnd.ipld.car")]
-pub struct BlockResponder(Vec<u8>);
+pub struct BlockResponder(pub Vec<u8>);
 
 async fn get_car_stream(
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     did: String,
     since: Option<String>,
     db: DbConn,
 ) -> Result<Vec<u8>> {
-    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
     let storage_guard = actor_store.storage.read().await;
     match storage_guard.get_car_stream(since).await {
         Err(_) => bail!("Could not find repo for DID: {did}"),
@@ -30,7 +29,7 @@ async fn get_car_stream(
 async fn inner_get_repo(
     did: String,
     since: Option<String>, // The revision ('rev') of the repo to create a diff from.
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
 ) -> Result<Vec<u8>> {
@@ -40,7 +39,7 @@ async fn inner_get_repo(
         false
     };
     let _ = assert_repo_availability(&did, is_user_or_admin).await?;
-    get_car_stream(s3_config, did, since, db).await
+    get_car_stream(blobstore, did, since, db).await
 }
 
 /// Download a repository export as CAR file. Optionally only a 'diff' since a previous revision.
@@ -50,11 +49,11 @@ async fn inner_get_repo(
 pub async fn get_repo(
     did: String,
     since: Option<String>, // The revision ('rev') of the repo to create a diff from.
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
 ) -> Result<BlockResponder, ApiError> {
-    match inner_get_repo(did, since, s3_config, auth, db).await {
+    match inner_get_repo(did, since, blobstore, auth, db).await {
         Ok(res) => Ok(BlockResponder(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
@@ -1,20 +1,19 @@
 use crate::account_manager::helpers::account::{
     format_account_status, AccountStatus, FormattedAccountStatus,
 };
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::apis::com::atproto::repo::assert_repo_availability;
 use crate::apis::ApiError;
 use crate::db::DbConn;
 use anyhow::Result;
-use aws_config::SdkConfig;
 use rocket::serde::json::Json;
 use rocket::State;
 use rsky_lexicon::com::atproto::sync::{GetRepoStatusOutput, RepoStatus};
 
 async fn inner_get_repo(
     did: String,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<GetRepoStatusOutput> {
     let account = assert_repo_availability(&did, true).await?;
@@ -22,8 +21,7 @@ async fn inner_get_repo(
 
     let mut rev: Option<String> = None;
     if active {
-        let actor_store =
-            ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+        let actor_store = ActorStore::new(did.clone(), blobstore.create(did.clone()), db);
         let storage_guard = actor_store.storage.read().await;
         let root = storage_guard.get_root_detailed().await?;
         rev = Some(root.rev);
@@ -52,10 +50,10 @@ async fn inner_get_repo(
 #[rocket::get("/xrpc/com.atproto.sync.getRepoStatus?<did>")]
 pub async fn get_repo_status(
     did: String,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     db: DbConn,
 ) -> Result<Json<GetRepoStatusOutput>, ApiError> {
-    match inner_get_repo(did, s3_config, db).await {
+    match inner_get_repo(did, blobstore, db).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
@@ -0,0 +1,45 @@
+use crate::apis::ApiError;
+use crate::config::ServerConfig;
+use crate::jobs::{self, JobKind};
+use anyhow::Result;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::atproto::sync::NotifyOfUpdateInput;
+
+async fn inner_notify_of_update(body: NotifyOfUpdateInput, cfg: &ServerConfig) -> Result<()> {
+    // this PDS doesn't crawl anyone, but a notice about itself is passed on to its crawlers
+    if body.hostname != cfg.service.hostname {
+        return Ok(());
+    }
+    for crawler in cfg.crawlers.iter() {
+        jobs::enqueue_once(
+            JobKind::NotifyCrawler {
+                hostname: cfg.service.hostname.clone(),
+                crawler: crawler.clone(),
+            },
+            format!("notify_crawler:{crawler}"),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Notify a crawling service of a recent update, and that crawling should resume.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/com.atproto.sync.notifyOfUpdate",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn notify_of_update(
+    body: Json<NotifyOfUpdateInput>,
+    cfg: &State<ServerConfig>,
+) -> Result<(), ApiError> {
+    match inner_notify_of_update(body.into_inner(), cfg).await {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
@@ -1,24 +1,25 @@
 use crate::config::ServerConfig;
-use crate::crawlers::Crawlers;
+use crate::metrics::{FirehoseSubscriber, FIREHOSE_EVENTS_SENT};
 use crate::sequencer::events::{
     AccountEvt, CommitEvt, HandleEvt, IdentityEvt, SeqEvt, TombstoneEvt, TypedAccountEvt,
     TypedCommitEvt, TypedHandleEvt, TypedIdentityEvt, TypedTombstoneEvt,
 };
-use crate::sequencer::outbox::{Outbox, OutboxOpts};
-use crate::sequencer::Sequencer;
+use crate::sequencer::outbox::{ConsumerTooSlowError, Outbox};
 use crate::xrpc_server::stream::frames::{ErrorFrame, Frame, MessageFrame, MessageFrameOpts};
 use crate::xrpc_server::stream::types::ErrorFrameBody;
+use crate::SharedSequencer;
+use anyhow::Result;
 use chrono::offset::Utc as UtcOffset;
-use chrono::{DateTime, Duration};
+use chrono::{DateTime, Duration, NaiveDateTime};
 use futures::{pin_mut, StreamExt};
 use rocket::tokio::select;
 use rocket::{Shutdown, State};
-use rsky_common::time::from_str_to_utc;
 use rsky_common::RFC3339_VARIANT;
 use rsky_lexicon::com::atproto::sync::{
     SubscribeReposAccount, SubscribeReposCommit, SubscribeReposCommitOperation,
     SubscribeReposHandle, SubscribeReposIdentity, SubscribeReposTombstone,
 };
+use serde::Serialize;
 use serde_json::json;
 use std::time::SystemTime;
 use tokio::time::{interval, Duration as TokioDuration};
@@ -31,6 +32,140 @@ fn get_backfill_limit(ms: u64) -> String {
     format!("{}", dt.format(RFC3339_VARIANT))
 }
 
+fn parse_time(time: &str) -> Result<DateTime<UtcOffset>> {
+    Ok(NaiveDateTime::parse_from_str(time, RFC3339_VARIANT)?.and_utc())
+}
+
+fn message_frame<T: Serialize>(r#type: String, body: T) -> Result<Vec<u8>> {
+    MessageFrame::new(
+        body,
+        Some(MessageFrameOpts {
+            r#type: Some(format!("#{0}", r#type)),
+        }),
+    )
+    .to_bytes()
+}
+
+/// Encodes an event as the binary frame sent to subscribers
+#[tracing::instrument(skip_all, fields(seq = evt.seq()))]
+fn event_frame(evt: SeqEvt) -> Result<Vec<u8>> {
+    match evt {
+        SeqEvt::TypedCommitEvt(commit) => {
+            let TypedCommitEvt {
+                r#type,
+                seq,
+                time,
+                evt,
+            } = commit;
+            let CommitEvt {
+                rebase,
+                too_big,
+                repo,
+                commit,
+                prev,
+                rev,
+                since,
+                blocks,
+                ops,
+                blobs,
+            } = evt;
+            let subscribe_commit_evt = SubscribeReposCommit {
+                seq,
+                time: parse_time(&time)?,
+                rebase,
+                too_big,
+                repo,
+                commit,
+                prev,
+                rev,
+                since,
+                blocks,
+                ops: ops
+                    .into_iter()
+                    .map(|op| SubscribeReposCommitOperation {
+                        path: op.path,
+                        cid: op.cid,
+                        action: op.action.to_string(),
+                    })
+                    .collect::<Vec<SubscribeReposCommitOperation>>(),
+                blobs: blobs
+                    .into_iter()
+                    .map(|blob| blob.to_string())
+                    .collect::<Vec<String>>(),
+            };
+            message_frame(r#type, subscribe_commit_evt)
+        }
+        SeqEvt::TypedHandleEvt(handle) => {
+            let TypedHandleEvt {
+                r#type,
+                seq,
+                time,
+                evt,
+            } = handle;
+            let HandleEvt { did, handle } = evt;
+            let subscribe_handle_evt = SubscribeReposHandle {
+                did,
+                handle,
+                seq,
+                time: parse_time(&time)?,
+            };
+            message_frame(r#type, subscribe_handle_evt)
+        }
+        SeqEvt::TypedIdentityEvt(identity) => {
+            let TypedIdentityEvt {
+                r#type,
+                seq,
+                time,
+                evt,
+            } = identity;
+            let IdentityEvt { did, handle } = evt;
+            let subscribe_identity_evt = SubscribeReposIdentity {
+                did,
+                seq,
+                handle,
+                time: parse_time(&time)?,
+            };
+            message_frame(r#type, subscribe_identity_evt)
+        }
+        SeqEvt::TypedAccountEvt(account) => {
+            let TypedAccountEvt {
+                r#type,
+                seq,
+                time,
+                evt,
+            } = account;
+            let AccountEvt {
+                did,
+                active,
+                status,
+            } = evt;
+            let subscribe_account_evt = SubscribeReposAccount {
+                did,
+                seq,
+                status,
+                active,
+                time: parse_time(&time)?,
+            };
+            message_frame(r#type, subscribe_account_evt)
+        }
+        SeqEvt::TypedTombstoneEvt(tombstone) => {
+            let TypedTombstoneEvt {
+                r#type,
+                seq,
+                time,
+                evt,
+            } = tombstone;
+            let TombstoneEvt { did } = evt;
+            let subscribe_tombstone_evt = SubscribeReposTombstone {
+                did,
+                seq,
+                time: parse_time(&time)?,
+            };
+            message_frame(r#type, subscribe_tombstone_evt)
+        }
+    }
+}
+
 /// Repository event stream, aka Firehose endpoint. Outputs repo commits with diff data,
 /// and identity update events, for all repositories on the current server. See the atproto
 /// specifications for details around stream sequencing, repo versioning, CAR diff format, and more.
@@ -40,20 +175,15 @@ fn get_backfill_limit(ms: u64) -> String {
 pub async fn subscribe_repos<'a>(
     cursor: Option<i64>,
     cfg: &'a State<ServerConfig>,
+    sequencer: &'a State<SharedSequencer>,
     mut shutdown: Shutdown,
     ws: ws::WebSocket,
 ) -> ws::Stream!['a] {
     ws::Stream! { ws =>
-        let sequencer_lock = Sequencer::new(
-            Crawlers::new(cfg.service.hostname.clone(), cfg.crawlers.clone()),
-            None,
-        );
-        let mut outbox = Outbox::new(
-            sequencer_lock.clone(),
-            Some(OutboxOpts {
-                max_buffer_size: cfg.subscription.repo_backfill_limit_ms as usize,
-            })
-        );
+        let _subscriber = FirehoseSubscriber::connect();
+        let span = tracing::info_span!("subscribe_repos", cursor = ?cursor);
+        let sequencer_lock = sequencer.sequencer.read().await.clone();
+        let mut outbox = Outbox::new(sequencer_lock.clone());
 
         tracing::debug!("@LOG DEBUG: request to com.atproto.sync.subscribeRepos; Cursor={cursor:?}");
         let backfill_time = get_backfill_limit(cfg.subscription.repo_backfill_limit_ms);
@@ -128,8 +258,12 @@ pub async fn subscribe_repos<'a>(
                     let evt = match evt {
                         Some(Ok(evt)) => evt,
                         Some(Err(err)) => {
+                            let error = match err.downcast_ref::<ConsumerTooSlowError>() {
+                                Some(_) => "ConsumerTooSlow",
+                                None => "EventStreamError",
+                            };
                             let error_frame = ErrorFrame::new(ErrorFrameBody {
-                                error: "EventStreamError".to_string(),
+                                error: error.to_string(),
                                 message: Some(err.to_string()),
                             });
                             yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
@@ -144,139 +278,20 @@ pub async fn subscribe_repos<'a>(
                             return;
                         }
                     };
-
-                    match evt {
-                        SeqEvt::TypedCommitEvt(commit) => {
-                            let TypedCommitEvt { r#type, seq, time, evt } = commit;
-                            let CommitEvt { rebase, too_big, repo, commit, prev, rev, since, blocks, ops, blobs } = evt;
-                            let subscribe_commit_evt = SubscribeReposCommit {
-                                seq,
-                                time: from_str_to_utc(&time),
-                                rebase,
-                                too_big,
-                                repo,
-                                commit,
-                                prev,
-                                rev,
-                                since,
-                                blocks,
-                                ops: ops.into_iter().map(|op| SubscribeReposCommitOperation {
-                                    path: op.path,
-                                    cid: match op.cid {
-                                        None => None,
-                                        Some(cid) => Some(cid)
-                                    },
-                                    action: op.action.to_string()
-                                }).collect::<Vec<SubscribeReposCommitOperation>>(),
-                                blobs: blobs.into_iter().map(|blob| blob.to_string()).collect::<Vec<String>>(),
-                            };
-                            let message_frame = MessageFrame::new(subscribe_commit_evt, Some(MessageFrameOpts { r#type: Some(format!("#{0}",r#type)) }));
-                            let binary = match message_frame.to_bytes() {
-                                Ok(binary) => binary,
-                                Err(_) => {
-                                    let error_frame = ErrorFrame::new(ErrorFrameBody {
-                                        error: "SerializationError".to_string(),
-                                        message: Some("Failed to serialize event to message frame.".to_string()),
-                                    });
-                                    yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
-                                    return;
-                                }
-                            };
-                            yield Message::Binary(binary);
-                        },
-                        SeqEvt::TypedHandleEvt(handle) => {
-                            let TypedHandleEvt { r#type, seq, time, evt } = handle;
-                            let HandleEvt { did, handle } = evt;
-                            let subscribe_handle_evt = SubscribeReposHandle {
-                                did,
-                                handle,
-                                seq,
-                                time: from_str_to_utc(&time),
-                            };
-                            let message_frame = MessageFrame::new(subscribe_handle_evt, Some(MessageFrameOpts { r#type: Some(format!("#{0}",r#type)) }));
-                            let binary = match message_frame.to_bytes() {
-                                Ok(binary) => binary,
-                                Err(_) => {
-                                    let error_frame = ErrorFrame::new(ErrorFrameBody {
-                                        error: "SerializationError".to_string(),
-                                        message: Some("Failed to serialize event to message frame.".to_string()),
-                                    });
-                                    yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
-                                    return;
-                                }
-                            };
-                            yield Message::Binary(binary);
-                        },
-                        SeqEvt::TypedIdentityEvt(identity) => {
-                            let TypedIdentityEvt { r#type, seq, time, evt } = identity;
-                            let IdentityEvt { did, handle } = evt;
-                            let subscribe_identity_evt = SubscribeReposIdentity {
-                                did,
-                                seq,
-                                handle,
-                                time: from_str_to_utc(&time),
-                            };
-                            let message_frame = MessageFrame::new(subscribe_identity_evt, Some(MessageFrameOpts { r#type: Some(format!("#{0}",r#type)) }));
-                            let binary = match message_frame.to_bytes() {
-                                Ok(binary) => binary,
-                                Err(_) => {
-                                    let error_frame = ErrorFrame::new(ErrorFrameBody {
-                                        error: "SerializationError".to_string(),
-                                        message: Some("Failed to serialize event to message frame.".to_string()),
-                                    });
-                                    yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
-                                    return;
-                                }
-                            };
-                            yield Message::Binary(binary);
-                        },
-                        SeqEvt::TypedAccountEvt(account) => {
-                            let TypedAccountEvt { r#type, seq, time, evt } = account;
-                            let AccountEvt { did, active, status } = evt;
-                            let subscribe_account_evt = SubscribeReposAccount {
-                                did,
-                                seq,
-                                status,
-                                active,
-                                time: from_str_to_utc(&time),
-                            };
-                            let message_frame = MessageFrame::new(subscribe_account_evt, Some(MessageFrameOpts { r#type: Some(format!("#{0}",r#type)) }));
-                            let binary = match message_frame.to_bytes() {
-                                Ok(binary) => binary,
-                                Err(_) => {
-                                    let error_frame = ErrorFrame::new(ErrorFrameBody {
-                                        error: "SerializationError".to_string(),
-                                        message: Some("Failed to serialize event to message frame.".to_string()),
-                                    });
-                                    yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
-                                    return;
-                                }
-                            };
-                            yield Message::Binary(binary);
-                        },
-                        SeqEvt::TypedTombstoneEvt(tombstone) => {
-                            let TypedTombstoneEvt { r#type, seq, time, evt } = tombstone;
-                            let TombstoneEvt { did } = evt;
-                            let subscribe_tombstone_evt = SubscribeReposTombstone {
-                                did,
-                                seq,
-                                time: from_str_to_utc(&time),
-                            };
-                            let message_frame = MessageFrame::new(subscribe_tombstone_evt, Some(MessageFrameOpts { r#type: Some(format!("#{0}",r#type)) }));
-                            let binary = match message_frame.to_bytes() {
-                                Ok(binary) => binary,
-                                Err(_) => {
-                                    let error_frame = ErrorFrame::new(ErrorFrameBody {
-                                        error: "SerializationError".to_string(),
-                                        message: Some("Failed to serialize event to message frame.".to_string()),
-                                    });
-                                    yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
-                                    return;
-                                }
-                            };
-                            yield Message::Binary(binary);
+                    let binary = match span.in_scope(|| event_frame(evt)) {
+                        Ok(binary) => binary,
+                        Err(error) => {
+                            tracing::error!("@LOG: ERROR: failed to serialize firehose event; err: {error}");
+                            let error_frame = ErrorFrame::new(ErrorFrameBody {
+                                error: "SerializationError".to_string(),
+                                message: Some("Failed to serialize event to message frame.".to_string()),
+                            });
+                            yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                            return;
                         }
-                    }
+                    };
+                    FIREHOSE_EVENTS_SENT.inc();
+                    yield Message::Binary(binary);
                 }
                message = ws.next() => {
                     match message {
@@ -0,0 +1,32 @@
+use crate::apis::ApiError;
+use crate::apis::firefly::models::RequestStatus;
+use crate::auth_verifier::AccessStandard;
+use crate::wallet::transfer_request;
+
+#[tracing::instrument(skip_all)]
+#[rocket::post("/request/<id>/cancel")]
+pub async fn cancel_transfer_request(id: String, auth: AccessStandard) -> Result<(), ApiError> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let request = transfer_request::get_transfer_request(&id)
+        .await?
+        .ok_or(ApiError::RecordNotFound)?;
+    if request.did != did {
+        return Err(ApiError::Forbidden(
+            "Only the requester can cancel a transfer request".to_string(),
+        ));
+    }
+
+    let cancelled = transfer_request::transition_transfer_request(
+        &id,
+        RequestStatus::ONGOING,
+        RequestStatus::CANCELLED,
+        None,
+    )
+    .await?;
+    if !cancelled {
+        return Err(ApiError::InvalidRequest(
+            "Transfer request is no longer ongoing".to_string(),
+        ));
+    }
+    Ok(())
+}
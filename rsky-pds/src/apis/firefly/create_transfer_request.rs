@@ -4,6 +4,7 @@ use rocket::serde::json::Json;
 use super::models::Stringified;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandard;
+use crate::wallet::transfer_request::{self, CreateTransferRequestOpts};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CreateTransferRequest {
@@ -22,10 +23,22 @@ pub async fn create_transfer_request(
     body: Json<CreateTransferRequest>,
     auth: AccessStandard,
 ) -> Result<(Status, Json<CreateTransferResponce>), ApiError> {
-    Ok((
-        Status::Created,
-        Json(CreateTransferResponce {
-            id: Default::default(),
-        }),
-    ))
+    let CreateTransferRequest {
+        amount: Stringified(amount),
+        description,
+    } = body.into_inner();
+    if amount == 0 {
+        return Err(ApiError::InvalidRequest(
+            "Amount must be greater than zero".to_string(),
+        ));
+    }
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let id = transfer_request::create_transfer_request(CreateTransferRequestOpts {
+        did,
+        amount,
+        description,
+    })
+    .await?;
+
+    Ok((Status::Created, Json(CreateTransferResponce { id })))
 }
@@ -1,8 +1,152 @@
+use std::time::Instant;
+
+use firefly_api::client::DeployRejected;
+use firefly_api::models::TransferResult;
+use firefly_api::providers::FireflyProvider;
+use rocket::State;
+use rocket::response::status::Accepted;
+
 use crate::apis::ApiError;
+use crate::apis::firefly::models::RequestStatus;
 use crate::auth_verifier::AccessStandard;
+use crate::metrics::observe_deploy;
+use crate::wallet::{WalletService, transfer_request};
 
 #[tracing::instrument(skip_all)]
-#[rocket::get("/request/<id>/fulfill")]
-pub async fn fulfill_transfer_request(id: String, auth: AccessStandard) -> Result<(), ApiError> {
+#[rocket::post("/request/<id>/fulfill")]
+pub async fn fulfill_transfer_request(
+    id: String,
+    auth: AccessStandard,
+    provider: &State<FireflyProvider>,
+    wallet_service: &State<WalletService>,
+) -> Result<Accepted<()>, ApiError> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let request = transfer_request::get_transfer_request(&id)
+        .await?
+        .ok_or(ApiError::RecordNotFound)?;
+    if request.did == did {
+        return Err(ApiError::InvalidRequest(
+            "Cannot fulfill your own transfer request".to_string(),
+        ));
+    }
+    let amount = request
+        .amount
+        .parse::<u128>()
+        .map_err(|_| ApiError::RuntimeError)?;
+    let payer = wallet_service.get_or_create_wallet(&did).await?;
+    let requester = wallet_service.get_or_create_wallet(&request.did).await?;
+
+    // Claim the request before paying so that concurrent fulfills can't pay it twice
+    let claimed = transfer_request::transition_transfer_request(
+        &id,
+        RequestStatus::ONGOING,
+        RequestStatus::PENDING,
+        Some(did.clone()),
+    )
+    .await?;
+    if !claimed {
+        return Err(ApiError::InvalidRequest(
+            "Transfer request is no longer ongoing".to_string(),
+        ));
+    }
+
+    let client = provider.firefly(&payer.address, &payer.key);
+    let started = Instant::now();
+    let deploy = client
+        .sign_transfer(&requester.address, amount, Some(request.description))
+        .await
+        // Only the recorded deploy can settle the request, so it's recorded before it's sent
+        .and_then(|deploy| {
+            transfer_request::record_transfer_deploy(&id, &deploy.handle.deploy_id)?;
+            Ok(deploy)
+        });
+    let deploy = match deploy {
+        Ok(deploy) => deploy,
+        Err(error) => return Err(reopen_unsent(&id, started, error).await),
+    };
+    let handle = match client.send_transfer(deploy.clone()).await {
+        Ok(handle) => handle,
+        Err(error) if error.downcast_ref::<DeployRejected>().is_some() => {
+            return Err(reopen_unsent(&id, started, error).await);
+        }
+        // The node may have received the deploy, it's tracked like a sent one. If it never
+        // arrived it expires and is resubmitted.
+        Err(error) => {
+            tracing::warn!("@LOG: WARN: transfer for request {id} may not be sent: {error:#}");
+            deploy.handle
+        }
+    };
+
+    // The request reads as pending until the transfer is in a block, clients poll it
+    let provider = provider.inner().clone();
+    tokio::spawn(async move {
+        let client = provider.firefly(&payer.address, &payer.key);
+        let result = client
+            .wait_for_transfer_with(handle, |resubmitted| {
+                record_deploy(&id, &resubmitted.deploy_id)
+            })
+            .await;
+        observe_deploy(
+            "fulfill_transfer_request",
+            started,
+            matches!(result, Ok(ref result) if result.succeeded),
+        );
+        if let Err(error) = settle_transfer_request(&id, did, result).await {
+            tracing::error!("@LOG: ERROR: failed to settle transfer request {id}: {error:#}");
+        }
+    });
+    Ok(Accepted(()))
+}
+
+fn record_deploy(id: &String, deploy_id: &str) {
+    if let Err(error) = transfer_request::record_transfer_deploy(id, deploy_id) {
+        tracing::error!(
+            "@LOG: ERROR: failed to record deploy {deploy_id} of transfer request {id}: {error:#}"
+        );
+    }
+}
+
+/// Hands a request back to the requester when its transfer was never sent
+async fn reopen_unsent(id: &String, started: Instant, error: anyhow::Error) -> ApiError {
+    observe_deploy("fulfill_transfer_request", started, false);
+    tracing::error!("@LOG: ERROR: transfer for request {id} not sent: {error:#}");
+    if let Err(error) = transfer_request::reopen_transfer_request(id).await {
+        tracing::error!("@LOG: ERROR: failed to reopen transfer request {id}: {error:#}");
+    }
+    ApiError::RuntimeError
+}
+
+async fn settle_transfer_request(
+    id: &String,
+    payer: String,
+    result: anyhow::Result<TransferResult>,
+) -> anyhow::Result<()> {
+    match result {
+        Ok(result) if result.succeeded => {
+            transfer_request::transition_transfer_request(
+                id,
+                RequestStatus::PENDING,
+                RequestStatus::DONE,
+                Some(payer),
+            )
+            .await?;
+        }
+        Ok(result) => {
+            // The deploy errored or the vault refused the transfer, hand the request back to the
+            // requester
+            tracing::warn!(
+                "@LOG: WARN: transfer for request {id} failed: {}",
+                result
+                    .system_deploy_error
+                    .unwrap_or_else(|| "Transfer refused".to_string())
+            );
+            transfer_request::reopen_transfer_request(id).await?;
+        }
+        // A timed out deploy can still land, so the request stays pending until the wallet
+        // indexer sees its deploy or it's too old to land
+        Err(error) => {
+            tracing::warn!("@LOG: WARN: transfer for request {id} not confirmed: {error:#}");
+        }
+    }
     Ok(())
 }
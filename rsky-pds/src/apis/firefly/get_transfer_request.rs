@@ -1,18 +1,37 @@
 use rocket::serde::json::Json;
+use rsky_common::time::from_str_to_utc;
+use rsky_syntax::handle::INVALID_HANDLE;
 
-use super::models::TransferRequest;
+use super::models::{RequestStatus, TransferRequest};
+use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandard;
+use crate::wallet::transfer_request;
 
 #[tracing::instrument(skip_all)]
 #[rocket::get("/request/<id>")]
 pub async fn get_transfer_request(
     id: String,
-    auth: AccessStandard,
+    _auth: AccessStandard,
 ) -> Result<Json<TransferRequest>, ApiError> {
+    let request = transfer_request::get_transfer_request(&id)
+        .await?
+        .ok_or(ApiError::RecordNotFound)?;
+    let user_handle = match AccountManager::get_account(&request.did, None).await? {
+        Some(account) => account.handle.unwrap_or(INVALID_HANDLE.to_string()),
+        None => INVALID_HANDLE.to_string(),
+    };
+
     Ok(Json(TransferRequest {
-        amount: 0.into(),
-        description: Default::default(),
-        user_handle: Default::default(),
+        id: request.id,
+        date: from_str_to_utc(&request.created_at).timestamp() as u64,
+        amount: request
+            .amount
+            .parse::<u128>()
+            .map_err(|_| ApiError::RuntimeError)?
+            .into(),
+        description: request.description,
+        user_handle,
+        status: request.status.parse::<RequestStatus>()?,
     }))
 }
@@ -1,65 +1,87 @@
 use firefly_api::providers::FireflyProvider;
 use rocket::serde::json::Json;
 use rocket::State;
+use rsky_common::time::from_str_to_utc;
 
-use super::models::{Direction, Transfer, WalletStateAndHistory};
+use super::models::{Direction, Request, RequestStatus, Transfer, WalletStateAndHistory};
+use super::transactions::query_millis;
 use crate::apis::ApiError;
+use crate::auth_verifier::AccessStandard;
+use crate::wallet::history::{ListWalletTransactionsOpts, TransactionsCursor};
+use crate::wallet::{WalletService, history, transfer_request};
 
 #[tracing::instrument(skip_all)]
-#[rocket::get("/state")]
+#[rocket::get("/state?<limit>&<cursor>&<since>&<until>")]
 pub async fn get_wallet_state_and_history(
-    // auth: AccessStandard,  // remove comment to turn on auth
-    // TODO: auth should be turned on when we'll solve storage parameters per user
+    limit: Option<i64>,
+    cursor: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+    auth: AccessStandard,
     provider: &State<FireflyProvider>,
+    wallet_service: &State<WalletService>,
 ) -> Result<Json<WalletStateAndHistory>, ApiError> {
-    let client = provider.firefly();
+    let cursor = cursor
+        .map(|cursor| cursor.parse::<TransactionsCursor>())
+        .transpose()
+        .map_err(|_| ApiError::InvalidRequest("Malformed cursor".to_string()))?;
+    let since = query_millis("since", since)?;
+    let until = query_millis("until", until)?;
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let wallet = wallet_service.get_or_create_wallet(&did).await?;
+    let client = provider.firefly(&wallet.address, &wallet.key);
     let wallet_address = client.get_wallet_address();
     let balance = client.get_balance().await.unwrap_or(0);
-    let transactions = client.get_transactions().await?;
-
-    let mut transfers: Vec<Transfer> = vec![];
-    for transaction in transactions {
-        if transaction.name != "SET_TRANSFER" {
-            continue;
-        }
-        let from_address = &transaction.arguments[0];
-        let to_address = &transaction.arguments[1];
-        if from_address != &wallet_address && to_address != &wallet_address {
-            continue;
-        }
-        let is_incoming = to_address == &wallet_address;
-        let direction = if is_incoming {
-            Direction::INCOMING
-        } else {
-            Direction::OUTGOING
-        };
-        let to_address = if is_incoming {
-            from_address.to_string()
-        } else {
-            to_address.to_string()
-        };
-
-        let amount = transaction.arguments[2].parse::<u128>().unwrap_or(0);
-        let date = transaction.date_time.timestamp() as u64;
+    let (transactions, cursor) = history::list_wallet_transactions(ListWalletTransactionsOpts {
+        address: wallet_address.to_string(),
+        limit: history::page_limit(limit),
+        cursor,
+        since,
+        until,
+    })
+    .await?;
 
-        let transfer = Transfer {
-            id: transaction.id.clone(),
-            direction,
-            date,
-            amount: amount.into(),
-            to_address,
+    let transfers = transactions
+        .into_iter()
+        .map(|transaction| Transfer {
+            direction: if transaction.direction == history::DIRECTION_INCOMING {
+                Direction::INCOMING
+            } else {
+                Direction::OUTGOING
+            },
+            date: (transaction.created_at / 1000) as u64,
+            amount: transaction.amount.parse::<u128>().unwrap_or(0).into(),
+            to_address: transaction.counterparty,
             cost: transaction.cost,
-        };
-        transfers.push(transfer);
-    }
+            id: transaction.id,
+        })
+        .collect();
+
+    let requests = transfer_request::list_transfer_requests(&did)
+        .await?
+        .into_iter()
+        .map(|request| {
+            Ok(Request {
+                date: from_str_to_utc(&request.created_at).timestamp() as u64,
+                amount: request
+                    .amount
+                    .parse::<u128>()
+                    .map_err(|_| ApiError::RuntimeError)?
+                    .into(),
+                status: request.status.parse::<RequestStatus>()?,
+                id: request.id,
+            })
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
 
     let state = WalletStateAndHistory {
         address: wallet_address.to_string(),
         balance: balance.into(),
         transfers,
-        requests: vec![],
+        requests,
         exchanges: vec![],
         boosts: vec![],
+        cursor,
     };
 
     Ok(Json(state))
@@ -0,0 +1,52 @@
+use firefly_api::client::helpers::rev_address_from_private_key;
+use rocket::State;
+use rocket::serde::json::Json;
+
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessStandard;
+use crate::wallet::{WalletExists, WalletService};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportWalletRequest {
+    private_key: String,
+    /// Replace a wallet the account already has, its key is kept but no longer used
+    #[serde(default)]
+    replace: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportWalletResponse {
+    address: String,
+}
+
+#[tracing::instrument(skip_all)]
+#[rocket::post("/import", format = "json", data = "<body>")]
+pub async fn import_wallet(
+    body: Json<ImportWalletRequest>,
+    auth: AccessStandard,
+    wallet_service: &State<WalletService>,
+) -> Result<Json<ImportWalletResponse>, ApiError> {
+    let ImportWalletRequest {
+        private_key,
+        replace,
+    } = body.into_inner();
+    if rev_address_from_private_key(&private_key).is_err() {
+        return Err(ApiError::InvalidRequest("Invalid wallet key".to_string()));
+    }
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let wallet = match wallet_service
+        .import_wallet(&did, private_key, replace)
+        .await
+    {
+        Ok(wallet) => wallet,
+        Err(error) if error.downcast_ref::<WalletExists>().is_some() => {
+            return Err(ApiError::Conflict(
+                "Account already has a wallet, set replace to import over it".to_string(),
+            ));
+        }
+        Err(error) => return Err(error.into()),
+    };
+    Ok(Json(ImportWalletResponse {
+        address: wallet.address,
+    }))
+}
@@ -1,7 +1,9 @@
+pub mod cancel_transfer_request;
 pub mod create_transfer_request;
 pub mod fulfill_transfer_request;
 pub mod get_transfer_request;
 pub mod get_wallet_state_and_history;
+pub mod import_wallet;
 pub mod models;
 pub mod providers;
 pub mod transactions;
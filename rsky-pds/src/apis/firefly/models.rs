@@ -1,5 +1,7 @@
 use std::str::FromStr;
 
+use anyhow::{Result, bail};
+use firefly_api::transaction::Transaction;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Debug, Clone, Copy)]
@@ -37,14 +39,41 @@ impl<T> From<T> for Stringified<T> {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum RequestStatus {
     DONE,
     ONGOING,
+    /// Claimed by a payer whose transfer isn't confirmed yet
+    PENDING,
     CANCELLED,
 }
 
+impl RequestStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestStatus::DONE => "done",
+            RequestStatus::ONGOING => "ongoing",
+            RequestStatus::PENDING => "pending",
+            RequestStatus::CANCELLED => "cancelled",
+        }
+    }
+}
+
+impl FromStr for RequestStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "done" => Ok(RequestStatus::DONE),
+            "ongoing" => Ok(RequestStatus::ONGOING),
+            "pending" => Ok(RequestStatus::PENDING),
+            "cancelled" => Ok(RequestStatus::CANCELLED),
+            _ => bail!("Unable to parse as RequestStatus: `{s:?}`"),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Request {
     pub id: String,
@@ -91,11 +120,46 @@ pub struct WalletStateAndHistory {
     pub exchanges: Vec<Exchange>,
     pub boosts: Vec<Boost>,
     pub transfers: Vec<Transfer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Page of `GET /api/wallet/transactions`, `cursor` is left out on the last page
+#[derive(Debug, Serialize)]
+pub struct TransactionsPage {
+    pub transactions: Vec<Transaction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TransferRequest {
+    pub id: String,
+    pub date: u64,
     pub amount: Stringified<u128>,
     pub description: String,
     pub user_handle: String,
+    pub status: RequestStatus,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_status_round_trips() {
+        for status in [
+            RequestStatus::DONE,
+            RequestStatus::ONGOING,
+            RequestStatus::PENDING,
+            RequestStatus::CANCELLED,
+        ] {
+            assert_eq!(status.as_str().parse::<RequestStatus>().unwrap(), status);
+            assert_eq!(
+                serde_json::to_string(&status).unwrap(),
+                format!("\"{}\"", status.as_str())
+            );
+        }
+        assert!("paid".parse::<RequestStatus>().is_err());
+    }
 }
@@ -1,4 +1,5 @@
 use anyhow::Context;
+use firefly_api::client::helpers::{DEFAULT_PHLO_LIMIT, DeployOptions, PhloLimit};
 use firefly_api::providers::FireflyProvider;
 use rsky_common::env::env_str;
 
@@ -12,18 +13,48 @@ pub fn get_firefly_provider() -> anyhow::Result<FireflyProvider> {
     let propose_service_url = env_str("PROPOSE_SERVICE_URL")
         .context("Failed to get read node url, set in .env PROPOSE_SERVICE_URL")?;
 
-    let default_wallet_key = env_str("DEFAULT_WALLET_KEY")
-        .context("Failed to get read node url, set in .env DEFAULT_WALLET_KEY")?;
-    let default_wallet_address = env_str("DEFAULT_WALLET_ADDRESS")
-        .context("Failed to get read node url, set in .env DEFAULT_WALLET_ADDRESS")?;
-
     let provider = FireflyProvider::new(
         write_node_url,
         read_node_url,
         deploy_service_url,
         propose_service_url,
-        default_wallet_address,
-        default_wallet_key,
-    );
+    )
+    .with_deploy_options(get_deploy_options()?);
     Ok(provider)
 }
+
+/// Reads deploy parameters from `FIREFLY_PHLO_PRICE`, `FIREFLY_PHLO_LIMIT` and `FIREFLY_SHARD_ID`.
+///
+/// When `FIREFLY_PHLO_ESTIMATE_MARGIN_PERCENT` is set the phlo limit of each deploy is sized from
+/// its cost on the read node, and `FIREFLY_PHLO_LIMIT` becomes the upper bound. Wallet transfers
+/// always use `FIREFLY_PHLO_LIMIT`, see [`PhloLimit::Estimate`].
+fn get_deploy_options() -> anyhow::Result<DeployOptions> {
+    let defaults = DeployOptions::default();
+    let phlo_price = match env_str("FIREFLY_PHLO_PRICE") {
+        Some(phlo_price) => phlo_price
+            .parse()
+            .context("FIREFLY_PHLO_PRICE must be an integer")?,
+        None => defaults.phlo_price,
+    };
+    let phlo_limit = match env_str("FIREFLY_PHLO_LIMIT") {
+        Some(phlo_limit) => phlo_limit
+            .parse()
+            .context("FIREFLY_PHLO_LIMIT must be an integer")?,
+        None => DEFAULT_PHLO_LIMIT,
+    };
+    let phlo_limit = match env_str("FIREFLY_PHLO_ESTIMATE_MARGIN_PERCENT") {
+        Some(margin_percent) => PhloLimit::Estimate {
+            margin_percent: margin_percent
+                .parse()
+                .context("FIREFLY_PHLO_ESTIMATE_MARGIN_PERCENT must be an integer")?,
+            max: phlo_limit,
+        },
+        None => PhloLimit::Fixed(phlo_limit),
+    };
+    Ok(DeployOptions {
+        phlo_price,
+        phlo_limit,
+        shard_id: env_str("FIREFLY_SHARD_ID").unwrap_or(defaults.shard_id),
+        ..defaults
+    })
+}
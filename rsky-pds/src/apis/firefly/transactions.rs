@@ -1,16 +1,94 @@
-use firefly_api::providers::FireflyProvider;
 use firefly_api::transaction::Transaction;
 use rocket::State;
 use rocket::serde::json::Json;
 
+use super::models::TransactionsPage;
 use crate::apis::ApiError;
+use crate::auth_verifier::AccessStandard;
+use crate::wallet::WalletService;
+use crate::wallet::history::{self, ListWalletTransactionsOpts, TransactionsCursor};
 
-#[rocket::get("/transactions")]
+/// `since` and `until` are unix seconds, the index is in milliseconds
+pub fn query_millis(name: &str, secs: Option<i64>) -> Result<Option<i64>, ApiError> {
+    secs.map(|secs| {
+        secs.checked_mul(1000)
+            .ok_or_else(|| ApiError::InvalidRequest(format!("`{name}` is out of range")))
+    })
+    .transpose()
+}
+
+#[rocket::get("/transactions?<limit>&<cursor>&<since>&<until>")]
 pub async fn get_transactions(
-    provider: &State<FireflyProvider>,
-) -> Result<Json<Vec<Transaction>>, ApiError> {
-    let client = provider.firefly();
-    let transactions = client.get_transactions().await?;
+    limit: Option<i64>,
+    cursor: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+    auth: AccessStandard,
+    wallet_service: &State<WalletService>,
+) -> Result<Json<TransactionsPage>, ApiError> {
+    let cursor = cursor
+        .map(|cursor| cursor.parse::<TransactionsCursor>())
+        .transpose()
+        .map_err(|_| ApiError::InvalidRequest("Malformed cursor".to_string()))?;
+    let since = query_millis("since", since)?;
+    let until = query_millis("until", until)?;
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let wallet = wallet_service.get_or_create_wallet(&did).await?;
+    let (transactions, cursor) = history::list_wallet_transactions(ListWalletTransactionsOpts {
+        address: wallet.address.clone(),
+        limit: history::page_limit(limit),
+        cursor,
+        since,
+        until,
+    })
+    .await?;
+
+    let transactions = transactions
+        .into_iter()
+        .map(|transaction| {
+            let (from_address, to_address) = if transaction.direction == history::DIRECTION_INCOMING
+            {
+                (transaction.counterparty, transaction.address)
+            } else {
+                (transaction.address, transaction.counterparty)
+            };
+            Transaction {
+                id: transaction.id,
+                date_time: chrono::DateTime::from_timestamp_millis(transaction.created_at)
+                    .unwrap_or_default(),
+                name: "SET_TRANSFER".to_string(),
+                arguments: vec![
+                    from_address,
+                    to_address,
+                    transaction.amount,
+                    transaction.description,
+                ],
+                cost: transaction.cost,
+            }
+        })
+        .collect::<Vec<Transaction>>();
+
+    Ok(Json(TransactionsPage {
+        transactions,
+        cursor,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(Json(transactions))
+    #[test]
+    fn rejects_timestamps_out_of_range() {
+        assert_eq!(query_millis("since", None).unwrap(), None);
+        assert_eq!(
+            query_millis("since", Some(1_700_000_000)).unwrap(),
+            Some(1_700_000_000_000)
+        );
+        assert!(matches!(
+            query_millis("until", Some(i64::MAX / 10)),
+            Err(ApiError::InvalidRequest(_))
+        ));
+        assert!(query_millis("since", Some(i64::MIN)).is_err());
+    }
 }
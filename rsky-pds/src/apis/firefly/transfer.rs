@@ -1,10 +1,17 @@
+use std::time::Instant;
+
 use firefly_api::client::helpers::verify_rev_addr;
+use firefly_api::client::tracker::DeployStatus;
 use firefly_api::providers::FireflyProvider;
-use rocket::serde::json::Json;
 use rocket::State;
+use rocket::response::status::Accepted;
+use rocket::serde::json::Json;
 
 use super::models::Stringified;
 use crate::apis::ApiError;
+use crate::auth_verifier::AccessStandard;
+use crate::metrics::observe_deploy;
+use crate::wallet::WalletService;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct TransferRequest {
@@ -13,18 +20,76 @@ pub struct TransferRequest {
     description: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferStatus {
+    /// Waiting to be added to a block
+    Pending,
+    /// In a block that can still be orphaned
+    Included,
+    Finalized,
+    /// Added to a block but failed on chain
+    Failed,
+    /// Never added to a block, it can be submitted again
+    Expired,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct TransferResponse {
     cost: String,
 }
 
+/// Transfers submitted to `POST /transfer/submit` aren't waited for, poll
+/// `GET /transfer/<deploy_id>` with both fields for the outcome
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferStatusResponse {
+    deploy_id: String,
+    valid_after_block_number: i64,
+    status: TransferStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl TransferStatusResponse {
+    fn new(deploy_id: String, valid_after_block_number: i64, status: DeployStatus) -> Self {
+        let (status, block) = match status {
+            DeployStatus::Pending => (TransferStatus::Pending, None),
+            DeployStatus::Expired => (TransferStatus::Expired, None),
+            DeployStatus::Included(block) if block.errored => (TransferStatus::Failed, Some(block)),
+            DeployStatus::Finalized(block) if block.errored => {
+                (TransferStatus::Failed, Some(block))
+            }
+            DeployStatus::Included(block) => (TransferStatus::Included, Some(block)),
+            DeployStatus::Finalized(block) => (TransferStatus::Finalized, Some(block)),
+        };
+        let error = match (status, &block) {
+            (TransferStatus::Failed, Some(block)) => Some(
+                block
+                    .system_deploy_error
+                    .clone()
+                    .unwrap_or_else(|| "Unknown error".to_string()),
+            ),
+            _ => None,
+        };
+        Self {
+            deploy_id,
+            valid_after_block_number,
+            status,
+            cost: block.map(|block| block.cost.to_string()),
+            error,
+        }
+    }
+}
+
 #[tracing::instrument(skip_all)]
 #[rocket::post("/transfer", format = "json", data = "<body>")]
 pub async fn transfer(
     body: Json<TransferRequest>,
+    auth: AccessStandard,
     provider: &State<FireflyProvider>,
-    // auth: AccessStandard  // remove comment to turn on auth
-    // TODO: auth should be turned on when we'll solve storage parameters per user
+    wallet_service: &State<WalletService>,
 ) -> Result<Json<TransferResponse>, ApiError> {
     let TransferRequest {
         amount: Stringified(amount),
@@ -38,18 +103,138 @@ pub async fn transfer(
             to_address
         )));
     }
-    let client = provider.firefly();
-    let response_block = client
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let wallet = wallet_service.get_or_create_wallet(&did).await?;
+    let client = provider.firefly(&wallet.address, &wallet.key);
+    let started = Instant::now();
+    let result = client
         .transfer_request(&to_address, amount, description)
-        .await
-        .map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
-    let system_deploy_error = response_block.system_deploy_error.map(|s| s.to_string());
-    if response_block.errored {
+        .await;
+    observe_deploy(
+        "transfer",
+        started,
+        matches!(result, Ok(ref result) if result.succeeded),
+    );
+    let result = result.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    if !result.succeeded {
         return Err(ApiError::InvalidRequest(
-            system_deploy_error.unwrap_or_else(|| "Unknown error".to_string()),
+            result
+                .system_deploy_error
+                .unwrap_or_else(|| "Transfer refused".to_string()),
         ));
     }
     Ok(Json(TransferResponse {
-        cost: response_block.cost.to_string(),
+        cost: result.cost.to_string(),
     }))
 }
+
+#[tracing::instrument(skip_all)]
+#[rocket::post("/transfer/submit", format = "json", data = "<body>")]
+pub async fn submit_transfer(
+    body: Json<TransferRequest>,
+    auth: AccessStandard,
+    provider: &State<FireflyProvider>,
+    wallet_service: &State<WalletService>,
+) -> Result<Accepted<Json<TransferStatusResponse>>, ApiError> {
+    let TransferRequest {
+        amount: Stringified(amount),
+        to_address,
+        description,
+        ..
+    } = body.into_inner();
+    if !verify_rev_addr(to_address.as_str()) {
+        return Err(ApiError::InvalidRequest(format!(
+            "Invalid address: {}",
+            to_address
+        )));
+    }
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let wallet = wallet_service.get_or_create_wallet(&did).await?;
+    let client = provider.firefly(&wallet.address, &wallet.key);
+    let started = Instant::now();
+    let result = client
+        .submit_transfer(&to_address, amount, description)
+        .await;
+    // the outcome is only known once the client polls, so this times the submission alone
+    observe_deploy("transfer_submit", started, result.is_ok());
+    let handle = result.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    Ok(Accepted(Json(TransferStatusResponse::new(
+        handle.deploy_id,
+        handle.valid_after_block_number,
+        DeployStatus::Pending,
+    ))))
+}
+
+#[tracing::instrument(skip_all)]
+#[rocket::get("/transfer/<deploy_id>?<valid_after_block_number>")]
+pub async fn get_transfer(
+    deploy_id: String,
+    valid_after_block_number: i64,
+    auth: AccessStandard,
+    provider: &State<FireflyProvider>,
+    wallet_service: &State<WalletService>,
+) -> Result<Json<TransferStatusResponse>, ApiError> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let wallet = wallet_service.get_or_create_wallet(&did).await?;
+    let client = provider.firefly(&wallet.address, &wallet.key);
+    match client
+        .transfer_status(&deploy_id, valid_after_block_number)
+        .await
+    {
+        Ok(status) => Ok(Json(TransferStatusResponse::new(
+            deploy_id,
+            valid_after_block_number,
+            status,
+        ))),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: failed to get status of transfer {deploy_id}: {error:#}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use firefly_api::client::tracker::DeployBlock;
+
+    use super::*;
+
+    fn block(errored: bool) -> DeployBlock {
+        DeployBlock {
+            block_hash: "hash".to_string(),
+            block_number: 12,
+            cost: 250,
+            errored,
+            system_deploy_error: errored.then(|| "Insufficient funds".to_string()),
+        }
+    }
+
+    #[test]
+    fn reports_the_outcome_of_a_transfer() {
+        let response = |status| TransferStatusResponse::new("deploy".to_string(), 10, status);
+
+        let pending = response(DeployStatus::Pending);
+        assert_eq!(pending.status, TransferStatus::Pending);
+        assert_eq!(pending.cost, None);
+
+        let included = response(DeployStatus::Included(block(false)));
+        assert_eq!(included.status, TransferStatus::Included);
+        assert_eq!(included.cost.as_deref(), Some("250"));
+        assert_eq!(included.error, None);
+
+        let failed = response(DeployStatus::Finalized(block(true)));
+        assert_eq!(failed.status, TransferStatus::Failed);
+        assert_eq!(failed.error.as_deref(), Some("Insufficient funds"));
+
+        let expired = response(DeployStatus::Expired);
+        assert_eq!(expired.status, TransferStatus::Expired);
+        assert_eq!(
+            serde_json::to_value(&expired).unwrap(),
+            serde_json::json!({
+                "deploy_id": "deploy",
+                "valid_after_block_number": 10,
+                "status": "expired",
+            })
+        );
+    }
+}
@@ -110,6 +110,9 @@ pub enum ApiError {
     BlobNotFound,
     BadRequest(String, String),
     AuthRequiredError(String),
+    Forbidden(String),
+    Conflict(String),
+    RateLimitExceeded,
 }
 
 #[derive(Serialize)]
@@ -418,6 +421,36 @@ impl<'r, 'o: 'r> ::rocket::response::Responder<'r, 'o> for ApiError {
                 res.set_status(Status { code: 401u16 });
                 Ok(res)
             }
+            ApiError::Forbidden(message) => {
+                let body = Json(ErrorBody {
+                    error: "Forbidden".to_string(),
+                    message,
+                });
+                let mut res =
+                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
+                res.set_header(ContentType(::rocket::http::MediaType::const_new(
+                    "application",
+                    "json",
+                    &[],
+                )));
+                res.set_status(Status { code: 403u16 });
+                Ok(res)
+            }
+            ApiError::Conflict(message) => {
+                let body = Json(ErrorBody {
+                    error: "Conflict".to_string(),
+                    message,
+                });
+                let mut res =
+                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
+                res.set_header(ContentType(::rocket::http::MediaType::const_new(
+                    "application",
+                    "json",
+                    &[],
+                )));
+                res.set_status(Status { code: 409u16 });
+                Ok(res)
+            }
             ApiError::RecordNotFound => {
                 let body = Json(ErrorBody {
                     error: "RecordNotFound".to_string(),
@@ -433,6 +466,21 @@ impl<'r, 'o: 'r> ::rocket::response::Responder<'r, 'o> for ApiError {
                 res.set_status(Status { code: 404u16 });
                 Ok(res)
             }
+            ApiError::RateLimitExceeded => {
+                let body = Json(ErrorBody {
+                    error: "RateLimitExceeded".to_string(),
+                    message: "Rate Limit Exceeded".to_string(),
+                });
+                let mut res =
+                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
+                res.set_header(ContentType(rocket::http::MediaType::const_new(
+                    "application",
+                    "json",
+                    &[],
+                )));
+                res.set_status(Status { code: 429u16 });
+                Ok(res)
+            }
         }
     }
 }
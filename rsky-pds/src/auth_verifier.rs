@@ -2,6 +2,10 @@ use crate::account_manager::helpers::account::{ActorAccount, AvailabilityFlags};
 use crate::account_manager::helpers::auth::CustomClaimObj;
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
+use crate::config::ServerConfig;
+use crate::oauth::dpop::{verify_dpop_proof, VerifyDpopOpts};
+use crate::oauth::store::get_token;
+use crate::oauth::token::{to_auth_scope, verify_access_token};
 use crate::xrpc_server::auth::{verify_jwt as verify_service_jwt_server, ServiceJwtPayload};
 use crate::SharedIdResolver;
 use anyhow::{bail, Result};
@@ -673,7 +677,7 @@ impl<'r> FromRequest<'r> for OptionalAccessOrAdminToken {
     type Error = AuthError;
 
     async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        if is_bearer_token(req) {
+        if is_bearer_token(req) || is_dpop_token(req) {
             match AccessFull::from_request(req).await {
                 Outcome::Success(output) => Outcome::Success(OptionalAccessOrAdminToken {
                     access: Some(output.access),
@@ -779,7 +783,63 @@ pub async fn validate_bearer_token<'r>(
     }
 }
 
-// @TODO: Implement DPop/OAuth
+/// OAuth access tokens, only accepted alongside a proof from the DPoP key they were issued to
+pub async fn validate_dpop_access_token<'r>(
+    request: &'r Request<'_>,
+    scopes: Vec<AuthScope>,
+) -> Result<ValidatedBearer> {
+    let token = match dpop_token_from_req(request) {
+        Some(token) => token,
+        None => bail!("AuthMissing"),
+    };
+    let cfg = match request.guard::<&State<ServerConfig>>().await {
+        Outcome::Success(cfg) => cfg,
+        _ => bail!("Server config unavailable"),
+    };
+    let claims = verify_access_token(&token, &cfg.service.public_url, &cfg.service.did)?;
+    let htu = format!("{}{}", cfg.service.public_url, request.uri().path());
+    let proof = verify_dpop_proof(
+        request.headers().get_one("DPoP"),
+        VerifyDpopOpts {
+            htm: request.method().as_str(),
+            htu: &htu,
+            access_token: Some(&token),
+            require_nonce: false,
+        },
+    )
+    .await?;
+    if proof.jkt != claims.custom.cnf.jkt {
+        bail!("DPoP key mismatch")
+    }
+    // revoking or refreshing the session invalidates the access tokens issued for it
+    let token_id = claims.jwt_id.clone().unwrap_or_default();
+    if get_token(&token_id).await?.is_none() {
+        bail!("Token has been revoked")
+    }
+    let scope = to_auth_scope(&claims.custom.scope)?;
+    if !scopes.is_empty() && !scopes.contains(&scope) {
+        bail!("Bad token scope")
+    }
+    let did = match claims.subject {
+        Some(sub) if sub.starts_with("did:") => sub,
+        _ => bail!("Malformed token"),
+    };
+    Ok(ValidatedBearer {
+        did: did.clone(),
+        scope: scope.clone(),
+        token,
+        payload: JwtPayload {
+            scope,
+            sub: Some(did),
+            aud: claims.audiences.clone(),
+            exp: claims.expires_at,
+            iat: claims.issued_at,
+            jti: claims.jwt_id,
+        },
+        audience: Some(cfg.service.did.clone()),
+    })
+}
+
 pub async fn validate_access_token<'r>(
     request: &'r Request<'_>,
     scopes: Vec<AuthScope>,
@@ -795,7 +855,10 @@ pub async fn validate_access_token<'r>(
         token,
         audience,
         ..
-    } = validate_bearer_token(request, scopes, Some(options)).await?;
+    } = match is_dpop_token(request) {
+        true => validate_dpop_access_token(request, scopes).await?,
+        false => validate_bearer_token(request, scopes, Some(options)).await?,
+    };
     let ValidateAccessTokenOpts {
         check_takedown,
         check_deactivated,
@@ -911,6 +974,7 @@ pub fn is_user_or_admin(auth: AccessOutput, did: &String) -> bool {
 
 const BEARER: &str = "Bearer ";
 const BASIC: &str = "Basic ";
+const DPOP: &str = "DPoP ";
 
 pub fn is_bearer_token(request: &Request) -> bool {
     match request.headers().get_one("Authorization") {
@@ -919,6 +983,13 @@ pub fn is_bearer_token(request: &Request) -> bool {
     }
 }
 
+pub fn is_dpop_token(request: &Request) -> bool {
+    match request.headers().get_one("Authorization") {
+        None => false,
+        Some(auth_header) => auth_header.starts_with(DPOP),
+    }
+}
+
 pub fn is_basic_token(request: &Request) -> bool {
     match request.headers().get_one("Authorization") {
         None => false,
@@ -937,6 +1008,13 @@ pub fn bearer_token_from_req(request: &Request) -> Result<Option<String>> {
     }
 }
 
+pub fn dpop_token_from_req(request: &Request) -> Option<String> {
+    match request.headers().get_one("Authorization") {
+        Some(header) if header.starts_with(DPOP) => Some(header[DPOP.len()..].to_string()),
+        _ => None,
+    }
+}
+
 pub async fn verify_jwt(
     jwt: String,
     jwt_key: Keypair,
@@ -944,7 +1022,10 @@ pub async fn verify_jwt(
 ) -> Result<JwtPayload> {
     let key = ES256kKeyPair::from_bytes(jwt_key.secret_bytes().as_slice())?;
     let public_key = key.public_key();
-    let claims = public_key.verify_token::<CustomClaimObj>(&jwt, verify_options)?;
+    // OAuth access tokens share the signing key but are typed `at+jwt`
+    let mut options = verify_options.unwrap_or_default();
+    options.required_signature_type = Some("JWT".to_string());
+    let claims = public_key.verify_token::<CustomClaimObj>(&jwt, Some(options))?;
 
     Ok(JwtPayload {
         scope: AuthScope::from_str(&claims.custom.scope)?,
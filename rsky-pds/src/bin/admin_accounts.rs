@@ -0,0 +1,204 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use dotenvy::dotenv;
+use rsky_pds::admin::{
+    apply_bulk_action, search_accounts, store, AccountSearch, AccountStatusFilter, BulkAction,
+    BulkActionInput,
+};
+use rsky_pds::config::env_to_cfg;
+use rsky_pds::crawlers::Crawlers;
+use rsky_pds::sequencer::Sequencer;
+use rsky_pds::SharedSequencer;
+use tokio::sync::RwLock;
+
+#[derive(Debug, Parser)]
+struct Args {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Debug, clap::Args)]
+struct SearchArgs {
+    /// Handles starting with this prefix
+    #[arg(long)]
+    handle_prefix: Option<String>,
+
+    /// Emails containing this text, ignoring case
+    #[arg(long)]
+    email: Option<String>,
+
+    /// Accounts created at or after this RFC3339 timestamp
+    #[arg(long)]
+    created_after: Option<String>,
+
+    /// Accounts created before this RFC3339 timestamp
+    #[arg(long)]
+    created_before: Option<String>,
+
+    /// One of `active`, `takendown` or `deactivated`
+    #[arg(long)]
+    status: Option<String>,
+
+    /// Accounts that signed up with this invite code
+    #[arg(long)]
+    invite_code: Option<String>,
+
+    /// Accounts that signed up with an invite code of this did
+    #[arg(long)]
+    invited_by: Option<String>,
+
+    #[arg(long)]
+    limit: Option<i64>,
+
+    #[arg(long)]
+    cursor: Option<String>,
+}
+
+impl SearchArgs {
+    fn into_search(self) -> Result<AccountSearch> {
+        Ok(AccountSearch {
+            handle_prefix: self.handle_prefix,
+            email: self.email,
+            created_after: self.created_after,
+            created_before: self.created_before,
+            status: match self.status {
+                Some(status) => Some(status.parse::<AccountStatusFilter>()?),
+                None => None,
+            },
+            invite_code: self.invite_code,
+            invited_by: self.invited_by,
+            limit: self.limit,
+            cursor: self.cursor,
+        })
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct TargetArgs {
+    /// Accounts to act on, the search filters are used when none are given
+    #[arg(long = "did")]
+    dids: Vec<String>,
+
+    #[command(flatten)]
+    search: SearchArgs,
+
+    /// Who to attribute the action to in the audit trail
+    #[arg(long, env = "USER")]
+    performed_by: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Lists accounts matching the filters, newest first
+    Search(SearchArgs),
+    /// Takes down the targeted accounts
+    Takedown {
+        #[command(flatten)]
+        target: TargetArgs,
+
+        /// Takedown reference, the batch id by default
+        #[arg(long)]
+        takedown_ref: Option<String>,
+    },
+    /// Lifts takedowns and deactivations of the targeted accounts
+    Reactivate {
+        #[command(flatten)]
+        target: TargetArgs,
+    },
+    /// Stops the targeted accounts from creating invite codes
+    DisableInvites {
+        #[command(flatten)]
+        target: TargetArgs,
+    },
+    /// Emails the targeted accounts
+    SendEmail {
+        #[command(flatten)]
+        target: TargetArgs,
+
+        #[arg(long)]
+        subject: Option<String>,
+
+        /// HTML body of the email
+        #[arg(long)]
+        content: String,
+    },
+    /// Shows the audit trail of bulk actions
+    Actions {
+        #[arg(long)]
+        batch_id: Option<String>,
+
+        #[arg(long)]
+        did: Option<String>,
+
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+
+        #[arg(long)]
+        cursor: Option<i64>,
+    },
+}
+
+async fn run_bulk_action(action: BulkAction, target: TargetArgs) -> Result<()> {
+    let cfg = env_to_cfg();
+    // events sequenced here are picked up by the running PDS through the `repo_seq` notification
+    let sequencer = SharedSequencer {
+        sequencer: RwLock::new(Sequencer::new(
+            Crawlers::new(cfg.service.hostname.clone(), cfg.crawlers.clone()),
+            None,
+            cfg.subscription.max_buffer as usize,
+        )),
+    };
+    let (dids, search) = match target.dids.is_empty() {
+        true => (None, Some(target.search.into_search()?)),
+        false => (Some(target.dids), None),
+    };
+    let output = apply_bulk_action(
+        BulkActionInput {
+            action,
+            dids,
+            search,
+            performed_by: target.performed_by.map(|user| format!("cli:{user}")),
+        },
+        &sequencer,
+    )
+    .await?;
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+    tracing_subscriber::fmt::init();
+    let args = Args::parse();
+
+    match args.command {
+        Commands::Search(args) => {
+            let output = search_accounts(args.into_search()?).await?;
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        Commands::Takedown {
+            target,
+            takedown_ref,
+        } => run_bulk_action(BulkAction::Takedown { takedown_ref }, target).await?,
+        Commands::Reactivate { target } => run_bulk_action(BulkAction::Reactivate, target).await?,
+        Commands::DisableInvites { target } => {
+            run_bulk_action(BulkAction::DisableInvites, target).await?
+        }
+        Commands::SendEmail {
+            target,
+            subject,
+            content,
+        } => run_bulk_action(BulkAction::SendEmail { subject, content }, target).await?,
+        Commands::Actions {
+            batch_id,
+            did,
+            limit,
+            cursor,
+        } => {
+            let actions = store::list_actions(batch_id, did, limit, cursor).await?;
+            println!("{}", serde_json::to_string_pretty(&actions)?);
+        }
+    }
+    Ok(())
+}
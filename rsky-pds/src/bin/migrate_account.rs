@@ -0,0 +1,112 @@
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand};
+use dotenvy::dotenv;
+use rsky_pds::config::env_to_cfg;
+use rsky_pds::migration::{store, MigrationInput, MigrationStatus, Migrator};
+
+#[derive(Debug, Parser)]
+struct Args {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Debug, clap::Args)]
+struct MigrationArgs {
+    /// Url of the PDS the account is moving away from
+    #[arg(long)]
+    old_pds: String,
+
+    /// Handle or did of the account on the old PDS
+    #[arg(long)]
+    identifier: String,
+
+    /// Password of the account on the old PDS
+    #[arg(long, env = "MIGRATION_PASSWORD", hide_env_values = true)]
+    password: String,
+
+    /// Url of the PDS the account is moving to, this PDS by default
+    #[arg(long)]
+    new_pds: Option<String>,
+
+    /// Handle of the account on the new PDS
+    #[arg(long)]
+    handle: String,
+
+    /// Email of the account on the new PDS
+    #[arg(long)]
+    email: String,
+
+    /// Password of the account on the new PDS, the old password by default
+    #[arg(long, env = "MIGRATION_NEW_PASSWORD", hide_env_values = true)]
+    new_password: Option<String>,
+
+    /// Invite code, if the new PDS requires one
+    #[arg(long)]
+    invite_code: Option<String>,
+
+    /// Token the old PDS emailed for signing the PLC operation
+    #[arg(long)]
+    plc_token: Option<String>,
+}
+
+impl MigrationArgs {
+    fn into_input(self) -> MigrationInput {
+        MigrationInput {
+            old_pds: self.old_pds,
+            identifier: self.identifier,
+            password: self.password,
+            new_pds: self
+                .new_pds
+                .unwrap_or_else(|| env_to_cfg().service.public_url),
+            handle: self.handle,
+            email: self.email,
+            new_password: self.new_password,
+            invite_code: self.invite_code,
+            plc_token: self.plc_token,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Reports what a migration would do without changing either PDS
+    DryRun(MigrationArgs),
+    /// Migrates the account, resuming from the last completed step if it was interrupted
+    Run(MigrationArgs),
+    /// Shows the recorded progress of a migration
+    Status {
+        #[arg(long)]
+        did: String,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+    tracing_subscriber::fmt::init();
+    let args = Args::parse();
+
+    match args.command {
+        Commands::DryRun(args) => {
+            let migrator = Migrator::new(args.into_input()).await?;
+            let report = migrator.dry_run().await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        Commands::Run(args) => {
+            let mut migrator = Migrator::new(args.into_input()).await?;
+            let migration = migrator.run().await?;
+            println!("{}", serde_json::to_string_pretty(&migration)?);
+            if migration.status == MigrationStatus::AwaitingPlcToken.as_str() {
+                println!(
+                    "The old PDS has emailed a PLC signing token, run again with --plc-token to \
+                     finish the migration"
+                );
+            }
+        }
+        Commands::Status { did } => match store::get_migration(&did).await? {
+            Some(migration) => println!("{}", serde_json::to_string_pretty(&migration)?),
+            None => bail!("No migration recorded for {did}"),
+        },
+    }
+    Ok(())
+}
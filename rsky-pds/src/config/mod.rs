@@ -2,7 +2,7 @@ use crate::context;
 use anyhow::{bail, Result};
 use reqwest::header::HeaderMap;
 use rsky_common::env::{env_bool, env_int, env_list, env_str};
-use rsky_common::time::{DAY, HOUR, SECOND};
+use rsky_common::time::{DAY, HOUR, MINUTE, SECOND};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ServerConfig {
@@ -13,6 +13,8 @@ pub struct ServerConfig {
     pub subscription: SubscriptionConfig,
     pub invites: InvitesConfig,
     pub identity: IdentityConfig,
+    pub blobstore: BlobStoreConfig,
+    pub rate_limits: RateLimitsConfig,
     pub crawlers: Vec<String>,
 }
 
@@ -30,12 +32,53 @@ pub struct SubscriptionConfig {
     pub repo_backfill_limit_ms: u64,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlobStoreConfig {
+    S3,
+    Disk {
+        location: String,
+        tmp_location: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitStoreConfig {
+    Memory,
+    Postgres,
+    Redis { url: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitsConfig {
+    pub enabled: bool,
+    pub store: RateLimitStoreConfig,
+    // requests per ip across all xrpc endpoints
+    pub global_points: i64,
+    pub global_window_ms: i64,
+    // repo write points per did, a create costs rate_limit::CREATE_POINTS
+    pub repo_write_hourly_points: i64,
+    pub repo_write_daily_points: i64,
+    // header a trusted proxy puts the client ip in, e.g. X-Real-IP. Without one limits key on the
+    // socket address, since clients could otherwise pick their own ip
+    pub ip_header: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DidCacheStoreConfig {
+    Memory,
+    Postgres,
+    Disk { dir: String },
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct IdentityConfig {
     pub plc_url: String,
     pub resolver_timeout: u64,
+    pub cache_store: DidCacheStoreConfig,
     pub cache_state_ttl: u64,
     pub cache_max_ttl: u64,
+    // how long a did or handle that didn't resolve is remembered
+    pub cache_negative_ttl: u64,
     pub recovery_did_key: Option<String>,
     pub service_handle_domains: Vec<String>,
     pub handle_backup_name_servers: Option<Vec<String>>,
@@ -96,12 +139,22 @@ pub fn env_to_cfg() -> ServerConfig {
             service_handle_domains = vec![format!(".{hostname}")];
         }
     }
+    let did_cache_store_cfg = match env_str("PDS_DID_CACHE_STORE").as_deref() {
+        Some("memory") => DidCacheStoreConfig::Memory,
+        Some("disk") => DidCacheStoreConfig::Disk {
+            dir: env_str("PDS_DID_CACHE_DIR").unwrap_or("did_cache".to_string()),
+        },
+        _ => DidCacheStoreConfig::Postgres,
+    };
     let identity_cfg: IdentityConfig = IdentityConfig {
         plc_url: env_str("PDS_DID_PLC_URL").unwrap_or("https://plc.directory".to_string()),
         resolver_timeout: env_int("PDS_ID_RESOLVER_TIMEOUT").unwrap_or_else(|| 3 * SECOND as usize)
             as u64,
+        cache_store: did_cache_store_cfg,
         cache_state_ttl: env_int("PDS_DID_CACHE_STALE_TTL").unwrap_or_else(|| HOUR as usize) as u64,
         cache_max_ttl: env_int("PDS_DID_CACHE_MAX_TTL").unwrap_or_else(|| DAY as usize) as u64,
+        cache_negative_ttl: env_int("PDS_DID_CACHE_NEGATIVE_TTL")
+            .unwrap_or_else(|| 5 * MINUTE as usize) as u64,
         recovery_did_key: env_str("PDS_RECOVERY_DID_KEY"),
         service_handle_domains,
         handle_backup_name_servers: Some(env_list("PDS_HANDLE_BACKUP_NAMESERVERS")),
@@ -158,6 +211,35 @@ pub fn env_to_cfg() -> ServerConfig {
             epoch: Some(env_int("PDS_INVITE_EPOCH").unwrap_or(0)),
         },
     };
+    // blobs go to the local disk when a location is given, otherwise to S3
+    let blobstore_cfg = match env_str("PDS_BLOBSTORE_DISK_LOCATION") {
+        None => BlobStoreConfig::S3,
+        Some(location) => BlobStoreConfig::Disk {
+            tmp_location: env_str("PDS_BLOBSTORE_DISK_TMP_LOCATION")
+                .unwrap_or(format!("{location}/temp")),
+            location,
+        },
+    };
+    // limits live in memory unless redis or postgres is configured
+    let rate_limit_store_cfg = match env_str("PDS_RATE_LIMIT_REDIS_URL") {
+        Some(url) => RateLimitStoreConfig::Redis { url },
+        None => match env_str("PDS_RATE_LIMIT_STORE").as_deref() {
+            Some("postgres") => RateLimitStoreConfig::Postgres,
+            _ => RateLimitStoreConfig::Memory,
+        },
+    };
+    let rate_limits_cfg = RateLimitsConfig {
+        enabled: env_bool("PDS_RATE_LIMITS_ENABLED").unwrap_or(false),
+        store: rate_limit_store_cfg,
+        global_points: env_int("PDS_RATE_LIMIT_GLOBAL_POINTS").unwrap_or(3000) as i64,
+        global_window_ms: env_int("PDS_RATE_LIMIT_GLOBAL_WINDOW_MS")
+            .unwrap_or_else(|| 5 * MINUTE as usize) as i64,
+        repo_write_hourly_points: env_int("PDS_RATE_LIMIT_REPO_WRITE_HOURLY_POINTS").unwrap_or(5000)
+            as i64,
+        repo_write_daily_points: env_int("PDS_RATE_LIMIT_REPO_WRITE_DAILY_POINTS").unwrap_or(35000)
+            as i64,
+        ip_header: env_str("PDS_RATE_LIMIT_IP_HEADER"),
+    };
     let crawlers_cfg = env_list("PDS_CRAWLERS");
 
     ServerConfig {
@@ -169,6 +251,8 @@ pub fn env_to_cfg() -> ServerConfig {
         invites: invites_cfg,
         crawlers: crawlers_cfg,
         identity: identity_cfg,
+        blobstore: blobstore_cfg,
+        rate_limits: rate_limits_cfg,
     }
 }
 
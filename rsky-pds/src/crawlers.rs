@@ -1,6 +1,6 @@
+use crate::jobs::{self, JobKind};
 use crate::APP_USER_AGENT;
 use anyhow::Result;
-use futures::stream::{self, StreamExt};
 use rsky_common::time::MINUTE;
 use std::time::SystemTime;
 
@@ -35,28 +35,31 @@ impl Crawlers {
         if now - &self.last_notified < NOTIFY_THRESHOLD as usize {
             return Ok(());
         }
-        let _ = stream::iter(self.crawlers.clone())
-            .then(|service: String| async move {
-                let client = reqwest::Client::builder()
-                    .user_agent(APP_USER_AGENT)
-                    .build()?;
-                let record = CrawlerRequest {
-                    hostname: service.clone(),
-                };
-                Ok::<reqwest::Response, anyhow::Error>(
-                    client
-                        .post(format!("{}/xrpc/com.atproto.sync.requestCrawl", service))
-                        .json(&record)
-                        .send()
-                        .await?,
-                )
+        // the requests themselves are queued so a crawler being down doesn't fail the write
+        for crawler in self.crawlers.iter() {
+            jobs::enqueue(JobKind::NotifyCrawler {
+                hostname: self.hostname.clone(),
+                crawler: crawler.clone(),
             })
-            .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?;
+            .await?;
+        }
 
         self.last_notified = now;
         Ok(())
     }
+
+    pub async fn request_crawl(hostname: &String, crawler: &String) -> Result<()> {
+        let client = reqwest::Client::builder()
+            .user_agent(APP_USER_AGENT)
+            .build()?;
+        client
+            .post(format!("{crawler}/xrpc/com.atproto.sync.requestCrawl"))
+            .json(&CrawlerRequest {
+                hostname: hostname.clone(),
+            })
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
 }
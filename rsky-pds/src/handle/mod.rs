@@ -56,9 +56,10 @@ pub async fn normalize_and_validate_handle(
             ));
         }
 
-        // Verify resolution of a non-service domain
+        // Verify resolution of a non-service domain, bypassing the cache since the record was
+        // likely just set up
         let mut lock = ctx.id_resolver.id_resolver.write().await;
-        match lock.handle.resolve(&handle).await.unwrap() {
+        match lock.handle.resolve_no_cache(&handle).await.unwrap() {
             Some(resolved_did) => {
                 if resolved_did != opts.did.unwrap() {
                     return Err(Error::new(
@@ -79,6 +80,26 @@ pub async fn normalize_and_validate_handle(
     Ok(handle)
 }
 
+/// Drops what the identity cache remembers about `did` and the handles it moved between, so the
+/// change is seen straight away rather than once the entries go stale
+pub async fn clear_cached_identity(id_resolver: &SharedIdResolver, did: &str, handles: &[&str]) {
+    let lock = id_resolver.id_resolver.read().await;
+    if let Some(ref cache) = lock.did.cache {
+        if let Err(error) = cache.clear_entry(did.to_string()).await {
+            tracing::error!("@LOG: ERROR: failed to clear cached DID {did}; err: {error}");
+        }
+    }
+    if let Some(ref cache) = lock.handle.cache {
+        for handle in handles {
+            if let Err(error) = cache.clear_handle(handle.to_string()).await {
+                tracing::error!(
+                    "@LOG: ERROR: failed to clear cached handle {handle}; err: {error}"
+                );
+            }
+        }
+    }
+}
+
 fn base_normalize_and_validate(handle: &str) -> Result<String> {
     match normalize_and_ensure_valid_handle(handle) {
         Ok(normalized) => Ok(normalized),
@@ -38,3 +38,6 @@ pub async fn maybe_get_info(bytes: Vec<u8>) -> Result<Option<ImageInfo>> {
         Err(_) => Ok(None),
     };
 }
+
+pub mod server;
+pub mod variants;
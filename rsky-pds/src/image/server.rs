@@ -0,0 +1,109 @@
+// based on https://github.com/bluesky-social/atproto/blob/main/packages/bsky/src/image/server.ts
+use crate::actor_store::blob::BlobReader;
+use crate::actor_store::blobstore::BlobStoreCreator;
+use crate::apis::com::atproto::repo::assert_repo_availability;
+use crate::apis::ApiError;
+use crate::image::variants::{process, variant_key, Preset, VariantFormat};
+use anyhow::{bail, Result};
+use lazy_static::lazy_static;
+use lexicon_cid::Cid;
+use rocket::http::Header;
+use rocket::{Responder, State};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread::available_parallelism;
+use tokio::sync::Semaphore;
+
+lazy_static! {
+    // variants are generated on blocking threads, one per core at most so a burst of uncached
+    // images can't tie up the whole blocking pool
+    static ref PROCESSING: Arc<Semaphore> = Arc::new(Semaphore::new(
+        available_parallelism().map(|n| n.get()).unwrap_or(1)
+    ));
+}
+
+#[derive(Responder)]
+#[response(status = 200)]
+pub struct ImageResponder(Vec<u8>, Header<'static>, Header<'static>);
+
+#[derive(Debug, thiserror::Error)]
+#[error("Not an image")]
+pub struct NotAnImageError;
+
+fn parse_path(preset: &String, cid_and_format: &String) -> Result<(Preset, Cid, VariantFormat)> {
+    let preset = preset.parse::<Preset>()?;
+    match cid_and_format.split_once("@") {
+        Some((cid, format)) => Ok((
+            preset,
+            Cid::from_str(cid)?,
+            format.parse::<VariantFormat>()?,
+        )),
+        None => bail!("Missing image format"),
+    }
+}
+
+async fn inner_get_image(
+    did: String,
+    preset: Preset,
+    cid: Cid,
+    format: VariantFormat,
+    blobstore: &State<BlobStoreCreator>,
+) -> Result<Vec<u8>> {
+    assert_repo_availability(&did, false).await?;
+
+    // the blob record is checked even for cached variants so takedowns apply right away
+    let reader = BlobReader::new(did.clone(), blobstore.create(did));
+    let metadata = reader.get_blob_metadata(cid).await?;
+    match metadata.mime_type {
+        Some(mime_type) if mime_type.starts_with("image/") => (),
+        _ => return Err(NotAnImageError.into()),
+    }
+
+    let key = variant_key(preset, format);
+    if let Some(bytes) = reader.blobstore.get_cached(cid, key.clone()).await? {
+        return Ok(bytes);
+    }
+    let original = reader.blobstore.get_bytes(cid).await?;
+    // the permit moves into the task, it's held until processing ends even if the request is gone
+    let permit = PROCESSING.clone().acquire_owned().await?;
+    let bytes = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        process(original, preset, format)
+    })
+    .await??;
+    if let Err(error) = reader.blobstore.put_cached(cid, key, bytes.clone()).await {
+        tracing::error!("@LOG: failed to cache image variant; err: {error}");
+    }
+    Ok(bytes)
+}
+
+/// Serves a resized, re-encoded variant of an image blob, e.g.
+/// `/img/avatar/plain/did:plc:abc/bafkrei...@jpeg`
+#[tracing::instrument(skip_all)]
+#[rocket::get("/img/<preset>/plain/<did>/<cid_and_format>")]
+pub async fn get_image(
+    preset: String,
+    did: String,
+    cid_and_format: String,
+    blobstore: &State<BlobStoreCreator>,
+) -> Result<ImageResponder, ApiError> {
+    let (preset, cid, format) = match parse_path(&preset, &cid_and_format) {
+        Ok(parsed) => parsed,
+        Err(error) => return Err(ApiError::InvalidRequest(error.to_string())),
+    };
+    match inner_get_image(did, preset, cid, format, blobstore).await {
+        Ok(bytes) => Ok(ImageResponder(
+            bytes,
+            Header::new("content-type", format.mime_type()),
+            // variants of a cid never change
+            Header::new("cache-control", "public, max-age=31536000"),
+        )),
+        Err(error) => {
+            tracing::error!("Error: {}", error);
+            match error.downcast_ref::<NotAnImageError>() {
+                Some(_) => Err(ApiError::InvalidRequest(error.to_string())),
+                None => Err(ApiError::BlobNotFound),
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+// based on https://github.com/bluesky-social/atproto/blob/main/packages/bsky/src/image/uri.ts
+use anyhow::{bail, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageDecoder, ImageReader, Limits};
+use std::io::Cursor;
+use std::str::FromStr;
+
+const JPEG_QUALITY: u8 = 85;
+// Blobs are fetched by anyone, a few kilobytes of PNG can claim a huge canvas. Bigger than any
+// preset, and small enough that decoding one stays well under `MAX_DECODE_ALLOC`.
+pub const MAX_IMAGE_DIMENSION: u32 = 5000;
+pub const MAX_DECODE_ALLOC: u64 = 128 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fit {
+    // crop to fill the box
+    Cover,
+    // scale down until the image fits in the box
+    Inside,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    Avatar,
+    Banner,
+    FeedThumbnail,
+    FeedFullsize,
+}
+
+pub struct PresetOptions {
+    pub width: u32,
+    pub height: u32,
+    pub fit: Fit,
+}
+
+impl Preset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Preset::Avatar => "avatar",
+            Preset::Banner => "banner",
+            Preset::FeedThumbnail => "feed_thumbnail",
+            Preset::FeedFullsize => "feed_fullsize",
+        }
+    }
+
+    pub fn options(&self) -> PresetOptions {
+        match self {
+            Preset::Avatar => PresetOptions {
+                width: 1000,
+                height: 1000,
+                fit: Fit::Cover,
+            },
+            Preset::Banner => PresetOptions {
+                width: 3000,
+                height: 1000,
+                fit: Fit::Cover,
+            },
+            Preset::FeedThumbnail => PresetOptions {
+                width: 2000,
+                height: 2000,
+                fit: Fit::Inside,
+            },
+            Preset::FeedFullsize => PresetOptions {
+                width: 1000,
+                height: 1000,
+                fit: Fit::Inside,
+            },
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = anyhow::Error;
+
+    fn from_str(preset: &str) -> Result<Self> {
+        match preset {
+            "avatar" => Ok(Preset::Avatar),
+            "banner" => Ok(Preset::Banner),
+            "feed_thumbnail" => Ok(Preset::FeedThumbnail),
+            "feed_fullsize" => Ok(Preset::FeedFullsize),
+            _ => bail!("Invalid preset: `{preset}`"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariantFormat {
+    Jpeg,
+    Png,
+}
+
+impl VariantFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VariantFormat::Jpeg => "jpeg",
+            VariantFormat::Png => "png",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            VariantFormat::Jpeg => "image/jpeg",
+            VariantFormat::Png => "image/png",
+        }
+    }
+}
+
+impl FromStr for VariantFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "jpeg" => Ok(VariantFormat::Jpeg),
+            "png" => Ok(VariantFormat::Png),
+            _ => bail!("Invalid format: `{format}`"),
+        }
+    }
+}
+
+fn decode_limits() -> Limits {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    limits
+}
+
+/// Key the generated variant is cached under next to the original blob
+pub fn variant_key(preset: Preset, format: VariantFormat) -> String {
+    format!("{}@{}", preset.as_str(), format.as_str())
+}
+
+fn resize(img: DynamicImage, options: PresetOptions) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    match options.fit {
+        Fit::Inside if width <= options.width && height <= options.height => img,
+        Fit::Inside => img.resize(options.width, options.height, FilterType::Lanczos3),
+        Fit::Cover => {
+            // never enlarge, shrink the box instead so the aspect ratio is kept
+            let scale = f64::min(
+                1.0,
+                f64::min(
+                    width as f64 / options.width as f64,
+                    height as f64 / options.height as f64,
+                ),
+            );
+            let box_width = ((options.width as f64 * scale).round() as u32).max(1);
+            let box_height = ((options.height as f64 * scale).round() as u32).max(1);
+            img.resize_to_fill(box_width, box_height, FilterType::Lanczos3)
+        }
+    }
+}
+
+/// Resizes and re-encodes an image blob. Only the pixels are carried over, so EXIF and any
+/// other metadata is dropped after the orientation it records has been applied.
+pub fn process(bytes: Vec<u8>, preset: Preset, format: VariantFormat) -> Result<Vec<u8>> {
+    let mut reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    reader.limits(decode_limits());
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    let img = resize(img, preset.options());
+
+    let mut buf = Vec::new();
+    match format {
+        VariantFormat::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut buf, JPEG_QUALITY))?,
+        VariantFormat::Png => img.write_with_encoder(PngEncoder::new(&mut buf))?,
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut buf = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+            .write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    fn dimensions(bytes: Vec<u8>) -> (u32, u32) {
+        image::load_from_memory(&bytes).unwrap().dimensions()
+    }
+
+    #[test]
+    fn resizes_to_preset() {
+        let avatar = process(png(2000, 1000), Preset::Avatar, VariantFormat::Jpeg).unwrap();
+        assert_eq!(dimensions(avatar), (1000, 1000));
+        let banner = process(png(1500, 1500), Preset::Banner, VariantFormat::Jpeg).unwrap();
+        assert_eq!(dimensions(banner), (1500, 500));
+        let thumb = process(png(4000, 1000), Preset::FeedThumbnail, VariantFormat::Png).unwrap();
+        assert_eq!(dimensions(thumb), (2000, 500));
+    }
+
+    #[test]
+    fn never_enlarges() {
+        let thumb = process(png(300, 200), Preset::FeedFullsize, VariantFormat::Png).unwrap();
+        assert_eq!(dimensions(thumb), (300, 200));
+        let avatar = process(png(300, 200), Preset::Avatar, VariantFormat::Png).unwrap();
+        assert_eq!(dimensions(avatar), (200, 200));
+    }
+
+    #[test]
+    fn rejects_oversized_canvas() {
+        let wide = png(MAX_IMAGE_DIMENSION + 1, 1);
+        assert!(process(wide, Preset::FeedThumbnail, VariantFormat::Png).is_err());
+    }
+
+    #[test]
+    fn parses_path_segments() {
+        assert_eq!("banner".parse::<Preset>().unwrap(), Preset::Banner);
+        assert_eq!("png".parse::<VariantFormat>().unwrap(), VariantFormat::Png);
+        assert!("huge".parse::<Preset>().is_err());
+        assert!("gif".parse::<VariantFormat>().is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use crate::actor_store::blobstore::BlobStoreCreator;
+use crate::crawlers::Crawlers;
+use crate::mailer::templates::MailContent;
+use crate::mailer::transport::{deliver, Mail};
+use crate::oauth;
+use crate::rate_limit::store::purge_expired_rate_limits;
+use anyhow::{bail, Result};
+use lexicon_cid::Cid;
+use rsky_common::time::{from_millis_to_str, HOUR, MINUTE, SECOND};
+use std::str::FromStr;
+
+pub const MAX_ATTEMPTS: i32 = 8;
+const BACKOFF_BASE_MS: i64 = 30 * SECOND as i64;
+const BACKOFF_MAX_MS: i64 = HOUR as i64;
+// temp uploads are usually referenced within seconds, a day leaves room for slow clients
+pub const TEMP_BLOB_MAX_AGE_MS: i64 = 24 * HOUR as i64;
+// matches the email token expiration in account_manager::helpers::email_token
+pub const EMAIL_TOKEN_MAX_AGE_MS: i64 = 15 * MINUTE as i64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    Pending,
+    Running,
+    // out of attempts, kept until an admin retries or discards it
+    Dead,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::Dead => "dead",
+        }
+    }
+}
+
+/// Work the PDS defers out of request handlers, stored as JSON in `pds.job`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobKind {
+    DeleteBlobs { did: String, cids: Vec<String> },
+    NotifyCrawler { hostname: String, crawler: String },
+    SendEmail(Mail),
+    PurgeExpiredTokens,
+    CleanupTempBlobs,
+}
+
+impl JobKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobKind::DeleteBlobs { .. } => "delete_blobs",
+            JobKind::NotifyCrawler { .. } => "notify_crawler",
+            JobKind::SendEmail(_) => "send_email",
+            JobKind::PurgeExpiredTokens => "purge_expired_tokens",
+            JobKind::CleanupTempBlobs => "cleanup_temp_blobs",
+        }
+    }
+
+    /// How often a scheduled job runs, `None` for one-off jobs
+    pub fn interval_ms(&self) -> Option<i64> {
+        match self {
+            JobKind::PurgeExpiredTokens => Some(HOUR as i64),
+            JobKind::CleanupTempBlobs => Some(HOUR as i64),
+            _ => None,
+        }
+    }
+
+    /// What a dead job keeps of its payload. Emails carry sign in and confirmation tokens, so
+    /// only the sender, recipient and subject are kept
+    pub fn redacted(&self) -> JobKind {
+        match self {
+            JobKind::SendEmail(mail) => JobKind::SendEmail(Mail {
+                content: MailContent {
+                    subject: mail.content.subject.clone(),
+                    html: String::new(),
+                    text: String::new(),
+                },
+                ..mail.clone()
+            }),
+            job => job.clone(),
+        }
+    }
+}
+
+pub struct JobContext {
+    pub blobstore: BlobStoreCreator,
+}
+
+/// Exponential backoff for the given attempt, starting at 30 seconds and capped at an hour
+pub fn backoff_ms(attempts: i32) -> i64 {
+    let exp = attempts.clamp(1, 16) as u32 - 1;
+    (BACKOFF_BASE_MS * 2_i64.pow(exp)).min(BACKOFF_MAX_MS)
+}
+
+/// Status, attempts and delay for a job whose `attempts`th attempt just failed. Scheduled jobs
+/// never die since `ensure_scheduled` won't queue them again while their row exists, one out of
+/// attempts starts over at its next run instead.
+pub fn after_failure(attempts: i32, interval_ms: Option<i64>) -> (JobStatus, i32, i64) {
+    match (attempts >= MAX_ATTEMPTS, interval_ms) {
+        (false, _) => (JobStatus::Pending, attempts, backoff_ms(attempts)),
+        (true, Some(interval_ms)) => (JobStatus::Pending, 0, interval_ms),
+        (true, None) => (JobStatus::Dead, attempts, backoff_ms(attempts)),
+    }
+}
+
+pub fn run_at_in(delay_ms: i64) -> String {
+    from_millis_to_str(chrono::Utc::now().timestamp_millis() + delay_ms)
+}
+
+pub async fn enqueue(job: JobKind) -> Result<()> {
+    store::insert_job(&job, None, run_at_in(0)).await
+}
+
+/// Queues the job unless one with the same key is already pending or running
+pub async fn enqueue_once(job: JobKind, unique_key: String) -> Result<()> {
+    store::insert_job(&job, Some(unique_key), run_at_in(0)).await
+}
+
+pub async fn run_job(job: JobKind, ctx: &JobContext) -> Result<()> {
+    match job {
+        JobKind::DeleteBlobs { did, cids } => {
+            let cids = cids
+                .iter()
+                .map(|cid| Ok(Cid::from_str(cid)?))
+                .collect::<Result<Vec<Cid>>>()?;
+            ctx.blobstore.create(did).delete_many(cids).await
+        }
+        JobKind::NotifyCrawler { hostname, crawler } => {
+            Crawlers::request_crawl(&hostname, &crawler).await
+        }
+        JobKind::SendEmail(mail) => deliver(mail).await,
+        JobKind::PurgeExpiredTokens => {
+            store::purge_expired_tokens().await?;
+            oauth::store::purge_used_jtis().await?;
+            // finished windows left behind by the postgres rate limit store
+            purge_expired_rate_limits().await
+        }
+        JobKind::CleanupTempBlobs => {
+            for (did, temp_key) in store::get_orphaned_temp_blobs().await? {
+                ctx.blobstore
+                    .create(did.clone())
+                    .delete_temp(temp_key.clone())
+                    .await?;
+                store::delete_temp_blob(&did, &temp_key).await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+pub fn parse_job(kind: &str, payload: &str) -> Result<JobKind> {
+    let job: JobKind = serde_json::from_str(payload)?;
+    if job.name() != kind {
+        bail!("Job payload is a `{}`, expected `{kind}`", job.name());
+    }
+    Ok(job)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_an_hour() {
+        assert_eq!(backoff_ms(1), 30_000);
+        assert_eq!(backoff_ms(2), 60_000);
+        assert_eq!(backoff_ms(4), 240_000);
+        assert_eq!(backoff_ms(MAX_ATTEMPTS), HOUR as i64);
+    }
+
+    #[test]
+    fn payload_round_trips() {
+        let job = JobKind::DeleteBlobs {
+            did: "did:plc:abc".to_string(),
+            cids: vec!["bafkrei".to_string()],
+        };
+        let payload = serde_json::to_string(&job).unwrap();
+        assert!(payload.starts_with("{\"type\":\"delete_blobs\""));
+        assert!(parse_job("delete_blobs", &payload).is_ok());
+        assert!(parse_job("send_email", &payload).is_err());
+    }
+
+    #[test]
+    fn scheduled_jobs_wait_for_their_next_run() {
+        assert_eq!(after_failure(1, None), (JobStatus::Pending, 1, 30_000));
+        assert_eq!(
+            after_failure(MAX_ATTEMPTS, None),
+            (JobStatus::Dead, MAX_ATTEMPTS, HOUR as i64)
+        );
+        let interval_ms = JobKind::PurgeExpiredTokens.interval_ms();
+        assert_eq!(
+            after_failure(MAX_ATTEMPTS, interval_ms),
+            (JobStatus::Pending, 0, HOUR as i64)
+        );
+    }
+
+    #[test]
+    fn dead_emails_drop_their_content() {
+        let job = JobKind::SendEmail(Mail {
+            from: crate::mailer::transport::Sender {
+                name: None,
+                address: "noreply@pds.test".to_string(),
+            },
+            to: "alice@example.com".to_string(),
+            content: MailContent {
+                subject: "Confirm your email".to_string(),
+                html: "<p>ABCDE-12345</p>".to_string(),
+                text: "ABCDE-12345".to_string(),
+            },
+        });
+        let payload = serde_json::to_string(&job.redacted()).unwrap();
+        assert!(!payload.contains("ABCDE-12345"));
+        assert!(payload.contains("alice@example.com"));
+        assert!(payload.contains("Confirm your email"));
+    }
+}
+
+pub mod routes;
+pub mod store;
+pub mod worker;
@@ -0,0 +1,62 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::jobs::store;
+use crate::models::Job;
+use rocket::serde::json::Json;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListDeadJobsOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub jobs: Vec<Job>,
+}
+
+/// Jobs that ran out of attempts, newest first
+#[tracing::instrument(skip_all)]
+#[rocket::get("/dead?<limit>&<cursor>")]
+pub async fn list_dead_jobs(
+    limit: Option<i64>,
+    cursor: Option<i64>,
+    _auth: AdminToken,
+) -> Result<Json<ListDeadJobsOutput>, ApiError> {
+    let limit = limit.unwrap_or(50).clamp(1, 100);
+    match store::list_dead_jobs(limit, cursor).await {
+        Ok(jobs) => Ok(Json(ListDeadJobsOutput {
+            cursor: match jobs.len() as i64 == limit {
+                true => jobs.last().map(|job| job.id.to_string()),
+                false => None,
+            },
+            jobs,
+        })),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+#[rocket::post("/<id>/retry")]
+pub async fn retry_dead_job(id: i64, _auth: AdminToken) -> Result<(), ApiError> {
+    match store::retry_dead_job(id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ApiError::RecordNotFound),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+#[rocket::post("/<id>/discard")]
+pub async fn discard_dead_job(id: i64, _auth: AdminToken) -> Result<(), ApiError> {
+    match store::discard_dead_job(id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ApiError::RecordNotFound),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
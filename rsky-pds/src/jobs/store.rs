@@ -0,0 +1,228 @@
+use crate::db::establish_connection;
+use crate::jobs::{
+    after_failure, run_at_in, JobKind, JobStatus, EMAIL_TOKEN_MAX_AGE_MS, TEMP_BLOB_MAX_AGE_MS,
+};
+use crate::models::Job;
+use anyhow::Result;
+use diesel::sql_types::{BigInt, Text};
+use diesel::*;
+use rsky_common::now;
+
+pub async fn insert_job(job: &JobKind, unique_key: Option<String>, run_at: String) -> Result<()> {
+    use crate::schema::pds::job::dsl as JobSchema;
+    let conn = &mut establish_connection()?;
+
+    let timestamp = now();
+    insert_into(JobSchema::job)
+        .values((
+            JobSchema::kind.eq(job.name()),
+            JobSchema::payload.eq(serde_json::to_string(job)?),
+            JobSchema::status.eq(JobStatus::Pending.as_str()),
+            JobSchema::uniqueKey.eq(unique_key),
+            JobSchema::runAt.eq(run_at),
+            JobSchema::createdAt.eq(&timestamp),
+            JobSchema::updatedAt.eq(&timestamp),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Queues a scheduled job unless it already is, keyed by its name
+pub async fn ensure_scheduled(job: &JobKind) -> Result<()> {
+    insert_job(job, Some(job.name().to_string()), run_at_in(0)).await
+}
+
+/// Locks up to `limit` due jobs for this worker. `SKIP LOCKED` lets several PDS instances share
+/// the queue without picking the same job.
+pub async fn claim_jobs(limit: i64) -> Result<Vec<Job>> {
+    let conn = &mut establish_connection()?;
+
+    let timestamp = now();
+    let claimed = sql_query(
+        "UPDATE pds.job \
+        SET status = 'running', \"lockedAt\" = $1, \"updatedAt\" = $1 \
+        WHERE id IN ( \
+            SELECT id FROM pds.job \
+            WHERE status = 'pending' AND \"runAt\" <= $1 \
+            ORDER BY \"runAt\" \
+            LIMIT $2 \
+            FOR UPDATE SKIP LOCKED \
+        ) \
+        RETURNING *",
+    )
+    .bind::<Text, _>(&timestamp)
+    .bind::<BigInt, _>(limit)
+    .load::<Job>(conn)?;
+    Ok(claimed)
+}
+
+/// Puts jobs back that were locked by a worker which died before finishing them
+pub async fn release_stale_jobs(locked_before: String) -> Result<usize> {
+    use crate::schema::pds::job::dsl as JobSchema;
+    let conn = &mut establish_connection()?;
+
+    Ok(update(JobSchema::job)
+        .filter(JobSchema::status.eq(JobStatus::Running.as_str()))
+        .filter(JobSchema::lockedAt.lt(locked_before))
+        .set((
+            JobSchema::status.eq(JobStatus::Pending.as_str()),
+            JobSchema::lockedAt.eq(None::<String>),
+            JobSchema::updatedAt.eq(now()),
+        ))
+        .execute(conn)?)
+}
+
+/// One-off jobs are removed once done, scheduled ones are queued again for their next run
+pub async fn complete_job(job: &Job, interval_ms: Option<i64>) -> Result<()> {
+    use crate::schema::pds::job::dsl as JobSchema;
+    let conn = &mut establish_connection()?;
+
+    match interval_ms {
+        None => {
+            delete(JobSchema::job)
+                .filter(JobSchema::id.eq(job.id))
+                .execute(conn)?;
+        }
+        Some(interval_ms) => {
+            update(JobSchema::job)
+                .filter(JobSchema::id.eq(job.id))
+                .set((
+                    JobSchema::status.eq(JobStatus::Pending.as_str()),
+                    JobSchema::attempts.eq(0),
+                    JobSchema::lastError.eq(None::<String>),
+                    JobSchema::runAt.eq(run_at_in(interval_ms)),
+                    JobSchema::lockedAt.eq(None::<String>),
+                    JobSchema::updatedAt.eq(now()),
+                ))
+                .execute(conn)?;
+        }
+    }
+    Ok(())
+}
+
+/// Retries the job after a backoff, or marks it dead once it's out of attempts. `kind` is the
+/// parsed payload, `None` if it didn't parse.
+pub async fn fail_job(job: &Job, kind: Option<&JobKind>, error: String) -> Result<()> {
+    use crate::schema::pds::job::dsl as JobSchema;
+    let conn = &mut establish_connection()?;
+
+    let (status, attempts, delay_ms) =
+        after_failure(job.attempts + 1, kind.and_then(JobKind::interval_ms));
+    let payload = match (status, kind) {
+        (JobStatus::Dead, Some(kind)) => serde_json::to_string(&kind.redacted())?,
+        _ => job.payload.clone(),
+    };
+    update(JobSchema::job)
+        .filter(JobSchema::id.eq(job.id))
+        .set((
+            JobSchema::status.eq(status.as_str()),
+            JobSchema::payload.eq(payload),
+            JobSchema::attempts.eq(attempts),
+            JobSchema::lastError.eq(error),
+            JobSchema::runAt.eq(run_at_in(delay_ms)),
+            JobSchema::lockedAt.eq(None::<String>),
+            JobSchema::updatedAt.eq(now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub async fn list_dead_jobs(limit: i64, cursor: Option<i64>) -> Result<Vec<Job>> {
+    use crate::schema::pds::job::dsl as JobSchema;
+    let conn = &mut establish_connection()?;
+
+    let mut builder = JobSchema::job
+        .filter(JobSchema::status.eq(JobStatus::Dead.as_str()))
+        .into_boxed();
+    if let Some(cursor) = cursor {
+        builder = builder.filter(JobSchema::id.lt(cursor));
+    }
+    Ok(builder
+        .order(JobSchema::id.desc())
+        .limit(limit)
+        .select(Job::as_select())
+        .load(conn)?)
+}
+
+/// Gives a dead job a fresh set of attempts, returns false if there was no such dead job. Dead
+/// emails can only be discarded, their content is redacted and their tokens have expired.
+pub async fn retry_dead_job(id: i64) -> Result<bool> {
+    use crate::schema::pds::job::dsl as JobSchema;
+    let conn = &mut establish_connection()?;
+
+    let updated = update(JobSchema::job)
+        .filter(JobSchema::id.eq(id))
+        .filter(JobSchema::status.eq(JobStatus::Dead.as_str()))
+        .filter(JobSchema::kind.ne("send_email"))
+        .set((
+            JobSchema::status.eq(JobStatus::Pending.as_str()),
+            JobSchema::attempts.eq(0),
+            JobSchema::runAt.eq(now()),
+            JobSchema::updatedAt.eq(now()),
+        ))
+        .execute(conn)?;
+    Ok(updated > 0)
+}
+
+pub async fn discard_dead_job(id: i64) -> Result<bool> {
+    use crate::schema::pds::job::dsl as JobSchema;
+    let conn = &mut establish_connection()?;
+
+    let deleted = delete(JobSchema::job)
+        .filter(JobSchema::id.eq(id))
+        .filter(JobSchema::status.eq(JobStatus::Dead.as_str()))
+        .execute(conn)?;
+    Ok(deleted > 0)
+}
+
+pub async fn purge_expired_tokens() -> Result<()> {
+    use crate::schema::pds::email_token::dsl as EmailTokenSchema;
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+    use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
+    let conn = &mut establish_connection()?;
+
+    let timestamp = now();
+    delete(RefreshTokenSchema::refresh_token)
+        .filter(RefreshTokenSchema::expiresAt.le(&timestamp))
+        .execute(conn)?;
+    delete(EmailTokenSchema::email_token)
+        .filter(EmailTokenSchema::requestedAt.le(run_at_in(-EMAIL_TOKEN_MAX_AGE_MS)))
+        .execute(conn)?;
+    delete(OAuthRequestSchema::oauth_request)
+        .filter(OAuthRequestSchema::expiresAt.le(&timestamp))
+        .execute(conn)?;
+    delete(OAuthTokenSchema::oauth_token)
+        .filter(OAuthTokenSchema::expiresAt.le(&timestamp))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Uploads that no record referenced in time, as (did, temp key)
+pub async fn get_orphaned_temp_blobs() -> Result<Vec<(String, String)>> {
+    use crate::schema::pds::blob::dsl as BlobSchema;
+    let conn = &mut establish_connection()?;
+
+    let rows: Vec<(String, Option<String>)> = BlobSchema::blob
+        .filter(BlobSchema::tempKey.is_not_null())
+        .filter(BlobSchema::createdAt.lt(run_at_in(-TEMP_BLOB_MAX_AGE_MS)))
+        .select((BlobSchema::did, BlobSchema::tempKey))
+        .limit(1000)
+        .load(conn)?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(did, temp_key)| temp_key.map(|temp_key| (did, temp_key)))
+        .collect())
+}
+
+pub async fn delete_temp_blob(did: &String, temp_key: &String) -> Result<()> {
+    use crate::schema::pds::blob::dsl as BlobSchema;
+    let conn = &mut establish_connection()?;
+
+    delete(BlobSchema::blob)
+        .filter(BlobSchema::did.eq(did))
+        .filter(BlobSchema::tempKey.eq(temp_key))
+        .execute(conn)?;
+    Ok(())
+}
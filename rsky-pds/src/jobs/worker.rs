@@ -0,0 +1,78 @@
+use crate::jobs::{parse_job, run_at_in, run_job, store, JobContext, JobKind};
+use crate::models::Job;
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use rsky_common::time::MINUTE;
+use std::time::Duration;
+
+const BATCH_SIZE: i64 = 20;
+const CONCURRENCY: usize = 5;
+// a job locked for longer than this belonged to a worker that went away
+const STALE_LOCK_MS: i64 = 10 * MINUTE as i64;
+
+pub struct JobWorker {
+    ctx: JobContext,
+}
+
+impl JobWorker {
+    pub fn new(ctx: JobContext) -> Self {
+        Self { ctx }
+    }
+
+    pub async fn run(&self, interval: Duration) {
+        for job in [JobKind::PurgeExpiredTokens, JobKind::CleanupTempBlobs] {
+            if let Err(error) = store::ensure_scheduled(&job).await {
+                tracing::error!("Failed to schedule {} job: {error}", job.name());
+            }
+        }
+        loop {
+            match self.run_due_jobs().await {
+                // keep draining while there's a backlog
+                Ok(ran) if ran as i64 == BATCH_SIZE => continue,
+                Ok(_) => (),
+                Err(error) => tracing::error!("Failed to run jobs: {error}"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Runs one batch of due jobs and returns how many were claimed
+    pub async fn run_due_jobs(&self) -> Result<usize> {
+        let released = store::release_stale_jobs(run_at_in(-STALE_LOCK_MS)).await?;
+        if released > 0 {
+            tracing::warn!("Released {released} stale jobs");
+        }
+        let jobs = store::claim_jobs(BATCH_SIZE).await?;
+        let claimed = jobs.len();
+        stream::iter(jobs)
+            .for_each_concurrent(CONCURRENCY, |job| async move {
+                if let Err(error) = self.run_one(&job).await {
+                    tracing::error!("Failed to record outcome of job {}: {error}", job.id);
+                }
+            })
+            .await;
+        Ok(claimed)
+    }
+
+    async fn run_one(&self, job: &Job) -> Result<()> {
+        let (outcome, kind) = match parse_job(&job.kind, &job.payload) {
+            Ok(kind) => (run_job(kind.clone(), &self.ctx).await, Some(kind)),
+            Err(error) => (Err(error), None),
+        };
+        match outcome {
+            Ok(()) => {
+                tracing::debug!("Job {} ({}) done", job.id, job.kind);
+                store::complete_job(job, kind.as_ref().and_then(JobKind::interval_ms)).await
+            }
+            Err(error) => {
+                tracing::warn!(
+                    "Job {} ({}) failed on attempt {}: {error}",
+                    job.id,
+                    job.kind,
+                    job.attempts + 1
+                );
+                store::fail_job(job, kind.as_ref(), error.to_string()).await
+            }
+        }
+    }
+}
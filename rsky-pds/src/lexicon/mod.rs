@@ -12,3 +12,4 @@ lazy_static! {
 }
 
 pub mod lexicons;
+pub mod validator;
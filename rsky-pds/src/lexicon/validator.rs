@@ -0,0 +1,692 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use lazy_static::lazy_static;
+use lexicon_cid::Cid;
+use regex::Regex;
+use rsky_repo::storage::Ipld;
+use rsky_repo::types::{BlobConstraint, Lex, RepoRecord, ValidationStatus};
+use rsky_syntax::aturi_validation::ensure_valid_at_uri;
+use rsky_syntax::datetime::is_valid_datetime;
+use rsky_syntax::did::ensure_valid_did;
+use rsky_syntax::handle::is_valid_handle;
+use rsky_syntax::nsid::ensure_valid_nsid;
+use rsky_syntax::record_key::is_valid_record_key;
+use rsky_syntax::tid::is_valid_tid;
+use serde_json::{json, Value as JsonValue};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::str::FromStr;
+use std::{env, fs};
+use unicode_segmentation::UnicodeSegmentation;
+
+lazy_static! {
+    /// Lexicons bundled in `lexicons.toml`, extended with the JSON lexicon documents found in
+    /// `PDS_LEXICON_DIR`.
+    pub static ref LEXICON_VALIDATOR: LexiconValidator = {
+        let mut validator = LexiconValidator::from_toml(include_str!("lexicons.toml"))
+            .expect("Failed to load lexicons.toml");
+        if let Ok(dir) = env::var("PDS_LEXICON_DIR") {
+            validator
+                .load_dir(&dir)
+                .expect("Failed to load lexicons from PDS_LEXICON_DIR");
+        }
+        validator
+    };
+    static ref LANGUAGE_REGEX: Regex = Regex::new(r"^(i|[a-z]{2,3})(-[a-zA-Z0-9]{1,8})*$").unwrap();
+}
+
+pub struct RecordValidation {
+    pub status: ValidationStatus,
+    /// Constraints of every blob referenced by the record, keyed by blob CID
+    pub blob_constraints: HashMap<String, BlobConstraint>,
+}
+
+/// Validates records against lexicon definitions, following
+/// https://atproto.com/specs/lexicon
+#[derive(Debug, Default)]
+pub struct LexiconValidator {
+    /// Definitions keyed by `<nsid>#<name>`
+    defs: HashMap<String, JsonValue>,
+}
+
+impl LexiconValidator {
+    /// Loads lexicon documents stored as tables of a TOML file, the format of `lexicons.toml`
+    pub fn from_toml(toml_str: &str) -> Result<Self> {
+        let documents: BTreeMap<String, toml::Value> = toml::from_str(toml_str)?;
+        let mut validator = Self::default();
+        for document in documents.into_values() {
+            validator.add_document(serde_json::to_value(document)?)?;
+        }
+        Ok(validator)
+    }
+
+    /// Loads every `.json` lexicon document in `dir` and its subdirectories
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.load_dir(&path)?;
+            } else if path.extension().is_some_and(|ext| ext == "json") {
+                let document = serde_json::from_str(&fs::read_to_string(&path)?)
+                    .with_context(|| format!("Invalid lexicon document {}", path.display()))?;
+                self.add_document(document)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_document(&mut self, document: JsonValue) -> Result<()> {
+        let id = document["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Lexicon document is missing its id"))?;
+        let defs = document["defs"]
+            .as_object()
+            .ok_or_else(|| anyhow!("Lexicon document {id} is missing its defs"))?;
+        for (name, def) in defs {
+            self.defs.insert(format!("{id}#{name}"), def.clone());
+        }
+        Ok(())
+    }
+
+    /// Validates `record` against the lexicon of its `$type`.
+    ///
+    /// Records of an unknown type are reported as `unknown` unless `require_lexicon` is set.
+    pub fn validate_record(
+        &self,
+        record_type: &str,
+        record: &RepoRecord,
+        require_lexicon: bool,
+    ) -> Result<RecordValidation> {
+        let mut validation = RecordValidation {
+            status: ValidationStatus::Valid,
+            blob_constraints: HashMap::new(),
+        };
+        let def = match self.defs.get(&format!("{record_type}#main")) {
+            Some(def) if def["type"] == "record" => def,
+            Some(_) => bail!("Lexicon {record_type} is not a record"),
+            None if require_lexicon => bail!("Lexicon not found: {record_type}"),
+            None => {
+                validation.status = ValidationStatus::Unknown;
+                return Ok(validation);
+            }
+        };
+        let value = lex_to_json(Lex::Map(record.clone()));
+        self.validate_value(
+            &mut validation,
+            record_type,
+            "Record",
+            &def["record"],
+            &value,
+        )?;
+        Ok(validation)
+    }
+
+    fn validate_value(
+        &self,
+        validation: &mut RecordValidation,
+        lexicon: &str,
+        path: &str,
+        def: &JsonValue,
+        value: &JsonValue,
+    ) -> Result<()> {
+        match def["type"].as_str() {
+            Some("object") => self.validate_object(validation, lexicon, path, def, value),
+            Some("ref") => {
+                let (lexicon, def) = self.resolve_ref(lexicon, &def["ref"])?;
+                self.validate_value(validation, &lexicon, path, def, value)
+            }
+            Some("union") => self.validate_union(validation, lexicon, path, def, value),
+            Some("array") => {
+                let Some(items) = value.as_array() else {
+                    bail!("{path} must be an array")
+                };
+                if let Some(max) = def["maxLength"].as_u64() {
+                    if items.len() as u64 > max {
+                        bail!("{path} must not have more than {max} elements")
+                    }
+                }
+                if let Some(min) = def["minLength"].as_u64() {
+                    if (items.len() as u64) < min {
+                        bail!("{path} must not have fewer than {min} elements")
+                    }
+                }
+                for (i, item) in items.iter().enumerate() {
+                    self.validate_value(
+                        validation,
+                        lexicon,
+                        &format!("{path}/{i}"),
+                        &def["items"],
+                        item,
+                    )?;
+                }
+                Ok(())
+            }
+            Some("string") => validate_string(path, def, value),
+            Some("integer") => validate_integer(path, def, value),
+            Some("boolean") => {
+                let Some(boolean) = value.as_bool() else {
+                    bail!("{path} must be a boolean")
+                };
+                if let Some(expected) = def["const"].as_bool() {
+                    if boolean != expected {
+                        bail!("{path} must be {expected}")
+                    }
+                }
+                Ok(())
+            }
+            Some("null") => match value {
+                JsonValue::Null => Ok(()),
+                _ => bail!("{path} must be null"),
+            },
+            Some("bytes") => {
+                let Some(bytes) = value["$bytes"].as_str() else {
+                    bail!("{path} must be a byte array")
+                };
+                let len = STANDARD_NO_PAD
+                    .decode(bytes.trim_end_matches('='))
+                    .map_err(|_| anyhow!("{path} must be a byte array"))?
+                    .len() as u64;
+                if def["maxLength"].as_u64().is_some_and(|max| len > max) {
+                    bail!("{path} must not be larger than {} bytes", def["maxLength"])
+                }
+                if def["minLength"].as_u64().is_some_and(|min| len < min) {
+                    bail!("{path} must not be smaller than {} bytes", def["minLength"])
+                }
+                Ok(())
+            }
+            Some("cid-link") => match value["$link"].as_str().map(Cid::from_str) {
+                Some(Ok(_)) => Ok(()),
+                _ => bail!("{path} must be a CID"),
+            },
+            Some("blob") => validate_blob(validation, path, def, value),
+            Some("unknown") => match value {
+                JsonValue::Object(_) => Ok(()),
+                _ => bail!("{path} must be an object"),
+            },
+            Some(other) => bail!("{path} has unsupported lexicon type {other}"),
+            None => bail!("{path} has a lexicon definition without a type"),
+        }
+    }
+
+    fn validate_object(
+        &self,
+        validation: &mut RecordValidation,
+        lexicon: &str,
+        path: &str,
+        def: &JsonValue,
+        value: &JsonValue,
+    ) -> Result<()> {
+        let Some(object) = value.as_object() else {
+            bail!("{path} must be an object")
+        };
+        let nullable = string_list(&def["nullable"]);
+        for key in string_list(&def["required"]) {
+            match object.get(key) {
+                None => bail!("{path} must have the property \"{key}\""),
+                Some(JsonValue::Null) if !nullable.contains(&key) => {
+                    bail!("{path} must have the property \"{key}\"")
+                }
+                _ => (),
+            }
+        }
+        let Some(properties) = def["properties"].as_object() else {
+            return Ok(());
+        };
+        for (key, property) in properties {
+            match object.get(key) {
+                None => (),
+                Some(JsonValue::Null) if nullable.contains(&key.as_str()) => (),
+                Some(value) => self.validate_value(
+                    validation,
+                    lexicon,
+                    &format!("{path}/{key}"),
+                    property,
+                    value,
+                )?,
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_union(
+        &self,
+        validation: &mut RecordValidation,
+        lexicon: &str,
+        path: &str,
+        def: &JsonValue,
+        value: &JsonValue,
+    ) -> Result<()> {
+        let Some(value_type) = value["$type"].as_str() else {
+            bail!("{path} must be an object which includes the \"$type\" property")
+        };
+        let value_type = match value_type.contains('#') {
+            true => value_type.to_string(),
+            false => format!("{value_type}#main"),
+        };
+        for r#ref in def["refs"].as_array().into_iter().flatten() {
+            let Some(r#ref) = r#ref.as_str() else {
+                continue;
+            };
+            if ref_key(lexicon, r#ref) == value_type {
+                let (lexicon, def) = self.resolve_ref(lexicon, &json!(r#ref))?;
+                return self.validate_value(validation, &lexicon, path, def, value);
+            }
+        }
+        // open unions accept types defined after the lexicon was published
+        if def["closed"].as_bool() == Some(true) {
+            bail!(
+                "{path} $type must be one of {}",
+                string_list(&def["refs"]).join(", ")
+            )
+        }
+        Ok(())
+    }
+
+    /// Returns the definition `r#ref` points to, together with the NSID of its lexicon so that
+    /// local refs (`#name`) inside it can be resolved
+    fn resolve_ref(&self, lexicon: &str, r#ref: &JsonValue) -> Result<(String, &JsonValue)> {
+        let r#ref = r#ref
+            .as_str()
+            .ok_or_else(|| anyhow!("Invalid ref in lexicon {lexicon}"))?;
+        let key = ref_key(lexicon, r#ref);
+        let def = self
+            .defs
+            .get(&key)
+            .ok_or_else(|| anyhow!("Lexicon not found: {key}"))?;
+        let nsid = key.split('#').next().unwrap_or_default().to_string();
+        match def["type"].as_str() {
+            Some("record") => Ok((nsid, &def["record"])),
+            _ => Ok((nsid, def)),
+        }
+    }
+}
+
+/// Normalizes `lex:nsid`, `nsid#name` and `#name` refs to `nsid#name`
+fn ref_key(lexicon: &str, reference: &str) -> String {
+    let reference = reference.strip_prefix("lex:").unwrap_or(reference);
+    if reference.starts_with('#') {
+        format!("{lexicon}{reference}")
+    } else if reference.contains('#') {
+        reference.to_string()
+    } else {
+        format!("{reference}#main")
+    }
+}
+
+fn string_list(value: &JsonValue) -> Vec<&str> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(JsonValue::as_str)
+        .collect()
+}
+
+fn validate_string(path: &str, def: &JsonValue, value: &JsonValue) -> Result<()> {
+    let Some(string) = value.as_str() else {
+        bail!("{path} must be a string")
+    };
+    if let Some(expected) = def["const"].as_str() {
+        if string != expected {
+            bail!("{path} must be {expected}")
+        }
+    }
+    if def["enum"].is_array() && !string_list(&def["enum"]).contains(&string) {
+        bail!(
+            "{path} must be one of ({})",
+            string_list(&def["enum"]).join("|")
+        )
+    }
+    // lengths are counted in UTF-8 bytes
+    if let Some(max) = def["maxLength"].as_u64() {
+        if string.len() as u64 > max {
+            bail!("{path} must not be longer than {max} characters")
+        }
+    }
+    if let Some(min) = def["minLength"].as_u64() {
+        if (string.len() as u64) < min {
+            bail!("{path} must not be shorter than {min} characters")
+        }
+    }
+    if def["maxGraphemes"].is_u64() || def["minGraphemes"].is_u64() {
+        let graphemes = string.graphemes(true).count() as u64;
+        if let Some(max) = def["maxGraphemes"].as_u64() {
+            if graphemes > max {
+                bail!("{path} must not be longer than {max} graphemes")
+            }
+        }
+        if let Some(min) = def["minGraphemes"].as_u64() {
+            if graphemes < min {
+                bail!("{path} must not be shorter than {min} graphemes")
+            }
+        }
+    }
+    match def["format"].as_str() {
+        Some(format) => validate_format(path, format, string),
+        None => Ok(()),
+    }
+}
+
+fn validate_format(path: &str, format: &str, value: &str) -> Result<()> {
+    let valid = match format {
+        "datetime" => is_valid_datetime(value),
+        "did" => ensure_valid_did(value).is_ok(),
+        "handle" => is_valid_handle(value),
+        "at-identifier" => ensure_valid_did(value).is_ok() || is_valid_handle(value),
+        "nsid" => ensure_valid_nsid(value).is_ok(),
+        "at-uri" => ensure_valid_at_uri(value).is_ok(),
+        "tid" => is_valid_tid(value),
+        "record-key" => is_valid_record_key(value),
+        "cid" => Cid::from_str(value).is_ok(),
+        "uri" => url::Url::parse(value).is_ok(),
+        "language" => LANGUAGE_REGEX.is_match(value),
+        _ => true,
+    };
+    match valid {
+        true => Ok(()),
+        false => bail!("{path} must be a valid {format}"),
+    }
+}
+
+fn validate_integer(path: &str, def: &JsonValue, value: &JsonValue) -> Result<()> {
+    let Some(integer) = value.as_i64() else {
+        bail!("{path} must be an integer")
+    };
+    if let Some(expected) = def["const"].as_i64() {
+        if integer != expected {
+            bail!("{path} must be {expected}")
+        }
+    }
+    if let Some(allowed) = def["enum"].as_array() {
+        if !allowed
+            .iter()
+            .any(|allowed| allowed.as_i64() == Some(integer))
+        {
+            bail!("{path} must be one of the enumerated values")
+        }
+    }
+    if let Some(max) = def["maximum"].as_i64() {
+        if integer > max {
+            bail!("{path} can not be greater than {max}")
+        }
+    }
+    if let Some(min) = def["minimum"].as_i64() {
+        if integer < min {
+            bail!("{path} can not be less than {min}")
+        }
+    }
+    Ok(())
+}
+
+fn validate_blob(
+    validation: &mut RecordValidation,
+    path: &str,
+    def: &JsonValue,
+    value: &JsonValue,
+) -> Result<()> {
+    let cid = match value["$type"].as_str() {
+        Some("blob") => value["ref"]["$link"].as_str(),
+        // legacy blob refs only carry the cid and mime type
+        _ => value["cid"].as_str(),
+    };
+    let (Some(cid), Some(_)) = (cid, value["mimeType"].as_str()) else {
+        bail!("{path} should be a blob ref")
+    };
+    let constraint = BlobConstraint {
+        max_size: def["maxSize"].as_u64().map(|max_size| max_size as usize),
+        accept: def["accept"].as_array().map(|_| {
+            string_list(&def["accept"])
+                .into_iter()
+                .map(str::to_string)
+                .collect()
+        }),
+    };
+    let constraint = match validation.blob_constraints.remove(cid) {
+        Some(existing) => merge_blob_constraints(existing, constraint),
+        None => constraint,
+    };
+    validation
+        .blob_constraints
+        .insert(cid.to_string(), constraint);
+    Ok(())
+}
+
+/// A blob referenced from several fields has to satisfy all of them: the smallest `maxSize` and
+/// only the mime types every `accept` allows
+fn merge_blob_constraints(a: BlobConstraint, b: BlobConstraint) -> BlobConstraint {
+    let max_size = match (a.max_size, b.max_size) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    let accept = match (a.accept, b.accept) {
+        (Some(a), Some(b)) => Some(intersect_accept(&a, &b)),
+        (a, b) => a.or(b),
+    };
+    BlobConstraint { max_size, accept }
+}
+
+/// Mime types matched by a pattern of both lists, `image/*` and `image/png` leave `image/png`
+fn intersect_accept(a: &[String], b: &[String]) -> Vec<String> {
+    let mut accept: Vec<String> = vec![];
+    for x in a {
+        for y in b {
+            let narrower = if mime_covers(x, y) {
+                y
+            } else if mime_covers(y, x) {
+                x
+            } else {
+                continue;
+            };
+            if !accept.contains(narrower) {
+                accept.push(narrower.clone());
+            }
+        }
+    }
+    accept
+}
+
+/// Whether `glob` accepts every mime type `pattern` does
+fn mime_covers(glob: &str, pattern: &str) -> bool {
+    glob == "*/*"
+        || glob == pattern
+        || glob
+            .strip_suffix("/*")
+            .is_some_and(|prefix| pattern.split('/').next() == Some(prefix))
+}
+
+/// Converts a record to its JSON data model representation (`$link`, `$bytes`, blob refs)
+pub fn lex_to_json(val: Lex) -> JsonValue {
+    match val {
+        Lex::Ipld(ipld) => ipld_to_json(ipld),
+        Lex::Blob(blob) => serde_json::to_value(blob.original).unwrap_or(JsonValue::Null),
+        Lex::List(list) => JsonValue::Array(list.into_iter().map(lex_to_json).collect()),
+        Lex::Map(map) => JsonValue::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, lex_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn ipld_to_json(val: Ipld) -> JsonValue {
+    match val {
+        Ipld::Link(cid) => json!({ "$link": cid.to_string() }),
+        Ipld::List(list) => JsonValue::Array(list.into_iter().map(ipld_to_json).collect()),
+        Ipld::Map(map) => JsonValue::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, ipld_to_json(value)))
+                .collect(),
+        ),
+        Ipld::String(string) => JsonValue::String(string),
+        Ipld::Bytes(bytes) => json!({ "$bytes": STANDARD_NO_PAD.encode(bytes) }),
+        Ipld::Json(json) => json,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator() -> LexiconValidator {
+        let mut validator = LexiconValidator::default();
+        validator
+            .add_document(json!({
+                "lexicon": 1,
+                "id": "com.example.post",
+                "defs": {
+                    "main": {
+                        "type": "record",
+                        "key": "tid",
+                        "record": {
+                            "type": "object",
+                            "required": ["text", "createdAt"],
+                            "properties": {
+                                "text": { "type": "string", "maxLength": 30, "maxGraphemes": 5 },
+                                "createdAt": { "type": "string", "format": "datetime" },
+                                "embed": { "type": "union", "refs": ["#image"], "closed": true }
+                            }
+                        }
+                    },
+                    "image": {
+                        "type": "object",
+                        "required": ["image"],
+                        "properties": {
+                            "image": { "type": "blob", "accept": ["image/*"], "maxSize": 1000 }
+                        }
+                    }
+                }
+            }))
+            .unwrap();
+        validator
+    }
+
+    fn record(value: JsonValue) -> RepoRecord {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn validates_records() {
+        let validator = validator();
+        let cid = "bafkreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm";
+        let valid = record(json!({
+            "$type": "com.example.post",
+            "text": "👋🏽👋🏽",
+            "createdAt": "2024-01-01T00:00:00.000Z",
+            "embed": {
+                "$type": "com.example.post#image",
+                "image": {
+                    "$type": "blob",
+                    "ref": { "$link": cid },
+                    "mimeType": "image/png",
+                    "size": 10
+                }
+            }
+        }));
+        let validation = validator
+            .validate_record("com.example.post", &valid, true)
+            .unwrap();
+        assert_eq!(validation.status, ValidationStatus::Valid);
+        assert_eq!(
+            validation.blob_constraints[cid],
+            BlobConstraint {
+                max_size: Some(1000),
+                accept: Some(vec!["image/*".to_string()]),
+            }
+        );
+
+        let too_long = record(json!({
+            "$type": "com.example.post",
+            "text": "abcdef",
+            "createdAt": "2024-01-01T00:00:00.000Z"
+        }));
+        assert!(validator
+            .validate_record("com.example.post", &too_long, true)
+            .is_err());
+
+        let missing = record(json!({ "$type": "com.example.post", "text": "hi" }));
+        assert!(validator
+            .validate_record("com.example.post", &missing, true)
+            .is_err());
+
+        let unknown = record(json!({ "$type": "com.example.like" }));
+        assert_eq!(
+            validator
+                .validate_record("com.example.like", &unknown, false)
+                .unwrap()
+                .status,
+            ValidationStatus::Unknown
+        );
+        assert!(validator
+            .validate_record("com.example.like", &unknown, true)
+            .is_err());
+    }
+
+    #[test]
+    fn merges_constraints_of_a_blob_used_twice() {
+        let mut validator = LexiconValidator::default();
+        validator
+            .add_document(json!({
+                "lexicon": 1,
+                "id": "com.example.profile",
+                "defs": {
+                    "main": {
+                        "type": "record",
+                        "key": "literal:self",
+                        "record": {
+                            "type": "object",
+                            "properties": {
+                                "avatar": {
+                                    "type": "blob",
+                                    "accept": ["image/png", "image/jpeg"],
+                                    "maxSize": 1000
+                                },
+                                "banner": { "type": "blob", "accept": ["image/*"], "maxSize": 500 },
+                                "video": { "type": "blob", "accept": ["video/mp4"] }
+                            }
+                        }
+                    }
+                }
+            }))
+            .unwrap();
+        let cid = "bafkreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm";
+        let blob = json!({
+            "$type": "blob",
+            "ref": { "$link": cid },
+            "mimeType": "image/png",
+            "size": 10
+        });
+
+        let profile = record(json!({
+            "$type": "com.example.profile",
+            "avatar": blob,
+            "banner": blob
+        }));
+        let validation = validator
+            .validate_record("com.example.profile", &profile, true)
+            .unwrap();
+        assert_eq!(
+            validation.blob_constraints[cid],
+            BlobConstraint {
+                max_size: Some(500),
+                accept: Some(vec!["image/png".to_string(), "image/jpeg".to_string()]),
+            }
+        );
+
+        let profile = record(json!({
+            "$type": "com.example.profile",
+            "banner": blob,
+            "video": blob
+        }));
+        let validation = validator
+            .validate_record("com.example.profile", &profile, true)
+            .unwrap();
+        assert_eq!(
+            validation.blob_constraints[cid],
+            BlobConstraint {
+                max_size: Some(500),
+                accept: Some(vec![]),
+            }
+        );
+    }
+}
@@ -9,8 +9,6 @@ use crate::read_after_write::viewer::LocalViewerCreator;
 use crate::sequencer::Sequencer;
 use atrium_api::client::AtpServiceClient;
 use atrium_xrpc_client::reqwest::ReqwestClient;
-use event_emitter_rs::EventEmitter;
-use lazy_static::lazy_static;
 use rsky_identity::IdResolver;
 use tokio::sync::RwLock;
 
@@ -38,14 +36,9 @@ pub struct SharedATPAgent {
     pub app_view_agent: Option<RwLock<AtpServiceClient<ReqwestClient>>>,
 }
 
-// Use lazy_static! because the size of EventEmitter is not known at compile time
-lazy_static! {
-    // Export the emitter with `pub` keyword
-    pub static ref EVENT_EMITTER: RwLock<EventEmitter> = RwLock::new(EventEmitter::new());
-}
-
 pub mod account_manager;
 pub mod actor_store;
+pub mod admin;
 pub mod apis;
 pub mod auth_verifier;
 pub mod config;
@@ -54,15 +47,21 @@ pub mod crawlers;
 pub mod db;
 pub mod handle;
 pub mod image;
+pub mod jobs;
 pub mod lexicon;
 pub mod mailer;
+pub mod metrics;
+pub mod migration;
 pub mod models;
+pub mod oauth;
 pub mod pipethrough;
 pub mod plc;
+pub mod rate_limit;
 pub mod read_after_write;
 pub mod repo;
 pub mod schema;
 pub mod sequencer;
+pub mod telemetry;
 pub mod wallet;
 pub mod well_known;
 pub mod xrpc_server;
@@ -1,6 +1,7 @@
+use crate::jobs::{self, JobKind};
+use crate::mailer::templates::from_html;
+use crate::mailer::transport::{Mail, Sender};
 use anyhow::Result;
-use mailgun_rs::{EmailAddress, Mailgun, MailgunRegion, Message};
-use std::env;
 
 pub struct HtmlMailOpts {
     pub to: String,
@@ -14,25 +15,11 @@ impl ModerationMailer {
     pub async fn send_html(opts: HtmlMailOpts) -> Result<()> {
         let HtmlMailOpts { to, subject, html } = opts;
 
-        let recipient = EmailAddress::address(&to);
-        let message = Message {
-            to: vec![recipient],
-            subject,
-            html,
-            ..Default::default()
-        };
-
-        let client = Mailgun {
-            api_key: env::var("PDS_MAILGUN_API_KEY").unwrap(),
-            domain: env::var("PDS_MAILGUN_DOMAIN").unwrap(),
-            message,
-        };
-        let sender = EmailAddress::name_address(
-            &env::var("PDS_MODERATION_EMAIL_FROM_NAME").unwrap(),
-            &env::var("PDS_MODERATION_EMAIL_FROM_ADDRESS").unwrap(),
-        );
-
-        client.async_send(MailgunRegion::US, &sender).await?;
-        Ok(())
+        jobs::enqueue(JobKind::SendEmail(Mail {
+            from: Sender::from_env("PDS_MODERATION_EMAIL_FROM"),
+            to,
+            content: from_html(&subject, &html),
+        }))
+        .await
     }
 }
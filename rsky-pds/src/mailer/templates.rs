@@ -0,0 +1,145 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use rsky_common::escape_html;
+
+lazy_static! {
+    static ref HTML_TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+}
+
+/// A rendered message, the text part is sent alongside the HTML for clients that don't
+/// render it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MailContent {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+/// Lays out a message around a single code the user has to enter in their app
+fn render(subject: &str, paragraphs: &[String], token: &str, footer: &str) -> MailContent {
+    let html_paragraphs = paragraphs
+        .iter()
+        .map(|p| format!("<p>{}</p>", escape_html(p)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let html = format!(
+        "<!DOCTYPE html>
+<html>
+<head><meta charset=\"utf-8\"><title>{subject}</title></head>
+<body style=\"font-family: sans-serif; line-height: 1.5;\">
+{html_paragraphs}
+<p style=\"font-family: monospace; font-size: 1.5em; letter-spacing: 0.1em;\">{token}</p>
+<p style=\"color: #666;\">{footer}</p>
+</body>
+</html>
+",
+        subject = escape_html(subject),
+        token = escape_html(token),
+        footer = escape_html(footer),
+    );
+    let text = format!("{}\n\n    {token}\n\n{footer}\n", paragraphs.join("\n\n"));
+    MailContent {
+        subject: subject.to_string(),
+        html,
+        text,
+    }
+}
+
+const IGNORE_FOOTER: &str = "If you did not make this request, you can ignore this email.";
+
+pub fn reset_password(identifier: &str, token: &str) -> MailContent {
+    render(
+        "Password Reset Requested",
+        &[
+            format!("We received a request to reset the password for {identifier}."),
+            "Enter the following reset code in the app to choose a new password.".to_string(),
+        ],
+        token,
+        IGNORE_FOOTER,
+    )
+}
+
+pub fn delete_account(token: &str) -> MailContent {
+    render(
+        "Account Deletion Requested",
+        &[
+            "We received a request to delete your account.".to_string(),
+            "Enter the following code in the app to confirm. Deleting an account can't be \
+             undone."
+                .to_string(),
+        ],
+        token,
+        "If you did not make this request, you can ignore this email and your account will \
+         stay as it is.",
+    )
+}
+
+pub fn confirm_email(token: &str) -> MailContent {
+    render(
+        "Email Confirmation",
+        &["Enter the following code in the app to confirm your email address.".to_string()],
+        token,
+        IGNORE_FOOTER,
+    )
+}
+
+pub fn update_email(token: &str) -> MailContent {
+    render(
+        "Email Update Requested",
+        &[
+            "We received a request to change the email address of your account.".to_string(),
+            "Enter the following code in the app to confirm the change.".to_string(),
+        ],
+        token,
+        IGNORE_FOOTER,
+    )
+}
+
+pub fn plc_operation(token: &str) -> MailContent {
+    render(
+        "PLC Update Operation Requested",
+        &[
+            "We received a request to sign an update to your account's DID document, such as \
+             when moving it to another PDS."
+                .to_string(),
+            "Enter the following code in the app to sign the operation.".to_string(),
+        ],
+        token,
+        "If you did not make this request, someone may be trying to take over your account. \
+         Don't share this code with anyone.",
+    )
+}
+
+/// Moderation mails arrive as HTML, the text part is derived by dropping the tags
+pub fn from_html(subject: &str, html: &str) -> MailContent {
+    let text = HTML_TAG
+        .replace_all(&html.replace("<br>", "\n").replace("</p>", "</p>\n"), "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    MailContent {
+        subject: subject.to_string(),
+        html: html.to_string(),
+        text: text.trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_html_and_text() {
+        let content = reset_password("<alice>.test", "ABCDE-12345");
+        assert_eq!(content.subject, "Password Reset Requested");
+        assert!(content.html.contains("&lt;alice&gt;.test"));
+        assert!(content.html.contains("ABCDE-12345"));
+        assert!(content.text.contains("<alice>.test"));
+        assert!(content.text.contains("    ABCDE-12345"));
+
+        let content = from_html("Notice", "<p>Your post &amp; reply</p><p>were removed</p>");
+        assert_eq!(content.text, "Your post & reply\nwere removed");
+    }
+}
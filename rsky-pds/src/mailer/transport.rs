@@ -0,0 +1,203 @@
+use crate::mailer::templates::MailContent;
+use anyhow::{bail, Result};
+use lazy_static::lazy_static;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::{
+    AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message as LettreMessage,
+    Tokio1Executor,
+};
+use mailgun_rs::{EmailAddress, Mailgun, MailgunRegion, Message as MailgunMessage};
+use rsky_common::env::env_str;
+
+lazy_static! {
+    static ref TRANSPORT: Option<Box<dyn MailTransport>> = match transport_from_env() {
+        Ok(transport) => transport,
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: invalid mail transport config; err: {error}");
+            None
+        }
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sender {
+    pub name: Option<String>,
+    pub address: String,
+}
+
+impl Sender {
+    /// Reads `{prefix}_NAME` and `{prefix}_ADDRESS`, the address defaults to noreply at the
+    /// PDS hostname
+    pub fn from_env(prefix: &str) -> Self {
+        Sender {
+            name: env_str(&format!("{prefix}_NAME")),
+            address: env_str(&format!("{prefix}_ADDRESS")).unwrap_or(format!(
+                "noreply@{}",
+                env_str("PDS_HOSTNAME").unwrap_or("localhost".to_string())
+            )),
+        }
+    }
+
+    fn mailbox(&self) -> Result<Mailbox> {
+        Ok(Mailbox::new(self.name.clone(), self.address.parse()?))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mail {
+    pub from: Sender,
+    pub to: String,
+    pub content: MailContent,
+}
+
+impl Mail {
+    fn to_message(&self) -> Result<LettreMessage> {
+        Ok(LettreMessage::builder()
+            .from(self.from.mailbox()?)
+            .to(self.to.parse()?)
+            .subject(&self.content.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                self.content.text.clone(),
+                self.content.html.clone(),
+            ))?)
+    }
+}
+
+#[rocket::async_trait]
+pub trait MailTransport: Send + Sync {
+    async fn send(&self, mail: &Mail) -> Result<()>;
+}
+
+/// Delivers through an SMTP relay. `smtps://` URLs use implicit TLS, `smtp://` URLs with
+/// `?tls=required` upgrade the connection with STARTTLS.
+pub struct SmtpMailTransport {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailTransport {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(SmtpMailTransport {
+            transport: AsyncSmtpTransport::<Tokio1Executor>::from_url(url)?.build(),
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl MailTransport for SmtpMailTransport {
+    async fn send(&self, mail: &Mail) -> Result<()> {
+        self.transport.send(mail.to_message()?).await?;
+        Ok(())
+    }
+}
+
+/// Writes every rendered message as an `.eml` file into a directory instead of sending it,
+/// meant for development
+pub struct FileMailTransport {
+    transport: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl FileMailTransport {
+    pub fn new(dir: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        Ok(FileMailTransport {
+            transport: AsyncFileTransport::<Tokio1Executor>::new(dir),
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl MailTransport for FileMailTransport {
+    async fn send(&self, mail: &Mail) -> Result<()> {
+        let id = self.transport.send(mail.to_message()?).await?;
+        tracing::info!("@LOG: wrote email `{}` to {id}.eml", mail.content.subject);
+        Ok(())
+    }
+}
+
+pub struct MailgunMailTransport {
+    pub api_key: String,
+    pub domain: String,
+}
+
+#[rocket::async_trait]
+impl MailTransport for MailgunMailTransport {
+    async fn send(&self, mail: &Mail) -> Result<()> {
+        let message = MailgunMessage {
+            to: vec![EmailAddress::address(&mail.to)],
+            subject: mail.content.subject.clone(),
+            text: mail.content.text.clone(),
+            html: mail.content.html.clone(),
+            ..Default::default()
+        };
+        let client = Mailgun {
+            api_key: self.api_key.clone(),
+            domain: self.domain.clone(),
+            message,
+        };
+        let sender = match &mail.from.name {
+            Some(name) => EmailAddress::name_address(name, &mail.from.address),
+            None => EmailAddress::address(&mail.from.address),
+        };
+        client.async_send(MailgunRegion::US, &sender).await?;
+        Ok(())
+    }
+}
+
+/// Picks the transport from the environment: `PDS_EMAIL_SMTP_URL`, then
+/// `PDS_EMAIL_DROP_DIRECTORY`, then `PDS_MAILGUN_API_KEY` with `PDS_MAILGUN_DOMAIN`
+pub fn transport_from_env() -> Result<Option<Box<dyn MailTransport>>> {
+    if let Some(url) = env_str("PDS_EMAIL_SMTP_URL") {
+        return Ok(Some(Box::new(SmtpMailTransport::new(&url)?)));
+    }
+    if let Some(dir) = env_str("PDS_EMAIL_DROP_DIRECTORY") {
+        return Ok(Some(Box::new(FileMailTransport::new(&dir)?)));
+    }
+    match (
+        env_str("PDS_MAILGUN_API_KEY"),
+        env_str("PDS_MAILGUN_DOMAIN"),
+    ) {
+        (Some(api_key), Some(domain)) => {
+            Ok(Some(Box::new(MailgunMailTransport { api_key, domain })))
+        }
+        (None, None) => Ok(None),
+        _ => bail!("PDS_MAILGUN_API_KEY and PDS_MAILGUN_DOMAIN must be set together"),
+    }
+}
+
+pub async fn deliver(mail: Mail) -> Result<()> {
+    match TRANSPORT.as_ref() {
+        Some(transport) => transport.send(&mail).await,
+        None => bail!("No mail transport configured"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_transport_writes_messages() {
+        let dir = std::env::temp_dir().join(format!("pds-mail-{}", rsky_common::get_random_str()));
+        let transport = FileMailTransport::new(dir.to_str().unwrap()).unwrap();
+        let mail = Mail {
+            from: Sender {
+                name: Some("PDS".to_string()),
+                address: "noreply@pds.test".to_string(),
+            },
+            to: "alice@example.com".to_string(),
+            content: MailContent {
+                subject: "Hello".to_string(),
+                html: "<p>Hello</p>".to_string(),
+                text: "Hello".to_string(),
+            },
+        };
+        transport.send(&mail).await.unwrap();
+
+        let files = std::fs::read_dir(&dir).unwrap().collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        let eml = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        assert!(eml.contains("To: alice@example.com"));
+        assert!(eml.contains("Subject: Hello"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,6 +1,9 @@
 #[macro_use]
 extern crate rocket;
 use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 use atrium_api::client::AtpServiceClient;
 use atrium_xrpc_client::reqwest::ReqwestClientBuilder;
@@ -9,7 +12,7 @@ use diesel::sql_types::Int4;
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
 use dotenvy::dotenv;
 use rocket::data::{Limits, ToByteUnit};
-use rocket::fairing::{Fairing, Info, Kind};
+use rocket::fairing::{AdHoc, Fairing, Info, Kind};
 use rocket::figment::util::map;
 use rocket::figment::value::{Map, Value};
 use rocket::http::{Header, Status};
@@ -17,18 +20,37 @@ use rocket::response::status;
 use rocket::serde::json::Json;
 use rocket::shield::{NoSniff, Shield};
 use rocket::{Request, Response};
-use rsky_common::env::env_list;
+use rsky_common::env::{env_int, env_list, env_str};
 use rsky_identity::IdResolver;
-use rsky_identity::types::{DidCache, IdentityResolverOpts};
+use rsky_identity::cache::disk::DiskCache;
+use rsky_identity::cache::memory::MemoryCache;
+use rsky_identity::cache::postgres::PostgresCache;
+use rsky_identity::cache::{CacheTtls, DidCache};
+use rsky_identity::types::IdentityResolverOpts;
 use rsky_pds::account_manager::AccountManager;
+use rsky_pds::actor_store::blobstore::BlobStoreCreator;
+use rsky_pds::admin;
 use rsky_pds::apis::firefly::providers::get_firefly_provider;
 use rsky_pds::apis::*;
-use rsky_pds::config::env_to_cfg;
+use rsky_pds::config::{DidCacheStoreConfig, IdentityConfig, env_to_cfg};
 use rsky_pds::crawlers::Crawlers;
 use rsky_pds::db::{DbConn, establish_connection};
+use rsky_pds::image;
+use rsky_pds::jobs;
+use rsky_pds::jobs::JobContext;
+use rsky_pds::jobs::worker::JobWorker;
+use rsky_pds::metrics;
+use rsky_pds::metrics::fairing::RequestMetrics;
+use rsky_pds::migration;
+use rsky_pds::oauth;
+use rsky_pds::rate_limit;
+use rsky_pds::rate_limit::RateLimiter;
+use rsky_pds::rate_limit::fairing::RateLimits;
 use rsky_pds::read_after_write::viewer::{LocalViewer, LocalViewerCreatorParams};
 use rsky_pds::sequencer::Sequencer;
+use rsky_pds::telemetry;
 use rsky_pds::wallet::WalletService;
+use rsky_pds::wallet::history::WalletHistoryIndexer;
 use rsky_pds::well_known::well_known;
 use rsky_pds::{
     APP_USER_AGENT, SharedATPAgent, SharedIdResolver, SharedLocalViewer, SharedSequencer,
@@ -135,16 +157,54 @@ impl Fairing for CORS {
             "POST, GET, PATCH, OPTIONS, DELETE",
         ));
         response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
+        response.set_header(Header::new(
+            "Access-Control-Expose-Headers",
+            "DPoP-Nonce, RateLimit-Limit, RateLimit-Remaining, RateLimit-Reset, RateLimit-Policy",
+        ));
         response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
     }
 }
 
+/// How often cached identities past their TTL are dropped, since lookups of handles that don't
+/// exist are cached too
+const IDENTITY_CACHE_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Falls back to caching in memory when the configured store can't be opened
+async fn did_cache(cfg: &IdentityConfig) -> Arc<dyn DidCache> {
+    let ttls = CacheTtls::new(
+        Some(Duration::from_millis(cfg.cache_state_ttl)),
+        Some(Duration::from_millis(cfg.cache_max_ttl)),
+        Some(Duration::from_millis(cfg.cache_negative_ttl)),
+    );
+    let store: anyhow::Result<Arc<dyn DidCache>> = match &cfg.cache_store {
+        DidCacheStoreConfig::Memory => Ok(Arc::new(MemoryCache::new(ttls))),
+        DidCacheStoreConfig::Postgres => PostgresCache::connect(
+            env::var("DATABASE_URL").unwrap_or("".into()),
+            "pds".to_string(),
+            ttls,
+        )
+        .await
+        .map(|cache| Arc::new(cache) as Arc<dyn DidCache>),
+        DidCacheStoreConfig::Disk { dir } => DiskCache::new(PathBuf::from(dir), ttls)
+            .await
+            .map(|cache| Arc::new(cache) as Arc<dyn DidCache>),
+    };
+    match store {
+        Ok(store) => store,
+        Err(error) => {
+            tracing::error!(
+                "@LOG: ERROR: failed to open DID cache, caching in memory; err: {error}"
+            );
+            Arc::new(MemoryCache::new(ttls))
+        }
+    }
+}
+
 #[launch]
 async fn rocket() -> _ {
     dotenv().ok();
 
-    let subscriber = tracing_subscriber::FmtSubscriber::new();
-    tracing::subscriber::set_global_default(subscriber).unwrap();
+    let tracer_provider = telemetry::init_tracing().unwrap();
 
     let conn = &mut establish_connection().unwrap();
     conn.run_pending_migrations(MIGRATIONS).unwrap();
@@ -157,15 +217,23 @@ async fn rocket() -> _ {
         "timeout" => 30.into(),
     };
 
+    let cfg = env_to_cfg();
+    // Rocket trusts X-Real-IP by default, which clients can set to dodge per ip rate limits
+    let ip_header: Value = match cfg.rate_limits.ip_header {
+        Some(ref header) => header.as_str().into(),
+        None => false.into(),
+    };
+
     let figment = rocket::Config::figment()
         .merge(("databases", map!["pg_db" => db]))
-        .merge(("limits", Limits::default().limit("file", 100.mebibytes())));
-    let cfg = env_to_cfg();
+        .merge(("limits", Limits::default().limit("file", 100.mebibytes())))
+        .merge(("ip_header", ip_header));
 
     let sequencer = SharedSequencer {
         sequencer: RwLock::new(Sequencer::new(
             Crawlers::new(cfg.service.hostname.clone(), cfg.crawlers.clone()),
             None,
+            cfg.subscription.max_buffer as usize,
         )),
     };
     let mut background_sequencer = sequencer.sequencer.write().await.clone();
@@ -175,6 +243,19 @@ async fn rocket() -> _ {
         .endpoint_url(env::var("AWS_ENDPOINT").unwrap_or("localhost".to_owned()))
         .load()
         .await;
+    let blobstore = BlobStoreCreator::new(&cfg.blobstore, aws_sdk_config);
+
+    let identity_cache = did_cache(&cfg.identity).await;
+    let purged_cache = identity_cache.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(IDENTITY_CACHE_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(error) = purged_cache.purge_expired().await {
+                tracing::error!("@LOG: ERROR: failed to purge DID cache; err: {error}");
+            }
+        }
+    });
 
     let id_resolver = SharedIdResolver {
         id_resolver: RwLock::new(IdResolver::new(IdentityResolverOpts {
@@ -182,8 +263,9 @@ async fn rocket() -> _ {
             plc_url: Some(
                 env::var("PDS_DID_PLC_URL").unwrap_or("https://plc.directory".to_owned()),
             ),
-            did_cache: Some(DidCache::new(None, None)),
+            did_cache: Some(identity_cache),
             backup_nameservers: Some(env_list("PDS_HANDLE_BACKUP_NAMESERVERS")),
+            allow_local_http: cfg.service.dev_mode,
         })),
     };
 
@@ -228,19 +310,55 @@ async fn rocket() -> _ {
 
     let shield = Shield::default().enable(NoSniff::Enable);
 
-    let wallet_service = WalletService {};
-    let firefly_provider = get_firefly_provider().unwrap();
+    // Wallets are enabled by setting their encryption key, without it the wallet API isn't mounted
+    let wallet = match env_str("PDS_WALLET_ENCRYPTION_KEY_HEX") {
+        None => {
+            tracing::info!("PDS_WALLET_ENCRYPTION_KEY_HEX not set, wallet API disabled");
+            None
+        }
+        Some(_) => {
+            let wallet_service = WalletService::from_env().unwrap();
+            let firefly_provider = get_firefly_provider().unwrap();
+            let wallet_indexer = WalletHistoryIndexer::new(firefly_provider.clone());
+            let wallet_index_interval =
+                Duration::from_secs(env_int("PDS_WALLET_INDEX_INTERVAL_SECS").unwrap_or(5) as u64);
+            tokio::spawn(async move { wallet_indexer.run(wallet_index_interval).await });
+            Some((wallet_service, firefly_provider))
+        }
+    };
+
+    let job_worker = JobWorker::new(JobContext {
+        blobstore: blobstore.clone(),
+    });
+    let job_poll_interval =
+        Duration::from_secs(env_int("PDS_JOB_POLL_INTERVAL_SECS").unwrap_or(5) as u64);
+    tokio::spawn(async move { job_worker.run(job_poll_interval).await });
+
+    let rate_limiter = RateLimiter::new(cfg.rate_limits.clone()).await;
 
-    rocket::custom(figment)
+    let rocket = rocket::custom(figment)
+        .mount(
+            "/api/admin/",
+            routes![
+                admin::routes::search,
+                admin::routes::bulk_action,
+                admin::routes::list_actions,
+            ]
+        )
+        .mount(
+            "/api/jobs/",
+            routes![
+                jobs::routes::list_dead_jobs,
+                jobs::routes::retry_dead_job,
+                jobs::routes::discard_dead_job,
+            ]
+        )
         .mount(
-            "/api/wallet/",
+            "/api/migration/",
             routes![
-                firefly::create_transfer_request::create_transfer_request,
-                firefly::fulfill_transfer_request::fulfill_transfer_request,
-                firefly::get_transfer_request::get_transfer_request,
-                firefly::get_wallet_state_and_history::get_wallet_state_and_history,
-                firefly::transfer::transfer,
-                firefly::transactions::get_transactions,
+                migration::routes::dry_run,
+                migration::routes::start_migration,
+                migration::routes::get_migration,
             ]
         )
         .mount(
@@ -254,6 +372,7 @@ async fn rocket() -> _ {
                 com::atproto::admin::disable_invite_codes::disable_invite_codes,
                 com::atproto::admin::enable_account_invites::enable_account_invites,
                 com::atproto::admin::get_account_info::get_account_info,
+                com::atproto::admin::get_account_infos::get_account_infos,
                 com::atproto::admin::get_invite_codes::get_invite_codes,
                 com::atproto::admin::get_subject_status::get_subject_status,
                 com::atproto::admin::send_email::send_email,
@@ -267,6 +386,8 @@ async fn rocket() -> _ {
                 com::atproto::identity::request_plc_operation_signature::request_plc_operation_signature,
                 com::atproto::identity::sign_plc_operation::sign_plc_operation,
                 com::atproto::identity::submit_plc_operation::submit_plc_operation,
+                com::atproto::label::query_labels::query_labels,
+                com::atproto::moderation::create_report::create_report,
                 com::atproto::repo::apply_writes::apply_writes,
                 com::atproto::repo::create_record::create_record,
                 com::atproto::repo::delete_record::delete_record,
@@ -304,13 +425,17 @@ async fn rocket() -> _ {
                 com::atproto::server::reserve_signing_key::reserve_signing_key,
                 com::atproto::sync::get_blob::get_blob,
                 com::atproto::sync::get_blocks::get_blocks,
+                com::atproto::sync::get_checkout::get_checkout,
+                com::atproto::sync::get_head::get_head,
                 com::atproto::sync::get_latest_commit::get_latest_commit,
                 com::atproto::sync::get_record::get_record,
                 com::atproto::sync::get_repo::get_repo,
                 com::atproto::sync::get_repo_status::get_repo_status,
                 com::atproto::sync::list_blobs::list_blobs,
                 com::atproto::sync::list_repos::list_repos,
+                com::atproto::sync::notify_of_update::notify_of_update,
                 com::atproto::sync::subscribe_repos::subscribe_repos,
+                com::atproto::temp::check_signup_queue::check_signup_queue,
                 app::bsky::actor::get_preferences::get_preferences,
                 app::bsky::actor::get_profile::get_profile,
                 app::bsky::actor::get_profiles::get_profiles,
@@ -324,19 +449,61 @@ async fn rocket() -> _ {
                 bsky_api_get_forwarder,
                 bsky_api_post_forwarder,
                 well_known,
+                image::server::get_image,
+                oauth::routes::oauth_protected_resource,
+                oauth::routes::oauth_authorization_server,
+                oauth::routes::par,
+                oauth::routes::authorize,
+                oauth::routes::sign_in,
+                oauth::routes::token,
+                oauth::routes::revoke,
+                rate_limit::rate_limit_exceeded,
+                metrics::routes::metrics,
                 all_options
             ],
         )
         .register("/", catchers![default_catcher])
         .attach(CORS)
+        .attach(RequestMetrics)
+        .attach(RateLimits)
         .attach(DbConn::fairing())
         .attach(shield)
+        .attach(AdHoc::on_shutdown("Flush traces", |_| {
+            Box::pin(async move {
+                if let Some(tracer_provider) = tracer_provider {
+                    if let Err(error) = tracer_provider.shutdown() {
+                        tracing::error!("Failed to flush traces: {error}");
+                    }
+                }
+            })
+        }))
         .manage(sequencer)
-        .manage(aws_sdk_config)
+        .manage(blobstore)
         .manage(id_resolver)
         .manage(cfg)
         .manage(local_viewer)
         .manage(app_view_agent)
-        .manage(wallet_service)
-        .manage(firefly_provider)
+        .manage(rate_limiter);
+
+    match wallet {
+        None => rocket,
+        Some((wallet_service, firefly_provider)) => rocket
+            .mount(
+                "/api/wallet/",
+                routes![
+                    firefly::cancel_transfer_request::cancel_transfer_request,
+                    firefly::create_transfer_request::create_transfer_request,
+                    firefly::fulfill_transfer_request::fulfill_transfer_request,
+                    firefly::get_transfer_request::get_transfer_request,
+                    firefly::get_wallet_state_and_history::get_wallet_state_and_history,
+                    firefly::import_wallet::import_wallet,
+                    firefly::transfer::transfer,
+                    firefly::transfer::submit_transfer,
+                    firefly::transfer::get_transfer,
+                    firefly::transactions::get_transactions,
+                ],
+            )
+            .manage(wallet_service)
+            .manage(firefly_provider),
+    }
 }
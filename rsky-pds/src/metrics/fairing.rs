@@ -0,0 +1,39 @@
+use crate::metrics::{HTTP_REQUESTS, HTTP_REQUEST_DURATION};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Data, Request, Response};
+use std::time::Instant;
+
+struct RequestStart(Instant);
+
+/// Counts and times every request by the route it matched. Unmatched requests share one label so
+/// scanners can't blow up the number of series.
+pub struct RequestMetrics;
+
+#[rocket::async_trait]
+impl Fairing for RequestMetrics {
+    fn info(&self) -> Info {
+        Info {
+            name: "Record request metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        req.local_cache(|| RequestStart(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let started = req.local_cache(|| RequestStart(Instant::now()));
+        let method = req.method().as_str();
+        let route = match req.route() {
+            Some(route) => route.uri.to_string(),
+            None => "unmatched".to_string(),
+        };
+        HTTP_REQUESTS
+            .with_label_values(&[method, &route, &res.status().code.to_string()])
+            .inc();
+        HTTP_REQUEST_DURATION
+            .with_label_values(&[method, &route])
+            .observe(started.0.elapsed().as_secs_f64());
+    }
+}
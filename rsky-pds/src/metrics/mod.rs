@@ -0,0 +1,177 @@
+use crate::db::establish_connection;
+use anyhow::Result;
+use diesel::*;
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+};
+use rsky_common::time::from_str_to_millis;
+use std::time::Instant;
+
+// seconds, from a fast xrpc read to a slow repo import
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+lazy_static! {
+    pub static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "pds_http_requests_total",
+        "Requests served, by route and response status",
+        &["method", "route", "status"]
+    )
+    .unwrap();
+    pub static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "pds_http_request_duration_seconds",
+        "Time spent serving a request, by route",
+        &["method", "route"],
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap();
+    pub static ref REPO_WRITES: IntCounterVec = register_int_counter_vec!(
+        "pds_repo_writes_total",
+        "Record writes committed to repos, by action",
+        &["action"]
+    )
+    .unwrap();
+    pub static ref SEQUENCER_LAST_SEQ: IntGauge = register_int_gauge!(
+        "pds_sequencer_last_seq",
+        "Last sequence number fanned out to firehose subscribers"
+    )
+    .unwrap();
+    pub static ref SEQUENCER_LAG: Histogram = register_histogram!(
+        "pds_sequencer_lag_seconds",
+        "Time from an event being sequenced to it being fanned out to subscribers",
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap();
+    pub static ref FIREHOSE_SUBSCRIBERS: IntGauge = register_int_gauge!(
+        "pds_firehose_subscribers",
+        "Open subscribeRepos connections"
+    )
+    .unwrap();
+    pub static ref FIREHOSE_EVENTS_SENT: IntCounter = register_int_counter!(
+        "pds_firehose_events_sent_total",
+        "Events sent to subscribeRepos connections"
+    )
+    .unwrap();
+    pub static ref BLOB_BYTES_STORED: IntGauge = register_int_gauge!(
+        "pds_blob_bytes_stored",
+        "Total size of the blobs stored for all accounts"
+    )
+    .unwrap();
+    pub static ref FIREFLY_DEPLOY_DURATION: HistogramVec = register_histogram_vec!(
+        "pds_firefly_deploy_duration_seconds",
+        "Time taken by Firefly deploys, by operation",
+        &["operation"],
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap();
+    pub static ref FIREFLY_DEPLOY_FAILURES: IntCounterVec = register_int_counter_vec!(
+        "pds_firefly_deploy_failures_total",
+        "Firefly deploys that failed or errored on chain, by operation",
+        &["operation"]
+    )
+    .unwrap();
+}
+
+/// Records how long a Firefly deploy took and whether it went through
+pub fn observe_deploy(operation: &str, started: Instant, succeeded: bool) {
+    FIREFLY_DEPLOY_DURATION
+        .with_label_values(&[operation])
+        .observe(started.elapsed().as_secs_f64());
+    if !succeeded {
+        FIREFLY_DEPLOY_FAILURES
+            .with_label_values(&[operation])
+            .inc();
+    }
+}
+
+/// Records an event being fanned out at `now_ms`. Lag is skipped for an event whose sequencing
+/// time doesn't parse rather than failing the fan out.
+pub fn observe_sequenced(seq: i64, time: &String, now_ms: i64) {
+    SEQUENCER_LAST_SEQ.set(seq);
+    match from_str_to_millis(time) {
+        Ok(sequenced_ms) => {
+            SEQUENCER_LAG.observe((now_ms - sequenced_ms).max(0) as f64 / 1000.0);
+        }
+        Err(error) => {
+            tracing::warn!("@LOG: WARN: unreadable time `{time}` on event {seq}; err: {error}")
+        }
+    }
+}
+
+/// Counts an open firehose connection for as long as it's held
+pub struct FirehoseSubscriber;
+
+impl FirehoseSubscriber {
+    pub fn connect() -> Self {
+        FIREHOSE_SUBSCRIBERS.inc();
+        FirehoseSubscriber
+    }
+}
+
+impl Drop for FirehoseSubscriber {
+    fn drop(&mut self) {
+        FIREHOSE_SUBSCRIBERS.dec();
+    }
+}
+
+/// Gauges that are cheaper to read from the database on scrape than to keep up to date
+pub async fn refresh_stored_gauges() -> Result<()> {
+    use crate::schema::pds::blob::dsl as BlobSchema;
+    let conn = &mut establish_connection()?;
+
+    let blob_bytes: Option<i64> = BlobSchema::blob
+        .select(dsl::sum(BlobSchema::size))
+        .first(conn)?;
+    BLOB_BYTES_STORED.set(blob_bytes.unwrap_or(0));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn sequenced_events_record_lag_unless_time_is_unreadable() {
+        let observed = SEQUENCER_LAG.get_sample_count();
+        observe_sequenced(
+            41,
+            &"2025-01-01T00:00:00.000Z".to_string(),
+            1_735_689_601_500,
+        );
+        assert_eq!(SEQUENCER_LAST_SEQ.get(), 41);
+        assert_eq!(SEQUENCER_LAG.get_sample_count(), observed + 1);
+
+        observe_sequenced(42, &"not a time".to_string(), 1_735_689_601_500);
+        assert_eq!(SEQUENCER_LAST_SEQ.get(), 42);
+        assert_eq!(SEQUENCER_LAG.get_sample_count(), observed + 1);
+    }
+
+    #[test]
+    fn failed_deploys_are_counted() {
+        let operation = "metrics_test";
+        let started = Instant::now() - Duration::from_millis(20);
+        observe_deploy(operation, started, true);
+        observe_deploy(operation, started, false);
+        let timed = FIREFLY_DEPLOY_DURATION.with_label_values(&[operation]);
+        assert_eq!(timed.get_sample_count(), 2);
+        assert!(timed.get_sample_sum() >= 0.04);
+        let failures = FIREFLY_DEPLOY_FAILURES.with_label_values(&[operation]);
+        assert_eq!(failures.get(), 1);
+    }
+
+    #[test]
+    fn firehose_subscribers_are_counted_while_connected() {
+        let connected = FIREHOSE_SUBSCRIBERS.get();
+        let subscriber = FirehoseSubscriber::connect();
+        assert_eq!(FIREHOSE_SUBSCRIBERS.get(), connected + 1);
+        drop(subscriber);
+        assert_eq!(FIREHOSE_SUBSCRIBERS.get(), connected);
+    }
+}
+
+pub mod fairing;
+pub mod routes;
@@ -0,0 +1,21 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::metrics::refresh_stored_gauges;
+use prometheus::{Encoder, TextEncoder};
+
+/// Prometheus scrape endpoint, in the text exposition format
+#[tracing::instrument(skip_all)]
+#[rocket::get("/metrics")]
+pub async fn metrics(_auth: AdminToken) -> Result<String, ApiError> {
+    if let Err(error) = refresh_stored_gauges().await {
+        tracing::error!("@LOG: ERROR: failed to refresh metrics; err: {error}");
+    }
+    let mut buffer = Vec::new();
+    match TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        Ok(()) => Ok(String::from_utf8_lossy(&buffer).into_owned()),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::APP_USER_AGENT;
+use anyhow::Result;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::{Body, Method, RequestBuilder, Response};
+use rsky_lexicon::com::atproto::server::{CreateSessionInput, CreateSessionOutput};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{nsid} failed with {status}: {error}: {message}")]
+pub struct XrpcError {
+    pub nsid: String,
+    pub status: u16,
+    pub error: String,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct XrpcErrorBody {
+    error: Option<String>,
+    message: Option<String>,
+}
+
+/// Response body of a raw query, handed to an upload as it arrives instead of being buffered
+pub struct StreamedBody {
+    pub body: Body,
+    pub content_type: Option<String>,
+    pub content_length: Option<u64>,
+}
+
+/// Minimal XRPC client for talking to the two PDS instances taking part in a migration
+pub struct XrpcClient {
+    pub service: String,
+    pub access_jwt: Option<String>,
+    http: reqwest::Client,
+}
+
+impl XrpcClient {
+    pub fn new(service: &str) -> Result<Self> {
+        Ok(XrpcClient {
+            service: service.trim_end_matches("/").to_string(),
+            access_jwt: None,
+            // repos and blobs can be large, so only connecting is bounded
+            http: reqwest::Client::builder()
+                .user_agent(APP_USER_AGENT)
+                .connect_timeout(Duration::from_secs(10))
+                .build()?,
+        })
+    }
+
+    fn request(&self, method: Method, nsid: &str, token: Option<&String>) -> RequestBuilder {
+        let req = self
+            .http
+            .request(method, format!("{}/xrpc/{nsid}", self.service));
+        match token.or(self.access_jwt.as_ref()) {
+            Some(token) => req.bearer_auth(token),
+            None => req,
+        }
+    }
+
+    async fn send(nsid: &str, req: RequestBuilder) -> Result<Response> {
+        let res = req.send().await?;
+        let status = res.status();
+        if status.is_success() {
+            return Ok(res);
+        }
+        let body = res.json::<XrpcErrorBody>().await.ok();
+        let (error, message) = match body {
+            Some(body) => (body.error, body.message),
+            None => (None, None),
+        };
+        Err(XrpcError {
+            nsid: nsid.to_string(),
+            status: status.as_u16(),
+            error: error.unwrap_or("Unknown".to_string()),
+            message: message.unwrap_or(status.to_string()),
+        }
+        .into())
+    }
+
+    pub async fn login(&mut self, identifier: &str, password: &str) -> Result<CreateSessionOutput> {
+        let session: CreateSessionOutput = self
+            .procedure(
+                "com.atproto.server.createSession",
+                &CreateSessionInput {
+                    identifier: identifier.to_string(),
+                    password: password.to_string(),
+                },
+            )
+            .await?;
+        self.access_jwt = Some(session.access_jwt.clone());
+        Ok(session)
+    }
+
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        nsid: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T> {
+        let req = self.request(Method::GET, nsid, None).query(params);
+        Ok(Self::send(nsid, req).await?.json::<T>().await?)
+    }
+
+    /// Raw response body with its content type, for `getRepo` and `getBlob`
+    pub async fn query_stream(&self, nsid: &str, params: &[(&str, &str)]) -> Result<StreamedBody> {
+        let req = self.request(Method::GET, nsid, None).query(params);
+        let res = Self::send(nsid, req).await?;
+        let content_type = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let content_length = res.content_length();
+        Ok(StreamedBody {
+            body: Body::wrap_stream(res.bytes_stream()),
+            content_type,
+            content_length,
+        })
+    }
+
+    pub async fn procedure<T: DeserializeOwned>(
+        &self,
+        nsid: &str,
+        body: &impl Serialize,
+    ) -> Result<T> {
+        self.procedure_as(nsid, body, None).await
+    }
+
+    /// Like `procedure` but authenticated with the given token instead of the session
+    pub async fn procedure_as<T: DeserializeOwned>(
+        &self,
+        nsid: &str,
+        body: &impl Serialize,
+        token: Option<&String>,
+    ) -> Result<T> {
+        let req = self.request(Method::POST, nsid, token).json(body);
+        Ok(Self::send(nsid, req).await?.json::<T>().await?)
+    }
+
+    /// For procedures without an output body
+    pub async fn call(&self, nsid: &str, body: &impl Serialize) -> Result<()> {
+        let req = self.request(Method::POST, nsid, None).json(body);
+        Self::send(nsid, req).await?;
+        Ok(())
+    }
+
+    pub async fn upload(
+        &self,
+        nsid: &str,
+        streamed: StreamedBody,
+        content_type: &str,
+    ) -> Result<()> {
+        let mut req = self
+            .request(Method::POST, nsid, None)
+            .header(CONTENT_TYPE, content_type);
+        if let Some(content_length) = streamed.content_length {
+            req = req.header(CONTENT_LENGTH, content_length);
+        }
+        Self::send(nsid, req.body(streamed.body)).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,570 @@
+use crate::migration::client::XrpcClient;
+use crate::models::AccountMigration;
+use anyhow::{bail, Result};
+use lazy_static::lazy_static;
+use rsky_lexicon::com::atproto::identity::{
+    GetRecommendedDidCredentialsResponse, SignPlcOperationRequest, SubmitPlcOperationRequest,
+};
+use rsky_lexicon::com::atproto::repo::DescribeRepoOutput;
+use rsky_lexicon::com::atproto::server::{
+    CreateAccountInput, CreateAccountOutput, DescribeServerOutput, GetServiceAuthOutput,
+};
+use rsky_lexicon::com::atproto::sync::{GetLatestCommitOutput, ListBlobsOutput};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+lazy_static! {
+    // dids with a migration running in this process
+    static ref RUNNING: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum MigrationStep {
+    CreateAccount,
+    ImportRepo,
+    ImportBlobs,
+    MigratePreferences,
+    RequestPlcSignature,
+    SubmitPlcOperation,
+    ActivateAccount,
+    DeactivateOldAccount,
+    Done,
+}
+
+impl MigrationStep {
+    pub const ALL: [MigrationStep; 9] = [
+        MigrationStep::CreateAccount,
+        MigrationStep::ImportRepo,
+        MigrationStep::ImportBlobs,
+        MigrationStep::MigratePreferences,
+        MigrationStep::RequestPlcSignature,
+        MigrationStep::SubmitPlcOperation,
+        MigrationStep::ActivateAccount,
+        MigrationStep::DeactivateOldAccount,
+        MigrationStep::Done,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationStep::CreateAccount => "create_account",
+            MigrationStep::ImportRepo => "import_repo",
+            MigrationStep::ImportBlobs => "import_blobs",
+            MigrationStep::MigratePreferences => "migrate_preferences",
+            MigrationStep::RequestPlcSignature => "request_plc_signature",
+            MigrationStep::SubmitPlcOperation => "submit_plc_operation",
+            MigrationStep::ActivateAccount => "activate_account",
+            MigrationStep::DeactivateOldAccount => "deactivate_old_account",
+            MigrationStep::Done => "done",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match Self::ALL.iter().position(|s| s == self) {
+            Some(i) if i + 1 < Self::ALL.len() => Self::ALL[i + 1],
+            _ => MigrationStep::Done,
+        }
+    }
+}
+
+impl FromStr for MigrationStep {
+    type Err = anyhow::Error;
+
+    fn from_str(step: &str) -> Result<Self> {
+        match Self::ALL.iter().find(|s| s.as_str() == step) {
+            Some(step) => Ok(*step),
+            None => bail!("Invalid migration step: `{step}`"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MigrationStatus {
+    Running,
+    // the old PDS mailed a PLC signing token, the migration continues once it's supplied
+    AwaitingPlcToken,
+    Failed,
+    Done,
+}
+
+impl MigrationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationStatus::Running => "running",
+            MigrationStatus::AwaitingPlcToken => "awaiting_plc_token",
+            MigrationStatus::Failed => "failed",
+            MigrationStatus::Done => "done",
+        }
+    }
+}
+
+/// What's needed to move an account from `old_pds` to `new_pds`. Passwords are never stored,
+/// resuming a migration takes the same input again.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationInput {
+    pub old_pds: String,
+    /// Handle or did on the old PDS
+    pub identifier: String,
+    pub password: String,
+    pub new_pds: String,
+    pub handle: String,
+    pub email: String,
+    /// Password for the new account, the old one is reused when left out
+    pub new_password: Option<String>,
+    pub invite_code: Option<String>,
+    /// Token the old PDS emailed for signing the PLC operation
+    pub plc_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunReport {
+    pub did: String,
+    pub old_handle: String,
+    pub new_handle: String,
+    pub new_pds_did: String,
+    pub repo_rev: String,
+    pub collections: Vec<String>,
+    pub blobs: usize,
+    pub preferences: usize,
+    pub steps: Vec<String>,
+    pub progress: Option<AccountMigration>,
+    pub warnings: Vec<String>,
+}
+
+pub fn is_running(did: &String) -> bool {
+    RUNNING.lock().unwrap().contains(did)
+}
+
+pub struct Migrator {
+    pub did: String,
+    input: MigrationInput,
+    old: XrpcClient,
+    new: XrpcClient,
+}
+
+impl Migrator {
+    /// Signs into the old PDS to learn which account is being migrated
+    pub async fn new(input: MigrationInput) -> Result<Self> {
+        let mut old = XrpcClient::new(&input.old_pds)?;
+        let session = old.login(&input.identifier, &input.password).await?;
+        Ok(Migrator {
+            did: session.did,
+            new: XrpcClient::new(&input.new_pds)?,
+            old,
+            input,
+        })
+    }
+
+    pub fn old_service(&self) -> String {
+        self.old.service.clone()
+    }
+
+    pub fn new_service(&self) -> String {
+        self.new.service.clone()
+    }
+
+    fn new_password(&self) -> &String {
+        self.input
+            .new_password
+            .as_ref()
+            .unwrap_or(&self.input.password)
+    }
+
+    /// Runs the remaining steps, stopping early when a PLC token is needed
+    pub async fn run(&mut self) -> Result<AccountMigration> {
+        let migration =
+            store::get_or_create_migration(&self.did, &self.old.service, &self.new.service).await?;
+        {
+            let mut running = RUNNING.lock().unwrap();
+            if !running.insert(self.did.clone()) {
+                bail!("A migration of {} is already running", self.did);
+            }
+        }
+        let result = self
+            .run_steps(migration.step.parse::<MigrationStep>()?)
+            .await;
+        RUNNING.lock().unwrap().remove(&self.did);
+
+        if let Err(error) = &result {
+            let step = match store::get_migration(&self.did).await? {
+                Some(migration) => migration.step.parse::<MigrationStep>()?,
+                None => MigrationStep::CreateAccount,
+            };
+            store::update_progress(
+                &self.did,
+                step,
+                MigrationStatus::Failed,
+                Some(error.to_string()),
+            )
+            .await?;
+        }
+        result?;
+        match store::get_migration(&self.did).await? {
+            Some(migration) => Ok(migration),
+            None => bail!("Migration of {} disappeared", self.did),
+        }
+    }
+
+    async fn run_steps(&mut self, mut step: MigrationStep) -> Result<()> {
+        store::update_progress(&self.did, step, MigrationStatus::Running, None).await?;
+        if step > MigrationStep::CreateAccount {
+            let password = self.new_password().clone();
+            self.new.login(&self.did, &password).await?;
+        }
+        while step != MigrationStep::Done {
+            tracing::info!("@LOG: migrating {}: {}", self.did, step.as_str());
+            step = match step {
+                MigrationStep::CreateAccount => {
+                    self.create_account().await?;
+                    step.next()
+                }
+                MigrationStep::ImportRepo => {
+                    self.import_repo().await?;
+                    step.next()
+                }
+                MigrationStep::ImportBlobs => {
+                    self.import_blobs().await?;
+                    step.next()
+                }
+                MigrationStep::MigratePreferences => {
+                    self.migrate_preferences().await?;
+                    step.next()
+                }
+                MigrationStep::RequestPlcSignature => {
+                    if !self.did.starts_with("did:plc:") {
+                        // did:web documents are updated by whoever hosts them
+                        MigrationStep::ActivateAccount
+                    } else if self.input.plc_token.is_some() {
+                        MigrationStep::SubmitPlcOperation
+                    } else {
+                        self.old
+                            .call(
+                                "com.atproto.identity.requestPlcOperationSignature",
+                                &json!({}),
+                            )
+                            .await?;
+                        return self.await_plc_token().await;
+                    }
+                }
+                MigrationStep::SubmitPlcOperation => match self.input.plc_token.clone() {
+                    None => return self.await_plc_token().await,
+                    Some(token) => {
+                        self.submit_plc_operation(token).await?;
+                        step.next()
+                    }
+                },
+                MigrationStep::ActivateAccount => {
+                    self.new
+                        .call("com.atproto.server.activateAccount", &json!({}))
+                        .await?;
+                    step.next()
+                }
+                MigrationStep::DeactivateOldAccount => {
+                    self.old
+                        .call("com.atproto.server.deactivateAccount", &json!({}))
+                        .await?;
+                    step.next()
+                }
+                MigrationStep::Done => step,
+            };
+            let status = match step {
+                MigrationStep::Done => MigrationStatus::Done,
+                _ => MigrationStatus::Running,
+            };
+            store::update_progress(&self.did, step, status, None).await?;
+        }
+        Ok(())
+    }
+
+    async fn await_plc_token(&self) -> Result<()> {
+        store::update_progress(
+            &self.did,
+            MigrationStep::SubmitPlcOperation,
+            MigrationStatus::AwaitingPlcToken,
+            None,
+        )
+        .await
+    }
+
+    /// The account is created deactivated, authorized by a service token from the old PDS
+    /// proving control of the did
+    async fn create_account(&mut self) -> Result<()> {
+        let server: DescribeServerOutput = self
+            .new
+            .query("com.atproto.server.describeServer", &[])
+            .await?;
+        let service_auth: GetServiceAuthOutput = self
+            .old
+            .query(
+                "com.atproto.server.getServiceAuth",
+                &[
+                    ("aud", server.did.as_str()),
+                    ("lxm", "com.atproto.server.createAccount"),
+                ],
+            )
+            .await?;
+        let account: CreateAccountOutput = self
+            .new
+            .procedure_as(
+                "com.atproto.server.createAccount",
+                &CreateAccountInput {
+                    email: Some(self.input.email.clone()),
+                    handle: self.input.handle.clone(),
+                    did: Some(self.did.clone()),
+                    invite_code: self.input.invite_code.clone(),
+                    verification_code: None,
+                    verification_phone: None,
+                    password: Some(self.new_password().clone()),
+                    recovery_key: None,
+                    plc_op: None,
+                },
+                Some(&service_auth.token),
+            )
+            .await?;
+        self.new.access_jwt = Some(account.access_jwt);
+        Ok(())
+    }
+
+    async fn import_repo(&self) -> Result<()> {
+        let car = self
+            .old
+            .query_stream("com.atproto.sync.getRepo", &[("did", self.did.as_str())])
+            .await?;
+        self.new
+            .upload(
+                "com.atproto.repo.importRepo",
+                car,
+                "application/vnd.ipld.car",
+            )
+            .await
+    }
+
+    /// Imported blobs drop out of `listMissingBlobs`, so a resumed import only fetches what's
+    /// still missing
+    async fn import_blobs(&self) -> Result<()> {
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut params = vec![("limit", "100")];
+            if let Some(cursor) = cursor.as_ref() {
+                params.push(("cursor", cursor.as_str()));
+            }
+            let res: Value = self
+                .new
+                .query("com.atproto.repo.listMissingBlobs", &params)
+                .await?;
+            let cids = res["blobs"]
+                .as_array()
+                .map(|blobs| {
+                    blobs
+                        .iter()
+                        .filter_map(|blob| blob["cid"].as_str().map(|cid| cid.to_string()))
+                        .collect::<Vec<String>>()
+                })
+                .unwrap_or_default();
+            for cid in cids.iter() {
+                let blob = self
+                    .old
+                    .query_stream(
+                        "com.atproto.sync.getBlob",
+                        &[("did", self.did.as_str()), ("cid", cid.as_str())],
+                    )
+                    .await?;
+                let content_type = blob
+                    .content_type
+                    .clone()
+                    .unwrap_or("application/octet-stream".to_string());
+                self.new
+                    .upload("com.atproto.repo.uploadBlob", blob, &content_type)
+                    .await?;
+                store::record_blob_imported(&self.did).await?;
+            }
+            cursor = res["cursor"].as_str().map(|cursor| cursor.to_string());
+            if cids.is_empty() || cursor.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn migrate_preferences(&self) -> Result<()> {
+        let prefs: Value = self.old.query("app.bsky.actor.getPreferences", &[]).await?;
+        self.new.call("app.bsky.actor.putPreferences", &prefs).await
+    }
+
+    /// Has the old PDS sign a PLC operation pointing the did at the new PDS, which submits it
+    async fn submit_plc_operation(&self, token: String) -> Result<()> {
+        let credentials: GetRecommendedDidCredentialsResponse = self
+            .new
+            .query("com.atproto.identity.getRecommendedDidCredentials", &[])
+            .await?;
+        let verification_methods: BTreeMap<String, String> =
+            serde_json::from_value(credentials.verification_methods)?;
+        let signed: Value = self
+            .old
+            .procedure(
+                "com.atproto.identity.signPlcOperation",
+                &SignPlcOperationRequest {
+                    token,
+                    rotation_keys: Some(credentials.rotation_keys),
+                    also_known_as: Some(credentials.also_known_as),
+                    verification_methods: Some(verification_methods),
+                    services: Some(credentials.services),
+                },
+            )
+            .await?;
+        self.new
+            .call(
+                "com.atproto.identity.submitPlcOperation",
+                &SubmitPlcOperationRequest {
+                    operation: signed["operation"].clone(),
+                },
+            )
+            .await
+    }
+
+    /// Reports what a migration would do without writing to either PDS
+    pub async fn dry_run(&self) -> Result<DryRunReport> {
+        let mut warnings = Vec::new();
+        let server: DescribeServerOutput = self
+            .new
+            .query("com.atproto.server.describeServer", &[])
+            .await?;
+        if !server
+            .available_user_domains
+            .iter()
+            .any(|domain| self.input.handle.ends_with(domain.as_str()))
+        {
+            warnings.push(format!(
+                "{} is not under a domain of the new PDS, it has to resolve to the did \
+                 before the account can be activated",
+                self.input.handle
+            ));
+        }
+        if server.invite_code_required.unwrap_or(false) && self.input.invite_code.is_none() {
+            warnings.push("The new PDS requires an invite code".to_string());
+        }
+        if let Err(error) = self
+            .old
+            .query::<GetServiceAuthOutput>(
+                "com.atproto.server.getServiceAuth",
+                &[
+                    ("aud", server.did.as_str()),
+                    ("lxm", "com.atproto.server.createAccount"),
+                ],
+            )
+            .await
+        {
+            warnings.push(format!("The old PDS won't issue service auth: {error}"));
+        }
+        if !self.did.starts_with("did:plc:") {
+            warnings.push(format!(
+                "{} is not a did:plc, its did document has to be pointed at the new PDS by hand",
+                self.did
+            ));
+        }
+
+        let repo: DescribeRepoOutput = self
+            .old
+            .query(
+                "com.atproto.repo.describeRepo",
+                &[("repo", self.did.as_str())],
+            )
+            .await?;
+        let commit: GetLatestCommitOutput = self
+            .old
+            .query(
+                "com.atproto.sync.getLatestCommit",
+                &[("did", self.did.as_str())],
+            )
+            .await?;
+        let mut blobs = 0;
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut params = vec![("did", self.did.as_str()), ("limit", "1000")];
+            if let Some(cursor) = cursor.as_ref() {
+                params.push(("cursor", cursor.as_str()));
+            }
+            let res: ListBlobsOutput = self
+                .old
+                .query("com.atproto.sync.listBlobs", &params)
+                .await?;
+            blobs += res.cids.len();
+            cursor = res.cursor;
+            if res.cids.is_empty() || cursor.is_none() {
+                break;
+            }
+        }
+        let prefs: Value = self.old.query("app.bsky.actor.getPreferences", &[]).await?;
+
+        let progress = match store::get_migration(&self.did).await? {
+            Some(migration)
+                if migration.old_pds == self.old.service
+                    && migration.new_pds == self.new.service =>
+            {
+                Some(migration)
+            }
+            _ => None,
+        };
+        let first_step = match &progress {
+            Some(migration) if migration.status != MigrationStatus::Done.as_str() => {
+                migration.step.parse::<MigrationStep>()?
+            }
+            _ => MigrationStep::CreateAccount,
+        };
+        let steps = MigrationStep::ALL
+            .iter()
+            .filter(|step| **step >= first_step && **step != MigrationStep::Done)
+            .filter(|step| {
+                self.did.starts_with("did:plc:")
+                    || !matches!(
+                        step,
+                        MigrationStep::RequestPlcSignature | MigrationStep::SubmitPlcOperation
+                    )
+            })
+            .map(|step| step.as_str().to_string())
+            .collect();
+
+        Ok(DryRunReport {
+            did: self.did.clone(),
+            old_handle: repo.handle,
+            new_handle: self.input.handle.clone(),
+            new_pds_did: server.did,
+            repo_rev: commit.rev,
+            collections: repo.collections,
+            blobs,
+            preferences: prefs["preferences"]
+                .as_array()
+                .map(|prefs| prefs.len())
+                .unwrap_or(0),
+            steps,
+            progress,
+            warnings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_round_trip_in_order() {
+        let mut step = MigrationStep::CreateAccount;
+        let mut seen = vec![step];
+        while step != MigrationStep::Done {
+            step = step.next();
+            seen.push(step);
+        }
+        assert_eq!(seen, MigrationStep::ALL.to_vec());
+        for step in MigrationStep::ALL {
+            assert_eq!(step.as_str().parse::<MigrationStep>().unwrap(), step);
+        }
+        assert!("unknown".parse::<MigrationStep>().is_err());
+    }
+}
+
+pub mod client;
+pub mod routes;
+pub mod store;
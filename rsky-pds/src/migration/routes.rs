@@ -0,0 +1,81 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::migration::{is_running, store, DryRunReport, MigrationInput, Migrator};
+use crate::models::AccountMigration;
+use anyhow::{bail, Result};
+use rocket::serde::json::Json;
+
+async fn inner_dry_run(body: MigrationInput) -> Result<DryRunReport> {
+    let migrator = Migrator::new(body).await?;
+    migrator.dry_run().await
+}
+
+#[tracing::instrument(skip_all)]
+#[rocket::post("/dry-run", format = "json", data = "<body>")]
+pub async fn dry_run(
+    body: Json<MigrationInput>,
+    _auth: AdminToken,
+) -> Result<Json<DryRunReport>, ApiError> {
+    match inner_dry_run(body.into_inner()).await {
+        Ok(report) => Ok(Json(report)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::InvalidRequest(error.to_string()))
+        }
+    }
+}
+
+async fn inner_start_migration(body: MigrationInput) -> Result<AccountMigration> {
+    let mut migrator = Migrator::new(body).await?;
+    if is_running(&migrator.did) {
+        bail!("A migration of {} is already running", migrator.did);
+    }
+    let migration = store::get_or_create_migration(
+        &migrator.did,
+        &migrator.old_service(),
+        &migrator.new_service(),
+    )
+    .await?;
+    tokio::spawn(async move {
+        if let Err(error) = migrator.run().await {
+            tracing::error!(
+                "@LOG: ERROR: migration of {} failed; err: {error}",
+                migrator.did
+            );
+        }
+    });
+    Ok(migration)
+}
+
+/// Starts a migration in the background, or resumes it from the last completed step when
+/// called again with the same input (and the PLC token once the old PDS has mailed it)
+#[tracing::instrument(skip_all)]
+#[rocket::post("/start", format = "json", data = "<body>")]
+pub async fn start_migration(
+    body: Json<MigrationInput>,
+    _auth: AdminToken,
+) -> Result<Json<AccountMigration>, ApiError> {
+    match inner_start_migration(body.into_inner()).await {
+        Ok(migration) => Ok(Json(migration)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::InvalidRequest(error.to_string()))
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+#[rocket::get("/<did>")]
+pub async fn get_migration(
+    did: String,
+    _auth: AdminToken,
+) -> Result<Json<AccountMigration>, ApiError> {
+    match store::get_migration(&did).await {
+        Ok(Some(migration)) => Ok(Json(migration)),
+        Ok(None) => Err(ApiError::RecordNotFound),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::db::establish_connection;
+use crate::migration::{MigrationStatus, MigrationStep};
+use crate::models::AccountMigration;
+use anyhow::Result;
+use diesel::*;
+use rsky_common::now;
+
+pub async fn get_migration(did: &String) -> Result<Option<AccountMigration>> {
+    use crate::schema::pds::account_migration::dsl as MigrationSchema;
+    let conn = &mut establish_connection()?;
+
+    Ok(MigrationSchema::account_migration
+        .find(did)
+        .first(conn)
+        .optional()?)
+}
+
+/// Starts tracking a migration, or picks an unfinished one up again when the same did
+/// is migrated between the same two instances
+pub async fn get_or_create_migration(
+    did: &String,
+    old_pds: &String,
+    new_pds: &String,
+) -> Result<AccountMigration> {
+    use crate::schema::pds::account_migration::dsl as MigrationSchema;
+    let conn = &mut establish_connection()?;
+
+    let existing: Option<AccountMigration> = MigrationSchema::account_migration
+        .find(did)
+        .first(conn)
+        .optional()?;
+    match existing {
+        Some(migration)
+            if &migration.old_pds == old_pds
+                && &migration.new_pds == new_pds
+                && migration.status != MigrationStatus::Done.as_str() =>
+        {
+            Ok(migration)
+        }
+        _ => {
+            let timestamp = now();
+            let migration = AccountMigration {
+                did: did.clone(),
+                old_pds: old_pds.clone(),
+                new_pds: new_pds.clone(),
+                step: MigrationStep::CreateAccount.as_str().to_string(),
+                status: MigrationStatus::Running.as_str().to_string(),
+                blobs_imported: 0,
+                error: None,
+                created_at: timestamp.clone(),
+                updated_at: timestamp,
+            };
+            // a finished or differently routed migration of this did starts over
+            delete(MigrationSchema::account_migration)
+                .filter(MigrationSchema::did.eq(did))
+                .execute(conn)?;
+            insert_into(MigrationSchema::account_migration)
+                .values(&migration)
+                .execute(conn)?;
+            Ok(migration)
+        }
+    }
+}
+
+pub async fn update_progress(
+    did: &String,
+    step: MigrationStep,
+    status: MigrationStatus,
+    error: Option<String>,
+) -> Result<()> {
+    use crate::schema::pds::account_migration::dsl as MigrationSchema;
+    let conn = &mut establish_connection()?;
+
+    update(MigrationSchema::account_migration)
+        .filter(MigrationSchema::did.eq(did))
+        .set((
+            MigrationSchema::step.eq(step.as_str()),
+            MigrationSchema::status.eq(status.as_str()),
+            MigrationSchema::error.eq(error),
+            MigrationSchema::updatedAt.eq(now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub async fn record_blob_imported(did: &String) -> Result<()> {
+    use crate::schema::pds::account_migration::dsl as MigrationSchema;
+    let conn = &mut establish_connection()?;
+
+    update(MigrationSchema::account_migration)
+        .filter(MigrationSchema::did.eq(did))
+        .set((
+            MigrationSchema::blobsImported.eq(MigrationSchema::blobsImported + 1),
+            MigrationSchema::updatedAt.eq(now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
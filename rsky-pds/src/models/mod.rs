@@ -1,7 +1,9 @@
 pub mod models;
 pub use self::models::Account;
+pub use self::models::AccountMigration;
 pub use self::models::AccountPref;
 pub use self::models::Actor;
+pub use self::models::AdminAction;
 pub use self::models::AppPassword;
 pub use self::models::Backlink;
 pub use self::models::Blob;
@@ -9,12 +11,21 @@ pub use self::models::DidDoc;
 pub use self::models::EmailToken;
 pub use self::models::InviteCode;
 pub use self::models::InviteCodeUse;
+pub use self::models::Job;
+pub use self::models::OAuthAuthorizedClient;
+pub use self::models::OAuthRequest;
+pub use self::models::OAuthToken;
+pub use self::models::RateLimit;
 pub use self::models::Record;
 pub use self::models::RecordBlob;
 pub use self::models::RefreshToken;
 pub use self::models::RepoBlock;
 pub use self::models::RepoRoot;
 pub use self::models::RepoSeq;
+pub use self::models::Wallet;
+pub use self::models::WalletIndexState;
+pub use self::models::WalletRequest;
+pub use self::models::WalletTransaction;
 pub mod error_code;
 pub use self::error_code::ErrorCode;
 pub mod error_message_response;
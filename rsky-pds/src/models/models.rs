@@ -37,6 +37,43 @@ pub struct Account {
     pub email_confirmed_at: Option<String>,
 }
 
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(did))]
+#[diesel(table_name = crate::schema::pds::account_migration)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AccountMigration {
+    pub did: String,
+    #[diesel(column_name = oldPds)]
+    #[serde(rename = "oldPds")]
+    pub old_pds: String,
+    #[diesel(column_name = newPds)]
+    #[serde(rename = "newPds")]
+    pub new_pds: String,
+    pub step: String,
+    pub status: String,
+    #[diesel(column_name = blobsImported)]
+    #[serde(rename = "blobsImported")]
+    pub blobs_imported: i32,
+    pub error: Option<String>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
 #[derive(
     Queryable,
     Identifiable,
@@ -82,6 +119,30 @@ pub struct Actor {
     pub delete_after: Option<String>,
 }
 
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::admin_action)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AdminAction {
+    pub id: i64,
+    #[diesel(column_name = batchId)]
+    #[serde(rename = "batchId")]
+    pub batch_id: String,
+    pub action: String,
+    pub did: String,
+    pub params: Option<String>,
+    #[diesel(column_name = performedBy)]
+    #[serde(rename = "performedBy")]
+    pub performed_by: String,
+    pub outcome: String,
+    pub error: Option<String>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
 #[derive(
     Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
 )]
@@ -282,6 +343,172 @@ pub struct InviteCodeUse {
     pub used_at: String,
 }
 
+#[derive(
+    Queryable,
+    QueryableByName,
+    Identifiable,
+    Selectable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::job)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Job {
+    pub id: i64,
+    pub kind: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    #[diesel(column_name = lastError)]
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    #[diesel(column_name = uniqueKey)]
+    #[serde(rename = "uniqueKey")]
+    pub unique_key: Option<String>,
+    #[diesel(column_name = runAt)]
+    #[serde(rename = "runAt")]
+    pub run_at: String,
+    #[diesel(column_name = lockedAt)]
+    #[serde(rename = "lockedAt")]
+    pub locked_at: Option<String>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(did, clientId))]
+#[diesel(table_name = crate::schema::pds::oauth_authorized_client)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OAuthAuthorizedClient {
+    pub did: String,
+    #[diesel(column_name = clientId)]
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub scope: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::oauth_request)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OAuthRequest {
+    pub id: String,
+    #[diesel(column_name = clientId)]
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub parameters: String,
+    #[diesel(column_name = dpopJkt)]
+    #[serde(rename = "dpopJkt")]
+    pub dpop_jkt: String,
+    pub did: Option<String>,
+    pub code: Option<String>,
+    #[diesel(column_name = expiresAt)]
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::oauth_token)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OAuthToken {
+    pub id: String,
+    pub did: String,
+    #[diesel(column_name = clientId)]
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub scope: String,
+    #[diesel(column_name = dpopJkt)]
+    #[serde(rename = "dpopJkt")]
+    pub dpop_jkt: String,
+    #[diesel(column_name = refreshToken)]
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    #[diesel(column_name = expiresAt)]
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+#[derive(
+    Queryable,
+    QueryableByName,
+    Identifiable,
+    Selectable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(key))]
+#[diesel(table_name = crate::schema::pds::rate_limit)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RateLimit {
+    pub key: String,
+    pub points: i64,
+    #[diesel(column_name = resetAt)]
+    #[serde(rename = "resetAt")]
+    pub reset_at: i64,
+}
+
 #[derive(
     Queryable,
     Identifiable,
@@ -444,3 +671,129 @@ impl RepoSeq {
         }
     }
 }
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(did))]
+#[diesel(table_name = crate::schema::pds::wallet)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Wallet {
+    pub did: String,
+    pub address: String,
+    #[diesel(column_name = encryptedKey)]
+    #[serde(rename = "encryptedKey")]
+    pub encrypted_key: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::wallet_request)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WalletRequest {
+    pub id: String,
+    pub did: String,
+    pub amount: String,
+    pub description: String,
+    pub status: String,
+    #[diesel(column_name = fulfilledBy)]
+    #[serde(rename = "fulfilledBy")]
+    pub fulfilled_by: Option<String>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    /// Deploy paying the request while it's pending
+    #[diesel(column_name = deployId)]
+    #[serde(rename = "deployId")]
+    pub deploy_id: Option<String>,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(id, address))]
+#[diesel(table_name = crate::schema::pds::wallet_transaction)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WalletTransaction {
+    pub id: String,
+    pub address: String,
+    pub direction: String,
+    pub counterparty: String,
+    pub amount: String,
+    pub description: String,
+    pub cost: String,
+    #[diesel(column_name = blockHash)]
+    #[serde(rename = "blockHash")]
+    pub block_hash: String,
+    #[diesel(column_name = blockNumber)]
+    #[serde(rename = "blockNumber")]
+    pub block_number: i64,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    /// Whether the vault accepted the transfer
+    pub succeeded: bool,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::wallet_index_state)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WalletIndexState {
+    pub id: String,
+    #[diesel(column_name = lastBlockHash)]
+    #[serde(rename = "lastBlockHash")]
+    pub last_block_hash: String,
+    #[diesel(column_name = lastBlockNumber)]
+    #[serde(rename = "lastBlockNumber")]
+    pub last_block_number: i64,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
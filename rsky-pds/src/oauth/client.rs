@@ -0,0 +1,430 @@
+// https://drafts.aaronpk.com/draft-parecki-oauth-client-id-metadata-document/draft-parecki-oauth-client-id-metadata-document.html
+use crate::oauth::jose::{decode_jwt, verify_es256, EcJwk};
+use crate::oauth::{now_secs, store, OAuthError, CLIENT_ASSERTION_TYPE_JWT_BEARER};
+use crate::APP_USER_AGENT;
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::{Host, Url};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+// client metadata and jwks documents are small, anything bigger is refused
+const MAX_DOCUMENT_BYTES: usize = 64 * 1024;
+// every authorization request needs the metadata, clients are only fetched again after this
+const DOCUMENT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const DOCUMENT_CACHE_MAX_ENTRIES: usize = 1000;
+// client assertions are minted per request, so they only need to live long enough to arrive
+const ASSERTION_MAX_AGE_SECS: u64 = 60;
+const ASSERTION_MAX_SKEW_SECS: u64 = 10;
+
+lazy_static! {
+    static ref DOCUMENT_CACHE: Mutex<HashMap<String, (Instant, Value)>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientMetadata {
+    pub client_id: String,
+    pub client_name: Option<String>,
+    pub client_uri: Option<String>,
+    pub logo_uri: Option<String>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    pub scope: Option<String>,
+    pub token_endpoint_auth_method: Option<String>,
+    pub dpop_bound_access_tokens: Option<bool>,
+    pub jwks: Option<Jwks>,
+    pub jwks_uri: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Jwks {
+    // keys other than P-256 can't be used with ES256 and are skipped
+    pub keys: Vec<Value>,
+}
+
+impl Jwks {
+    fn find(&self, kid: Option<&str>) -> Vec<EcJwk> {
+        self.keys
+            .iter()
+            .filter_map(|key| serde_json::from_value::<EcJwk>(key.clone()).ok())
+            .filter(|key| kid.is_none() || key.kid.as_deref() == kid)
+            .collect()
+    }
+}
+
+impl ClientMetadata {
+    pub fn display_name(&self) -> &str {
+        self.client_name.as_deref().unwrap_or(&self.client_id)
+    }
+
+    /// Loopback clients may redirect to any port, per RFC 8252
+    pub fn has_redirect_uri(&self, redirect_uri: &str) -> bool {
+        if !is_loopback_client(&self.client_id) {
+            return self.redirect_uris.iter().any(|uri| uri == redirect_uri);
+        }
+        let without_port = |uri: &str| -> Option<Url> {
+            let mut url = Url::parse(uri).ok()?;
+            url.set_port(None).ok()?;
+            Some(url)
+        };
+        match without_port(redirect_uri) {
+            None => false,
+            Some(requested) => self
+                .redirect_uris
+                .iter()
+                .any(|uri| without_port(uri).as_ref() == Some(&requested)),
+        }
+    }
+
+    pub fn allows_scope(&self, scope: &str) -> bool {
+        let allowed = self
+            .scope
+            .as_deref()
+            .unwrap_or("")
+            .split(" ")
+            .collect::<Vec<&str>>();
+        scope.split(" ").all(|scope| allowed.contains(&scope))
+    }
+
+    fn validate(&self) -> Result<(), OAuthError> {
+        let invalid = |message: &str| OAuthError::InvalidClient(message.to_string());
+        if self.redirect_uris.is_empty() {
+            return Err(invalid("Client metadata must declare redirect_uris"));
+        }
+        if self.dpop_bound_access_tokens != Some(true) {
+            return Err(invalid("Client must use dpop_bound_access_tokens"));
+        }
+        if !self.allows_scope("atproto") {
+            return Err(invalid("Client scope must include atproto"));
+        }
+        match self.token_endpoint_auth_method.as_deref() {
+            None | Some("none") => Ok(()),
+            Some("private_key_jwt") if self.jwks.is_some() || self.jwks_uri.is_some() => Ok(()),
+            Some("private_key_jwt") => Err(invalid("private_key_jwt clients must declare jwks")),
+            Some(_) => Err(invalid("Unsupported token_endpoint_auth_method")),
+        }
+    }
+}
+
+pub fn is_loopback_client(client_id: &str) -> bool {
+    client_id == "http://localhost" || client_id.starts_with("http://localhost?")
+}
+
+/// Loopback clients have no metadata document, it is derived from the client_id query
+fn loopback_client_metadata(client_id: &str) -> Result<ClientMetadata, OAuthError> {
+    let url = Url::parse(client_id)
+        .map_err(|_| OAuthError::InvalidClient("Invalid loopback client_id".to_string()))?;
+    let mut redirect_uris = Vec::new();
+    let mut scope = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "redirect_uri" => redirect_uris.push(value.to_string()),
+            "scope" => scope = Some(value.to_string()),
+            _ => (),
+        }
+    }
+    if redirect_uris.is_empty() {
+        redirect_uris = vec!["http://127.0.0.1/".to_string(), "http://[::1]/".to_string()];
+    }
+    Ok(ClientMetadata {
+        client_id: client_id.to_string(),
+        client_name: None,
+        client_uri: None,
+        logo_uri: None,
+        redirect_uris,
+        scope: Some(scope.unwrap_or("atproto".to_string())),
+        token_endpoint_auth_method: Some("none".to_string()),
+        dpop_bound_access_tokens: Some(true),
+        jwks: None,
+        jwks_uri: None,
+    })
+}
+
+/// Fetches a document published by a client, cached for `DOCUMENT_CACHE_TTL`. The url is
+/// supplied by the client, so only public addresses are contacted, redirects aren't followed and
+/// the body is capped at `MAX_DOCUMENT_BYTES`.
+async fn fetch_json(url: &str) -> Result<Value, OAuthError> {
+    if let Some(document) = cached_document(url) {
+        return Ok(document);
+    }
+    let failed =
+        |reason: &str| OAuthError::InvalidClient(format!("Failed to fetch {url}: {reason}"));
+    let parsed = Url::parse(url).map_err(|_| failed("invalid url"))?;
+    if parsed.scheme() != "https" {
+        return Err(failed("not an https url"));
+    }
+    let addr = resolve_public_addr(&parsed)
+        .await
+        .map_err(|reason| failed(&reason))?;
+    let host = parsed.host_str().unwrap_or_default();
+    let client = reqwest::Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .timeout(FETCH_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        // connect to the checked address, so a second lookup can't point somewhere else
+        .resolve(host, addr)
+        .build()
+        .map_err(anyhow::Error::new)?;
+    let mut res = client
+        .get(url)
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .map_err(|error| failed(&error.to_string()))?;
+    if !res.status().is_success() {
+        return Err(failed(&format!("status {}", res.status())));
+    }
+    if res.content_length().unwrap_or(0) > MAX_DOCUMENT_BYTES as u64 {
+        return Err(failed("document too large"));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = res
+        .chunk()
+        .await
+        .map_err(|error| failed(&error.to_string()))?
+    {
+        if body.len() + chunk.len() > MAX_DOCUMENT_BYTES {
+            return Err(failed("document too large"));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let document: Value = serde_json::from_slice(&body)
+        .map_err(|_| OAuthError::InvalidClient(format!("Invalid JSON at {url}")))?;
+    cache_document(url, &document);
+    Ok(document)
+}
+
+fn cached_document(url: &str) -> Option<Value> {
+    let cache = DOCUMENT_CACHE.lock().unwrap();
+    match cache.get(url) {
+        Some((fetched_at, document)) if fetched_at.elapsed() < DOCUMENT_CACHE_TTL => {
+            Some(document.clone())
+        }
+        _ => None,
+    }
+}
+
+fn cache_document(url: &str, document: &Value) {
+    let mut cache = DOCUMENT_CACHE.lock().unwrap();
+    if cache.len() >= DOCUMENT_CACHE_MAX_ENTRIES {
+        cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < DOCUMENT_CACHE_TTL);
+    }
+    if cache.len() < DOCUMENT_CACHE_MAX_ENTRIES {
+        cache.insert(url.to_string(), (Instant::now(), document.clone()));
+    }
+}
+
+/// Resolves the url's host, refusing it if any of its addresses isn't publicly routable
+async fn resolve_public_addr(url: &Url) -> Result<SocketAddr, String> {
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(IpAddr::V4(ip), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(IpAddr::V6(ip), port)],
+        Some(Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+            .await
+            .map_err(|error| error.to_string())?
+            .collect(),
+        None => return Err("missing host".to_string()),
+    };
+    if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err("host resolves to a non public address".to_string());
+    }
+    addrs
+        .into_iter()
+        .next()
+        .ok_or_else(|| "host has no addresses".to_string())
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                // shared address space, 100.64.0.0/10
+                || (a == 100 && (b & 0xc0) == 64)
+                // protocol assignments and benchmarking, 192.0.0.0/24 and 198.18.0.0/15
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (b & 0xfe) == 18))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // unique local fc00::/7, link local fe80::/10, documentation 2001:db8::/32
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && ip.segments()[1] == 0xdb8))
+        }
+    }
+}
+
+pub async fn get_client_metadata(client_id: &str) -> Result<ClientMetadata, OAuthError> {
+    if is_loopback_client(client_id) {
+        return loopback_client_metadata(client_id);
+    }
+    match Url::parse(client_id) {
+        Ok(url) if url.scheme() == "https" && url.fragment().is_none() => (),
+        _ => {
+            return Err(OAuthError::InvalidClient(
+                "client_id must be an https URL".to_string(),
+            ))
+        }
+    }
+    let metadata: ClientMetadata = serde_json::from_value(fetch_json(client_id).await?)
+        .map_err(|error| OAuthError::InvalidClient(format!("Invalid client metadata: {error}")))?;
+    if metadata.client_id != client_id {
+        return Err(OAuthError::InvalidClient(
+            "client_id does not match the client metadata".to_string(),
+        ));
+    }
+    metadata.validate()?;
+    Ok(metadata)
+}
+
+/// Checks the `private_key_jwt` client assertion of confidential clients, public clients
+/// must not send one
+pub async fn authenticate_client(
+    metadata: &ClientMetadata,
+    client_assertion_type: Option<&str>,
+    client_assertion: Option<&str>,
+    issuer: &str,
+) -> Result<(), OAuthError> {
+    let invalid = |message: &str| OAuthError::InvalidClient(message.to_string());
+    let assertion = match metadata.token_endpoint_auth_method.as_deref() {
+        Some("private_key_jwt") => match (client_assertion_type, client_assertion) {
+            (Some(CLIENT_ASSERTION_TYPE_JWT_BEARER), Some(assertion)) => assertion,
+            _ => return Err(invalid("Client authentication required")),
+        },
+        _ if client_assertion.is_some() => return Err(invalid("Unexpected client assertion")),
+        _ => return Ok(()),
+    };
+
+    let (header, _) = decode_jwt(assertion).map_err(|_| invalid("Malformed client assertion"))?;
+    let jwks = match (&metadata.jwks, &metadata.jwks_uri) {
+        (Some(jwks), _) => jwks.clone(),
+        (None, Some(jwks_uri)) => serde_json::from_value(fetch_json(jwks_uri).await?)
+            .map_err(|_| invalid("Invalid client jwks"))?,
+        (None, None) => return Err(invalid("Client has no jwks")),
+    };
+    let payload = jwks
+        .find(header["kid"].as_str())
+        .iter()
+        .find_map(|key| verify_es256(assertion, key).ok())
+        .ok_or_else(|| invalid("Invalid client assertion signature"))?;
+
+    let client_id = metadata.client_id.as_str();
+    if payload["iss"] != client_id || payload["sub"] != client_id {
+        return Err(invalid(
+            "Client assertion iss and sub must be the client_id",
+        ));
+    }
+    let aud_matches = match &payload["aud"] {
+        Value::String(aud) => aud == issuer,
+        Value::Array(auds) => auds.iter().any(|aud| aud == issuer),
+        _ => false,
+    };
+    if !aud_matches {
+        return Err(invalid("Client assertion aud must be the issuer"));
+    }
+    let exp =
+        check_assertion_lifetime(payload["iat"].as_u64(), payload["exp"].as_u64(), now_secs())?;
+    let jti = payload["jti"]
+        .as_str()
+        .ok_or_else(|| invalid("Client assertion jti missing"))?;
+    match store::use_jti(jti, exp).await? {
+        true => Ok(()),
+        false => Err(invalid("Client assertion replayed")),
+    }
+}
+
+/// Checks the assertion was issued recently and doesn't outlive `ASSERTION_MAX_AGE_SECS`, so its
+/// jti only has to be remembered that long. Returns when it expires.
+fn check_assertion_lifetime(
+    iat: Option<u64>,
+    exp: Option<u64>,
+    now: u64,
+) -> Result<u64, OAuthError> {
+    let invalid = |message: &str| OAuthError::InvalidClient(message.to_string());
+    let iat = iat.ok_or_else(|| invalid("Client assertion iat missing"))?;
+    let exp = exp.ok_or_else(|| invalid("Client assertion exp missing"))?;
+    if iat > now + ASSERTION_MAX_SKEW_SECS {
+        return Err(invalid("Client assertion issued in the future"));
+    }
+    if exp < now || iat + ASSERTION_MAX_AGE_SECS < now {
+        return Err(invalid("Client assertion expired"));
+    }
+    if exp > iat + ASSERTION_MAX_AGE_SECS {
+        return Err(invalid("Client assertion lifetime too long"));
+    }
+    Ok(exp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback_redirects_ignore_port() {
+        let metadata = loopback_client_metadata(
+            "http://localhost?redirect_uri=http%3A%2F%2F127.0.0.1%2Fcallback&scope=atproto%20transition%3Ageneric",
+        )
+        .unwrap();
+        assert!(metadata.has_redirect_uri("http://127.0.0.1:8080/callback"));
+        assert!(!metadata.has_redirect_uri("http://127.0.0.1:8080/other"));
+        assert!(metadata.allows_scope("atproto transition:generic"));
+        assert!(!metadata.allows_scope("atproto transition:chat.bsky"));
+    }
+
+    #[test]
+    fn assertions_must_be_short_lived() {
+        let now = 1_700_000_000;
+        assert_eq!(
+            check_assertion_lifetime(Some(now - 5), Some(now + 55), now).unwrap(),
+            now + 55
+        );
+        // no iat, or an exp far in the future, would make the jti live forever
+        assert!(check_assertion_lifetime(None, Some(now + 55), now).is_err());
+        assert!(check_assertion_lifetime(Some(now), Some(now + 86_400), now).is_err());
+        assert!(check_assertion_lifetime(Some(now - 120), Some(now + 10), now).is_err());
+        assert!(check_assertion_lifetime(Some(now + 60), Some(now + 90), now).is_err());
+        assert!(check_assertion_lifetime(Some(now - 30), Some(now - 1), now).is_err());
+    }
+
+    #[test]
+    fn only_fetches_from_public_addresses() {
+        for ip in ["93.184.215.14", "2606:2800:21f:cb07:6820:80da:af6b:8b2c"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// https://datatracker.ietf.org/doc/html/rfc9449
+use crate::oauth::jose::{decode_jwt, verify_es256, EcJwk};
+use crate::oauth::{now_secs, store, OAuthError};
+use lazy_static::lazy_static;
+use rocket::request::{FromRequest, Outcome, Request};
+use rsky_common::env::env_str;
+use rsky_common::get_random_str;
+use sha2::{Digest, Sha256};
+
+/// Nonces rotate every window, a nonce stays valid for the neighbouring windows
+const NONCE_WINDOW_SECS: u64 = 60;
+const PROOF_MAX_AGE_SECS: u64 = 60;
+const PROOF_MAX_SKEW_SECS: u64 = 10;
+
+lazy_static! {
+    // shared between instances through the env so any of them accepts the others' nonces
+    static ref DPOP_SECRET: String = env_str("PDS_DPOP_SECRET").unwrap_or_else(get_random_str);
+}
+
+/// Raw `DPoP` header of the request, if any
+pub struct DpopHeader(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for DpopHeader {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(DpopHeader(
+            req.headers().get_one("DPoP").map(|proof| proof.to_string()),
+        ))
+    }
+}
+
+pub struct VerifyDpopOpts<'a> {
+    pub htm: &'a str,
+    pub htu: &'a str,
+    // access token the proof is presented with, checked against `ath`
+    pub access_token: Option<&'a str>,
+    pub require_nonce: bool,
+}
+
+/// A verified proof, `jkt` is the thumbprint of the key that signed it
+pub struct DpopProof {
+    pub jkt: String,
+    pub jti: String,
+}
+
+fn nonce_for(window: u64) -> String {
+    let hash = Sha256::digest(format!("{}:{window}", *DPOP_SECRET));
+    base64_url::encode(&hash).replace("=", "")
+}
+
+pub fn next_nonce() -> String {
+    nonce_for(now_secs() / NONCE_WINDOW_SECS)
+}
+
+fn is_valid_nonce(nonce: &str) -> bool {
+    let current = now_secs() / NONCE_WINDOW_SECS;
+    [current - 1, current, current + 1]
+        .iter()
+        .any(|window| nonce_for(*window) == nonce)
+}
+
+fn strip_query(url: &str) -> &str {
+    match url.find(|c| c == '?' || c == '#') {
+        Some(i) => &url[..i],
+        None => url,
+    }
+}
+
+pub async fn verify_dpop_proof(
+    proof: Option<&str>,
+    opts: VerifyDpopOpts<'_>,
+) -> Result<DpopProof, OAuthError> {
+    let invalid = |message: &str| OAuthError::InvalidDpopProof(message.to_string());
+    let proof = proof.ok_or_else(|| invalid("DPoP proof required"))?;
+    let (header, _) = decode_jwt(proof).map_err(|_| invalid("Malformed DPoP proof"))?;
+    if header["typ"] != "dpop+jwt" {
+        return Err(invalid("Invalid DPoP proof typ"));
+    }
+    if header["jwk"].get("d").is_some() {
+        return Err(invalid("DPoP proof must not contain a private key"));
+    }
+    let jwk: EcJwk = serde_json::from_value(header["jwk"].clone())
+        .map_err(|_| invalid("Invalid DPoP proof jwk"))?;
+    let payload = verify_es256(proof, &jwk).map_err(|e| invalid(&e.to_string()))?;
+
+    let jti = payload["jti"]
+        .as_str()
+        .ok_or_else(|| invalid("DPoP proof jti missing"))?;
+    if payload["htm"] != opts.htm {
+        return Err(invalid("DPoP htm mismatch"));
+    }
+    match payload["htu"].as_str() {
+        Some(htu) if strip_query(htu) == strip_query(opts.htu) => (),
+        _ => return Err(invalid("DPoP htu mismatch")),
+    }
+    let iat = payload["iat"]
+        .as_u64()
+        .ok_or_else(|| invalid("DPoP proof iat missing"))?;
+    let now = now_secs();
+    if iat > now + PROOF_MAX_SKEW_SECS || iat + PROOF_MAX_AGE_SECS < now {
+        return Err(invalid("DPoP proof expired"));
+    }
+    if let Some(access_token) = opts.access_token {
+        let ath = base64_url::encode(&Sha256::digest(access_token)).replace("=", "");
+        if payload["ath"] != ath.as_str() {
+            return Err(invalid("DPoP ath mismatch"));
+        }
+    }
+    match payload["nonce"].as_str() {
+        Some(nonce) if !is_valid_nonce(nonce) => return Err(OAuthError::UseDpopNonce),
+        None if opts.require_nonce => return Err(OAuthError::UseDpopNonce),
+        _ => (),
+    }
+    if !store::use_jti(jti, iat + PROOF_MAX_AGE_SECS).await? {
+        return Err(invalid("DPoP proof replayed"));
+    }
+    Ok(DpopProof {
+        jkt: jwk.thumbprint(),
+        jti: jti.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_nonces_from_neighbouring_windows() {
+        let current = now_secs() / NONCE_WINDOW_SECS;
+        assert!(is_valid_nonce(&next_nonce()));
+        assert!(is_valid_nonce(&nonce_for(current - 1)));
+        assert!(!is_valid_nonce(&nonce_for(current - 2)));
+    }
+
+    #[test]
+    fn compares_htu_without_query() {
+        assert_eq!(
+            strip_query("https://pds.test/oauth/token?x=1#y"),
+            "https://pds.test/oauth/token"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{bail, Result};
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// Public P-256 key as found in DPoP proof headers and client `jwks`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EcJwk {
+    pub kty: String,
+    pub crv: String,
+    pub x: String,
+    pub y: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub d: Option<String>,
+}
+
+impl EcJwk {
+    /// RFC 7638 thumbprint, used as the `jkt` tokens are bound to
+    pub fn thumbprint(&self) -> String {
+        // required members only, in lexicographic order
+        let canonical = json!({
+            "crv": self.crv,
+            "kty": self.kty,
+            "x": self.x,
+            "y": self.y
+        });
+        base64_url::encode(&Sha256::digest(canonical.to_string())).replace("=", "")
+    }
+
+    pub fn verifying_key(&self) -> Result<VerifyingKey> {
+        if self.kty != "EC" || self.crv != "P-256" {
+            bail!("Unsupported key type")
+        }
+        let x = base64_url::decode(&self.x)?;
+        let y = base64_url::decode(&self.y)?;
+        if x.len() != 32 || y.len() != 32 {
+            bail!("Malformed key")
+        }
+        let mut sec1 = vec![0x04];
+        sec1.extend(x);
+        sec1.extend(y);
+        Ok(VerifyingKey::from_sec1_bytes(&sec1)?)
+    }
+}
+
+/// Splits a compact JWS into its decoded header and payload without verifying it
+pub fn decode_jwt(token: &str) -> Result<(Value, Value)> {
+    let parts = token.split(".").collect::<Vec<&str>>();
+    if parts.len() != 3 {
+        bail!("Malformed jwt")
+    }
+    let header: Value = serde_json::from_slice(&base64_url::decode(parts[0])?)?;
+    let payload: Value = serde_json::from_slice(&base64_url::decode(parts[1])?)?;
+    Ok((header, payload))
+}
+
+/// Verifies an ES256 compact JWS against `jwk` and returns its payload
+pub fn verify_es256(token: &str, jwk: &EcJwk) -> Result<Value> {
+    let (header, payload) = decode_jwt(token)?;
+    if header["alg"] != "ES256" {
+        bail!("Unsupported jwt alg")
+    }
+    // decode_jwt already checked there are three parts
+    let (signing_input, signature) = token.rsplit_once(".").unwrap();
+    let signature = Signature::from_slice(&base64_url::decode(signature)?)?;
+    match jwk
+        .verifying_key()?
+        .verify(signing_input.as_bytes(), &signature)
+    {
+        Ok(_) => Ok(payload),
+        Err(_) => bail!("Invalid jwt signature"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::SigningKey;
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+
+    fn sign(key: &SigningKey, header: Value, payload: Value) -> String {
+        let signing_input = format!(
+            "{}.{}",
+            base64_url::encode(&header.to_string()).replace("=", ""),
+            base64_url::encode(&payload.to_string()).replace("=", "")
+        );
+        let signature: Signature = key.sign(signing_input.as_bytes());
+        format!(
+            "{signing_input}.{}",
+            base64_url::encode(&signature.to_bytes()).replace("=", "")
+        )
+    }
+
+    fn to_jwk(key: &SigningKey) -> EcJwk {
+        let point = key.verifying_key().to_encoded_point(false);
+        EcJwk {
+            kty: "EC".to_string(),
+            crv: "P-256".to_string(),
+            x: base64_url::encode(point.x().unwrap()).replace("=", ""),
+            y: base64_url::encode(point.y().unwrap()).replace("=", ""),
+            kid: None,
+            d: None,
+        }
+    }
+
+    #[test]
+    fn verifies_es256_signatures() {
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let jwk = to_jwk(&key);
+        let token = sign(&key, json!({"alg": "ES256"}), json!({"jti": "abc"}));
+        assert_eq!(verify_es256(&token, &jwk).unwrap()["jti"], "abc");
+
+        let other = to_jwk(&SigningKey::from_slice(&[8u8; 32]).unwrap());
+        assert!(verify_es256(&token, &other).is_err());
+    }
+
+    #[test]
+    fn thumbprint_ignores_optional_members() {
+        let jwk = to_jwk(&SigningKey::from_slice(&[7u8; 32]).unwrap());
+        let with_kid = EcJwk {
+            kid: Some("key-1".to_string()),
+            ..jwk.clone()
+        };
+        assert_eq!(jwk.thumbprint(), with_kid.thumbprint());
+        assert_eq!(jwk.thumbprint().len(), 43);
+    }
+}
@@ -0,0 +1,126 @@
+// based on https://github.com/bluesky-social/atproto/tree/main/packages/oauth/oauth-provider
+use crate::oauth::dpop::next_nonce;
+use chrono::{Duration, Utc};
+use rocket::http::{Header, Status};
+use rocket::serde::json::Json;
+use rocket::Responder;
+use rsky_common::RFC3339_VARIANT;
+use serde_json::{json, Value};
+use std::time::SystemTime;
+use thiserror::Error;
+
+/// How long a pushed authorization request can wait for the user to sign in
+pub const REQUEST_LIFETIME_SECS: i64 = 5 * 60;
+/// How long an issued authorization code can wait to be exchanged
+pub const CODE_LIFETIME_SECS: i64 = 60;
+pub const ACCESS_TOKEN_LIFETIME_SECS: i64 = 60 * 60;
+pub const REFRESH_TOKEN_LIFETIME_SECS: i64 = 90 * 24 * 60 * 60;
+
+pub const REQUEST_URI_PREFIX: &str = "urn:ietf:params:oauth:request_uri:";
+pub const CLIENT_ASSERTION_TYPE_JWT_BEARER: &str =
+    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+pub const SCOPES_SUPPORTED: [&str; 3] = ["atproto", "transition:generic", "transition:chat.bsky"];
+
+#[derive(Error, Debug)]
+pub enum OAuthError {
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error("{0}")]
+    InvalidClient(String),
+    #[error("{0}")]
+    InvalidGrant(String),
+    #[error("{0}")]
+    InvalidScope(String),
+    #[error("{0}")]
+    InvalidDpopProof(String),
+    #[error("Authorization server requires nonce in DPoP proof")]
+    UseDpopNonce,
+    #[error("Access denied")]
+    AccessDenied,
+    #[error("Internal server error")]
+    ServerError,
+}
+
+impl OAuthError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidRequest(_) => "invalid_request",
+            OAuthError::InvalidClient(_) => "invalid_client",
+            OAuthError::InvalidGrant(_) => "invalid_grant",
+            OAuthError::InvalidScope(_) => "invalid_scope",
+            OAuthError::InvalidDpopProof(_) => "invalid_dpop_proof",
+            OAuthError::UseDpopNonce => "use_dpop_nonce",
+            OAuthError::AccessDenied => "access_denied",
+            OAuthError::ServerError => "server_error",
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            OAuthError::InvalidClient(_) => Status::Unauthorized,
+            OAuthError::AccessDenied => Status::Forbidden,
+            OAuthError::ServerError => Status::InternalServerError,
+            _ => Status::BadRequest,
+        }
+    }
+}
+
+impl From<anyhow::Error> for OAuthError {
+    fn from(error: anyhow::Error) -> Self {
+        tracing::error!("@LOG: ERROR: {error}");
+        OAuthError::ServerError
+    }
+}
+
+/// JSON response of the authorization server endpoints. Every response carries a fresh DPoP
+/// nonce and must never be cached.
+#[derive(Responder)]
+pub struct OAuthResponse {
+    inner: (Status, Json<Value>),
+    dpop_nonce: Header<'static>,
+    cache_control: Header<'static>,
+}
+
+impl OAuthResponse {
+    pub fn new(status: Status, body: Value) -> Self {
+        OAuthResponse {
+            inner: (status, Json(body)),
+            dpop_nonce: Header::new("DPoP-Nonce", next_nonce()),
+            cache_control: Header::new("Cache-Control", "no-store"),
+        }
+    }
+}
+
+impl From<OAuthError> for OAuthResponse {
+    fn from(error: OAuthError) -> Self {
+        OAuthResponse::new(
+            error.status(),
+            json!({
+                "error": error.code(),
+                "error_description": error.to_string()
+            }),
+        )
+    }
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("timestamp in seconds since UNIX epoch")
+        .as_secs()
+}
+
+pub fn expires_at(lifetime_secs: i64) -> String {
+    format!(
+        "{}",
+        (Utc::now() + Duration::seconds(lifetime_secs)).format(RFC3339_VARIANT)
+    )
+}
+
+pub mod client;
+pub mod dpop;
+pub mod jose;
+pub mod routes;
+pub mod store;
+pub mod token;
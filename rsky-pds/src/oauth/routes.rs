@@ -0,0 +1,660 @@
+use crate::account_manager::helpers::account::{ActorAccount, AvailabilityFlags};
+use crate::account_manager::AccountManager;
+use crate::config::ServerConfig;
+use crate::models::{OAuthRequest, OAuthToken};
+use crate::oauth::client::{authenticate_client, get_client_metadata, ClientMetadata};
+use crate::oauth::dpop::{verify_dpop_proof, DpopHeader, DpopProof, VerifyDpopOpts};
+use crate::oauth::token::{create_access_token, verify_access_token};
+use crate::oauth::{
+    expires_at, store, OAuthError, OAuthResponse, ACCESS_TOKEN_LIFETIME_SECS, CODE_LIFETIME_SECS,
+    REFRESH_TOKEN_LIFETIME_SECS, REQUEST_LIFETIME_SECS, REQUEST_URI_PREFIX, SCOPES_SUPPORTED,
+};
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::response::content::RawHtml;
+use rocket::response::{status, Redirect};
+use rocket::serde::json::Json;
+use rocket::{FromForm, Responder, State};
+use rsky_common::{escape_html, get_random_str, now};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use url::{form_urlencoded, Url};
+
+/// Authorization request parameters, pushed by the client and stored until the code is
+/// exchanged. Everything is optional so validation errors can be reported as OAuth errors.
+#[derive(FromForm, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AuthorizationParams {
+    pub client_id: Option<String>,
+    pub response_type: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub state: Option<String>,
+    pub scope: Option<String>,
+    pub response_mode: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub login_hint: Option<String>,
+    pub prompt: Option<String>,
+    pub client_assertion_type: Option<String>,
+    pub client_assertion: Option<String>,
+}
+
+#[derive(FromForm, Debug)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub client_id: Option<String>,
+    pub client_assertion_type: Option<String>,
+    pub client_assertion: Option<String>,
+}
+
+#[derive(FromForm, Debug)]
+pub struct RevokeRequest {
+    pub token: Option<String>,
+}
+
+#[derive(FromForm)]
+pub struct SignInForm {
+    pub request_uri: String,
+    pub identifier: String,
+    pub password: String,
+    // "accept" or "deny"
+    pub decision: String,
+}
+
+#[derive(Responder)]
+pub enum AuthorizePage {
+    Page(RawHtml<String>),
+    Error(status::Custom<RawHtml<String>>),
+    Redirect(Redirect),
+}
+
+fn required<'a>(value: &'a Option<String>, name: &str) -> Result<&'a String, OAuthError> {
+    value
+        .as_ref()
+        .ok_or_else(|| OAuthError::InvalidRequest(format!("Missing {name}")))
+}
+
+fn pkce_challenge(code_verifier: &str) -> String {
+    base64_url::encode(&Sha256::digest(code_verifier)).replace("=", "")
+}
+
+#[rocket::get("/.well-known/oauth-protected-resource")]
+pub async fn oauth_protected_resource(cfg: &State<ServerConfig>) -> Json<Value> {
+    let issuer = &cfg.service.public_url;
+    Json(json!({
+        "resource": issuer,
+        "authorization_servers": [issuer],
+        "scopes_supported": SCOPES_SUPPORTED,
+        "bearer_methods_supported": ["header"],
+    }))
+}
+
+#[rocket::get("/.well-known/oauth-authorization-server")]
+pub async fn oauth_authorization_server(cfg: &State<ServerConfig>) -> Json<Value> {
+    let issuer = &cfg.service.public_url;
+    Json(json!({
+        "issuer": issuer,
+        "scopes_supported": SCOPES_SUPPORTED,
+        "subject_types_supported": ["public"],
+        "response_types_supported": ["code"],
+        "response_modes_supported": ["query", "fragment"],
+        "grant_types_supported": ["authorization_code", "refresh_token"],
+        "code_challenge_methods_supported": ["S256"],
+        "ui_locales_supported": ["en-US"],
+        "display_values_supported": ["page"],
+        "authorization_response_iss_parameter_supported": true,
+        "require_pushed_authorization_requests": true,
+        "pushed_authorization_request_endpoint": format!("{issuer}/oauth/par"),
+        "authorization_endpoint": format!("{issuer}/oauth/authorize"),
+        "token_endpoint": format!("{issuer}/oauth/token"),
+        "revocation_endpoint": format!("{issuer}/oauth/revoke"),
+        "token_endpoint_auth_methods_supported": ["none", "private_key_jwt"],
+        "token_endpoint_auth_signing_alg_values_supported": ["ES256"],
+        "dpop_signing_alg_values_supported": ["ES256"],
+        "client_id_metadata_document_supported": true,
+        "protected_resources": [issuer],
+    }))
+}
+
+#[tracing::instrument(skip_all)]
+async fn inner_par(
+    params: AuthorizationParams,
+    dpop: DpopHeader,
+    cfg: &ServerConfig,
+) -> Result<Value, OAuthError> {
+    let issuer = &cfg.service.public_url;
+    let proof = verify_dpop_proof(
+        dpop.0.as_deref(),
+        VerifyDpopOpts {
+            htm: "POST",
+            htu: &format!("{issuer}/oauth/par"),
+            access_token: None,
+            require_nonce: true,
+        },
+    )
+    .await?;
+    let client_id = required(&params.client_id, "client_id")?;
+    let client = get_client_metadata(client_id).await?;
+    authenticate_client(
+        &client,
+        params.client_assertion_type.as_deref(),
+        params.client_assertion.as_deref(),
+        issuer,
+    )
+    .await?;
+
+    if params.response_type.as_deref() != Some("code") {
+        return Err(OAuthError::InvalidRequest(
+            "Unsupported response_type".to_string(),
+        ));
+    }
+    let redirect_uri = match &params.redirect_uri {
+        Some(redirect_uri) => redirect_uri.clone(),
+        None if client.redirect_uris.len() == 1 => client.redirect_uris[0].clone(),
+        None => {
+            return Err(OAuthError::InvalidRequest(
+                "Missing redirect_uri".to_string(),
+            ))
+        }
+    };
+    if !client.has_redirect_uri(&redirect_uri) {
+        return Err(OAuthError::InvalidRequest(
+            "redirect_uri is not registered for this client".to_string(),
+        ));
+    }
+    match (
+        &params.code_challenge,
+        params.code_challenge_method.as_deref(),
+    ) {
+        (Some(_), Some("S256")) => (),
+        _ => {
+            return Err(OAuthError::InvalidRequest(
+                "An S256 code_challenge is required".to_string(),
+            ))
+        }
+    }
+    match params.response_mode.as_deref() {
+        None | Some("query") | Some("fragment") => (),
+        Some(_) => {
+            return Err(OAuthError::InvalidRequest(
+                "Unsupported response_mode".to_string(),
+            ))
+        }
+    }
+    let scope = params.scope.clone().unwrap_or("atproto".to_string());
+    if !scope.split(" ").any(|scope| scope == "atproto") {
+        return Err(OAuthError::InvalidScope(
+            "The atproto scope is required".to_string(),
+        ));
+    }
+    if !scope
+        .split(" ")
+        .all(|scope| SCOPES_SUPPORTED.contains(&scope))
+        || !client.allows_scope(&scope)
+    {
+        return Err(OAuthError::InvalidScope(format!(
+            "Scope `{scope}` is not allowed for this client"
+        )));
+    }
+
+    let id = format!("req-{}", get_random_str());
+    let parameters = AuthorizationParams {
+        redirect_uri: Some(redirect_uri),
+        scope: Some(scope),
+        client_assertion_type: None,
+        client_assertion: None,
+        ..params.clone()
+    };
+    store::create_request(OAuthRequest {
+        id: id.clone(),
+        client_id: client.client_id,
+        parameters: serde_json::to_string(&parameters).map_err(anyhow::Error::new)?,
+        dpop_jkt: proof.jkt,
+        did: None,
+        code: None,
+        expires_at: expires_at(REQUEST_LIFETIME_SECS),
+        created_at: now(),
+    })
+    .await?;
+    Ok(json!({
+        "request_uri": format!("{REQUEST_URI_PREFIX}{id}"),
+        "expires_in": REQUEST_LIFETIME_SECS
+    }))
+}
+
+#[rocket::post("/oauth/par", data = "<body>")]
+pub async fn par(
+    body: Form<AuthorizationParams>,
+    dpop: DpopHeader,
+    cfg: &State<ServerConfig>,
+) -> OAuthResponse {
+    match inner_par(body.into_inner(), dpop, cfg).await {
+        Ok(res) => OAuthResponse::new(Status::Created, res),
+        Err(error) => error.into(),
+    }
+}
+
+// AUTHORIZATION PAGES
+// ---------
+
+fn describe_scope(scope: &str) -> &'static str {
+    match scope {
+        "atproto" => "Identify you by your account",
+        "transition:generic" => "Read and write to your repository, like an app password",
+        "transition:chat.bsky" => "Access your direct messages",
+        _ => "Unknown permission",
+    }
+}
+
+fn render_page(title: &str, body: String) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1">
+  <title>{title}</title>
+  <style>
+    body {{ font-family: sans-serif; max-width: 28rem; margin: 4rem auto; padding: 0 1rem; }}
+    input {{ display: block; width: 100%; margin: 0.5rem 0 1rem; padding: 0.5rem; box-sizing: border-box; }}
+    button {{ padding: 0.5rem 1rem; margin-right: 0.5rem; }}
+    .error {{ color: #b00020; }}
+  </style>
+</head>
+<body>
+  <h1>{title}</h1>
+  {body}
+</body>
+</html>"#,
+        title = escape_html(title)
+    )
+}
+
+fn render_sign_in(
+    client: &ClientMetadata,
+    request_uri: &str,
+    params: &AuthorizationParams,
+    error: Option<&str>,
+) -> RawHtml<String> {
+    let permissions = params
+        .scope
+        .as_deref()
+        .unwrap_or("atproto")
+        .split(" ")
+        .map(|scope| format!("<li>{}</li>", escape_html(describe_scope(scope))))
+        .collect::<String>();
+    let error = match error {
+        Some(error) => format!(r#"<p class="error">{}</p>"#, escape_html(error)),
+        None => String::new(),
+    };
+    let body = format!(
+        r#"<p><strong>{client}</strong> is asking to access your account. It will be able to:</p>
+  <ul>{permissions}</ul>
+  {error}
+  <form method="post" action="/oauth/authorize/sign-in">
+    <input type="hidden" name="request_uri" value="{request_uri}">
+    <label>Handle or email<input name="identifier" value="{identifier}" autocomplete="username" required></label>
+    <label>Password<input name="password" type="password" autocomplete="current-password"></label>
+    <button type="submit" name="decision" value="accept">Sign in and allow</button>
+    <button type="submit" name="decision" value="deny" formnovalidate>Deny</button>
+  </form>"#,
+        client = escape_html(client.display_name()),
+        request_uri = escape_html(request_uri),
+        identifier = escape_html(params.login_hint.as_deref().unwrap_or("")),
+    );
+    RawHtml(render_page("Sign in", body))
+}
+
+fn error_page(status: Status, message: &str) -> AuthorizePage {
+    let body = format!("<p>{}</p>", escape_html(message));
+    AuthorizePage::Error(status::Custom(
+        status,
+        RawHtml(render_page("Authorization failed", body)),
+    ))
+}
+
+/// Sends the user back to the client, with the response in the query or fragment
+fn redirect_to_client(
+    params: &AuthorizationParams,
+    issuer: &String,
+    mut response: Vec<(&str, String)>,
+) -> AuthorizePage {
+    let mut url = match Url::parse(params.redirect_uri.as_deref().unwrap_or("")) {
+        Ok(url) => url,
+        Err(_) => return error_page(Status::BadRequest, "Invalid redirect_uri"),
+    };
+    if let Some(state) = &params.state {
+        response.push(("state", state.clone()));
+    }
+    response.push(("iss", issuer.clone()));
+    match params.response_mode.as_deref() {
+        Some("fragment") => {
+            let fragment = form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(response)
+                .finish();
+            url.set_fragment(Some(&fragment));
+        }
+        _ => {
+            url.query_pairs_mut().extend_pairs(response);
+        }
+    }
+    AuthorizePage::Redirect(Redirect::to(url.to_string()))
+}
+
+async fn load_request(
+    request_uri: &str,
+    client_id: Option<&str>,
+) -> Result<(OAuthRequest, AuthorizationParams, ClientMetadata), AuthorizePage> {
+    let id = request_uri
+        .strip_prefix(REQUEST_URI_PREFIX)
+        .unwrap_or(request_uri)
+        .to_string();
+    let request = match store::get_request(&id).await {
+        Ok(Some(request)) => request,
+        Ok(None) => {
+            return Err(error_page(
+                Status::BadRequest,
+                "This authorization request has expired, please try again from the app",
+            ))
+        }
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            return Err(error_page(
+                Status::InternalServerError,
+                "Internal server error",
+            ));
+        }
+    };
+    if client_id.is_some() && client_id != Some(request.client_id.as_str()) {
+        return Err(error_page(Status::BadRequest, "client_id mismatch"));
+    }
+    let params: AuthorizationParams = match serde_json::from_str(&request.parameters) {
+        Ok(params) => params,
+        Err(_) => {
+            return Err(error_page(
+                Status::InternalServerError,
+                "Internal server error",
+            ))
+        }
+    };
+    match get_client_metadata(&request.client_id).await {
+        Ok(client) => Ok((request, params, client)),
+        Err(error) => Err(error_page(Status::BadRequest, &error.to_string())),
+    }
+}
+
+#[rocket::get("/oauth/authorize?<client_id>&<request_uri>")]
+pub async fn authorize(client_id: String, request_uri: String) -> AuthorizePage {
+    match load_request(&request_uri, Some(&client_id)).await {
+        Ok((_, params, client)) => {
+            AuthorizePage::Page(render_sign_in(&client, &request_uri, &params, None))
+        }
+        Err(page) => page,
+    }
+}
+
+async fn find_account(identifier: &String) -> anyhow::Result<Option<ActorAccount>> {
+    let flags = Some(AvailabilityFlags {
+        include_deactivated: Some(true),
+        include_taken_down: Some(true),
+    });
+    match identifier.contains("@") {
+        true => AccountManager::get_account_by_email(identifier, flags).await,
+        false => AccountManager::get_account(identifier, flags).await,
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn inner_sign_in(form: SignInForm, cfg: &ServerConfig) -> AuthorizePage {
+    let (request, params, client) = match load_request(&form.request_uri, None).await {
+        Ok(loaded) => loaded,
+        Err(page) => return page,
+    };
+    let issuer = &cfg.service.public_url;
+    if form.decision != "accept" {
+        if let Err(error) = store::delete_request(&request.id).await {
+            tracing::error!("@LOG: ERROR: {error}");
+        }
+        return redirect_to_client(
+            &params,
+            issuer,
+            vec![
+                ("error", "access_denied".to_string()),
+                ("error_description", "Access denied".to_string()),
+            ],
+        );
+    }
+
+    // app passwords are deliberately not accepted, OAuth replaces them
+    let identifier = form.identifier.to_lowercase();
+    let user = match find_account(&identifier).await {
+        Ok(Some(user)) => {
+            match AccountManager::verify_account_password(&user.did, &form.password).await {
+                Ok(true) => user,
+                Ok(false) => {
+                    return AuthorizePage::Page(render_sign_in(
+                        &client,
+                        &form.request_uri,
+                        &params,
+                        Some("Invalid identifier or password"),
+                    ))
+                }
+                Err(error) => {
+                    tracing::error!("@LOG: ERROR: {error}");
+                    return error_page(Status::InternalServerError, "Internal server error");
+                }
+            }
+        }
+        Ok(None) => {
+            return AuthorizePage::Page(render_sign_in(
+                &client,
+                &form.request_uri,
+                &params,
+                Some("Invalid identifier or password"),
+            ))
+        }
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            return error_page(Status::InternalServerError, "Internal server error");
+        }
+    };
+    if user.takedown_ref.is_some() {
+        return error_page(Status::Forbidden, "Account has been taken down");
+    }
+
+    let code = format!("cod-{}", get_random_str());
+    let scope = params.scope.clone().unwrap_or("atproto".to_string());
+    let stored = async {
+        store::set_request_code(
+            &request.id,
+            &user.did,
+            &code,
+            expires_at(CODE_LIFETIME_SECS),
+        )
+        .await?;
+        store::upsert_authorized_client(&user.did, &request.client_id, &scope).await
+    };
+    if let Err(error) = stored.await {
+        tracing::error!("@LOG: ERROR: {error}");
+        return error_page(Status::InternalServerError, "Internal server error");
+    }
+    redirect_to_client(&params, issuer, vec![("code", code)])
+}
+
+#[rocket::post("/oauth/authorize/sign-in", data = "<body>")]
+pub async fn sign_in(body: Form<SignInForm>, cfg: &State<ServerConfig>) -> AuthorizePage {
+    inner_sign_in(body.into_inner(), cfg).await
+}
+
+// TOKENS
+// ---------
+
+async fn exchange_code(
+    body: &TokenRequest,
+    client_id: &String,
+    proof: &DpopProof,
+) -> Result<OAuthToken, OAuthError> {
+    let code = required(&body.code, "code")?;
+    let request = store::consume_code(code)
+        .await?
+        .ok_or_else(|| OAuthError::InvalidGrant("Invalid code".to_string()))?;
+    if request.expires_at < now() {
+        return Err(OAuthError::InvalidGrant("Code expired".to_string()));
+    }
+    if &request.client_id != client_id {
+        return Err(OAuthError::InvalidGrant(
+            "Code was issued to another client".to_string(),
+        ));
+    }
+    if request.dpop_jkt != proof.jkt {
+        return Err(OAuthError::InvalidGrant("DPoP key mismatch".to_string()));
+    }
+    let params: AuthorizationParams =
+        serde_json::from_str(&request.parameters).map_err(anyhow::Error::new)?;
+    if body.redirect_uri != params.redirect_uri {
+        return Err(OAuthError::InvalidGrant(
+            "redirect_uri mismatch".to_string(),
+        ));
+    }
+    let code_verifier = required(&body.code_verifier, "code_verifier")?;
+    if Some(pkce_challenge(code_verifier)) != params.code_challenge {
+        return Err(OAuthError::InvalidGrant(
+            "Invalid code_verifier".to_string(),
+        ));
+    }
+    let did = request
+        .did
+        .ok_or_else(|| OAuthError::InvalidGrant("Invalid code".to_string()))?;
+
+    let timestamp = now();
+    let token = OAuthToken {
+        id: format!("tok-{}", get_random_str()),
+        did,
+        client_id: client_id.clone(),
+        scope: params.scope.unwrap_or("atproto".to_string()),
+        dpop_jkt: proof.jkt.clone(),
+        refresh_token: format!("ref-{}", get_random_str()),
+        expires_at: expires_at(REFRESH_TOKEN_LIFETIME_SECS),
+        created_at: timestamp.clone(),
+        updated_at: timestamp,
+    };
+    store::create_token(token.clone()).await?;
+    Ok(token)
+}
+
+async fn refresh(
+    body: &TokenRequest,
+    client_id: &String,
+    proof: &DpopProof,
+) -> Result<OAuthToken, OAuthError> {
+    let refresh_token = required(&body.refresh_token, "refresh_token")?;
+    let invalid = || OAuthError::InvalidGrant("Invalid refresh token".to_string());
+    let existing = store::get_token_by_refresh_token(refresh_token)
+        .await?
+        .ok_or_else(invalid)?;
+    if &existing.client_id != client_id || existing.dpop_jkt != proof.jkt {
+        return Err(invalid());
+    }
+    // takedowns revoke sessions, this catches any issued while one was being applied
+    let flags = Some(AvailabilityFlags {
+        include_deactivated: Some(true),
+        include_taken_down: Some(true),
+    });
+    match AccountManager::get_account(&existing.did, flags).await? {
+        Some(account) if account.takedown_ref.is_none() => (),
+        account => {
+            store::revoke_token(refresh_token).await?;
+            return Err(match account {
+                Some(_) => OAuthError::InvalidGrant("Account has been taken down".to_string()),
+                None => invalid(),
+            });
+        }
+    }
+    // a concurrent refresh with the same token loses the race and gets nothing
+    store::rotate_token(
+        refresh_token,
+        &format!("tok-{}", get_random_str()),
+        &format!("ref-{}", get_random_str()),
+        expires_at(REFRESH_TOKEN_LIFETIME_SECS),
+    )
+    .await?
+    .ok_or_else(invalid)
+}
+
+#[tracing::instrument(skip_all)]
+async fn inner_token(
+    body: TokenRequest,
+    dpop: DpopHeader,
+    cfg: &ServerConfig,
+) -> Result<Value, OAuthError> {
+    let issuer = &cfg.service.public_url;
+    let proof = verify_dpop_proof(
+        dpop.0.as_deref(),
+        VerifyDpopOpts {
+            htm: "POST",
+            htu: &format!("{issuer}/oauth/token"),
+            access_token: None,
+            require_nonce: true,
+        },
+    )
+    .await?;
+    let client_id = required(&body.client_id, "client_id")?;
+    let client = get_client_metadata(client_id).await?;
+    authenticate_client(
+        &client,
+        body.client_assertion_type.as_deref(),
+        body.client_assertion.as_deref(),
+        issuer,
+    )
+    .await?;
+
+    let token = match body.grant_type.as_deref() {
+        Some("authorization_code") => exchange_code(&body, client_id, &proof).await?,
+        Some("refresh_token") => refresh(&body, client_id, &proof).await?,
+        _ => {
+            return Err(OAuthError::InvalidRequest(
+                "Unsupported grant_type".to_string(),
+            ))
+        }
+    };
+    let access_token = create_access_token(&token, issuer, &cfg.service.did)?;
+    Ok(json!({
+        "access_token": access_token,
+        "token_type": "DPoP",
+        "expires_in": ACCESS_TOKEN_LIFETIME_SECS,
+        "refresh_token": token.refresh_token,
+        "scope": token.scope,
+        "sub": token.did
+    }))
+}
+
+#[rocket::post("/oauth/token", data = "<body>")]
+pub async fn token(
+    body: Form<TokenRequest>,
+    dpop: DpopHeader,
+    cfg: &State<ServerConfig>,
+) -> OAuthResponse {
+    match inner_token(body.into_inner(), dpop, cfg).await {
+        Ok(res) => OAuthResponse::new(Status::Ok, res),
+        Err(error) => error.into(),
+    }
+}
+
+/// Always succeeds, per RFC 7009 unknown tokens are not an error
+#[rocket::post("/oauth/revoke", data = "<body>")]
+pub async fn revoke(body: Form<RevokeRequest>, cfg: &State<ServerConfig>) -> OAuthResponse {
+    if let Some(token) = &body.token {
+        // access tokens are revoked along with the session they were issued for
+        let id = match verify_access_token(token, &cfg.service.public_url, &cfg.service.did) {
+            Ok(claims) => claims.jwt_id.unwrap_or(token.clone()),
+            Err(_) => token.clone(),
+        };
+        if let Err(error) = store::revoke_token(&id).await {
+            tracing::error!("@LOG: ERROR: {error}");
+        }
+    }
+    OAuthResponse::new(Status::Ok, json!({}))
+}
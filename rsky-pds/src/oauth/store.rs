@@ -0,0 +1,214 @@
+use crate::db::establish_connection;
+use crate::models::{OAuthRequest, OAuthToken};
+use crate::oauth::now_secs;
+use anyhow::Result;
+use diesel::sql_types::{BigInt, Text};
+use diesel::*;
+use rsky_common::now;
+
+pub async fn create_request(request: OAuthRequest) -> Result<()> {
+    use crate::schema::pds::oauth_request::dsl as RequestSchema;
+    let conn = &mut establish_connection()?;
+
+    // abandoned requests are cleaned up whenever a new one comes in
+    delete(RequestSchema::oauth_request)
+        .filter(RequestSchema::expiresAt.lt(now()))
+        .execute(conn)?;
+    insert_into(RequestSchema::oauth_request)
+        .values(request)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub async fn get_request(id: &String) -> Result<Option<OAuthRequest>> {
+    use crate::schema::pds::oauth_request::dsl as RequestSchema;
+    let conn = &mut establish_connection()?;
+
+    Ok(RequestSchema::oauth_request
+        .find(id)
+        .filter(RequestSchema::expiresAt.gt(now()))
+        .filter(RequestSchema::code.is_null())
+        .first(conn)
+        .optional()?)
+}
+
+pub async fn set_request_code(
+    id: &String,
+    did: &String,
+    code: &String,
+    expires_at: String,
+) -> Result<()> {
+    use crate::schema::pds::oauth_request::dsl as RequestSchema;
+    let conn = &mut establish_connection()?;
+
+    update(RequestSchema::oauth_request)
+        .filter(RequestSchema::id.eq(id))
+        .set((
+            RequestSchema::did.eq(did),
+            RequestSchema::code.eq(code),
+            RequestSchema::expiresAt.eq(expires_at),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Codes can only be exchanged once, the request is gone after this whether it's valid or not
+pub async fn consume_code(code: &String) -> Result<Option<OAuthRequest>> {
+    use crate::schema::pds::oauth_request::dsl as RequestSchema;
+    let conn = &mut establish_connection()?;
+
+    Ok(delete(RequestSchema::oauth_request)
+        .filter(RequestSchema::code.eq(code))
+        .get_result(conn)
+        .optional()?)
+}
+
+pub async fn delete_request(id: &String) -> Result<()> {
+    use crate::schema::pds::oauth_request::dsl as RequestSchema;
+    let conn = &mut establish_connection()?;
+
+    delete(RequestSchema::oauth_request)
+        .filter(RequestSchema::id.eq(id))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub async fn upsert_authorized_client(
+    did: &String,
+    client_id: &String,
+    scope: &String,
+) -> Result<()> {
+    use crate::schema::pds::oauth_authorized_client::dsl as AuthorizedClientSchema;
+    let conn = &mut establish_connection()?;
+
+    let timestamp = now();
+    insert_into(AuthorizedClientSchema::oauth_authorized_client)
+        .values((
+            AuthorizedClientSchema::did.eq(did),
+            AuthorizedClientSchema::clientId.eq(client_id),
+            AuthorizedClientSchema::scope.eq(scope),
+            AuthorizedClientSchema::createdAt.eq(&timestamp),
+            AuthorizedClientSchema::updatedAt.eq(&timestamp),
+        ))
+        .on_conflict((
+            AuthorizedClientSchema::did,
+            AuthorizedClientSchema::clientId,
+        ))
+        .do_update()
+        .set((
+            AuthorizedClientSchema::scope.eq(scope),
+            AuthorizedClientSchema::updatedAt.eq(&timestamp),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub async fn create_token(token: OAuthToken) -> Result<()> {
+    use crate::schema::pds::oauth_token::dsl as TokenSchema;
+    let conn = &mut establish_connection()?;
+
+    insert_into(TokenSchema::oauth_token)
+        .values(token)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub async fn get_token(id: &String) -> Result<Option<OAuthToken>> {
+    use crate::schema::pds::oauth_token::dsl as TokenSchema;
+    let conn = &mut establish_connection()?;
+
+    Ok(TokenSchema::oauth_token
+        .find(id)
+        .filter(TokenSchema::expiresAt.gt(now()))
+        .first(conn)
+        .optional()?)
+}
+
+/// Swaps the token id and refresh token so the previous access and refresh tokens stop working
+pub async fn rotate_token(
+    refresh_token: &String,
+    next_id: &String,
+    next_refresh_token: &String,
+    expires_at: String,
+) -> Result<Option<OAuthToken>> {
+    use crate::schema::pds::oauth_token::dsl as TokenSchema;
+    let conn = &mut establish_connection()?;
+
+    Ok(update(TokenSchema::oauth_token)
+        .filter(TokenSchema::refreshToken.eq(refresh_token))
+        .filter(TokenSchema::expiresAt.gt(now()))
+        .set((
+            TokenSchema::id.eq(next_id),
+            TokenSchema::refreshToken.eq(next_refresh_token),
+            TokenSchema::expiresAt.eq(expires_at),
+            TokenSchema::updatedAt.eq(now()),
+        ))
+        .returning(OAuthToken::as_select())
+        .get_result(conn)
+        .optional()?)
+}
+
+pub async fn get_token_by_refresh_token(refresh_token: &String) -> Result<Option<OAuthToken>> {
+    use crate::schema::pds::oauth_token::dsl as TokenSchema;
+    let conn = &mut establish_connection()?;
+
+    Ok(TokenSchema::oauth_token
+        .filter(TokenSchema::refreshToken.eq(refresh_token))
+        .filter(TokenSchema::expiresAt.gt(now()))
+        .first(conn)
+        .optional()?)
+}
+
+/// Revokes by token id (the access token `jti`) or by refresh token
+pub async fn revoke_token(token: &String) -> Result<bool> {
+    use crate::schema::pds::oauth_token::dsl as TokenSchema;
+    let conn = &mut establish_connection()?;
+
+    let deleted_rows = delete(TokenSchema::oauth_token)
+        .filter(
+            TokenSchema::id
+                .eq(token)
+                .or(TokenSchema::refreshToken.eq(token)),
+        )
+        .execute(conn)?;
+    Ok(deleted_rows > 0)
+}
+
+/// Every session of the account, on takedown or deletion
+pub async fn revoke_tokens_by_did(did: &String) -> Result<bool> {
+    use crate::schema::pds::oauth_token::dsl as TokenSchema;
+    let conn = &mut establish_connection()?;
+
+    let deleted_rows = delete(TokenSchema::oauth_token)
+        .filter(TokenSchema::did.eq(did))
+        .execute(conn)?;
+    Ok(deleted_rows > 0)
+}
+
+/// Records `jti` as used until `expires_at` (unix seconds), returning false if it's still
+/// remembered from an earlier use. Kept in Postgres so a proof can't be replayed against another
+/// instance or after a restart.
+pub async fn use_jti(jti: &str, expires_at: u64) -> Result<bool> {
+    let conn = &mut establish_connection()?;
+
+    let inserted_rows = sql_query(
+        "INSERT INTO pds.oauth_used_jti (jti, \"expiresAt\") VALUES ($1, $2) \
+        ON CONFLICT (jti) DO UPDATE SET \"expiresAt\" = EXCLUDED.\"expiresAt\" \
+        WHERE pds.oauth_used_jti.\"expiresAt\" < $3",
+    )
+    .bind::<Text, _>(jti)
+    .bind::<BigInt, _>(expires_at as i64)
+    .bind::<BigInt, _>(now_secs() as i64)
+    .execute(conn)?;
+    Ok(inserted_rows > 0)
+}
+
+pub async fn purge_used_jtis() -> Result<()> {
+    use crate::schema::pds::oauth_used_jti::dsl as UsedJtiSchema;
+    let conn = &mut establish_connection()?;
+
+    delete(UsedJtiSchema::oauth_used_jti)
+        .filter(UsedJtiSchema::expiresAt.lt(now_secs() as i64))
+        .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use crate::auth_verifier::AuthScope;
+use crate::models::OAuthToken;
+use crate::oauth::ACCESS_TOKEN_LIFETIME_SECS;
+use anyhow::{bail, Result};
+use jwt_simple::prelude::*;
+use std::env;
+
+/// `typ` of OAuth access tokens (RFC 9068), legacy session tokens are plain `JWT`, so neither
+/// can be passed off as the other even though both are signed with the same key
+pub const ACCESS_TOKEN_TYP: &str = "at+jwt";
+
+#[derive(Serialize, Deserialize)]
+pub struct Confirmation {
+    pub jkt: String,
+}
+
+/// Claims of OAuth access tokens, `cnf.jkt` binds the token to the client's DPoP key
+#[derive(Serialize, Deserialize)]
+pub struct OAuthAccessClaims {
+    pub scope: String,
+    pub client_id: String,
+    pub cnf: Confirmation,
+}
+
+fn jwt_key() -> Result<ES256kKeyPair> {
+    let private_key = env::var("PDS_JWT_KEY_K256_PRIVATE_KEY_HEX")?;
+    Ok(ES256kKeyPair::from_bytes(&hex::decode(
+        private_key.as_bytes(),
+    )?)?)
+}
+
+pub fn create_access_token(
+    token: &OAuthToken,
+    issuer: &String,
+    service_did: &String,
+) -> Result<String> {
+    let claims = Claims::with_custom_claims(
+        OAuthAccessClaims {
+            scope: token.scope.clone(),
+            client_id: token.client_id.clone(),
+            cnf: Confirmation {
+                jkt: token.dpop_jkt.clone(),
+            },
+        },
+        Duration::from_secs(ACCESS_TOKEN_LIFETIME_SECS as u64),
+    )
+    .with_issuer(issuer)
+    .with_audience(service_did)
+    .with_subject(&token.did)
+    .with_jwt_id(&token.id);
+    let header = HeaderOptions {
+        signature_type: Some(ACCESS_TOKEN_TYP.to_string()),
+        ..Default::default()
+    };
+    // alg ES256K
+    Ok(jwt_key()?.sign_with_options(claims, &header)?)
+}
+
+pub fn verify_access_token(
+    jwt: &String,
+    issuer: &String,
+    service_did: &String,
+) -> Result<JWTClaims<OAuthAccessClaims>> {
+    let mut options = VerificationOptions::default();
+    options.required_signature_type = Some(ACCESS_TOKEN_TYP.to_string());
+    options.allowed_issuers = Some(HashSet::from_strings(&[issuer]));
+    options.allowed_audiences = Some(HashSet::from_strings(&[service_did]));
+    Ok(jwt_key()?
+        .public_key()
+        .verify_token::<OAuthAccessClaims>(jwt, Some(options))?)
+}
+
+/// Maps the granted OAuth scopes onto what an app password of the same reach could do
+pub fn to_auth_scope(scope: &str) -> Result<AuthScope> {
+    let scopes = scope.split(" ").collect::<Vec<&str>>();
+    if !scopes.contains(&"atproto") {
+        bail!("Bad token scope")
+    }
+    match (
+        scopes.contains(&"transition:generic"),
+        scopes.contains(&"transition:chat.bsky"),
+    ) {
+        (true, true) => Ok(AuthScope::AppPassPrivileged),
+        (true, false) => Ok(AuthScope::AppPass),
+        _ => bail!("Bad token scope"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_transition_scopes() {
+        assert_eq!(
+            to_auth_scope("atproto transition:generic").unwrap(),
+            AuthScope::AppPass
+        );
+        assert_eq!(
+            to_auth_scope("atproto transition:generic transition:chat.bsky").unwrap(),
+            AuthScope::AppPassPrivileged
+        );
+        assert!(to_auth_scope("atproto").is_err());
+        assert!(to_auth_scope("transition:generic").is_err());
+    }
+}
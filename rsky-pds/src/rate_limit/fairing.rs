@@ -0,0 +1,66 @@
+use crate::rate_limit::{
+    endpoint_policies, is_admin_request, RateLimitState, RateLimiter, OAUTH_SIGN_IN_PATH,
+    RATE_LIMIT_EXCEEDED_PATH,
+};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::Method;
+use rocket::{Data, Request, Response};
+
+/// Applies the global and per endpoint ip limits to xrpc requests and oauth sign ins. A request
+/// over a limit is rerouted to `rate_limit_exceeded` since fairings can't respond themselves.
+pub struct RateLimits;
+
+#[rocket::async_trait]
+impl Fairing for RateLimits {
+    fn info(&self) -> Info {
+        Info {
+            name: "Rate limit xrpc requests",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        let limiter = match req.rocket().state::<RateLimiter>() {
+            Some(limiter) if limiter.cfg.enabled => limiter,
+            _ => return,
+        };
+        let path = req.uri().path().as_str();
+        let endpoint = match path.strip_prefix("/xrpc/") {
+            Some(nsid) if !nsid.starts_with('_') => nsid.to_string(),
+            None if path == OAUTH_SIGN_IN_PATH && req.method() == Method::Post => path.to_string(),
+            _ => return,
+        };
+        if req.method() == Method::Options || is_admin_request(req) {
+            return;
+        }
+        // the socket address unless a trusted proxy header is configured, see `main`
+        let ip = match req.client_ip() {
+            Some(ip) => ip.to_string(),
+            None => return,
+        };
+
+        let state = req.local_cache(RateLimitState::default);
+        let mut exceeded = false;
+        let global = limiter.global_policy();
+        for policy in std::iter::once(&global).chain(endpoint_policies(&endpoint)) {
+            if let Some(status) = limiter.consume(policy, &ip, 1).await {
+                exceeded |= status.exceeded;
+                state.record(status);
+            }
+        }
+        if exceeded {
+            tracing::warn!("Rate limited {ip} on {endpoint}");
+            req.set_method(Method::Get);
+            req.set_uri(Origin::parse(RATE_LIMIT_EXCEEDED_PATH).unwrap());
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        if let Some(status) = req.local_cache(RateLimitState::default).get() {
+            for header in status.headers() {
+                res.set_header(header);
+            }
+        }
+    }
+}
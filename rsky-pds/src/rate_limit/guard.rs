@@ -0,0 +1,56 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessOutput;
+use crate::rate_limit::{is_admin_request, RateLimitState, RateLimiter};
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+
+/// Spends the per did repo write budget. Handlers call `consume` once they know what the
+/// request writes.
+pub struct RepoWriteLimit<'r> {
+    // None when rate limits are off or the request is from an admin
+    limiter: Option<&'r RateLimiter>,
+    state: &'r RateLimitState,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RepoWriteLimit<'r> {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let limiter = match req.rocket().state::<RateLimiter>() {
+            Some(limiter) if limiter.cfg.enabled && !is_admin_request(req) => Some(limiter),
+            _ => None,
+        };
+        Outcome::Success(RepoWriteLimit {
+            limiter,
+            state: req.local_cache(RateLimitState::default),
+        })
+    }
+}
+
+impl<'r> RepoWriteLimit<'r> {
+    pub async fn consume(&self, access: &AccessOutput, points: i64) -> Result<(), ApiError> {
+        let limiter = match self.limiter {
+            Some(limiter) => limiter,
+            None => return Ok(()),
+        };
+        let did = match access.credentials.as_ref().and_then(|c| c.did.as_ref()) {
+            Some(did) => did,
+            None => return Ok(()),
+        };
+        let mut exceeded = false;
+        for policy in limiter.repo_write_policies() {
+            if let Some(status) = limiter.consume(&policy, did, points).await {
+                exceeded |= status.exceeded;
+                self.state.record(status);
+            }
+        }
+        match exceeded {
+            true => {
+                tracing::warn!("Rate limited repo writes of {did}");
+                Err(ApiError::RateLimitExceeded)
+            }
+            false => Ok(()),
+        }
+    }
+}
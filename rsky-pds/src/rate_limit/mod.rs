@@ -0,0 +1,241 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::{parse_basic_auth, BasicAuth};
+use crate::config::{RateLimitStoreConfig, RateLimitsConfig};
+use crate::rate_limit::store::{
+    MemoryRateLimitStore, PostgresRateLimitStore, RateLimitStore, RedisRateLimitStore,
+};
+use anyhow::Result;
+use rocket::http::Header;
+use rocket::Request;
+use rsky_common::env::env_str;
+use rsky_common::time::{DAY, HOUR, MINUTE};
+use std::sync::Mutex;
+
+// repo write costs, from the atproto write budget
+pub const CREATE_POINTS: i64 = 3;
+pub const UPDATE_POINTS: i64 = 2;
+pub const DELETE_POINTS: i64 = 1;
+
+pub const RATE_LIMIT_EXCEEDED_PATH: &str = "/xrpc/_rateLimitExceeded";
+// the oauth sign in form checks passwords, so it shares the createSession budget
+pub const OAUTH_SIGN_IN_PATH: &str = "/oauth/authorize/sign-in";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitPolicy {
+    pub name: &'static str,
+    pub points: i64,
+    pub window_ms: i64,
+}
+
+impl RateLimitPolicy {
+    const fn new(name: &'static str, points: i64, window_ms: i32) -> Self {
+        Self {
+            name,
+            points,
+            window_ms: window_ms as i64,
+        }
+    }
+}
+
+const CREATE_SESSION: &[RateLimitPolicy] = &[
+    RateLimitPolicy::new("create-session-5m", 30, 5 * MINUTE),
+    RateLimitPolicy::new("create-session-day", 300, DAY),
+];
+const CREATE_ACCOUNT: &[RateLimitPolicy] =
+    &[RateLimitPolicy::new("create-account-5m", 100, 5 * MINUTE)];
+const RESET_PASSWORD: &[RateLimitPolicy] =
+    &[RateLimitPolicy::new("reset-password-5m", 50, 5 * MINUTE)];
+const REQUEST_PASSWORD_RESET: &[RateLimitPolicy] = &[RateLimitPolicy::new(
+    "request-password-reset-hour",
+    15,
+    HOUR,
+)];
+const UPDATE_HANDLE: &[RateLimitPolicy] = &[
+    RateLimitPolicy::new("update-handle-5m", 10, 5 * MINUTE),
+    RateLimitPolicy::new("update-handle-day", 50, DAY),
+];
+const UPLOAD_BLOB: &[RateLimitPolicy] = &[RateLimitPolicy::new("upload-blob-hour", 1000, HOUR)];
+
+/// Per ip limits on endpoints that are expensive or abusable, on top of the global limit
+pub fn endpoint_policies(endpoint: &str) -> &'static [RateLimitPolicy] {
+    match endpoint {
+        "com.atproto.server.createSession" | OAUTH_SIGN_IN_PATH => CREATE_SESSION,
+        "com.atproto.server.createAccount" => CREATE_ACCOUNT,
+        "com.atproto.server.resetPassword" => RESET_PASSWORD,
+        "com.atproto.server.requestPasswordReset" => REQUEST_PASSWORD_RESET,
+        "com.atproto.identity.updateHandle" => UPDATE_HANDLE,
+        "com.atproto.repo.uploadBlob" => UPLOAD_BLOB,
+        _ => &[],
+    }
+}
+
+/// Where a client stands against one limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitStatus {
+    pub limit: i64,
+    pub remaining: i64,
+    pub reset_at_ms: i64,
+    pub window_ms: i64,
+    pub exceeded: bool,
+}
+
+impl RateLimitStatus {
+    pub fn headers(&self) -> Vec<Header<'static>> {
+        vec![
+            Header::new("RateLimit-Limit", self.limit.to_string()),
+            Header::new("RateLimit-Remaining", self.remaining.to_string()),
+            Header::new("RateLimit-Reset", (self.reset_at_ms / 1000).to_string()),
+            Header::new(
+                "RateLimit-Policy",
+                format!("{};w={}", self.limit, self.window_ms / 1000),
+            ),
+        ]
+    }
+}
+
+/// The tightest limit a request ran into, reported back in its response headers
+#[derive(Debug, Default)]
+pub struct RateLimitState(Mutex<Option<RateLimitStatus>>);
+
+impl RateLimitState {
+    pub fn record(&self, status: RateLimitStatus) {
+        let mut current = self.0.lock().unwrap();
+        match *current {
+            Some(ref tightest) if tightest.remaining <= status.remaining => (),
+            _ => *current = Some(status),
+        }
+    }
+
+    pub fn get(&self) -> Option<RateLimitStatus> {
+        *self.0.lock().unwrap()
+    }
+}
+
+pub struct RateLimiter {
+    pub cfg: RateLimitsConfig,
+    store: Box<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+    /// Falls back to counting in memory when the configured store can't be opened, limits are
+    /// then per instance until the next restart
+    pub async fn new(cfg: RateLimitsConfig) -> Self {
+        let store = match Self::open_store(&cfg.store).await {
+            Ok(store) => store,
+            Err(error) => {
+                tracing::error!(
+                    "@LOG: ERROR: failed to open rate limit store, counting in memory; err: {error}"
+                );
+                Box::new(MemoryRateLimitStore::new())
+            }
+        };
+        Self { cfg, store }
+    }
+
+    async fn open_store(cfg: &RateLimitStoreConfig) -> Result<Box<dyn RateLimitStore>> {
+        Ok(match cfg {
+            RateLimitStoreConfig::Memory => Box::new(MemoryRateLimitStore::new()),
+            RateLimitStoreConfig::Postgres => Box::new(PostgresRateLimitStore::new()?),
+            RateLimitStoreConfig::Redis { url } => Box::new(RedisRateLimitStore::new(url).await?),
+        })
+    }
+
+    pub fn global_policy(&self) -> RateLimitPolicy {
+        RateLimitPolicy {
+            name: "global",
+            points: self.cfg.global_points,
+            window_ms: self.cfg.global_window_ms,
+        }
+    }
+
+    pub fn repo_write_policies(&self) -> [RateLimitPolicy; 2] {
+        [
+            RateLimitPolicy {
+                name: "repo-write-hour",
+                points: self.cfg.repo_write_hourly_points,
+                window_ms: HOUR as i64,
+            },
+            RateLimitPolicy {
+                name: "repo-write-day",
+                points: self.cfg.repo_write_daily_points,
+                window_ms: DAY as i64,
+            },
+        ]
+    }
+
+    /// Spends `points` against the policy for `subject` (an ip or did). A failing store lets the
+    /// request through rather than taking the PDS down with it.
+    pub async fn consume(
+        &self,
+        policy: &RateLimitPolicy,
+        subject: &str,
+        points: i64,
+    ) -> Option<RateLimitStatus> {
+        let key = format!("{}:{subject}", policy.name);
+        match self.store.consume(&key, points, policy.window_ms).await {
+            Ok((used, reset_at_ms)) => Some(RateLimitStatus {
+                limit: policy.points,
+                remaining: (policy.points - used).max(0),
+                reset_at_ms,
+                window_ms: policy.window_ms,
+                exceeded: used > policy.points,
+            }),
+            Err(error) => {
+                tracing::error!("@LOG: ERROR: failed to check rate limit {key}; err: {error}");
+                None
+            }
+        }
+    }
+}
+
+/// Admin basic auth isn't rate limited
+pub fn is_admin_request(req: &Request<'_>) -> bool {
+    let auth_header = req.headers().get_one("Authorization").unwrap_or("");
+    match (parse_basic_auth(auth_header), env_str("PDS_ADMIN_PASS")) {
+        (Some(BasicAuth { username, password }), Some(admin_pass)) => {
+            username == "admin" && password == admin_pass
+        }
+        _ => false,
+    }
+}
+
+/// Rate limited requests are rerouted here by the fairing
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/_rateLimitExceeded")]
+pub async fn rate_limit_exceeded() -> ApiError {
+    ApiError::RateLimitExceeded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_keeps_the_tightest_limit() {
+        let status = |limit, remaining| RateLimitStatus {
+            limit,
+            remaining,
+            reset_at_ms: 0,
+            window_ms: 0,
+            exceeded: false,
+        };
+        let state = RateLimitState::default();
+        state.record(status(3000, 2990));
+        state.record(status(30, 20));
+        state.record(status(300, 290));
+        assert_eq!(state.get(), Some(status(30, 20)));
+    }
+
+    #[test]
+    fn sign_in_shares_the_create_session_limits() {
+        assert_eq!(
+            endpoint_policies(OAUTH_SIGN_IN_PATH),
+            endpoint_policies("com.atproto.server.createSession")
+        );
+        assert!(endpoint_policies("com.atproto.repo.getRecord").is_empty());
+    }
+}
+
+pub mod fairing;
+pub mod guard;
+pub mod store;
@@ -0,0 +1,226 @@
+use crate::db::establish_connection;
+use crate::models::RateLimit;
+use anyhow::Result;
+use diesel::pg::PgConnection;
+use diesel::r2d2::{self, Pool};
+use diesel::sql_types::{BigInt, Text};
+use diesel::*;
+use redis::aio::ConnectionManager;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::env;
+use std::sync::Mutex;
+
+// connections the postgres store keeps open for checking limits
+const POSTGRES_POOL_SIZE: u32 = 4;
+
+#[rocket::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Adds `points` to the window for `key`, starting a new window of `window_ms` if the last one
+    /// is over. Returns the points used in the window and when it resets, in ms since epoch.
+    async fn consume(&self, key: &str, points: i64, window_ms: i64) -> Result<(i64, i64)>;
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Keeps windows in process, fine for a single PDS instance
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+    windows: Mutex<MemoryWindows>,
+}
+
+#[derive(Default)]
+struct MemoryWindows {
+    by_key: HashMap<String, (i64, i64)>,
+    // when each window started ends, soonest first, so finished ones are dropped without a scan
+    expiries: BinaryHeap<Reverse<(i64, String)>>,
+}
+
+impl MemoryWindows {
+    fn prune(&mut self, now: i64) {
+        while let Some(Reverse((reset_at, _))) = self.expiries.peek() {
+            if *reset_at > now {
+                break;
+            }
+            let Reverse((reset_at, key)) = self.expiries.pop().unwrap();
+            // the key may have started a newer window since
+            if self.by_key.get(&key).map(|window| window.1) == Some(reset_at) {
+                self.by_key.remove(&key);
+            }
+        }
+    }
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[rocket::async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn consume(&self, key: &str, points: i64, window_ms: i64) -> Result<(i64, i64)> {
+        let now = now_ms();
+        let mut windows = self.windows.lock().unwrap();
+        windows.prune(now);
+        let window = match windows.by_key.get_mut(key) {
+            Some(window) => {
+                window.0 += points;
+                *window
+            }
+            None => {
+                let window = (points, now + window_ms);
+                windows.by_key.insert(key.to_string(), window);
+                windows.expiries.push(Reverse((window.1, key.to_string())));
+                window
+            }
+        };
+        Ok(window)
+    }
+}
+
+/// Shares windows between PDS instances through `pds.rate_limit`
+pub struct PostgresRateLimitStore {
+    pool: Pool<r2d2::ConnectionManager<PgConnection>>,
+}
+
+impl PostgresRateLimitStore {
+    pub fn new() -> Result<Self> {
+        let database_url = env::var("DATABASE_URL").unwrap_or("".into());
+        let pool = Pool::builder()
+            .max_size(POSTGRES_POOL_SIZE)
+            .build(r2d2::ConnectionManager::new(database_url))?;
+        Ok(Self { pool })
+    }
+}
+
+#[rocket::async_trait]
+impl RateLimitStore for PostgresRateLimitStore {
+    async fn consume(&self, key: &str, points: i64, window_ms: i64) -> Result<(i64, i64)> {
+        let pool = self.pool.clone();
+        let key = key.to_string();
+        tokio::task::spawn_blocking(move || {
+            let conn = &mut pool.get()?;
+            consume_postgres_window(conn, &key, points, window_ms)
+        })
+        .await?
+    }
+}
+
+fn consume_postgres_window(
+    conn: &mut PgConnection,
+    key: &str,
+    points: i64,
+    window_ms: i64,
+) -> Result<(i64, i64)> {
+    let now = now_ms();
+    let window = sql_query(
+        "INSERT INTO pds.rate_limit (key, points, \"resetAt\") \
+        VALUES ($1, $2, $3) \
+        ON CONFLICT (key) DO UPDATE SET \
+            points = CASE WHEN pds.rate_limit.\"resetAt\" <= $4 \
+                THEN EXCLUDED.points ELSE pds.rate_limit.points + EXCLUDED.points END, \
+            \"resetAt\" = CASE WHEN pds.rate_limit.\"resetAt\" <= $4 \
+                THEN EXCLUDED.\"resetAt\" ELSE pds.rate_limit.\"resetAt\" END \
+        RETURNING *",
+    )
+    .bind::<Text, _>(key)
+    .bind::<BigInt, _>(points)
+    .bind::<BigInt, _>(now + window_ms)
+    .bind::<BigInt, _>(now)
+    .get_result::<RateLimit>(conn)?;
+    Ok((window.points, window.reset_at))
+}
+
+/// Increments the key and sets its expiry when the window starts, in one round trip
+const REDIS_CONSUME_SCRIPT: &str = r#"
+local points = redis.call('INCRBY', KEYS[1], ARGV[1])
+if points == tonumber(ARGV[1]) then
+    redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return {points, redis.call('PTTL', KEYS[1])}
+"#;
+
+/// Shares windows between PDS instances through Redis, letting keys expire with their window
+pub struct RedisRateLimitStore {
+    conn: ConnectionManager,
+    script: redis::Script,
+}
+
+impl RedisRateLimitStore {
+    pub async fn new(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url)?;
+        Ok(Self {
+            conn: ConnectionManager::new(client).await?,
+            script: redis::Script::new(REDIS_CONSUME_SCRIPT),
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn consume(&self, key: &str, points: i64, window_ms: i64) -> Result<(i64, i64)> {
+        let mut conn = self.conn.clone();
+        let (points, ttl_ms): (i64, i64) = self
+            .script
+            .key(format!("rl:{key}"))
+            .arg(points)
+            .arg(window_ms)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok((points, now_ms() + ttl_ms.max(0)))
+    }
+}
+
+pub async fn purge_expired_rate_limits() -> Result<()> {
+    use crate::schema::pds::rate_limit::dsl as RateLimitSchema;
+    let conn = &mut establish_connection()?;
+
+    delete(RateLimitSchema::rate_limit)
+        .filter(RateLimitSchema::resetAt.le(now_ms()))
+        .execute(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_store_starts_a_new_window() {
+        let store = MemoryRateLimitStore::new();
+        let (points, reset_at) = store.consume("test", 2, 60_000).await.unwrap();
+        assert_eq!(points, 2);
+        assert_eq!(
+            store.consume("test", 3, 60_000).await.unwrap(),
+            (5, reset_at)
+        );
+        // a zero length window is over as soon as it starts
+        store.consume("short", 4, 0).await.unwrap();
+        assert_eq!(store.consume("short", 1, 60_000).await.unwrap().0, 1);
+    }
+
+    #[test]
+    fn memory_store_drops_finished_windows() {
+        let mut windows = MemoryWindows::default();
+        for (key, reset_at) in [("a", 10), ("b", 30), ("c", 20)] {
+            windows.by_key.insert(key.to_string(), (1, reset_at));
+            windows.expiries.push(Reverse((reset_at, key.to_string())));
+        }
+        // "a" started a newer window after its first one, so only the newer expiry drops it
+        windows.by_key.insert("a".to_string(), (1, 40));
+        windows.expiries.push(Reverse((40, "a".to_string())));
+
+        windows.prune(20);
+        let mut keys: Vec<_> = windows.by_key.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b"]);
+        assert_eq!(windows.expiries.len(), 2);
+
+        windows.prune(40);
+        assert!(windows.by_key.is_empty());
+        assert!(windows.expiries.is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::blobstore::BlobStoreCreator;
 use crate::actor_store::ActorStore;
 use crate::db::DbConn;
 use crate::pipethrough::parse_res;
@@ -7,7 +7,6 @@ use crate::read_after_write::viewer::{get_records_since_rev, LocalViewer};
 use crate::xrpc_server::types::HandlerPipeThrough;
 use crate::SharedLocalViewer;
 use anyhow::Result;
-use aws_config::SdkConfig;
 use chrono::offset::Utc as UtcOffset;
 use chrono::DateTime;
 use rocket::http::Status;
@@ -113,7 +112,7 @@ pub async fn handle_read_after_write<T: DeserializeOwned + serde::Serialize>(
     requester: String,
     res: HandlerPipeThrough,
     munge: MungeFn<T>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     db: DbConn,
 ) -> Result<ReadAfterWriteResponse<T>> {
@@ -122,7 +121,7 @@ pub async fn handle_read_after_write<T: DeserializeOwned + serde::Serialize>(
         requester.clone(),
         res.clone(),
         munge,
-        s3_config,
+        blobstore,
         state_local_viewer,
         db,
     )
@@ -145,7 +144,7 @@ pub async fn read_after_write_internal<T: DeserializeOwned + serde::Serialize>(
     requester: String,
     res: HandlerPipeThrough,
     munge: MungeFn<T>,
-    s3_config: &State<SdkConfig>,
+    blobstore: &State<BlobStoreCreator>,
     state_local_viewer: &State<SharedLocalViewer>,
     db: DbConn,
 ) -> Result<ReadAfterWriteResponse<T>> {
@@ -154,11 +153,8 @@ pub async fn read_after_write_internal<T: DeserializeOwned + serde::Serialize>(
     match rev {
         None => Ok(ReadAfterWriteResponse::HandlerPipeThrough(res)),
         Some(rev) => {
-            let actor_store = ActorStore::new(
-                requester.clone(),
-                S3BlobStore::new(requester.clone(), s3_config),
-                db,
-            );
+            let actor_store =
+                ActorStore::new(requester.clone(), blobstore.create(requester.clone()), db);
             let local = get_records_since_rev(&actor_store, rev).await?;
             if local.count <= 0 {
                 return Ok(ReadAfterWriteResponse::HandlerPipeThrough(res));
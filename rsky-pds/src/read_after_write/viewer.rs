@@ -123,12 +123,10 @@ impl LocalViewer {
 
     pub fn get_image_url(&self, pattern: String, cid: String) -> String {
         match &self.appview_cdn_url_pattern {
+            // served by the PDS itself when there is no app view CDN
             None => format!(
-                "https://{}/xrpc/{}?did={}&cid={}",
-                self.pds_hostname,
-                Ids::ComAtprotoSyncGetBlob.as_str(),
-                self.did,
-                cid
+                "https://{}/img/{}/plain/{}/{}@jpeg",
+                self.pds_hostname, pattern, self.did, cid
             ),
             Some(appview_cdn_url_pattern) => {
                 util::nodejs_format(&*appview_cdn_url_pattern, &[&pattern, &self.did, &cid])
@@ -1,18 +1,23 @@
-use crate::lexicon::LEXICONS;
+use crate::lexicon::validator::{RecordValidation, LEXICON_VALIDATOR};
 use anyhow::bail;
-use lazy_static::lazy_static;
 use lexicon_cid::Cid;
 use rsky_common::ipld::cid_for_cbor;
 use rsky_common::tid::Ticker;
 use rsky_lexicon::blob_refs::{BlobRef, JsonBlobRef};
 use rsky_repo::storage::Ipld;
 use rsky_repo::types::{
-    BlobConstraint, Ids, Lex, PreparedBlobRef, PreparedCreateOrUpdate, PreparedDelete, RepoRecord,
+    BlobConstraint, Lex, PreparedBlobRef, PreparedCreateOrUpdate, PreparedDelete, RepoRecord,
     WriteOpAction,
 };
 use rsky_repo::util::{cbor_to_lex, lex_to_ipld};
 use rsky_syntax::aturi::AtUri;
-use serde_json::{json, Value as JsonValue};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+/// A record that doesn't match its lexicon, reported to the client as `InvalidRecord`
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct InvalidRecordError(pub String);
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct FoundBlobRef {
@@ -45,38 +50,31 @@ pub struct PrepareDeleteOpts {
     pub swap_cid: Option<Cid>,
 }
 
-pub fn blobs_for_write(record: RepoRecord, validate: bool) -> anyhow::Result<Vec<PreparedBlobRef>> {
-    let refs = find_blob_refs(Lex::Map(record.clone()), None, None);
-    let record_type = match record.get("$type") {
-        Some(Lex::Ipld(Ipld::String(t))) => Some(t),
-        _ => None,
-    };
+/// `blob_constraints` come from lexicon validation, blobs of records which weren't validated are
+/// accepted as is
+pub fn blobs_for_write(
+    record: RepoRecord,
+    blob_constraints: &HashMap<String, BlobConstraint>,
+) -> anyhow::Result<Vec<PreparedBlobRef>> {
+    let refs = find_blob_refs(Lex::Map(record), None, None);
     for r#ref in refs.clone() {
         if matches!(r#ref.r#ref.original, JsonBlobRef::Untyped(_)) {
             bail!("Legacy blob ref at `{}`", r#ref.path.join("/"))
         }
     }
     refs.into_iter()
-        .map(|FoundBlobRef { r#ref, path }| {
-            let constraints: BlobConstraint = match (validate, record_type) {
-                (true, Some(record_type)) => {
-                    let properties: crate::lexicon::lexicons::Image2 = serde_json::from_value(
-                        crate::repo::prepare::CONSTRAINTS[record_type.as_str()][path.join("/")]
-                            .clone(),
-                    )?;
-                    BlobConstraint {
-                        max_size: Some(properties.max_size as usize),
-                        accept: Some(properties.accept),
-                    }
-                }
-                (_, _) => BlobConstraint {
+        .map(|FoundBlobRef { r#ref, .. }| {
+            let cid = r#ref.get_cid()?;
+            let constraints = match blob_constraints.get(&cid.to_string()) {
+                Some(constraints) => constraints.clone(),
+                None => BlobConstraint {
                     max_size: None,
                     accept: None,
                 },
             };
 
             Ok(PreparedBlobRef {
-                cid: r#ref.get_cid()?,
+                cid,
                 mime_type: r#ref.get_mime_type().to_string(),
                 constraints,
             })
@@ -137,10 +135,33 @@ pub fn find_blob_refs(val: Lex, path: Option<Vec<String>>, layer: Option<u8>) ->
     }
 }
 
-pub fn assert_valid_record(record: &RepoRecord) -> anyhow::Result<()> {
-    match record.get("$type") {
-        Some(Lex::Ipld(Ipld::String(_))) => Ok(()),
-        _ => bail!("No $type provided"),
+/// Validates the record against the lexicon of its `$type`. Unless `require_lexicon` is set, a
+/// record of an unknown type passes with an `unknown` status.
+pub fn assert_valid_record(
+    record: &RepoRecord,
+    require_lexicon: bool,
+) -> anyhow::Result<RecordValidation> {
+    let record_type = match record.get("$type") {
+        Some(Lex::Ipld(Ipld::String(record_type)))
+        | Some(Lex::Ipld(Ipld::Json(JsonValue::String(record_type)))) => record_type,
+        _ => bail!(InvalidRecordError("No $type provided".to_string())),
+    };
+    LEXICON_VALIDATOR
+        .validate_record(record_type, record, require_lexicon)
+        .map_err(|error| {
+            InvalidRecordError(format!("Invalid {record_type} record: {error}")).into()
+        })
+}
+
+/// `validate` is the flag of the request: `true` requires a known lexicon, unset validates
+/// records of known types only and `false` skips validation
+fn validate_record(
+    record: &RepoRecord,
+    validate: Option<bool>,
+) -> anyhow::Result<Option<RecordValidation>> {
+    match validate {
+        Some(false) => Ok(None),
+        _ => Ok(Some(assert_valid_record(record, validate == Some(true))?)),
     }
 }
 
@@ -155,7 +176,9 @@ pub fn set_collection_name(
             Lex::Ipld(Ipld::Json(JsonValue::String(collection.clone()))),
         );
     }
-    if let Some(Lex::Ipld(Ipld::Json(JsonValue::String(record_type)))) = record.get("$type") {
+    if let Some(Lex::Ipld(Ipld::String(record_type)))
+    | Some(Lex::Ipld(Ipld::Json(JsonValue::String(record_type)))) = record.get("$type")
+    {
         if validate && record_type.to_string() != *collection {
             bail!("Invalid $type: expected {collection}, got {record_type}")
         }
@@ -180,12 +203,12 @@ pub async fn prepare_create(opts: PrepareCreateOpts) -> anyhow::Result<PreparedC
         validate,
         ..
     } = opts;
-    let validate = validate.unwrap_or_else(|| true);
-
-    let record = set_collection_name(&collection, opts.record, validate)?;
-    if validate {
-        assert_valid_record(&record)?;
-    }
+    let record = set_collection_name(&collection, opts.record, validate != Some(false))?;
+    let validation = validate_record(&record, validate)?;
+    let blob_constraints = validation
+        .as_ref()
+        .map(|validation| validation.blob_constraints.clone())
+        .unwrap_or_default();
 
     // assert_no_explicit_slurs(rkey, record).await?;
     let next_rkey = Ticker::new().next(None);
@@ -197,7 +220,8 @@ pub async fn prepare_create(opts: PrepareCreateOpts) -> anyhow::Result<PreparedC
         cid: cid_for_safe_record(record.clone()).await?,
         swap_cid,
         record: record.clone(),
-        blobs: blobs_for_write(record, validate)?,
+        blobs: blobs_for_write(record, &blob_constraints)?,
+        validation_status: validation.map(|validation| validation.status),
     })
 }
 
@@ -210,12 +234,12 @@ pub async fn prepare_update(opts: PrepareUpdateOpts) -> anyhow::Result<PreparedC
         validate,
         ..
     } = opts;
-    let validate = validate.unwrap_or_else(|| true);
-
-    let record = set_collection_name(&collection, opts.record, validate)?;
-    if validate {
-        assert_valid_record(&record)?;
-    }
+    let record = set_collection_name(&collection, opts.record, validate != Some(false))?;
+    let validation = validate_record(&record, validate)?;
+    let blob_constraints = validation
+        .as_ref()
+        .map(|validation| validation.blob_constraints.clone())
+        .unwrap_or_default();
     // assert_no_explicit_slurs(rkey, record).await?;
     let uri = AtUri::make(did, Some(collection), Some(rkey))?;
     Ok(PreparedCreateOrUpdate {
@@ -224,7 +248,8 @@ pub async fn prepare_update(opts: PrepareUpdateOpts) -> anyhow::Result<PreparedC
         cid: cid_for_safe_record(record.clone()).await?,
         swap_cid,
         record: record.clone(),
-        blobs: blobs_for_write(record, validate)?,
+        blobs: blobs_for_write(record, &blob_constraints)?,
+        validation_status: validation.map(|validation| validation.status),
     })
 }
 
@@ -243,25 +268,22 @@ pub fn prepare_delete(opts: PrepareDeleteOpts) -> anyhow::Result<PreparedDelete>
     })
 }
 
-lazy_static! {
-    static ref CONSTRAINTS: JsonValue = {
-        json!({
-            Ids::AppBskyActorProfile.as_str(): {
-                "avatar": LEXICONS.app_bsky_actor_profile.defs.main.record.properties.avatar,
-                "banner": LEXICONS.app_bsky_actor_profile.defs.main.record.properties.banner
-            },
-            Ids::AppBskyFeedGenerator.as_str(): {
-                "avatar": LEXICONS.app_bsky_feed_generator.defs.main.record.properties.avatar
-            },
-            Ids::AppBskyGraphList.as_str(): {
-                "avatar": LEXICONS.app_bsky_graph_list.defs.main.record.properties.avatar
-            },
-            Ids::AppBskyFeedPost.as_str(): {
-                "embed/images/image": LEXICONS.app_bsky_embed_images.defs.image.properties.image,
-                "embed/external/thumb": LEXICONS.app_bsky_embed_external.defs.external.properties.thumb,
-                "embed/media/images/image": LEXICONS.app_bsky_embed_images.defs.image.properties.image,
-                "embed/media/external/thumb": LEXICONS.app_bsky_embed_external.defs.external.properties.thumb
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reports_invalid_records() {
+        let error = |value: JsonValue| {
+            let record: RepoRecord = serde_json::from_value(value).unwrap();
+            match assert_valid_record(&record, false) {
+                Ok(_) => panic!("record should be invalid"),
+                Err(error) => error.downcast::<InvalidRecordError>().unwrap().0,
             }
-        })
-    };
+        };
+        assert_eq!(error(json!({ "text": "hi" })), "No $type provided");
+        assert!(error(json!({ "$type": "app.bsky.feed.post", "text": 5 }))
+            .starts_with("Invalid app.bsky.feed.post record: "));
+    }
 }
@@ -13,6 +13,20 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.account_migration (did) {
+            did -> Varchar,
+            oldPds -> Varchar,
+            newPds -> Varchar,
+            step -> Varchar,
+            status -> Varchar,
+            blobsImported -> Int4,
+            error -> Nullable<Varchar>,
+            createdAt -> Varchar,
+            updatedAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.account_pref (id) {
             id -> Int4,
@@ -22,6 +36,20 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.admin_action (id) {
+            id -> Int8,
+            batchId -> Varchar,
+            action -> Varchar,
+            did -> Varchar,
+            params -> Nullable<Varchar>,
+            performedBy -> Varchar,
+            outcome -> Varchar,
+            error -> Nullable<Varchar>,
+            createdAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.actor (did) {
             did -> Varchar,
@@ -64,6 +92,14 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.did_cache (did) {
+            did -> Varchar,
+            doc -> Nullable<Text>,
+            updatedAt -> Int8,
+        }
+    }
+
     diesel::table! {
         pds.did_doc (did) {
             did -> Varchar,
@@ -81,6 +117,14 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.handle_cache (handle) {
+            handle -> Varchar,
+            did -> Nullable<Varchar>,
+            updatedAt -> Int8,
+        }
+    }
+
     diesel::table! {
         pds.invite_code (code) {
             code -> Varchar,
@@ -100,6 +144,74 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.job (id) {
+            id -> Int8,
+            kind -> Varchar,
+            payload -> Varchar,
+            status -> Varchar,
+            attempts -> Int4,
+            lastError -> Nullable<Varchar>,
+            uniqueKey -> Nullable<Varchar>,
+            runAt -> Varchar,
+            lockedAt -> Nullable<Varchar>,
+            createdAt -> Varchar,
+            updatedAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.oauth_authorized_client (did, clientId) {
+            did -> Varchar,
+            clientId -> Varchar,
+            scope -> Varchar,
+            createdAt -> Varchar,
+            updatedAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.oauth_request (id) {
+            id -> Varchar,
+            clientId -> Varchar,
+            parameters -> Varchar,
+            dpopJkt -> Varchar,
+            did -> Nullable<Varchar>,
+            code -> Nullable<Varchar>,
+            expiresAt -> Varchar,
+            createdAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.oauth_token (id) {
+            id -> Varchar,
+            did -> Varchar,
+            clientId -> Varchar,
+            scope -> Varchar,
+            dpopJkt -> Varchar,
+            refreshToken -> Varchar,
+            expiresAt -> Varchar,
+            createdAt -> Varchar,
+            updatedAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.oauth_used_jti (jti) {
+            jti -> Varchar,
+            expiresAt -> Int8,
+        }
+    }
+
+    diesel::table! {
+        pds.rate_limit (key) {
+            key -> Varchar,
+            points -> Int8,
+            resetAt -> Int8,
+        }
+    }
+
     diesel::table! {
         pds.record (uri) {
             uri -> Varchar,
@@ -161,22 +273,95 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.wallet (did) {
+            did -> Varchar,
+            address -> Varchar,
+            encryptedKey -> Varchar,
+            createdAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.wallet_index_state (id) {
+            id -> Varchar,
+            lastBlockHash -> Varchar,
+            lastBlockNumber -> Int8,
+            updatedAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.wallet_request (id) {
+            id -> Varchar,
+            did -> Varchar,
+            amount -> Varchar,
+            description -> Varchar,
+            status -> Varchar,
+            fulfilledBy -> Nullable<Varchar>,
+            createdAt -> Varchar,
+            updatedAt -> Varchar,
+            deployId -> Nullable<Varchar>,
+        }
+    }
+
+    diesel::table! {
+        pds.wallet_retired (did, address) {
+            did -> Varchar,
+            address -> Varchar,
+            encryptedKey -> Varchar,
+            createdAt -> Varchar,
+            retiredAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.wallet_transaction (id, address) {
+            id -> Varchar,
+            address -> Varchar,
+            direction -> Varchar,
+            counterparty -> Varchar,
+            amount -> Varchar,
+            description -> Varchar,
+            cost -> Varchar,
+            blockHash -> Varchar,
+            blockNumber -> Int8,
+            createdAt -> Int8,
+            succeeded -> Bool,
+        }
+    }
+
     diesel::allow_tables_to_appear_in_same_query!(
         account,
+        account_migration,
         account_pref,
         actor,
+        admin_action,
         app_password,
         backlink,
         blob,
+        did_cache,
         did_doc,
         email_token,
+        handle_cache,
         invite_code,
         invite_code_use,
+        job,
+        oauth_authorized_client,
+        oauth_request,
+        oauth_token,
+        oauth_used_jti,
+        rate_limit,
         record,
         record_blob,
         refresh_token,
         repo_block,
         repo_root,
         repo_seq,
+        wallet,
+        wallet_index_state,
+        wallet_request,
+        wallet_retired,
+        wallet_transaction,
     );
 }
@@ -213,6 +213,17 @@ impl SeqEvt {
             SeqEvt::TypedTombstoneEvt(this) => this.seq,
         }
     }
+
+    /// When the event was sequenced
+    pub fn time(&self) -> &String {
+        match self {
+            SeqEvt::TypedCommitEvt(this) => &this.time,
+            SeqEvt::TypedHandleEvt(this) => &this.time,
+            SeqEvt::TypedIdentityEvt(this) => &this.time,
+            SeqEvt::TypedAccountEvt(this) => &this.time,
+            SeqEvt::TypedTombstoneEvt(this) => &this.time,
+        }
+    }
 }
 
 pub async fn format_seq_commit(
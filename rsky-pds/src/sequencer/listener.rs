@@ -0,0 +1,55 @@
+use crate::sequencer::REPO_SEQ_CHANNEL;
+use anyhow::{anyhow, Result};
+use dotenvy::dotenv;
+use futures::{stream, StreamExt};
+use native_tls::TlsConnector;
+use postgres_native_tls::MakeTlsConnector;
+use std::env;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::time::{sleep, Duration};
+use tokio_postgres::AsyncMessage;
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Wakes the sequencer whenever any instance sharing the database sequences an event.
+/// Runs for the lifetime of the process, reconnecting whenever the connection drops.
+pub async fn listen(new_evts: Arc<Notify>) {
+    loop {
+        if let Err(err) = listen_once(Arc::clone(&new_evts)).await {
+            tracing::error!(
+                "@LOG: sequencer failed to LISTEN on {REPO_SEQ_CHANNEL}, polling only; err: {err}"
+            );
+        }
+        sleep(RECONNECT_INTERVAL).await;
+    }
+}
+
+async fn listen_once(new_evts: Arc<Notify>) -> Result<()> {
+    dotenv().ok();
+    let database_url = env::var("DATABASE_URL").unwrap_or("".into());
+    // TLS is used as the url's `sslmode` asks, `prefer` by default
+    let tls = MakeTlsConnector::new(TlsConnector::new()?);
+    let (client, mut connection) = tokio_postgres::connect(&database_url, tls).await?;
+
+    // notifications are only delivered while the connection is being polled
+    let notify = Arc::clone(&new_evts);
+    let driver = tokio::spawn(async move {
+        let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            if let AsyncMessage::Notification(_) = message? {
+                notify.notify_one();
+            }
+        }
+        anyhow::Ok(())
+    });
+
+    client
+        .batch_execute(&format!("LISTEN {REPO_SEQ_CHANNEL}"))
+        .await?;
+    // catch up on anything sequenced elsewhere while we weren't listening
+    new_evts.notify_one();
+
+    driver.await??;
+    Err(anyhow!("connection closed"))
+}
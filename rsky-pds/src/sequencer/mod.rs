@@ -1,22 +1,22 @@
 use crate::account_manager::helpers::account::AccountStatus;
 use crate::crawlers::Crawlers;
 use crate::db::establish_connection;
+use crate::metrics::{observe_sequenced, REPO_WRITES};
 use crate::models;
 use crate::sequencer::events::{
     format_seq_account_evt, format_seq_commit, format_seq_handle_update, format_seq_identity_evt,
     format_seq_tombstone, SeqEvt, TypedAccountEvt, TypedCommitEvt, TypedHandleEvt,
     TypedIdentityEvt, TypedTombstoneEvt,
 };
-use crate::EVENT_EMITTER;
 use anyhow::Result;
+use chrono::Utc;
 use diesel::*;
-use futures::{Stream, StreamExt};
-use rsky_common::time::SECOND;
-use rsky_common::{cbor_to_struct, wait};
+use rsky_common::cbor_to_struct;
 use rsky_repo::types::{CommitData, PreparedWrite};
-use std::cmp;
-use std::pin::Pin;
-use std::task::{Context, Poll, Waker};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Notify};
+use tokio::time::{timeout, Duration};
 
 pub struct RequestSeqRangeOpts {
     pub earliest_seq: Option<i64>,
@@ -25,45 +25,88 @@ pub struct RequestSeqRangeOpts {
     pub limit: Option<i64>,
 }
 
+/// Channel other instances sharing the database are notified on when an event is sequenced
+pub const REPO_SEQ_CHANNEL: &str = "repo_seq";
+/// Catch-up poll in case a notification was missed, e.g. while LISTEN was reconnecting
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const POLL_LIMIT: i64 = 1000;
+
 #[derive(Debug, Clone)]
 pub struct Sequencer {
-    pub destroyed: bool,
-    pub tries_with_no_results: u32,
-    pub waker: Option<Waker>,
+    pub destroyed: Arc<AtomicBool>,
     pub crawlers: Crawlers,
     pub last_seen: Option<i64>,
+    events: broadcast::Sender<SeqEvt>,
+    new_evts: Arc<Notify>,
 }
 
 impl Sequencer {
-    pub fn new(crawlers: Crawlers, last_seen: Option<i64>) -> Self {
+    /// `max_buffer` bounds how far a subscriber may fall behind before it is dropped, at least
+    /// one event is always buffered
+    pub fn new(crawlers: Crawlers, last_seen: Option<i64>, max_buffer: usize) -> Self {
+        let (events, _) = broadcast::channel(max_buffer.max(1));
         Sequencer {
-            destroyed: false,
-            tries_with_no_results: 0,
+            destroyed: Arc::new(AtomicBool::new(false)),
             last_seen: Some(last_seen.unwrap_or(0)),
-            waker: None,
             crawlers,
+            events,
+            new_evts: Arc::new(Notify::new()),
         }
     }
 
+    /// Reads newly sequenced events and fans them out to every subscriber. Wakes up when this
+    /// instance sequences an event, on a `repo_seq` notification from another instance, and
+    /// every `POLL_INTERVAL` otherwise.
     pub async fn start(&mut self) -> Result<()> {
         let curr = self.curr().await?;
         self.last_seen = Some(curr.unwrap_or(0));
-        if self.waker.is_none() {
-            loop {
-                while let Some(_) = self.next().await {
-                    ()
-                }
+        tokio::spawn(listener::listen(Arc::clone(&self.new_evts)));
+        while !self.destroyed.load(Ordering::Relaxed) {
+            let _ = timeout(POLL_INTERVAL, self.new_evts.notified()).await;
+            if let Err(err) = self.poll_db().await {
+                tracing::error!(
+                    "@LOG: sequencer failed to poll db, err: {}, last_seen: {:?}",
+                    err.to_string(),
+                    self.last_seen
+                );
             }
         }
         Ok(())
     }
 
     pub async fn destroy(&mut self) {
-        self.destroyed = true;
-        if let Some(waker) = self.waker.take() {
-            waker.wake();
+        self.destroyed.store(true, Ordering::Relaxed);
+        self.new_evts.notify_one();
+    }
+
+    /// Live events, starting with the next one read by the running sequencer
+    pub fn subscribe(&self) -> broadcast::Receiver<SeqEvt> {
+        self.events.subscribe()
+    }
+
+    async fn poll_db(&mut self) -> Result<()> {
+        loop {
+            let evts = self
+                .request_seq_range(RequestSeqRangeOpts {
+                    earliest_seq: self.last_seen,
+                    latest_seq: None,
+                    earliest_time: None,
+                    limit: Some(POLL_LIMIT),
+                })
+                .await?;
+            let fetched = evts.len() as i64;
+            if let Some(last_evt) = evts.last() {
+                self.last_seen = Some(last_evt.seq());
+            }
+            for evt in evts {
+                observe_sequenced(evt.seq(), evt.time(), Utc::now().timestamp_millis());
+                // fails only when nobody is subscribed
+                let _ = self.events.send(evt);
+            }
+            if fetched < POLL_LIMIT {
+                return Ok(());
+            }
         }
-        EVENT_EMITTER.write().await.emit("close", ());
     }
 
     pub async fn curr(&self) -> Result<Option<i64>> {
@@ -189,18 +232,10 @@ impl Sequencer {
         Ok(seq_evts)
     }
 
-    async fn exponential_backoff(&mut self) -> () {
-        self.tries_with_no_results += 1;
-        let wait_time = cmp::min(
-            2u64.checked_pow(self.tries_with_no_results).unwrap_or(2),
-            SECOND as u64,
-        );
-        wait(wait_time);
-        if let Some(waker) = self.waker.take() {
-            waker.wake();
-        }
-    }
-
+    #[tracing::instrument(
+        skip_all,
+        fields(did = %evt.did, event_type = %evt.event_type, seq = tracing::field::Empty)
+    )]
     pub async fn sequence_evt(&mut self, evt: models::RepoSeq) -> Result<i64> {
         use crate::schema::pds::repo_seq::dsl as RepoSeqSchema;
         let conn = &mut establish_connection()?;
@@ -213,8 +248,15 @@ impl Sequencer {
                 RepoSeqSchema::sequencedAt.eq(evt.sequenced_at),
             ))
             .get_result::<models::RepoSeq>(conn)?;
+        let seq = res.seq.expect("Sequence number wasn't updated on insert.");
+        tracing::Span::current().record("seq", seq);
+        sql_query("SELECT pg_notify($1, $2)")
+            .bind::<sql_types::Text, _>(REPO_SEQ_CHANNEL)
+            .bind::<sql_types::Text, _>(seq.to_string())
+            .execute(conn)?;
+        self.new_evts.notify_one();
         self.crawlers.notify_of_update().await?;
-        Ok(res.seq.expect("Sequence number wasn't updated on insert."))
+        Ok(seq)
     }
 
     pub async fn sequence_commit(
@@ -223,6 +265,14 @@ impl Sequencer {
         commit_data: CommitData,
         writes: Vec<PreparedWrite>,
     ) -> Result<i64> {
+        for write in writes.iter() {
+            let action = match write {
+                PreparedWrite::Create(_) => "create",
+                PreparedWrite::Update(_) => "update",
+                PreparedWrite::Delete(_) => "delete",
+            };
+            REPO_WRITES.with_label_values(&[action]).inc();
+        }
         let evt = format_seq_commit(did, commit_data, writes).await?;
         self.sequence_evt(evt).await
     }
@@ -256,56 +306,6 @@ impl Sequencer {
     }
 }
 
-impl Stream for Sequencer {
-    type Item = Result<(), anyhow::Error>;
-
-    #[tracing::instrument(skip_all)]
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if self.destroyed {
-            return Poll::Ready(None);
-        }
-        // if already polling, do not start another poll
-        return match futures::executor::block_on(self.request_seq_range(RequestSeqRangeOpts {
-            earliest_seq: self.last_seen,
-            latest_seq: None,
-            earliest_time: None,
-            limit: Some(1000),
-        })) {
-            Err(err) => {
-                tracing::error!(
-                    "@LOG: sequencer failed to poll db, err: {}, last_seen: {:?}",
-                    err.to_string(),
-                    self.last_seen
-                );
-                self.waker = Some(cx.waker().clone());
-                futures::executor::block_on(self.exponential_backoff());
-                Poll::Ready(Some(Err(err)))
-            }
-            Ok(evts) => {
-                if evts.len() > 0 {
-                    self.tries_with_no_results = 0;
-                    futures::executor::block_on(EVENT_EMITTER.write()).emit(
-                        "events",
-                        evts.iter()
-                            .map(|evt| serde_json::to_string(evt).unwrap())
-                            .collect::<Vec<String>>(),
-                    );
-                    self.last_seen = match evts.last() {
-                        None => self.last_seen,
-                        Some(last_evt) => Some(last_evt.seq()),
-                    };
-                    self.waker = Some(cx.waker().clone());
-                    Poll::Ready(Some(Ok(())))
-                } else {
-                    self.waker = Some(cx.waker().clone());
-                    futures::executor::block_on(self.exponential_backoff());
-                    Poll::Pending
-                }
-            }
-        };
-    }
-}
-
 pub async fn delete_all_for_user(did: &String, excluding_seqs: Option<Vec<i64>>) -> Result<()> {
     use crate::schema::pds::repo_seq::dsl as RepoSeqSchema;
     let conn = &mut establish_connection()?;
@@ -322,4 +322,5 @@ pub async fn delete_all_for_user(did: &String, excluding_seqs: Option<Vec<i64>>)
 }
 
 pub mod events;
+pub mod listener;
 pub mod outbox;
@@ -1,47 +1,33 @@
 use crate::sequencer::events::SeqEvt;
 use crate::sequencer::{RequestSeqRangeOpts, Sequencer};
-use crate::EVENT_EMITTER;
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use futures::stream::Stream;
 use futures::{pin_mut, StreamExt};
 use rocket::async_stream::try_stream;
-use rsky_common::r#async::{AsyncBuffer, AsyncBufferFullError};
-use std::sync::Arc;
-use tokio::runtime::Runtime;
-use tokio::sync::{Mutex, RwLock};
-use tokio::time::{timeout, Duration};
+use tokio::sync::broadcast::error::RecvError;
 
-#[derive(Debug, Clone)]
-pub struct OutboxOpts {
-    pub max_buffer_size: usize,
-}
+/// The subscriber fell further behind the live events than the shared buffer holds
+#[derive(Debug, thiserror::Error)]
+#[error("Stream consumer too slow")]
+pub struct ConsumerTooSlowError;
 
 pub struct Outbox {
-    caught_up: Arc<Mutex<bool>>,
     pub last_seen: i64,
-    pub cutover_buffer: Arc<Mutex<Vec<SeqEvt>>>,
-    pub out_buffer: Arc<RwLock<AsyncBuffer<SeqEvt>>>,
     pub sequencer: Sequencer,
-    pub backfill_cursor: Option<i64>,
 }
 
 const PAGE_SIZE: i64 = 500;
 
 impl Outbox {
-    pub fn new(sequencer: Sequencer, opts: Option<OutboxOpts>) -> Self {
-        let OutboxOpts { max_buffer_size } = opts.unwrap_or(OutboxOpts {
-            max_buffer_size: 500,
-        });
+    pub fn new(sequencer: Sequencer) -> Self {
         Self {
             sequencer,
-            caught_up: Arc::new(Mutex::new(false)),
             last_seen: -1,
-            cutover_buffer: Arc::new(Mutex::new(vec![])),
-            out_buffer: Arc::new(RwLock::new(AsyncBuffer::new(Some(max_buffer_size)))),
-            backfill_cursor: None,
         }
     }
 
+    /// Backfills from the database starting after `backfill_cursor`, then cuts over to the
+    /// events broadcast by the running sequencer
     pub async fn events<'a>(
         &'a mut self,
         backfill_cursor: Option<i64>,
@@ -50,82 +36,32 @@ impl Outbox {
             if let Some(cursor) = backfill_cursor {
                 let backfill_stream = self.get_backfill(cursor).await;
                 pin_mut!(backfill_stream);
-                while let Some(Ok(evt)) = backfill_stream.next().await {
-                    yield evt;
+                while let Some(evt) = backfill_stream.next().await {
+                    yield evt?;
                 }
-            } else {
-                let mut bool_lock = self.caught_up.lock().await;
-                *bool_lock = true;
             }
 
-            let caught_up = Arc::clone(&self.caught_up);
-            let out_buffer = Arc::clone(&self.out_buffer);
-            let cutover_buffer = Arc::clone(&self.cutover_buffer);
-
-            let add_to_buffer = move |evts: Vec<String>| {
-                let evts = evts
-                    .into_iter()
-                    .map(|evt| serde_json::from_str(evt.as_str()).unwrap())
-                    .collect::<Vec<SeqEvt>>();
-                let caught_up = Arc::clone(&caught_up);
-                let out_buffer = Arc::clone(&out_buffer);
-                let cutover_buffer = Arc::clone(&cutover_buffer);
-
-                async move {
-                    if *caught_up.lock().await {
-                        out_buffer.read().await.push_many(evts);
-                    } else {
-                        cutover_buffer.lock().await.extend(evts);
-                    }
-                }
-            };
-
-            EVENT_EMITTER.write().await.on("events", move |evts| {
-                let rt = Runtime::new().unwrap();
-                // By entering the context, we tie `tokio::spawn` to this executor.
-                let _guard = rt.enter();
-                rt.block_on(tokio::spawn(
-                    add_to_buffer(evts)
-                )).unwrap();
-            });
-
+            // subscribing only once mostly caught up keeps a long backfill from overflowing
+            // the live buffer, the second pass picks up what was sequenced in between
+            let mut live = self.sequencer.subscribe();
             if let Some(cursor) = backfill_cursor {
-                let earliest_seq = if self.last_seen > -1 {
-                    Some(self.last_seen)
-                } else {
-                    Some(cursor)
-                };
-                let cutover_evts = self.sequencer.request_seq_range(RequestSeqRangeOpts {
-                    earliest_seq,
-                    latest_seq: None,
-                    earliest_time: None,
-                    limit: Some(PAGE_SIZE),
-                }).await?;
-                {
-                    let out_buffer_lock = self.out_buffer.read().await;
-                    let mut cutover_lock = self.cutover_buffer.lock().await;
-                    out_buffer_lock.push_many(cutover_evts);
-                    out_buffer_lock.push_many(cutover_lock.drain(..).collect());
+                let cutover_stream = self.get_backfill(cursor).await;
+                pin_mut!(cutover_stream);
+                while let Some(evt) = cutover_stream.next().await {
+                    yield evt?;
                 }
-                let mut bool_lock = self.caught_up.lock().await;
-                *bool_lock = true;
-            } else {
-                let mut bool_lock = self.caught_up.lock().await;
-                *bool_lock = true;
             }
 
             loop {
-                while let Ok(Some(res)) = timeout(Duration::from_secs(2),self.out_buffer.write().await.next()).await {
-                    let evt = res.map_err(|error| {
-                        match error.downcast_ref() {
-                            Some(AsyncBufferFullError(_)) => anyhow!("Stream consumer too slow.".to_string()),
-                            _ => anyhow!(error.to_string())
+                match live.recv().await {
+                    Ok(evt) => {
+                        if evt.seq() > self.last_seen {
+                            self.last_seen = evt.seq();
+                            yield evt;
                         }
-                    })?;
-                    if evt.seq() > self.last_seen {
-                        self.last_seen = evt.seq();
-                        yield evt;
                     }
+                    Err(RecvError::Lagged(_)) => Err::<(), _>(ConsumerTooSlowError)?,
+                    Err(RecvError::Closed) => break,
                 }
             }
         }
@@ -142,27 +78,90 @@ impl Outbox {
                 } else {
                     Some(backfill_cursor)
                 };
-                let evts = match self.sequencer.request_seq_range(RequestSeqRangeOpts {
+                let evts = self.sequencer.request_seq_range(RequestSeqRangeOpts {
                     earliest_seq,
                     latest_seq: None,
                     earliest_time: None,
                     limit: Some(PAGE_SIZE),
-                }).await {
-                    Ok(res) => res,
-                    Err(_) => break
-                };
-                for evt in evts.iter() {
+                }).await?;
+                let fetched = evts.len() as i64;
+                for evt in evts {
                     self.last_seen = evt.seq();
-                    yield evt.clone();
-                }
-                let seq_cursor = self.sequencer.last_seen.unwrap_or(-1);
-                if seq_cursor - self.last_seen < (PAGE_SIZE / 2)  {
-                    break;
+                    yield evt;
                 }
-                if evts.is_empty() {
+                if fetched < PAGE_SIZE {
                     break;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawlers::Crawlers;
+    use crate::sequencer::events::{TombstoneEvt, TypedTombstoneEvt};
+
+    fn tombstone(seq: i64) -> SeqEvt {
+        SeqEvt::TypedTombstoneEvt(TypedTombstoneEvt {
+            r#type: "tombstone".to_string(),
+            seq,
+            time: "2025-01-01T00:00:00.000Z".to_string(),
+            evt: TombstoneEvt {
+                did: "did:plc:alice".to_string(),
+            },
+        })
+    }
+
+    fn sequencer(max_buffer: usize) -> Sequencer {
+        Sequencer::new(
+            Crawlers::new("pds.test".to_string(), vec![]),
+            None,
+            max_buffer,
+        )
+    }
+
+    /// Sends `seqs` once the outbox has subscribed, the stream only subscribes when first polled
+    async fn broadcast(sequencer: Sequencer, seqs: Vec<i64>) {
+        tokio::task::yield_now().await;
+        for seq in seqs {
+            sequencer.events.send(tombstone(seq)).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_live_events_the_backfill_already_sent() {
+        let sequencer = sequencer(8);
+        let mut outbox = Outbox::new(sequencer.clone());
+        // as if the cutover pass had read up to seq 5
+        outbox.last_seen = 5;
+        let events = outbox.events(None).await;
+        pin_mut!(events);
+        let received = events
+            .take(2)
+            .map(|evt| evt.unwrap().seq())
+            .collect::<Vec<i64>>();
+        let (_, received) = tokio::join!(broadcast(sequencer, vec![4, 5, 6, 6, 7]), received);
+        assert_eq!(received, vec![6, 7]);
+    }
+
+    #[tokio::test]
+    async fn drops_subscribers_that_fall_behind_the_buffer() {
+        let sequencer = sequencer(2);
+        let mut outbox = Outbox::new(sequencer.clone());
+        let events = outbox.events(None).await;
+        pin_mut!(events);
+        let first = events.next();
+        let (_, first) = tokio::join!(broadcast(sequencer, vec![1, 2, 3, 4]), first);
+        let error = first.unwrap().unwrap_err();
+        assert!(error.downcast_ref::<ConsumerTooSlowError>().is_some());
+    }
+
+    #[test]
+    fn buffers_at_least_one_event() {
+        let sequencer = sequencer(0);
+        let _subscriber = sequencer.subscribe();
+        assert!(sequencer.events.send(tombstone(1)).is_ok());
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use rsky_common::env::env_str;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Installs the global tracing subscriber. Logs are filtered by `RUST_LOG` (`info` by default) and
+/// written as JSON lines when `PDS_LOG_FORMAT=json`. Spans are exported over OTLP when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set; the returned provider has to be shut down to flush them.
+pub fn init_tracing() -> Result<Option<TracerProvider>> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let fmt = match env_str("PDS_LOG_FORMAT").as_deref() {
+        Some("json") => tracing_subscriber::fmt::layer().json().boxed(),
+        _ => tracing_subscriber::fmt::layer().boxed(),
+    };
+    let provider = match env_str("OTEL_EXPORTER_OTLP_ENDPOINT") {
+        None => None,
+        Some(endpoint) => Some(otlp_tracer_provider(endpoint)?),
+    };
+    let otel = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("rsky-pds")));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt)
+        .with(otel)
+        .try_init()?;
+    Ok(provider)
+}
+
+fn otlp_tracer_provider(endpoint: String) -> Result<TracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let service_name = env_str("OTEL_SERVICE_NAME").unwrap_or("rsky-pds".to_string());
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            service_name,
+        )]))
+        .build())
+}
@@ -0,0 +1,382 @@
+use crate::db::establish_connection;
+use crate::models::{WalletIndexState, WalletTransaction};
+use crate::wallet::transfer_request;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use diesel::*;
+use firefly_api::models::rhoapi::Par;
+use firefly_api::providers::FireflyProvider;
+use firefly_api::set_transfer_succeeded;
+use firefly_api::transaction::{OperationHeader, Transaction};
+use firefly_api::write_node_client::BlockInfo;
+use rsky_common;
+use std::cmp;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+const INDEX_STATE_ID: &str = "transfers";
+// RNode refuses block ranges wider than its `max-blocks-limit`, 50 by default
+const BLOCKS_PAGE_SIZE: i64 = 50;
+
+pub const DIRECTION_INCOMING: &str = "incoming";
+pub const DIRECTION_OUTGOING: &str = "outgoing";
+
+pub const DEFAULT_LIMIT: i64 = 100;
+pub const MAX_LIMIT: i64 = 500;
+
+/// Keeps `pds.wallet_transaction` in sync with the Firefly chain.
+///
+/// Every run only fetches blocks from the last indexed height up to the newest block, so the
+/// cost of serving wallet history no longer grows with the length of the chain.
+pub struct WalletHistoryIndexer {
+    provider: FireflyProvider,
+}
+
+impl WalletHistoryIndexer {
+    pub fn new(provider: FireflyProvider) -> Self {
+        Self { provider }
+    }
+
+    pub async fn run(&self, interval: Duration) {
+        loop {
+            match self.index_new_blocks().await {
+                Ok(0) => (),
+                Ok(indexed) => tracing::debug!("Indexed {indexed} Firefly blocks"),
+                Err(error) => tracing::error!("Failed to index wallet transactions: {error}"),
+            }
+            if let Err(error) = transfer_request::reconcile_pending_transfer_requests().await {
+                tracing::error!("Failed to reconcile pending transfer requests: {error}");
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Indexes every block above the last indexed height and returns how many were processed.
+    pub async fn index_new_blocks(&self) -> Result<usize> {
+        let client = self.provider.write_client();
+        let latest = client.get_latest_block().await?;
+        let state = get_index_state().await?;
+
+        // The last indexed height is scanned again because sibling blocks at the same height
+        // can be added to the DAG after we've seen the first one.
+        let mut start = match state {
+            Some(ref state) if state.last_block_hash == latest.block_hash => return Ok(0),
+            Some(ref state) => state.last_block_number,
+            None => 0,
+        };
+
+        let mut indexed = 0;
+        while start <= latest.block_number {
+            let end = cmp::min(start + BLOCKS_PAGE_SIZE - 1, latest.block_number);
+            for block in client.get_blocks_by_heights(start, end).await? {
+                let deploys = client.get_block_transactions(&block.block_hash).await?;
+                let mut transfers = Vec::new();
+                for transaction in parse_block_transactions(&block, deploys) {
+                    if !is_transfer(&transaction) {
+                        continue;
+                    }
+                    let result = client
+                        .get_deploy_data::<Par>(&block.block_hash, &transaction.id)
+                        .await?;
+                    transfers.push((transaction, set_transfer_succeeded(result)));
+                }
+                store_block_transfers(&block, transfers).await?;
+                update_index_state(&block).await?;
+                indexed += 1;
+            }
+            start = end + 1;
+        }
+        Ok(indexed)
+    }
+}
+
+/// Anyone can deploy, so a malformed operation header is logged and skipped. Failing on it would
+/// retry the same block forever.
+fn parse_block_transactions(
+    block: &BlockInfo,
+    deploys: Vec<(String, DateTime<Utc>, OperationHeader, u64)>,
+) -> Vec<Transaction> {
+    deploys
+        .into_iter()
+        .filter_map(|deploy| {
+            let id = deploy.0.clone();
+            match Transaction::new(deploy) {
+                Ok(transaction) => Some(transaction),
+                Err(error) => {
+                    tracing::warn!(
+                        "Skipping deploy {id} in block {}: {error}",
+                        block.block_hash
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn is_transfer(transaction: &Transaction) -> bool {
+    transaction.name == "SET_TRANSFER" && transaction.arguments.len() >= 3
+}
+
+pub async fn get_index_state() -> Result<Option<WalletIndexState>> {
+    use crate::schema::pds::wallet_index_state::dsl as WalletIndexStateSchema;
+    let conn = &mut establish_connection()?;
+
+    let state = WalletIndexStateSchema::wallet_index_state
+        .filter(WalletIndexStateSchema::id.eq(INDEX_STATE_ID))
+        .select(WalletIndexState::as_select())
+        .first(conn)
+        .optional()?;
+    Ok(state)
+}
+
+async fn update_index_state(block: &BlockInfo) -> Result<()> {
+    use crate::schema::pds::wallet_index_state::dsl as WalletIndexStateSchema;
+    let conn = &mut establish_connection()?;
+
+    let now = rsky_common::now();
+    insert_into(WalletIndexStateSchema::wallet_index_state)
+        .values((
+            WalletIndexStateSchema::id.eq(INDEX_STATE_ID),
+            WalletIndexStateSchema::lastBlockHash.eq(&block.block_hash),
+            WalletIndexStateSchema::lastBlockNumber.eq(block.block_number),
+            WalletIndexStateSchema::updatedAt.eq(&now),
+        ))
+        .on_conflict(WalletIndexStateSchema::id)
+        .do_update()
+        .set((
+            WalletIndexStateSchema::lastBlockHash.eq(&block.block_hash),
+            WalletIndexStateSchema::lastBlockNumber.eq(block.block_number),
+            WalletIndexStateSchema::updatedAt.eq(&now),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+async fn store_block_transfers(
+    block: &BlockInfo,
+    transfers: Vec<(Transaction, bool)>,
+) -> Result<()> {
+    use crate::schema::pds::wallet_transaction::dsl as WalletTransactionSchema;
+
+    let rows: Vec<WalletTransaction> = transfers
+        .into_iter()
+        .flat_map(|(transaction, succeeded)| {
+            let from_address = transaction.arguments[0].clone();
+            let to_address = transaction.arguments[1].clone();
+            let row = WalletTransaction {
+                id: transaction.id,
+                address: from_address.clone(),
+                direction: DIRECTION_OUTGOING.to_string(),
+                counterparty: to_address.clone(),
+                amount: transaction.arguments[2].clone(),
+                description: transaction.arguments.get(3).cloned().unwrap_or_default(),
+                cost: transaction.cost,
+                block_hash: block.block_hash.clone(),
+                block_number: block.block_number,
+                created_at: transaction.date_time.timestamp_millis(),
+                succeeded,
+            };
+            let incoming = WalletTransaction {
+                address: to_address,
+                direction: DIRECTION_INCOMING.to_string(),
+                counterparty: from_address,
+                ..row.clone()
+            };
+            [row, incoming]
+        })
+        .collect();
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let conn = &mut establish_connection()?;
+    insert_into(WalletTransactionSchema::wallet_transaction)
+        .values(&rows)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Position after the last transaction of a page, packed as `<createdAt millis>::<deploy id>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionsCursor {
+    pub created_at: i64,
+    pub id: String,
+}
+
+impl fmt::Display for TransactionsCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}", self.created_at, self.id)
+    }
+}
+
+impl FromStr for TransactionsCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(cursor: &str) -> Result<Self> {
+        let (created_at, id) = cursor
+            .split_once("::")
+            .filter(|(_, id)| !id.is_empty())
+            .ok_or_else(|| anyhow!("Malformed cursor"))?;
+        let created_at = created_at
+            .parse::<i64>()
+            .map_err(|_| anyhow!("Malformed cursor"))?;
+        Ok(Self {
+            created_at,
+            id: id.to_string(),
+        })
+    }
+}
+
+/// Page size for a requested `limit`
+pub fn page_limit(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+/// A full page may be followed by more transactions, a shorter one is the last
+fn next_cursor(transactions: &[WalletTransaction], limit: i64) -> Option<TransactionsCursor> {
+    match transactions.last() {
+        Some(last) if transactions.len() as i64 == limit => Some(TransactionsCursor {
+            created_at: last.created_at,
+            id: last.id.clone(),
+        }),
+        _ => None,
+    }
+}
+
+pub struct ListWalletTransactionsOpts {
+    pub address: String,
+    pub limit: i64,
+    pub cursor: Option<TransactionsCursor>,
+    /// Unix timestamp in milliseconds, inclusive
+    pub since: Option<i64>,
+    /// Unix timestamp in milliseconds, exclusive
+    pub until: Option<i64>,
+}
+
+/// Lists indexed transactions of a wallet, newest first.
+pub async fn list_wallet_transactions(
+    opts: ListWalletTransactionsOpts,
+) -> Result<(Vec<WalletTransaction>, Option<String>)> {
+    use crate::schema::pds::wallet_transaction::dsl as WalletTransactionSchema;
+    let conn = &mut establish_connection()?;
+
+    let ListWalletTransactionsOpts {
+        address,
+        limit,
+        cursor,
+        since,
+        until,
+    } = opts;
+
+    // failed transfers only cost the deployer phlo, they aren't part of the history
+    let mut builder = WalletTransactionSchema::wallet_transaction
+        .filter(WalletTransactionSchema::address.eq(address))
+        .filter(WalletTransactionSchema::succeeded.eq(true))
+        .into_boxed();
+    if let Some(since) = since {
+        builder = builder.filter(WalletTransactionSchema::createdAt.ge(since));
+    }
+    if let Some(until) = until {
+        builder = builder.filter(WalletTransactionSchema::createdAt.lt(until));
+    }
+    if let Some(TransactionsCursor { created_at, id }) = cursor {
+        builder = builder.filter(
+            WalletTransactionSchema::createdAt.lt(created_at).or(
+                WalletTransactionSchema::createdAt
+                    .eq(created_at)
+                    .and(WalletTransactionSchema::id.lt(id)),
+            ),
+        );
+    }
+
+    let transactions = builder
+        .order((
+            WalletTransactionSchema::createdAt.desc(),
+            WalletTransactionSchema::id.desc(),
+        ))
+        .limit(limit)
+        .select(WalletTransaction::as_select())
+        .load(conn)?;
+
+    let cursor = next_cursor(&transactions, limit).map(|cursor| cursor.to_string());
+    Ok((transactions, cursor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(id: &str, created_at: i64) -> WalletTransaction {
+        WalletTransaction {
+            id: id.to_string(),
+            address: "1111from".to_string(),
+            direction: DIRECTION_OUTGOING.to_string(),
+            counterparty: "1111to".to_string(),
+            amount: "100".to_string(),
+            description: String::new(),
+            cost: "0".to_string(),
+            block_hash: "hash".to_string(),
+            block_number: 1,
+            created_at,
+            succeeded: true,
+        }
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor: TransactionsCursor = "1742817600000::3045022100ab".parse().unwrap();
+        assert_eq!(cursor.created_at, 1742817600000);
+        assert_eq!(cursor.id, "3045022100ab");
+        assert_eq!(cursor.to_string(), "1742817600000::3045022100ab");
+
+        assert!("1742817600000".parse::<TransactionsCursor>().is_err());
+        assert!("abc::3045022100ab".parse::<TransactionsCursor>().is_err());
+        assert!("1742817600000::".parse::<TransactionsCursor>().is_err());
+    }
+
+    #[test]
+    fn only_full_pages_have_a_cursor() {
+        let page = vec![transaction("b", 2), transaction("a", 1)];
+        assert_eq!(
+            next_cursor(&page, 2),
+            Some(TransactionsCursor {
+                created_at: 1,
+                id: "a".to_string()
+            })
+        );
+        assert_eq!(next_cursor(&page, 3), None);
+        assert_eq!(next_cursor(&[], 2), None);
+
+        assert_eq!(page_limit(None), DEFAULT_LIMIT);
+        assert_eq!(page_limit(Some(0)), 1);
+        assert_eq!(page_limit(Some(10_000)), MAX_LIMIT);
+    }
+
+    #[test]
+    fn skips_malformed_deploys() {
+        let block = BlockInfo {
+            block_hash: "hash".to_string(),
+            block_number: 1,
+        };
+        let deploys = vec![
+            (
+                "bad".to_string(),
+                Utc::now(),
+                OperationHeader::new("", vec![]),
+                0,
+            ),
+            (
+                "good".to_string(),
+                Utc::now(),
+                OperationHeader::new("SET_TRANSFER", vec!["a".into(), "b".into(), "1".into()]),
+                0,
+            ),
+        ];
+        let transactions = parse_block_transactions(&block, deploys);
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].id, "good");
+    }
+}
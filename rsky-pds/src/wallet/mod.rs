@@ -1 +1,232 @@
-pub struct WalletService {}
+use crate::db::establish_connection;
+use crate::models::Wallet;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use diesel::*;
+use firefly_api::client::helpers::rev_address_from_private_key;
+use rsky_common;
+use rsky_common::env::env_str;
+use secp256k1::SecretKey;
+
+const NONCE_LEN: usize = 12;
+
+/// An import without `replace` for an account that already has a different wallet
+#[derive(Debug, thiserror::Error)]
+#[error("Account already has a wallet")]
+pub struct WalletExists;
+
+/// Decrypted wallet of a PDS account, ready to be handed to `FireflyProvider::firefly()`
+#[derive(Debug, Clone)]
+pub struct AccountWallet {
+    pub did: String,
+    pub address: String,
+    pub key: String,
+}
+
+/// Manages the Firefly wallet owned by each account.
+///
+/// Private keys are stored in `pds.wallet` encrypted with AES-256-GCM under
+/// `PDS_WALLET_ENCRYPTION_KEY_HEX`; they are only decrypted when a request needs to sign a deploy.
+#[derive(Clone)]
+pub struct WalletService {
+    cipher: Aes256Gcm,
+}
+
+impl WalletService {
+    pub fn new(encryption_key: &[u8]) -> Result<Self> {
+        if encryption_key.len() != 32 {
+            bail!("Wallet encryption key must be 32 bytes");
+        }
+        let key = Key::<Aes256Gcm>::from_slice(encryption_key);
+        Ok(Self {
+            cipher: Aes256Gcm::new(key),
+        })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let encryption_key = env_str("PDS_WALLET_ENCRYPTION_KEY_HEX").context(
+            "Failed to get wallet encryption key, set in .env PDS_WALLET_ENCRYPTION_KEY_HEX",
+        )?;
+        Self::new(&hex::decode(encryption_key)?)
+    }
+
+    pub async fn get_wallet(&self, did: &String) -> Result<Option<AccountWallet>> {
+        use crate::schema::pds::wallet::dsl as WalletSchema;
+        let conn = &mut establish_connection()?;
+
+        let wallet = WalletSchema::wallet
+            .filter(WalletSchema::did.eq(did))
+            .select(Wallet::as_select())
+            .first(conn)
+            .optional()?;
+        match wallet {
+            None => Ok(None),
+            Some(wallet) => Ok(Some(AccountWallet {
+                key: self.decrypt_key(&wallet.encrypted_key)?,
+                did: wallet.did,
+                address: wallet.address,
+            })),
+        }
+    }
+
+    /// Returns the account's wallet, generating a fresh secp256k1 key on first use.
+    pub async fn get_or_create_wallet(&self, did: &String) -> Result<AccountWallet> {
+        if let Some(wallet) = self.get_wallet(did).await? {
+            return Ok(wallet);
+        }
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        self.store_wallet(did, hex::encode(secret_key.secret_bytes()))
+            .await
+    }
+
+    /// Sets the account's wallet to an existing hex encoded secp256k1 private key. An account that
+    /// already has another wallet fails with [`WalletExists`] unless `replace` is set, then the
+    /// previous key is kept in `pds.wallet_retired` so funds left at its address stay reachable.
+    pub async fn import_wallet(
+        &self,
+        did: &String,
+        wallet_key: String,
+        replace: bool,
+    ) -> Result<AccountWallet> {
+        use crate::schema::pds::wallet::dsl as WalletSchema;
+        use crate::schema::pds::wallet_retired::dsl as RetiredSchema;
+        let wallet_key = wallet_key.to_lowercase();
+
+        let wallet = self.store_wallet(did, wallet_key.clone()).await?;
+        if wallet.key == wallet_key {
+            return Ok(wallet);
+        }
+        if !replace {
+            bail!(WalletExists);
+        }
+
+        let conn = &mut establish_connection()?;
+        let address = rev_address_from_private_key(&wallet_key)?;
+        let encrypted_key = self.encrypt_key(&wallet_key)?;
+        let now = rsky_common::now();
+        conn.transaction(|conn| {
+            let previous = WalletSchema::wallet
+                .filter(WalletSchema::did.eq(did))
+                .select(Wallet::as_select())
+                .for_update()
+                .first(conn)?;
+            insert_into(RetiredSchema::wallet_retired)
+                .values((
+                    RetiredSchema::did.eq(&previous.did),
+                    RetiredSchema::address.eq(&previous.address),
+                    RetiredSchema::encryptedKey.eq(&previous.encrypted_key),
+                    RetiredSchema::createdAt.eq(&previous.created_at),
+                    RetiredSchema::retiredAt.eq(&now),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            update(WalletSchema::wallet)
+                .filter(WalletSchema::did.eq(did))
+                .set((
+                    WalletSchema::address.eq(&address),
+                    WalletSchema::encryptedKey.eq(&encrypted_key),
+                    WalletSchema::createdAt.eq(&now),
+                ))
+                .execute(conn)?;
+            Ok::<_, anyhow::Error>(())
+        })?;
+        Ok(AccountWallet {
+            did: did.clone(),
+            address,
+            key: wallet_key,
+        })
+    }
+
+    /// Stores `wallet_key` as the account's wallet unless it already has one, which is returned
+    /// instead
+    async fn store_wallet(&self, did: &String, wallet_key: String) -> Result<AccountWallet> {
+        use crate::schema::pds::wallet::dsl as WalletSchema;
+        let conn = &mut establish_connection()?;
+
+        let address = rev_address_from_private_key(&wallet_key)?;
+        let encrypted_key = self.encrypt_key(&wallet_key)?;
+        let now = rsky_common::now();
+
+        let inserted = insert_into(WalletSchema::wallet)
+            .values((
+                WalletSchema::did.eq(did),
+                WalletSchema::address.eq(&address),
+                WalletSchema::encryptedKey.eq(&encrypted_key),
+                WalletSchema::createdAt.eq(&now),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        if inserted == 0 {
+            // The account already has a wallet, possibly created by a concurrent request
+            return self
+                .get_wallet(did)
+                .await?
+                .ok_or_else(|| anyhow!("Wallet for {did} could not be created"));
+        }
+        Ok(AccountWallet {
+            did: did.clone(),
+            address,
+            key: wallet_key,
+        })
+    }
+
+    fn encrypt_key(&self, wallet_key: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, wallet_key.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt wallet key"))?;
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+        Ok(hex::encode(payload))
+    }
+
+    fn decrypt_key(&self, encrypted_key: &str) -> Result<String> {
+        let payload = hex::decode(encrypted_key)?;
+        if payload.len() <= NONCE_LEN {
+            bail!("Encrypted wallet key is malformed");
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt wallet key"))?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLET_KEY: &str = "5f668a7ee96d944a4494cc947e4005e172d7ab3461ee5538f1f2a45a835e9657";
+
+    #[test]
+    fn round_trips_wallet_keys() {
+        let service = WalletService::new(&[7; 32]).unwrap();
+        let encrypted = service.encrypt_key(WALLET_KEY).unwrap();
+        assert!(!encrypted.contains(WALLET_KEY));
+        assert_eq!(service.decrypt_key(&encrypted).unwrap(), WALLET_KEY);
+        // a fresh nonce every time
+        assert_ne!(service.encrypt_key(WALLET_KEY).unwrap(), encrypted);
+    }
+
+    #[test]
+    fn rejects_other_keys_and_tampering() {
+        let service = WalletService::new(&[7; 32]).unwrap();
+        let encrypted = service.encrypt_key(WALLET_KEY).unwrap();
+        let other = WalletService::new(&[8; 32]).unwrap();
+        assert!(other.decrypt_key(&encrypted).is_err());
+
+        let mut tampered = hex::decode(&encrypted).unwrap();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(service.decrypt_key(&hex::encode(tampered)).is_err());
+        assert!(service.decrypt_key(&encrypted[..NONCE_LEN * 2]).is_err());
+        assert!(WalletService::new(&[7; 16]).is_err());
+    }
+}
+
+pub mod history;
+pub mod transfer_request;
@@ -0,0 +1,308 @@
+use crate::apis::firefly::models::RequestStatus;
+use crate::db::establish_connection;
+use crate::models::{WalletRequest, WalletTransaction};
+use crate::wallet::history::DIRECTION_OUTGOING;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use diesel::*;
+use rsky_common;
+use rsky_common::get_random_str;
+
+/// A deploy is only accepted within its lifespan of 50 blocks, which is well under a day. A
+/// pending transfer which isn't indexed a day after it was last submitted never landed.
+const PENDING_EXPIRY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+pub struct CreateTransferRequestOpts {
+    pub did: String,
+    pub amount: u128,
+    pub description: String,
+}
+
+pub async fn create_transfer_request(opts: CreateTransferRequestOpts) -> Result<String> {
+    use crate::schema::pds::wallet_request::dsl as WalletRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    let CreateTransferRequestOpts {
+        did,
+        amount,
+        description,
+    } = opts;
+    let id = get_random_str();
+    let now = rsky_common::now();
+
+    insert_into(WalletRequestSchema::wallet_request)
+        .values((
+            WalletRequestSchema::id.eq(&id),
+            WalletRequestSchema::did.eq(did),
+            WalletRequestSchema::amount.eq(amount.to_string()),
+            WalletRequestSchema::description.eq(description),
+            WalletRequestSchema::status.eq(RequestStatus::ONGOING.as_str()),
+            WalletRequestSchema::createdAt.eq(&now),
+            WalletRequestSchema::updatedAt.eq(&now),
+        ))
+        .execute(conn)?;
+    Ok(id)
+}
+
+pub async fn get_transfer_request(id: &String) -> Result<Option<WalletRequest>> {
+    use crate::schema::pds::wallet_request::dsl as WalletRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    let request = WalletRequestSchema::wallet_request
+        .filter(WalletRequestSchema::id.eq(id))
+        .select(WalletRequest::as_select())
+        .first(conn)
+        .optional()?;
+    Ok(request)
+}
+
+pub async fn list_transfer_requests(did: &String) -> Result<Vec<WalletRequest>> {
+    use crate::schema::pds::wallet_request::dsl as WalletRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    let requests = WalletRequestSchema::wallet_request
+        .filter(WalletRequestSchema::did.eq(did))
+        .order(WalletRequestSchema::createdAt.desc())
+        .select(WalletRequest::as_select())
+        .load(conn)?;
+    Ok(requests)
+}
+
+/// Moves a request from `from` to `to` only if it is still in the `from` status.
+/// Returns `false` when another caller changed the status first, which is what keeps a
+/// request from being paid or cancelled twice.
+pub async fn transition_transfer_request(
+    id: &String,
+    from: RequestStatus,
+    to: RequestStatus,
+    fulfilled_by: Option<String>,
+) -> Result<bool> {
+    use crate::schema::pds::wallet_request::dsl as WalletRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    let updated = update(WalletRequestSchema::wallet_request)
+        .filter(WalletRequestSchema::id.eq(id))
+        .filter(WalletRequestSchema::status.eq(from.as_str()))
+        .set((
+            WalletRequestSchema::status.eq(to.as_str()),
+            WalletRequestSchema::fulfilledBy.eq(fulfilled_by),
+            WalletRequestSchema::updatedAt.eq(rsky_common::now()),
+        ))
+        .execute(conn)?;
+    Ok(updated > 0)
+}
+
+/// Hands a pending request back to the requester, forgetting the deploy that failed to pay it
+pub async fn reopen_transfer_request(id: &String) -> Result<bool> {
+    use crate::schema::pds::wallet_request::dsl as WalletRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    let updated = update(WalletRequestSchema::wallet_request)
+        .filter(WalletRequestSchema::id.eq(id))
+        .filter(WalletRequestSchema::status.eq(RequestStatus::PENDING.as_str()))
+        .set((
+            WalletRequestSchema::status.eq(RequestStatus::ONGOING.as_str()),
+            WalletRequestSchema::fulfilledBy.eq(None::<String>),
+            WalletRequestSchema::deployId.eq(None::<String>),
+            WalletRequestSchema::updatedAt.eq(rsky_common::now()),
+        ))
+        .execute(conn)?;
+    Ok(updated > 0)
+}
+
+/// Records the deploy paying a pending request, again whenever an expired one is resubmitted
+pub fn record_transfer_deploy(id: &String, deploy_id: &str) -> Result<()> {
+    use crate::schema::pds::wallet_request::dsl as WalletRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    update(WalletRequestSchema::wallet_request)
+        .filter(WalletRequestSchema::id.eq(id))
+        .filter(WalletRequestSchema::status.eq(RequestStatus::PENDING.as_str()))
+        .set((
+            WalletRequestSchema::deployId.eq(deploy_id),
+            WalletRequestSchema::updatedAt.eq(rsky_common::now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Outcome of `payment` if it is the deploy recorded for `request`: `Some(true)` when it paid
+/// the request and `Some(false)` when the vault refused it. Descriptions can be written by any
+/// deployer, so only the deploy id identifies the payment.
+fn payment_outcome(
+    request: &WalletRequest,
+    requester_address: &str,
+    payment: &WalletTransaction,
+) -> Option<bool> {
+    let pays_request = request.deploy_id.as_deref() == Some(payment.id.as_str())
+        && payment.direction == DIRECTION_OUTGOING
+        && payment.counterparty == requester_address
+        && payment.amount == request.amount;
+    pays_request.then_some(payment.succeeded)
+}
+
+async fn wallet_address(did: &String) -> Result<Option<String>> {
+    use crate::schema::pds::wallet::dsl as WalletSchema;
+    let conn = &mut establish_connection()?;
+
+    let address = WalletSchema::wallet
+        .filter(WalletSchema::did.eq(did))
+        .select(WalletSchema::address)
+        .first(conn)
+        .optional()?;
+    Ok(address)
+}
+
+async fn outgoing_payment(
+    address: String,
+    deploy_id: &String,
+) -> Result<Option<WalletTransaction>> {
+    use crate::schema::pds::wallet_transaction::dsl as WalletTransactionSchema;
+    let conn = &mut establish_connection()?;
+
+    let payment = WalletTransactionSchema::wallet_transaction
+        .filter(WalletTransactionSchema::id.eq(deploy_id))
+        .filter(WalletTransactionSchema::address.eq(address))
+        .filter(WalletTransactionSchema::direction.eq(DIRECTION_OUTGOING))
+        .select(WalletTransaction::as_select())
+        .first(conn)
+        .optional()?;
+    Ok(payment)
+}
+
+/// Settles requests left `pending` by a transfer whose outcome wasn't known when it was paid.
+/// They're marked done once the indexer has their deploy, and handed back to the requester when
+/// the vault refused it or it never showed up.
+pub async fn reconcile_pending_transfer_requests() -> Result<()> {
+    use crate::schema::pds::wallet_request::dsl as WalletRequestSchema;
+
+    let pending = {
+        let conn = &mut establish_connection()?;
+        WalletRequestSchema::wallet_request
+            .filter(WalletRequestSchema::status.eq(RequestStatus::PENDING.as_str()))
+            .select(WalletRequest::as_select())
+            .load(conn)?
+    };
+
+    for request in pending {
+        let Some(payer) = request.fulfilled_by.clone() else {
+            continue;
+        };
+        let claimed_at = DateTime::parse_from_rfc3339(&request.updated_at)?.with_timezone(&Utc);
+        let (Some(payer_address), Some(requester_address)) = (
+            wallet_address(&payer).await?,
+            wallet_address(&request.did).await?,
+        ) else {
+            continue;
+        };
+
+        let outcome = match &request.deploy_id {
+            Some(deploy_id) => outgoing_payment(payer_address, deploy_id)
+                .await?
+                .and_then(|payment| payment_outcome(&request, &requester_address, &payment)),
+            None => None,
+        };
+        match outcome {
+            Some(true) => {
+                tracing::info!("Transfer request {} was paid by {payer}", request.id);
+                transition_transfer_request(
+                    &request.id,
+                    RequestStatus::PENDING,
+                    RequestStatus::DONE,
+                    Some(payer),
+                )
+                .await?;
+            }
+            Some(false) => {
+                tracing::warn!(
+                    "Transfer request {} was refused by the vault, reopening it",
+                    request.id
+                );
+                reopen_transfer_request(&request.id).await?;
+            }
+            None if Utc::now().timestamp_millis() - claimed_at.timestamp_millis()
+                > PENDING_EXPIRY_MILLIS =>
+            {
+                tracing::warn!(
+                    "Transfer request {} was never paid, reopening it",
+                    request.id
+                );
+                reopen_transfer_request(&request.id).await?;
+            }
+            None => (),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> WalletRequest {
+        WalletRequest {
+            id: "request".to_string(),
+            did: "did:plc:requester".to_string(),
+            amount: "100".to_string(),
+            description: "lunch".to_string(),
+            status: RequestStatus::PENDING.as_str().to_string(),
+            fulfilled_by: Some("did:plc:payer".to_string()),
+            created_at: "2025-05-01T10:00:00.000Z".to_string(),
+            updated_at: "2025-05-01T12:00:00.000Z".to_string(),
+            deploy_id: Some("deploy".to_string()),
+        }
+    }
+
+    fn payment(id: &str, amount: &str) -> WalletTransaction {
+        WalletTransaction {
+            id: id.to_string(),
+            address: "1111payer".to_string(),
+            direction: DIRECTION_OUTGOING.to_string(),
+            counterparty: "1111requester".to_string(),
+            amount: amount.to_string(),
+            description: "lunch".to_string(),
+            cost: "0".to_string(),
+            block_hash: "hash".to_string(),
+            block_number: 1,
+            created_at: 0,
+            succeeded: true,
+        }
+    }
+
+    #[test]
+    fn matches_only_the_recorded_deploy() {
+        let request = request();
+        let outcome =
+            |payment: &WalletTransaction| payment_outcome(&request, "1111requester", payment);
+
+        assert_eq!(outcome(&payment("deploy", "100")), Some(true));
+        // an identical transfer, or one forging the description, is another deploy
+        assert_eq!(outcome(&payment("other-deploy", "100")), None);
+        assert_eq!(outcome(&payment("deploy", "99")), None);
+        assert_eq!(
+            payment_outcome(&request, "1111someone-else", &payment("deploy", "100")),
+            None
+        );
+
+        let unsubmitted = WalletRequest {
+            deploy_id: None,
+            ..request.clone()
+        };
+        assert_eq!(
+            payment_outcome(&unsubmitted, "1111requester", &payment("deploy", "100")),
+            None
+        );
+    }
+
+    #[test]
+    fn refused_transfers_do_not_pay_the_request() {
+        let refused = WalletTransaction {
+            succeeded: false,
+            ..payment("deploy", "100")
+        };
+        assert_eq!(
+            payment_outcome(&request(), "1111requester", &refused),
+            Some(false)
+        );
+    }
+}
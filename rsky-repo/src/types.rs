@@ -111,6 +111,8 @@ pub struct PreparedCreateOrUpdate {
     pub swap_cid: Option<Cid>,
     pub record: RepoRecord,
     pub blobs: Vec<PreparedBlobRef>,
+    /// `None` when lexicon validation was skipped
+    pub validation_status: Option<ValidationStatus>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -151,6 +153,14 @@ impl PreparedWrite {
             PreparedWrite::Delete(w) => &w.swap_cid,
         }
     }
+
+    pub fn validation_status(&self) -> Option<ValidationStatus> {
+        match self {
+            PreparedWrite::Create(w) => w.validation_status,
+            PreparedWrite::Update(w) => w.validation_status,
+            PreparedWrite::Delete(_) => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -161,6 +171,24 @@ pub enum WriteOpAction {
     Delete,
 }
 
+/// Outcome of validating a record against its lexicon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationStatus {
+    Valid,
+    /// No lexicon is known for the record type, so the record was not checked
+    Unknown,
+}
+
+impl fmt::Display for ValidationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationStatus::Valid => write!(f, "valid"),
+            ValidationStatus::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
 impl fmt::Display for WriteOpAction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // Match each variant and write its lowercase representation.